@@ -7,12 +7,15 @@ use rt_rust::objects::Object;
 use rt_rust::transformations::{scaling, translation, view_transform};
 use rt_rust::tuples::Tuple;
 use rt_rust::worlds::World;
+use std::sync::Arc;
 
 fn main() {
     let mut floor = Object::new_plane().with_transform(Matrix::identity());
     floor.material.ambient = 0.0;
     floor.material.diffuse = 0.8;
     floor.material.reflective = 0.5;
+    floor.material.roughness = 0.05;
+    floor.material.glossy_samples = 4;
 
     let mut red = Object::new_sphere().with_transform(translation(0.0, 1.5, 0.0));
     red.material.color = Color::new(1.0, 0.0, 0.0);
@@ -26,12 +29,12 @@ fn main() {
 
     let mut world = World::new();
     world.lights = vec![light];
-    world.objects = vec![floor, red, green, blue];
+    world.objects = [floor, red, green, blue].map(Arc::new).into();
 
     let mut camera = Camera::new(
         1000,
         800,
-        4.0,
+        1.0,
         PI / 2.0,
         view_transform(
             Tuple::point(0.0, 1.0, 0.0) + Tuple::vector(1.0, 0.1, 0.0) * 4.0,
@@ -39,7 +42,8 @@ fn main() {
             Tuple::vector(0.0, 1.0, 0.0),
         ),
     );
-    camera.aperture = 0.01;
+    camera.aperture = 0.04;
+    camera.focus_distance = 4.0;
     camera.blur_oversampling = 20;
     camera.oversampling = 3;
     camera.threads = 8;
@@ -7,6 +7,7 @@ use rt_rust::shapes::csg::Operation;
 use rt_rust::transformations::{rotation_x, rotation_z, scaling, view_transform};
 use rt_rust::tuples::Tuple;
 use rt_rust::worlds::World;
+use std::sync::Arc;
 
 fn main() {
     let mut cyl1 = Object::new_cylinder();
@@ -27,7 +28,7 @@ fn main() {
     let light = Light::new_point(Tuple::point(10.0, 10.0, 2.0), Color::new(1.0, 1.0, 1.0));
     let mut world = World::new();
     world.lights = vec![light];
-    world.objects = vec![csg];
+    world.objects = [csg].map(Arc::new).into();
 
     let camera = Camera::new(
         500,
@@ -6,6 +6,7 @@ use rt_rust::objects::Object;
 use rt_rust::transformations::{rotation_y, rotation_z, scaling, translation, view_transform};
 use rt_rust::tuples::Tuple;
 use rt_rust::worlds::World;
+use std::sync::Arc;
 
 fn main() {
     let mut floor = Object::new_plane().with_transform(translation(0.0, -2.0, 0.0));
@@ -36,7 +37,7 @@ fn main() {
     let light = Light::new_point(Tuple::point(10.0, 10.0, 2.0), Color::new(1.0, 1.0, 1.0));
     let mut world = World::new();
     world.lights = vec![light];
-    world.objects = vec![floor, middle, left, right];
+    world.objects = [floor, middle, left, right].map(Arc::new).into();
 
     let camera = Camera::new(
         500,
@@ -7,6 +7,7 @@ use rt_rust::objects::Object;
 use rt_rust::transformations::{rotation_y, rotation_z, scaling, translation, view_transform};
 use rt_rust::tuples::Tuple;
 use rt_rust::worlds::World;
+use std::sync::Arc;
 
 fn main() {
     let hexagon = hexagon();
@@ -14,7 +15,7 @@ fn main() {
     let light = Light::new_point(Tuple::point(0.0, 50.0, 25.0), Color::new(1.0, 0.2, 1.0));
     let mut world = World::new();
     world.lights = vec![light];
-    world.objects = vec![hexagon];
+    world.objects = [hexagon].map(Arc::new).into();
 
     let camera = Camera::new(
         500,
@@ -38,14 +39,8 @@ fn hexagon_corner() -> Object {
 }
 
 fn hexagon_edge() -> Object {
-    let mut cyl = Object::new_cylinder().with_transform(
-        translation(0.0, 0.0, -1.0)
-            * rotation_y(-PI / 6.0)
-            * rotation_z(-PI / 2.0)
-            * scaling(0.25, 1.0, 0.25),
-    );
-    cyl.as_mut_cylinder().truncate(0.0, 1.0, false);
-    cyl
+    Object::new_tube(0.25, 1.0)
+        .with_transform(translation(0.0, 0.0, -1.0) * rotation_y(-PI / 6.0) * rotation_z(-PI / 2.0))
 }
 
 fn hexagon_side() -> Object {
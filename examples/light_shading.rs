@@ -33,7 +33,7 @@ fn main() {
                 let color = sphere.material.lighting(
                     &sphere,
                     WHITE,
-                    &vec![light],
+                    &vec![light.clone()],
                     hit_point,
                     eyev,
                     normalv,
@@ -8,6 +8,7 @@ use rt_rust::patterns::Pattern;
 use rt_rust::transformations::{rotation_y, scaling, translation, view_transform};
 use rt_rust::tuples::Tuple;
 use rt_rust::worlds::World;
+use std::sync::Arc;
 
 fn main() {
     let mut floor = Object::new_plane().with_transform(Matrix::identity());
@@ -39,7 +40,7 @@ fn main() {
     let light = Light::new_point(Tuple::point(10.0, 10.0, 0.0), Color::new(1.0, 1.0, 1.0));
     let mut world = World::new();
     world.lights = vec![light];
-    world.objects = vec![floor, middle, left, right];
+    world.objects = [floor, middle, left, right].map(Arc::new).into();
 
     let mut camera = Camera::new(
         1000,
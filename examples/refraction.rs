@@ -8,10 +8,11 @@ use rt_rust::patterns::Pattern;
 use rt_rust::transformations::{scaling, translation, view_transform};
 use rt_rust::tuples::Tuple;
 use rt_rust::worlds::World;
+use std::sync::Arc;
 
 fn main() {
     let mut floor = Object::new_plane().with_transform(translation(0.0, -2.0, 0.0));
-    floor.material.pattern = Some(Pattern::new_checker(BLACK, WHITE));
+    floor.material.pattern = Some(Pattern::new_filtered_checker(BLACK, WHITE));
 
     let mut glass = Object::new_sphere()
         .made_of_glass()
@@ -34,7 +35,7 @@ fn main() {
     let light = Light::new_point(Tuple::point(0.0, 100.0, 100.0), Color::new(1.0, 1.0, 1.0));
     let mut world = World::new();
     world.lights = vec![light];
-    world.objects = vec![floor, glass, air];
+    world.objects = [floor, glass, air].map(Arc::new).into();
 
     let mut camera = Camera::new(
         1000,
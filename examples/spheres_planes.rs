@@ -7,16 +7,19 @@ use rt_rust::objects::Object;
 use rt_rust::transformations::{rotation_z, scaling, translation, view_transform};
 use rt_rust::tuples::Tuple;
 use rt_rust::worlds::World;
+use std::sync::Arc;
 
 fn main() {
-    let mut wall_material = Material::default();
-    wall_material.color = Color::new(1.0, 0.9, 0.9);
-    wall_material.specular = 0.0;
+    let wall_material = Material {
+        color: Color::new(1.0, 0.9, 0.9),
+        specular: 0.0,
+        ..Material::default()
+    };
     let mut floor = Object::new_plane();
     floor.material = wall_material.clone();
     let mut left_wall =
         Object::new_plane().with_transform(rotation_z(PI / 2.0) * translation(0.0, -5.0, 0.0));
-    left_wall.material = wall_material.clone();
+    left_wall.material = wall_material;
 
     let mut middle = Object::new_sphere().with_transform(translation(-0.5, 1.0, 0.5));
     middle.material.color = Color::new(0.1, 1.0, 0.5);
@@ -38,7 +41,7 @@ fn main() {
     let light = Light::new_point(Tuple::point(-10.0, 10.0, -10.0), Color::new(1.0, 1.0, 1.0));
     let mut world = World::new();
     world.lights = vec![light];
-    world.objects = vec![floor, left_wall, middle, right, left];
+    world.objects = [floor, left_wall, middle, right, left].map(Arc::new).into();
 
     let camera = Camera::new(
         500,
@@ -7,6 +7,7 @@ use rt_rust::objects::Object;
 use rt_rust::transformations::{translation, view_transform};
 use rt_rust::tuples::Tuple;
 use rt_rust::worlds::World;
+use std::sync::Arc;
 
 fn main() {
     let floor = Object::new_plane().with_transform(Matrix::identity());
@@ -23,7 +24,7 @@ fn main() {
 
     let mut world = World::new();
     world.lights = vec![light];
-    world.objects = vec![floor, sphere];
+    world.objects = [floor, sphere].map(Arc::new).into();
 
     let camera = Camera::new(
         500,
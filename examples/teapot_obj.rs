@@ -6,18 +6,19 @@ use rt_rust::obj_files::parse_obj_file_path;
 use rt_rust::transformations::view_transform;
 use rt_rust::tuples::Tuple;
 use rt_rust::worlds::World;
+use std::sync::Arc;
 
 fn main() {
-    let teapot_low_obj = parse_obj_file_path("examples/teapot_low.obj");
-    let teapot_obj = parse_obj_file_path("examples/teapot.obj");
+    let teapot_low_obj = parse_obj_file_path("examples/teapot_low.obj").unwrap();
+    let teapot_obj = parse_obj_file_path("examples/teapot.obj").unwrap();
 
     let light = Light::new_point(Tuple::point(100.0, 100.0, 100.0), Color::new(1.0, 1.0, 1.0));
     let mut world_low = World::new();
-    world_low.lights = vec![light];
-    world_low.objects = vec![teapot_low_obj.default_group];
+    world_low.lights = vec![light.clone()];
+    world_low.objects = [teapot_low_obj.default_group].map(Arc::new).into();
     let mut world = World::new();
     world.lights = vec![light];
-    world.objects = vec![teapot_obj.default_group];
+    world.objects = [teapot_obj.default_group].map(Arc::new).into();
 
     let mut camera = Camera::new(
         1000,
@@ -0,0 +1,14 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use rt_rust::scene_files::parse_scene_file;
+use std::path::Path;
+
+fuzz_target!(|data: &[u8]| {
+    if let Ok(text) = std::str::from_utf8(data) {
+        // Referenced assets are looked up in a directory that has none.
+        if let Ok(scene) = parse_scene_file(text, Path::new("/nonexistent")) {
+            let _ = scene.camera();
+        }
+    }
+});
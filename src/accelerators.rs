@@ -0,0 +1,194 @@
+use crate::bounds::Bounds;
+use crate::floats::Float;
+use crate::intersections::Intersection;
+use crate::objects::Object;
+use crate::rays::Ray;
+use std::fmt::Debug;
+use std::sync::Arc;
+
+pub mod bvhs;
+pub mod grids;
+
+const AUTO_MIN_OBJECTS: usize = 16;
+
+pub trait Accelerator: Debug + Send + Sync {
+    fn object_count(&self) -> usize;
+
+    fn intersect<'a>(&self, objects: &'a [Arc<Object>], ray: &Ray, xs: &mut Vec<Intersection<'a>>);
+
+    // Only the intersections up to the first one past `t_min` are needed,
+    // so the parts of the scene beyond it may be skipped.
+    fn intersect_until_hit<'a>(
+        &self,
+        objects: &'a [Arc<Object>],
+        ray: &Ray,
+        _t_min: Float,
+        xs: &mut Vec<Intersection<'a>>,
+    ) {
+        self.intersect(objects, ray, xs);
+    }
+
+    // Whether an intersection of some object `blocks` the ray, up to the
+    // end of its range. Stops at the first one, and leaves out the objects
+    // in `skip`, already tested by the caller.
+    fn intersect_any(
+        &self,
+        objects: &[Arc<Object>],
+        ray: &Ray,
+        skip: &[usize],
+        blocks: &dyn Fn(&Intersection) -> bool,
+    ) -> bool;
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum AcceleratorKind {
+    None,
+    Auto,
+    Bvh,
+    Grid,
+}
+
+pub fn build(kind: AcceleratorKind, objects: &[Arc<Object>]) -> Option<Arc<dyn Accelerator>> {
+    match kind {
+        AcceleratorKind::None => None,
+        AcceleratorKind::Auto => build(choose(objects), objects),
+        AcceleratorKind::Bvh => Some(Arc::new(bvhs::Bvh::new(objects))),
+        AcceleratorKind::Grid => Some(Arc::new(grids::Grid::new(objects))),
+    }
+}
+
+// Grids suit many objects of similar size, BVHs adapt better to everything else.
+pub fn choose(objects: &[Arc<Object>]) -> AcceleratorKind {
+    if objects.len() < AUTO_MIN_OBJECTS {
+        return AcceleratorKind::None;
+    }
+    let sizes: Vec<Float> = objects
+        .iter()
+        .map(|o| o.parent_bounds())
+        .filter(Bounds::is_finite)
+        .map(|b| (b.max - b.min).magnitude())
+        .collect();
+    if sizes.len() < AUTO_MIN_OBJECTS {
+        return AcceleratorKind::Bvh;
+    }
+    let mean = sizes.iter().sum::<Float>() / sizes.len() as Float;
+    let variance = sizes.iter().map(|s| (s - mean).powi(2)).sum::<Float>() / sizes.len() as Float;
+    if variance.sqrt() < 0.5 * mean {
+        AcceleratorKind::Grid
+    } else {
+        AcceleratorKind::Bvh
+    }
+}
+
+fn partition_bounded(objects: &[Arc<Object>]) -> (Vec<(usize, Bounds)>, Vec<usize>) {
+    let mut bounded = vec![];
+    let mut unbounded = vec![];
+    for (i, o) in objects.iter().enumerate() {
+        let bounds = o.parent_bounds();
+        if bounds.is_finite() {
+            bounded.push((i, bounds));
+        } else {
+            unbounded.push(i);
+        }
+    }
+    (bounded, unbounded)
+}
+
+fn blocked_by<'a>(
+    object: &'a Object,
+    ray: &Ray,
+    blocks: &dyn Fn(&Intersection) -> bool,
+    xs: &mut Vec<Intersection<'a>>,
+) -> bool {
+    xs.clear();
+    object.intersect(ray, xs);
+    xs.iter().any(blocks)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::transformations::{scaling, translation};
+    use crate::tuples::Tuple;
+    use std::ptr;
+
+    fn spheres(scales: &[Float]) -> Vec<Arc<Object>> {
+        scales
+            .iter()
+            .enumerate()
+            .map(|(i, s)| {
+                let mut o = Object::new_sphere()
+                    .with_transform(translation(i as Float * 3.0, 0.0, 0.0) * scaling(*s, *s, *s));
+                o.prepare();
+                Arc::new(o)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn small_worlds_are_not_accelerated() {
+        assert_eq!(choose(&spheres(&[1.0; 4])), AcceleratorKind::None);
+        assert!(build(AcceleratorKind::Auto, &spheres(&[1.0; 4])).is_none());
+    }
+
+    #[test]
+    fn uniform_objects_choose_a_grid() {
+        assert_eq!(choose(&spheres(&[1.0; 20])), AcceleratorKind::Grid);
+    }
+
+    #[test]
+    fn objects_of_mixed_sizes_choose_a_bvh() {
+        let mut scales = vec![0.1; 19];
+        scales.push(50.0);
+        assert_eq!(choose(&spheres(&scales)), AcceleratorKind::Bvh);
+    }
+
+    #[test]
+    fn accelerators_find_the_same_intersections_as_a_linear_scan() {
+        let mut objects = spheres(&[1.0, 0.5, 2.0, 0.2, 1.0, 1.5, 0.7, 1.0]);
+        let mut plane = Object::new_plane();
+        plane.prepare();
+        objects.push(Arc::new(plane));
+        for kind in [AcceleratorKind::Bvh, AcceleratorKind::Grid] {
+            let accelerator = build(kind, &objects).unwrap();
+            assert_eq!(accelerator.object_count(), objects.len());
+            for (x, y) in [
+                (0.0, 0.0),
+                (3.0, 0.3),
+                (6.2, -1.0),
+                (12.0, 0.5),
+                (40.0, 0.0),
+            ] {
+                let r = Ray::new(
+                    Tuple::point(x, y + 5.0, -5.0),
+                    Tuple::vector(0.1, -1.0, 1.0),
+                );
+                let mut expected = vec![];
+                for o in &objects {
+                    o.intersect(&r, &mut expected);
+                }
+                let mut xs = vec![];
+                accelerator.intersect(&objects, &r, &mut xs);
+                let ts = |xs: &[Intersection]| {
+                    let mut ts: Vec<Float> = xs.iter().map(|x| x.t).collect();
+                    ts.sort_by(Float::total_cmp);
+                    ts
+                };
+                assert_eq!(ts(&xs), ts(&expected), "{:?} at ({}, {})", kind, x, y);
+                let blocks = |x: &Intersection| x.t > 0.0;
+                let skipped = [objects.len() - 1];
+                assert_eq!(
+                    accelerator.intersect_any(&objects, &r, &[], &blocks),
+                    expected.iter().any(blocks)
+                );
+                assert_eq!(
+                    accelerator.intersect_any(&objects, &r, &skipped, &blocks),
+                    expected
+                        .iter()
+                        .filter(|x| !ptr::eq(x.object, &*objects[skipped[0]]))
+                        .any(blocks)
+                );
+            }
+        }
+    }
+}
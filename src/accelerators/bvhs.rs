@@ -0,0 +1,251 @@
+use super::{blocked_by, partition_bounded, Accelerator};
+use crate::bounds::{sah_partition, Bounds};
+use crate::floats::Float;
+use crate::intersections::Intersection;
+use crate::objects::Object;
+use crate::rays::Ray;
+use std::sync::Arc;
+
+const LEAF_SIZE: usize = 2;
+
+#[derive(Debug, Clone)]
+enum Node {
+    Leaf(Bounds, Vec<usize>),
+    Inner(Bounds, usize, usize),
+}
+
+#[derive(Debug, Clone)]
+pub struct Bvh {
+    nodes: Vec<Node>,
+    unbounded: Vec<usize>,
+    object_count: usize,
+}
+
+impl Bvh {
+    pub fn new(objects: &[Arc<Object>]) -> Bvh {
+        let (bounded, unbounded) = partition_bounded(objects);
+        let mut bvh = Bvh {
+            nodes: vec![],
+            unbounded,
+            object_count: objects.len(),
+        };
+        if !bounded.is_empty() {
+            bvh.build(bounded);
+        }
+        bvh
+    }
+
+    fn build(&mut self, items: Vec<(usize, Bounds)>) -> usize {
+        let mut bounds = Bounds::empty();
+        for (_, b) in &items {
+            bounds.merge(b);
+        }
+        let index = self.nodes.len();
+        let split = if items.len() > LEAF_SIZE {
+            let item_bounds: Vec<Bounds> = items.iter().map(|(_, b)| b.clone()).collect();
+            sah_partition(&item_bounds)
+        } else {
+            None
+        };
+        let Some((left, right)) = split else {
+            let objects = items.into_iter().map(|(i, _)| i).collect();
+            self.nodes.push(Node::Leaf(bounds, objects));
+            return index;
+        };
+        self.nodes.push(Node::Inner(bounds.clone(), 0, 0));
+        let pick = |indices: Vec<usize>| indices.into_iter().map(|i| items[i].clone()).collect();
+        let left = self.build(pick(left));
+        let right = self.build(pick(right));
+        self.nodes[index] = Node::Inner(bounds, left, right);
+        index
+    }
+}
+
+impl Accelerator for Bvh {
+    fn object_count(&self) -> usize {
+        self.object_count
+    }
+
+    fn intersect<'a>(&self, objects: &'a [Arc<Object>], ray: &Ray, xs: &mut Vec<Intersection<'a>>) {
+        for &i in &self.unbounded {
+            objects[i].intersect(ray, xs);
+        }
+        if self.nodes.is_empty() {
+            return;
+        }
+        let mut stack = vec![0];
+        while let Some(index) = stack.pop() {
+            match &self.nodes[index] {
+                Node::Leaf(bounds, leaf) => {
+                    if bounds.intersect(ray) {
+                        for &i in leaf {
+                            objects[i].intersect(ray, xs);
+                        }
+                    }
+                }
+                Node::Inner(bounds, left, right) => {
+                    if bounds.intersect(ray) {
+                        stack.push(*right);
+                        stack.push(*left);
+                    }
+                }
+            }
+        }
+    }
+
+    // Visits the nearer child first, and skips the nodes that start past
+    // the closest hit found so far.
+    fn intersect_until_hit<'a>(
+        &self,
+        objects: &'a [Arc<Object>],
+        ray: &Ray,
+        t_min: Float,
+        xs: &mut Vec<Intersection<'a>>,
+    ) {
+        for &i in &self.unbounded {
+            objects[i].intersect(ray, xs);
+        }
+        let Some(span) = self.nodes.first().and_then(|n| n.bounds().span(ray)) else {
+            return;
+        };
+        let closest = |xs: &[Intersection]| {
+            xs.iter()
+                .map(|x| x.t)
+                .filter(|&t| t >= t_min)
+                .fold(Float::INFINITY, Float::min)
+        };
+        let mut hit = closest(xs);
+        let mut stack = vec![(0, span.0)];
+        while let Some((index, start)) = stack.pop() {
+            if start > hit {
+                continue;
+            }
+            match &self.nodes[index] {
+                Node::Leaf(_, leaf) => {
+                    let before = xs.len();
+                    for &i in leaf {
+                        objects[i].intersect(ray, xs);
+                    }
+                    hit = hit.min(closest(&xs[before..]));
+                }
+                Node::Inner(_, left, right) => {
+                    let span = |i: usize| self.nodes[i].bounds().span(ray).map(|s| (i, s.0));
+                    let mut children: Vec<_> =
+                        [span(*left), span(*right)].into_iter().flatten().collect();
+                    children.sort_by(|a, b| b.1.total_cmp(&a.1));
+                    stack.extend(children);
+                }
+            }
+        }
+    }
+
+    fn intersect_any(
+        &self,
+        objects: &[Arc<Object>],
+        ray: &Ray,
+        skip: &[usize],
+        blocks: &dyn Fn(&Intersection) -> bool,
+    ) -> bool {
+        let mut xs = vec![];
+        let mut blocked =
+            |i: usize| !skip.contains(&i) && blocked_by(&objects[i], ray, blocks, &mut xs);
+        if self.unbounded.iter().any(|&i| blocked(i)) {
+            return true;
+        }
+        if self.nodes.is_empty() {
+            return false;
+        }
+        let mut stack = vec![0];
+        while let Some(index) = stack.pop() {
+            let node = &self.nodes[index];
+            if !node.bounds().intersect(ray) {
+                continue;
+            }
+            match node {
+                Node::Leaf(_, leaf) => {
+                    if leaf.iter().any(|&i| blocked(i)) {
+                        return true;
+                    }
+                }
+                Node::Inner(_, left, right) => {
+                    stack.push(*right);
+                    stack.push(*left);
+                }
+            }
+        }
+        false
+    }
+}
+
+impl Node {
+    fn bounds(&self) -> &Bounds {
+        match self {
+            Node::Leaf(bounds, _) | Node::Inner(bounds, ..) => bounds,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::transformations::translation;
+    use crate::tuples::Tuple;
+
+    fn row(n: usize) -> Vec<Arc<Object>> {
+        (0..n)
+            .map(|i| {
+                let mut o =
+                    Object::new_sphere().with_transform(translation(i as Float * 3.0, 0.0, 0.0));
+                o.prepare();
+                Arc::new(o)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn building_a_bvh_splits_distant_objects() {
+        let bvh = Bvh::new(&row(8));
+        assert!(bvh.nodes.len() > 1);
+        assert!(bvh.unbounded.is_empty());
+        assert!(bvh.nodes.iter().all(|n| match n {
+            Node::Leaf(_, objects) => objects.len() <= LEAF_SIZE,
+            Node::Inner(..) => true,
+        }));
+    }
+
+    #[test]
+    fn a_bvh_only_intersects_objects_along_the_ray() {
+        let objects = row(8);
+        let bvh = Bvh::new(&objects);
+        let r = Ray::new(Tuple::point(9.0, 0.0, -5.0), Tuple::vector(0.0, 0.0, 1.0));
+        let mut xs = vec![];
+        bvh.intersect(&objects, &r, &mut xs);
+        assert_eq!(xs.len(), 2);
+        assert!(xs.iter().all(|x| std::ptr::eq(x.object, &*objects[3])));
+    }
+
+    #[test]
+    fn a_bvh_stops_past_the_closest_hit() {
+        let objects = row(8);
+        let bvh = Bvh::new(&objects);
+        let r = Ray::new(Tuple::point(-5.0, 0.0, 0.0), Tuple::vector(1.0, 0.0, 0.0));
+        let mut all = vec![];
+        bvh.intersect(&objects, &r, &mut all);
+        assert_eq!(all.len(), 16);
+        let mut xs = vec![];
+        bvh.intersect_until_hit(&objects, &r, 0.0, &mut xs);
+        assert!(xs.len() < 16);
+        let first = |xs: &[Intersection], t_min: Float| {
+            xs.iter()
+                .map(|x| x.t)
+                .filter(|&t| t >= t_min)
+                .fold(Float::INFINITY, Float::min)
+        };
+        assert_eq!(first(&xs, 0.0), 4.0);
+        assert_eq!(first(&xs, 0.0), first(&all, 0.0));
+        // Past the first sphere, the next one is found.
+        let mut xs = vec![];
+        bvh.intersect_until_hit(&objects, &r, 6.5, &mut xs);
+        assert_eq!(first(&xs, 6.5), 7.0);
+    }
+}
@@ -0,0 +1,232 @@
+use super::{blocked_by, partition_bounded, Accelerator};
+use crate::bounds::Bounds;
+use crate::floats::{Float, EPSILON};
+use crate::intersections::Intersection;
+use crate::objects::Object;
+use crate::rays::Ray;
+use crate::tuples::Tuple;
+use std::sync::Arc;
+
+const CELLS_PER_OBJECT: Float = 2.0;
+const MAX_RESOLUTION: usize = 128;
+
+#[derive(Debug, Clone)]
+pub struct Grid {
+    bounds: Bounds,
+    resolution: [usize; 3],
+    cell_size: [Float; 3],
+    cells: Vec<Vec<usize>>,
+    unbounded: Vec<usize>,
+    object_count: usize,
+}
+
+fn axes(t: Tuple) -> [Float; 3] {
+    [t.x(), t.y(), t.z()]
+}
+
+impl Grid {
+    pub fn new(objects: &[Arc<Object>]) -> Grid {
+        let (bounded, unbounded) = partition_bounded(objects);
+        let mut bounds = Bounds::empty();
+        for (_, b) in &bounded {
+            bounds.merge(b);
+        }
+        let mut grid = Grid {
+            bounds,
+            resolution: [0; 3],
+            cell_size: [0.0; 3],
+            cells: vec![],
+            unbounded,
+            object_count: objects.len(),
+        };
+        if bounded.is_empty() {
+            return grid;
+        }
+        let extent = axes(grid.bounds.max - grid.bounds.min);
+        let largest = extent.iter().cloned().fold(EPSILON, Float::max);
+        let extent = extent.map(|e| e.max(largest * 1e-3));
+        let volume: Float = extent.iter().product();
+        let scale = (CELLS_PER_OBJECT * bounded.len() as Float / volume).cbrt();
+        grid.resolution = extent.map(|e| ((e * scale).ceil() as usize).clamp(1, MAX_RESOLUTION));
+        grid.cell_size = [0, 1, 2].map(|a| extent[a] / grid.resolution[a] as Float);
+        grid.cells = vec![vec![]; grid.resolution.iter().product()];
+        for (i, b) in bounded {
+            let min = grid.cell_of(axes(b.min));
+            let max = grid.cell_of(axes(b.max));
+            for x in min[0]..=max[0] {
+                for y in min[1]..=max[1] {
+                    for z in min[2]..=max[2] {
+                        let index = grid.cell_index([x, y, z]);
+                        grid.cells[index].push(i);
+                    }
+                }
+            }
+        }
+        grid
+    }
+
+    fn cell_of(&self, point: [Float; 3]) -> [usize; 3] {
+        let min = axes(self.bounds.min);
+        [0, 1, 2].map(|a| {
+            let cell = ((point[a] - min[a]) / self.cell_size[a]).floor();
+            (cell.max(0.0) as usize).min(self.resolution[a] - 1)
+        })
+    }
+
+    fn cell_index(&self, [x, y, z]: [usize; 3]) -> usize {
+        (z * self.resolution[1] + y) * self.resolution[0] + x
+    }
+
+    // Slab test clipped to the ray range, like Bounds::intersect, but keeping
+    // the entry and exit distances needed to walk the cells.
+    fn line_interval(&self, ray: &Ray) -> Option<(Float, Float)> {
+        let (origin, direction) = (axes(ray.origin), axes(ray.direction));
+        let (min, max) = (axes(self.bounds.min), axes(self.bounds.max));
+        let mut interval = (Float::NEG_INFINITY, Float::INFINITY);
+        for a in 0..3 {
+            if direction[a].abs() < EPSILON {
+                if origin[a] < min[a] - EPSILON || origin[a] > max[a] + EPSILON {
+                    return None;
+                }
+                continue;
+            }
+            let t0 = (min[a] - EPSILON - origin[a]) / direction[a];
+            let t1 = (max[a] + EPSILON - origin[a]) / direction[a];
+            interval.0 = interval.0.max(t0.min(t1));
+            interval.1 = interval.1.min(t0.max(t1));
+        }
+        interval = (interval.0.max(ray.t_min), interval.1.min(ray.t_max));
+        (interval.0 <= interval.1).then_some(interval)
+    }
+
+    fn candidates(&self, ray: &Ray) -> Vec<usize> {
+        let mut candidates = vec![];
+        let Some((t_enter, t_exit)) = self.line_interval(ray) else {
+            return candidates;
+        };
+        let direction = axes(ray.direction);
+        let entry = axes(ray.position(t_enter));
+        let min = axes(self.bounds.min);
+        let mut cell = self.cell_of(entry).map(|c| c as isize);
+        let mut step = [0isize; 3];
+        let mut t_next = [Float::INFINITY; 3];
+        let mut t_delta = [Float::INFINITY; 3];
+        for a in 0..3 {
+            if direction[a].abs() < EPSILON {
+                continue;
+            }
+            step[a] = if direction[a] > 0.0 { 1 } else { -1 };
+            t_delta[a] = self.cell_size[a] / direction[a].abs();
+            let boundary = min[a] + (cell[a] + (step[a] > 0) as isize) as Float * self.cell_size[a];
+            t_next[a] = t_enter + (boundary - entry[a]) / direction[a];
+        }
+        loop {
+            let index = self.cell_index(cell.map(|c| c as usize));
+            candidates.extend_from_slice(&self.cells[index]);
+            let a = (0..3)
+                .min_by(|&i, &j| t_next[i].total_cmp(&t_next[j]))
+                .unwrap();
+            if t_next[a] > t_exit {
+                break;
+            }
+            cell[a] += step[a];
+            if cell[a] < 0 || cell[a] >= self.resolution[a] as isize {
+                break;
+            }
+            t_next[a] += t_delta[a];
+        }
+        candidates.sort_unstable();
+        candidates.dedup();
+        candidates
+    }
+}
+
+impl Accelerator for Grid {
+    fn object_count(&self) -> usize {
+        self.object_count
+    }
+
+    fn intersect<'a>(&self, objects: &'a [Arc<Object>], ray: &Ray, xs: &mut Vec<Intersection<'a>>) {
+        for &i in &self.unbounded {
+            objects[i].intersect(ray, xs);
+        }
+        if self.cells.is_empty() {
+            return;
+        }
+        for i in self.candidates(ray) {
+            objects[i].intersect(ray, xs);
+        }
+    }
+
+    fn intersect_any(
+        &self,
+        objects: &[Arc<Object>],
+        ray: &Ray,
+        skip: &[usize],
+        blocks: &dyn Fn(&Intersection) -> bool,
+    ) -> bool {
+        let mut xs = vec![];
+        let mut blocked =
+            |i: usize| !skip.contains(&i) && blocked_by(&objects[i], ray, blocks, &mut xs);
+        if self.unbounded.iter().any(|&i| blocked(i)) {
+            return true;
+        }
+        !self.cells.is_empty() && self.candidates(ray).into_iter().any(blocked)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::transformations::translation;
+
+    fn lattice() -> Vec<Arc<Object>> {
+        let mut objects = vec![];
+        for x in 0..4 {
+            for z in 0..4 {
+                let mut o = Object::new_sphere().with_transform(translation(
+                    x as Float * 3.0,
+                    0.0,
+                    z as Float * 3.0,
+                ));
+                o.prepare();
+                objects.push(Arc::new(o));
+            }
+        }
+        objects
+    }
+
+    #[test]
+    fn building_a_grid_covers_every_object() {
+        let grid = Grid::new(&lattice());
+        assert_eq!(grid.bounds.min, Tuple::point(-1.0, -1.0, -1.0));
+        assert_eq!(grid.bounds.max, Tuple::point(10.0, 1.0, 10.0));
+        assert!(grid.resolution.iter().all(|&r| r >= 1));
+        let mut seen: Vec<usize> = grid.cells.iter().flatten().cloned().collect();
+        seen.sort_unstable();
+        seen.dedup();
+        assert_eq!(seen, (0..16).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn a_grid_only_returns_objects_near_the_ray() {
+        let objects = lattice();
+        let grid = Grid::new(&objects);
+        let r = Ray::new(Tuple::point(3.0, 0.0, -5.0), Tuple::vector(0.0, 0.0, 1.0));
+        let candidates = grid.candidates(&r);
+        assert!(candidates.len() < objects.len());
+        for i in [4, 5, 6, 7] {
+            assert!(candidates.contains(&i));
+        }
+        let mut xs = vec![];
+        grid.intersect(&objects, &r, &mut xs);
+        assert_eq!(xs.len(), 8);
+    }
+
+    #[test]
+    fn a_ray_missing_the_grid_has_no_candidates() {
+        let grid = Grid::new(&lattice());
+        let r = Ray::new(Tuple::point(0.0, 5.0, -5.0), Tuple::vector(0.0, 0.0, 1.0));
+        assert!(grid.candidates(&r).is_empty());
+    }
+}
@@ -0,0 +1,234 @@
+use rt_rust::cameras::{render_contact_sheet, Camera};
+use rt_rust::canvas::Canvas;
+use rt_rust::colors::WHITE;
+use rt_rust::floats::Float;
+use rt_rust::materials::Material;
+use rt_rust::scene_files::parse_scene_file_path;
+use rt_rust::worlds::World;
+use std::path::Path;
+use std::process::exit;
+use std::thread;
+use std::time::{Duration, Instant, SystemTime};
+
+const DRAFT_DEPTH: usize = 2;
+const WATCH_INTERVAL: Duration = Duration::from_millis(250);
+const WIREFRAME_ISOLINES: Float = 4.0;
+const CONTACT_SHEET_SAMPLES: [usize; 3] = [1, 2, 4];
+
+const USAGE: &str =
+    "usage: rt SCENE [-o OUTPUT] [--width W] [--height H] [--samples N] [--threads N] [--passes N] [--importance MAP] [--preview] [--clay] [--wireframe] [--contact-sheet] [--watch]
+
+  -o, --output PATH  output PPM file (default: SCENE with a .ppm extension)
+  --width W          image width, overrides the scene camera
+  --height H         image height, overrides the scene camera
+  --samples N        render N x N samples per pixel
+  --threads N        number of render threads
+  --passes N         accumulate N one-sample passes, saving the image after each
+  --importance MAP   only take the --samples samples where the MAP PPM is bright,
+                     or where a one sample draft has edges if MAP is 'saliency'
+  --preview          render 1/8, 1/4 and 1/2 resolution previews first, saving each one
+  --clay             replace every material with a neutral gray
+  --wireframe        draw primitive edges and isolines over the image
+  --contact-sheet    render a labeled grid of the scene, with its materials and
+                     in clay, at 1, 2 and 4 samples
+  --watch            re-render at draft settings whenever SCENE changes";
+
+struct Options {
+    scene: String,
+    output: Option<String>,
+    width: Option<usize>,
+    height: Option<usize>,
+    samples: Option<usize>,
+    passes: Option<usize>,
+    importance: Option<String>,
+    preview: bool,
+    threads: Option<usize>,
+    clay: bool,
+    wireframe: bool,
+    contact_sheet: bool,
+    watch: bool,
+}
+
+fn parse_args(args: &[String]) -> Result<Options, String> {
+    let mut options = Options {
+        scene: String::new(),
+        output: None,
+        width: None,
+        height: None,
+        samples: None,
+        passes: None,
+        importance: None,
+        preview: false,
+        threads: None,
+        clay: false,
+        wireframe: false,
+        contact_sheet: false,
+        watch: false,
+    };
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        let mut value = |name: &str| {
+            args.next()
+                .cloned()
+                .ok_or_else(|| format!("missing value for {}", name))
+        };
+        let number = |name: &str, v: String| {
+            v.parse::<usize>()
+                .ok()
+                .filter(|n| *n > 0)
+                .ok_or_else(|| format!("invalid value '{}' for {}", v, name))
+        };
+        match arg.as_str() {
+            "-o" | "--output" => options.output = Some(value(arg)?),
+            "--width" => options.width = Some(number(arg, value(arg)?)?),
+            "--height" => options.height = Some(number(arg, value(arg)?)?),
+            "--samples" => options.samples = Some(number(arg, value(arg)?)?),
+            "--passes" => options.passes = Some(number(arg, value(arg)?)?),
+            "--threads" => options.threads = Some(number(arg, value(arg)?)?),
+            "--importance" => options.importance = Some(value(arg)?),
+            "--preview" => options.preview = true,
+            "--clay" => options.clay = true,
+            "--wireframe" => options.wireframe = true,
+            "--contact-sheet" => options.contact_sheet = true,
+            "--watch" => options.watch = true,
+            "-h" | "--help" => return Err(String::new()),
+            _ if arg.starts_with('-') => return Err(format!("unknown option {}", arg)),
+            _ if options.scene.is_empty() => options.scene = arg.clone(),
+            _ => return Err(format!("unexpected argument {}", arg)),
+        }
+    }
+    if options.scene.is_empty() {
+        return Err(String::from("missing scene file"));
+    }
+    Ok(options)
+}
+
+fn output_path(options: &Options) -> String {
+    options.output.clone().unwrap_or_else(|| {
+        Path::new(&options.scene)
+            .with_extension("ppm")
+            .to_string_lossy()
+            .into_owned()
+    })
+}
+
+fn render(options: &Options, draft: bool) -> Result<(), String> {
+    let mut scene = parse_scene_file_path(&options.scene)?;
+    let aspect = scene.height as Float / scene.width as Float;
+    match (options.width, options.height) {
+        (Some(w), Some(h)) => (scene.width, scene.height) = (w, h),
+        (Some(w), None) => (scene.width, scene.height) = (w, ((w as Float * aspect) as usize).max(1)),
+        (None, Some(h)) => (scene.width, scene.height) = (((h as Float / aspect) as usize).max(1), h),
+        (None, None) => {}
+    }
+    if options.clay {
+        scene.world.material_override = Some(Material::clay());
+    }
+    let mut camera = scene.camera();
+    if let Some(samples) = options.samples {
+        camera.oversampling = samples;
+    }
+    if draft {
+        camera.oversampling = 1;
+        camera.render_depth = DRAFT_DEPTH;
+    }
+    camera.threads = options
+        .threads
+        .unwrap_or_else(|| std::thread::available_parallelism().map_or(1, |n| n.get()));
+    let output = output_path(options);
+    if options.contact_sheet && !draft {
+        let sheet = render_contact_sheet(camera, &mut scene.world, &CONTACT_SHEET_SAMPLES);
+        return std::fs::write(&output, sheet.to_ppm()).map_err(|e| format!("{}: {}", output, e));
+    }
+    let wires = options
+        .wireframe
+        .then(|| camera.wireframe(&mut scene.world, WIREFRAME_ISOLINES, WHITE));
+    let write = |image: &Canvas| {
+        let mut image = image.clone();
+        if let Some(wires) = &wires {
+            image.over(wires, 0, 0);
+        }
+        std::fs::write(&output, image.to_ppm()).map_err(|e| format!("{}: {}", output, e))
+    };
+    match options.passes.filter(|_| !draft) {
+        Some(passes) => {
+            let mut result = Ok(());
+            camera.render_progressive(&mut scene.world, passes, |image, pass| {
+                eprintln!("rt: pass {}/{}", pass, passes);
+                result = write(image);
+                result.is_ok()
+            });
+            result
+        }
+        None if options.preview && !draft => {
+            let mut result = Ok(());
+            camera.render_pyramid(&mut scene.world, |image, spacing| {
+                eprintln!("rt: 1/{} resolution", spacing);
+                result = write(image);
+                result.is_ok()
+            });
+            result
+        }
+        None => match options.importance.as_ref().filter(|_| !draft) {
+            Some(map) => {
+                let importance = importance_map(map, camera, &mut scene.world)?;
+                let samples = camera.oversampling;
+                camera.oversampling = 1;
+                write(&camera.render_importance(&mut scene.world, &importance, samples))
+            }
+            None => write(&camera.render(&mut scene.world)),
+        },
+    }
+}
+
+fn importance_map(map: &str, mut camera: Camera, world: &mut World) -> Result<Canvas, String> {
+    if map == "saliency" {
+        camera.oversampling = 1;
+        return Ok(camera.render(world).saliency());
+    }
+    let ppm = std::fs::read_to_string(map).map_err(|e| format!("{}: {}", map, e))?;
+    Canvas::from_ppm(&ppm).map_err(|e| format!("{}: {}", map, e))
+}
+
+fn modified(path: &str) -> Option<SystemTime> {
+    std::fs::metadata(path).and_then(|m| m.modified()).ok()
+}
+
+fn watch(options: &Options) -> ! {
+    let mut last_modified = None;
+    loop {
+        let current = modified(&options.scene);
+        if current.is_some() && current != last_modified {
+            last_modified = current;
+            let start = Instant::now();
+            match render(options, true) {
+                Ok(()) => eprintln!(
+                    "rt: rendered {} in {:.2}s",
+                    output_path(options),
+                    start.elapsed().as_secs_f64()
+                ),
+                Err(e) => eprintln!("rt: {}", e),
+            }
+            eprintln!("rt: watching {} for changes", options.scene);
+        }
+        thread::sleep(WATCH_INTERVAL);
+    }
+}
+
+fn main() {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let options = parse_args(&args).unwrap_or_else(|e| {
+        if !e.is_empty() {
+            eprintln!("rt: {}", e);
+        }
+        eprintln!("{}", USAGE);
+        exit(2);
+    });
+    if options.watch {
+        watch(&options);
+    }
+    if let Err(e) = render(&options, false) {
+        eprintln!("rt: {}", e);
+        exit(1);
+    }
+}
@@ -2,6 +2,7 @@ use crate::floats::{Float, EPSILON};
 use crate::matrices::Matrix;
 use crate::rays::Ray;
 use crate::tuples::Tuple;
+use std::ops;
 
 #[derive(Debug, Clone, PartialEq)]
 pub struct Bounds {
@@ -20,6 +21,12 @@ impl Default for Bounds {
 
 impl Bounds {
     pub fn intersect(&self, ray: &Ray) -> bool {
+        self.span(ray).is_some()
+    }
+
+    // The range of t where the ray is inside the bounds, within the range
+    // of the ray.
+    pub fn span(&self, ray: &Ray) -> Option<(Float, Float)> {
         let (xtmin, xtmax) = check_axis(
             ray.origin.x(),
             ray.direction.x(),
@@ -40,104 +47,187 @@ impl Bounds {
         );
         let tmin = xtmin.max(ytmin).max(ztmin);
         let tmax = xtmax.min(ytmax).min(ztmax);
-        tmin <= tmax
+        (tmin <= tmax && tmin <= ray.t_max && tmax >= ray.t_min)
+            .then(|| (tmin.max(ray.t_min), tmax.min(ray.t_max)))
     }
 
     pub fn transform(&self, transform: &Matrix<4>) -> Bounds {
-        let min = self.min;
-        let max = self.max;
-        let (min_x, min_y, min_z) = (min.x(), min.y(), min.z());
-        let (max_x, max_y, max_z) = (max.x(), max.y(), max.z());
-        let corners = (
-            *transform * Tuple::point(min_x, min_y, min_z),
-            *transform * Tuple::point(min_x, min_y, max_z),
-            *transform * Tuple::point(min_x, max_y, min_z),
-            *transform * Tuple::point(min_x, max_y, max_z),
-            *transform * Tuple::point(max_x, min_y, min_z),
-            *transform * Tuple::point(max_x, min_y, max_z),
-            *transform * Tuple::point(max_x, max_y, min_z),
-            *transform * Tuple::point(max_x, max_y, max_z),
-        );
-        let min_x = corners
-            .0
-            .x()
-            .min(corners.1.x())
-            .min(corners.2.x())
-            .min(corners.3.x())
-            .min(corners.4.x())
-            .min(corners.5.x())
-            .min(corners.6.x())
-            .min(corners.7.x());
-        let min_y = corners
-            .0
-            .y()
-            .min(corners.1.y())
-            .min(corners.2.y())
-            .min(corners.3.y())
-            .min(corners.4.y())
-            .min(corners.5.y())
-            .min(corners.6.y())
-            .min(corners.7.y());
-        let min_z = corners
-            .0
-            .z()
-            .min(corners.1.z())
-            .min(corners.2.z())
-            .min(corners.3.z())
-            .min(corners.4.z())
-            .min(corners.5.z())
-            .min(corners.6.z())
-            .min(corners.7.z());
-        let max_x = corners
-            .0
-            .x()
-            .max(corners.1.x())
-            .max(corners.2.x())
-            .max(corners.3.x())
-            .max(corners.4.x())
-            .max(corners.5.x())
-            .max(corners.6.x())
-            .max(corners.7.x());
-        let max_y = corners
-            .0
-            .y()
-            .max(corners.1.y())
-            .max(corners.2.y())
-            .max(corners.3.y())
-            .max(corners.4.y())
-            .max(corners.5.y())
-            .max(corners.6.y())
-            .max(corners.7.y());
-        let max_z = corners
-            .0
-            .z()
-            .max(corners.1.z())
-            .max(corners.2.z())
-            .max(corners.3.z())
-            .max(corners.4.z())
-            .max(corners.5.z())
-            .max(corners.6.z())
-            .max(corners.7.z());
+        let mut bounds = Bounds::empty();
+        for corner in self.corners() {
+            bounds.expand(*transform * corner);
+        }
+        bounds
+    }
+
+    pub fn corners(&self) -> [Tuple; 8] {
+        let (min, max) = (self.min, self.max);
+        [0, 1, 2, 3, 4, 5, 6, 7].map(|i| {
+            Tuple::point(
+                if i & 4 == 0 { min.x() } else { max.x() },
+                if i & 2 == 0 { min.y() } else { max.y() },
+                if i & 1 == 0 { min.z() } else { max.z() },
+            )
+        })
+    }
+
+    pub fn empty() -> Bounds {
         Bounds {
-            min: Tuple::point(min_x, min_y, min_z),
-            max: Tuple::point(max_x, max_y, max_z),
+            min: Tuple::point(Float::INFINITY, Float::INFINITY, Float::INFINITY),
+            max: Tuple::point(-Float::INFINITY, -Float::INFINITY, -Float::INFINITY),
+        }
+    }
+
+    pub fn centroid(&self) -> Tuple {
+        Tuple::point(
+            (self.min.x() + self.max.x()) / 2.0,
+            (self.min.y() + self.max.y()) / 2.0,
+            (self.min.z() + self.max.z()) / 2.0,
+        )
+    }
+
+    pub fn is_finite(&self) -> bool {
+        [self.min, self.max]
+            .iter()
+            .all(|p| p.x().is_finite() && p.y().is_finite() && p.z().is_finite())
+    }
+
+    pub fn surface_area(&self) -> Float {
+        let d = self.max - self.min;
+        if d.x() < 0.0 || d.y() < 0.0 || d.z() < 0.0 {
+            return 0.0;
+        }
+        2.0 * (d.x() * d.y() + d.y() * d.z() + d.z() * d.x())
+    }
+
+    pub fn clip(&self, other: &Bounds) -> Bounds {
+        Bounds {
+            min: self.min.max(other.min),
+            max: self.max.min(other.max),
         }
     }
 
     pub fn merge(&mut self, other: &Bounds) {
-        self.min = Tuple::point(
-            self.min.x().min(other.min.x()),
-            self.min.y().min(other.min.y()),
-            self.min.z().min(other.min.z()),
-        );
-        self.max = Tuple::point(
-            self.max.x().max(other.max.x()),
-            self.max.y().max(other.max.y()),
-            self.max.z().max(other.max.z()),
-        );
+        self.min = self.min.min(other.min);
+        self.max = self.max.max(other.max);
+    }
+
+    pub fn union(&self, other: &Bounds) -> Bounds {
+        let mut union = self.clone();
+        union.merge(other);
+        union
+    }
+
+    // Grows the bounds to include `point`.
+    pub fn expand(&mut self, point: Tuple) {
+        self.min = self.min.min(point);
+        self.max = self.max.max(point);
+    }
+
+    pub fn contains(&self, point: Tuple) -> bool {
+        (0..3).all(|i| self.min[i] <= point[i] && point[i] <= self.max[i])
+    }
+}
+
+impl ops::BitOr for &Bounds {
+    type Output = Bounds;
+
+    fn bitor(self, other: &Bounds) -> Bounds {
+        self.union(other)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BoundingSphere {
+    pub center: Tuple,
+    pub radius: Float,
+}
+
+impl BoundingSphere {
+    pub fn from_bounds(bounds: &Bounds) -> BoundingSphere {
+        let center = bounds.centroid();
+        BoundingSphere {
+            center,
+            radius: (bounds.max - center).magnitude(),
+        }
+    }
+
+    pub fn transform(&self, transform: &Matrix<4>) -> BoundingSphere {
+        let scale = (0..3)
+            .map(|j| {
+                Tuple::vector(transform[(0, j)], transform[(1, j)], transform[(2, j)]).magnitude()
+            })
+            .fold(0.0, Float::max);
+        BoundingSphere {
+            center: *transform * self.center,
+            radius: self.radius * scale,
+        }
+    }
+
+    pub fn to_bounds(&self) -> Bounds {
+        let r = self.radius;
+        Bounds {
+            min: self.center - Tuple::vector(r, r, r),
+            max: self.center + Tuple::vector(r, r, r),
+        }
+    }
+
+    pub fn intersect(&self, ray: &Ray) -> bool {
+        let to_ray = ray.origin - self.center;
+        let a = ray.direction.dot(ray.direction);
+        let b = 2.0 * ray.direction.dot(to_ray);
+        let c = to_ray.dot(to_ray) - (self.radius + EPSILON).powi(2);
+        let discriminant = b * b - 4.0 * a * c;
+        discriminant >= 0.0 && (c <= 0.0 || b <= 0.0)
     }
 }
 
+pub fn sah_partition(bounds: &[Bounds]) -> Option<(Vec<usize>, Vec<usize>)> {
+    let n = bounds.len();
+    if n < 2 {
+        return None;
+    }
+    let mut total = Bounds::empty();
+    for b in bounds {
+        total.merge(b);
+    }
+    let sorted = |axis: usize| {
+        let mut order: Vec<usize> = (0..n).collect();
+        order.sort_by(|&i, &j| {
+            let ci = bounds[i].centroid();
+            let cj = bounds[j].centroid();
+            [ci.x(), ci.y(), ci.z()][axis].total_cmp(&[cj.x(), cj.y(), cj.z()][axis])
+        });
+        order
+    };
+    let mut best: Option<(Float, usize, usize)> = None;
+    for axis in 0..3 {
+        let order = sorted(axis);
+        let mut left_areas = vec![0.0; n];
+        let mut acc = Bounds::empty();
+        for (k, &i) in order.iter().enumerate() {
+            acc.merge(&bounds[i]);
+            left_areas[k] = acc.surface_area();
+        }
+        let mut acc = Bounds::empty();
+        for k in (1..n).rev() {
+            acc.merge(&bounds[order[k]]);
+            let cost = left_areas[k - 1] * k as Float + acc.surface_area() * (n - k) as Float;
+            if best.is_none_or(|(c, _, _)| cost < c) {
+                best = Some((cost, axis, k));
+            }
+        }
+    }
+    let (cost, axis, split) = best?;
+    if cost >= total.surface_area() * n as Float || cost.is_nan() {
+        return None;
+    }
+    // Only the order along the best axis is kept, sorting it again is
+    // cheaper than copying each better candidate.
+    let mut left = sorted(axis);
+    let right = left.split_off(split);
+    Some((left, right))
+}
+
 fn check_axis(origin: Float, direction: Float, min: Float, max: Float) -> (Float, Float) {
     let tmin_numerator = min - origin - EPSILON;
     let tmax_numerator = max - origin + EPSILON;
@@ -156,3 +246,123 @@ fn check_axis(origin: Float, direction: Float, min: Float, max: Float) -> (Float
         (tmin, tmax)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::floats::{equals, PI, SQRT_3};
+    use crate::transformations::{rotation_y, scaling, translation};
+
+    fn cube_at(x: Float) -> Bounds {
+        Bounds {
+            min: Tuple::point(x - 1.0, -1.0, -1.0),
+            max: Tuple::point(x + 1.0, 1.0, 1.0),
+        }
+    }
+
+    #[test]
+    fn the_corners_of_bounds() {
+        let b = Bounds {
+            min: Tuple::point(-1.0, -2.0, -3.0),
+            max: Tuple::point(1.0, 2.0, 3.0),
+        };
+        let corners = b.corners();
+        assert_eq!(corners[0], b.min);
+        assert_eq!(corners[7], b.max);
+        assert_eq!(corners[5], Tuple::point(1.0, -2.0, 3.0));
+        for corner in corners {
+            assert!(b.contains(corner));
+        }
+        assert!(!b.contains(Tuple::point(0.0, 2.5, 0.0)));
+    }
+
+    #[test]
+    fn growing_bounds() {
+        let mut b = Bounds::empty();
+        assert!(!b.contains(Tuple::point(0.0, 0.0, 0.0)));
+        b.expand(Tuple::point(1.0, 0.0, -1.0));
+        b.expand(Tuple::point(-1.0, 2.0, 0.0));
+        assert_eq!(b.min, Tuple::point(-1.0, 0.0, -1.0));
+        assert_eq!(b.max, Tuple::point(1.0, 2.0, 0.0));
+        let union = &cube_at(0.0) | &cube_at(4.0);
+        assert_eq!(union, cube_at(0.0).union(&cube_at(4.0)));
+        assert_eq!(union.min, Tuple::point(-1.0, -1.0, -1.0));
+        assert_eq!(union.max, Tuple::point(5.0, 1.0, 1.0));
+        assert_eq!(union.surface_area(), 2.0 * (12.0 + 4.0 + 12.0));
+        assert_eq!(union.centroid(), Tuple::point(2.0, 0.0, 0.0));
+    }
+
+    #[test]
+    fn the_surface_area_and_centroid_of_bounds() {
+        let b = cube_at(3.0);
+        assert_eq!(b.surface_area(), 24.0);
+        assert_eq!(b.centroid(), Tuple::point(3.0, 0.0, 0.0));
+        assert_eq!(Bounds::empty().surface_area(), 0.0);
+    }
+
+    #[test]
+    fn clipping_bounds_keeps_their_overlap() {
+        let b = cube_at(0.0).clip(&cube_at(1.0));
+        assert_eq!(b.min, Tuple::point(0.0, -1.0, -1.0));
+        assert_eq!(b.max, Tuple::point(1.0, 1.0, 1.0));
+    }
+
+    #[test]
+    fn a_bounding_sphere_encloses_bounds() {
+        let s = BoundingSphere::from_bounds(&cube_at(2.0));
+        assert_eq!(s.center, Tuple::point(2.0, 0.0, 0.0));
+        assert_eq!(s.radius, SQRT_3);
+        let s = s.transform(
+            &(translation(0.0, 1.0, 0.0) * rotation_y(PI / 4.0) * scaling(2.0, 1.0, 1.0)),
+        );
+        assert!(equals(s.radius, 2.0 * SQRT_3));
+        assert_eq!(
+            s.to_bounds().max - s.to_bounds().min,
+            Tuple::vector(4.0 * SQRT_3, 4.0 * SQRT_3, 4.0 * SQRT_3)
+        );
+    }
+
+    #[test]
+    fn intersecting_a_bounding_sphere() {
+        let s = BoundingSphere::from_bounds(&cube_at(0.0));
+        let hit = Ray::new(Tuple::point(0.0, 1.5, -5.0), Tuple::vector(0.0, 0.0, 1.0));
+        let miss = Ray::new(Tuple::point(0.0, 2.0, -5.0), Tuple::vector(0.0, 0.0, 1.0));
+        let behind = Ray::new(Tuple::point(0.0, 0.0, 5.0), Tuple::vector(0.0, 0.0, 1.0));
+        let inside = Ray::new(Tuple::point(0.0, 0.0, 0.0), Tuple::vector(0.0, 0.0, 1.0));
+        assert!(s.intersect(&hit));
+        assert!(!s.intersect(&miss));
+        assert!(!s.intersect(&behind));
+        assert!(s.intersect(&inside));
+    }
+
+    #[test]
+    fn partitioning_bounds_with_the_surface_area_heuristic() {
+        let bounds = [cube_at(10.0), cube_at(0.0), cube_at(11.0), cube_at(1.0)];
+        let (mut left, mut right) = sah_partition(&bounds).unwrap();
+        left.sort();
+        right.sort();
+        assert_eq!((left, right), (vec![1, 3], vec![0, 2]));
+    }
+
+    #[test]
+    fn overlapping_bounds_are_not_partitioned() {
+        let bounds = [cube_at(0.0), cube_at(0.0), cube_at(0.0)];
+        assert!(sah_partition(&bounds).is_none());
+        assert!(sah_partition(&[cube_at(0.0)]).is_none());
+    }
+
+    #[test]
+    fn the_span_of_a_ray_through_bounds() {
+        let b = cube_at(0.0);
+        let r = Ray::new(Tuple::point(0.0, 0.0, -5.0), Tuple::vector(0.0, 0.0, 1.0));
+        // The bounds are padded a little against rounding errors.
+        let close = |(t0, t1): (Float, Float), (e0, e1): (Float, Float)| {
+            (t0 - e0).abs() < 0.001 && (t1 - e1).abs() < 0.001
+        };
+        assert!(close(b.span(&r).unwrap(), (4.0, 6.0)));
+        assert!(close(b.span(&r.with_range(5.0, 10.0)).unwrap(), (5.0, 6.0)));
+        assert_eq!(b.span(&r.with_range(0.0, 3.0)), None);
+        let miss = Ray::new(Tuple::point(0.0, 2.0, -5.0), Tuple::vector(0.0, 0.0, 1.0));
+        assert_eq!(b.span(&miss), None);
+    }
+}
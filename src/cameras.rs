@@ -1,26 +1,121 @@
-use crate::canvas::Canvas;
-use crate::floats::{rand, Float};
+use crate::canvas::{contact_sheet, Accumulator, Canvas, TiledCanvas};
+use crate::colors::{Color, ColorSpace, BLACK};
+use crate::floats::{rand, Float, EPSILON};
+use crate::materials::Material;
 use crate::matrices::Matrix;
-use crate::rays::Ray;
+use crate::objects::Object;
+use crate::rays::{Ray, RayDifferentials};
+use crate::render_contexts::RenderContext;
+use crate::transformations::{rotation_x, rotation_y, view_transform};
 use crate::tuples::Tuple;
-use crate::worlds::World;
+use crate::worlds::{RayTrace, World};
 use indicatif::ProgressBar;
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::mpsc;
 use std::sync::{Arc, Mutex};
 use std::thread;
 
+const PYRAMID_SPACINGS: [usize; 4] = [8, 4, 2, 1];
+
+// A render that may have been stopped early.
+#[derive(Clone)]
+pub struct PartialRender {
+    pub image: Canvas,
+    // Whether each pixel was rendered, row by row.
+    pub done: Vec<bool>,
+}
+
+impl PartialRender {
+    pub fn is_done(&self, x: usize, y: usize) -> bool {
+        self.done[y * self.image.width() + x]
+    }
+
+    pub fn is_complete(&self) -> bool {
+        self.done.iter().all(|d| *d)
+    }
+}
+
+// Settings of a real camera, for scenes lit in physical units. Scene
+// luminances are scaled so that the brightest one the settings can take
+// without clipping is 1, as for a sensor of saturation based sensitivity.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PhysicalExposure {
+    pub iso: Float,
+    // In seconds.
+    pub shutter: Float,
+    pub f_number: Float,
+}
+
+impl Default for PhysicalExposure {
+    // The sunny 16 rule.
+    fn default() -> PhysicalExposure {
+        PhysicalExposure {
+            iso: 100.0,
+            shutter: 1.0 / 100.0,
+            f_number: 16.0,
+        }
+    }
+}
+
+impl PhysicalExposure {
+    // The exposure value at ISO 100.
+    pub fn ev100(&self) -> Float {
+        (self.f_number * self.f_number / self.shutter * 100.0 / self.iso).log2()
+    }
+
+    pub fn stops(&self) -> Float {
+        -self.ev100() - (1.2 as Float).log2()
+    }
+}
+
+// The surface seen through the center of a pixel, see `Camera::pick`.
+#[derive(Debug, Clone)]
+pub struct PickResult<'a> {
+    // Index in `World::objects` of the object hit, or of its top group.
+    pub id: usize,
+    // The name of the primitive hit, or else of its top object.
+    pub name: Option<&'a str>,
+    pub object: &'a Object,
+    pub point: Tuple,
+    pub normal: Tuple,
+    pub t: Float,
+}
+
 #[derive(Debug, Clone, Copy)]
 pub struct Camera {
+    // Distance from the lens to the image plane.
     focal_length: Float,
     pub aperture: Float,
+    // Distance from the lens to the plane in focus. It starts at the focal
+    // length, and may be moved without changing the field of view.
+    pub focus_distance: Float,
     hsize: usize,
     vsize: usize,
     half_width: Float,
     half_height: Float,
     pixel_size: Float,
+    pub max_sample_value: Float,
+    pub exposure: Float,
+    pub white_balance: Option<Float>,
+    pub color_space: ColorSpace,
     pub blur_oversampling: usize,
+    pub differentials: bool,
     pub oversampling: usize,
     pub render_depth: usize,
     pub threads: usize,
+    // Slides the image across the lens, in fractions of its width and
+    // height, to frame a tall building without converging verticals.
+    pub shift: (Float, Float),
+    // Turns the camera around its view direction, counterclockwise.
+    pub roll: Float,
+    // Radial distortion coefficients of r² and r⁴, r being the distance to
+    // the lens axis over the focal length. Positive values bulge the image
+    // like a wide angle lens (barrel), negative ones pinch it (pincushion).
+    pub distortion: (Float, Float),
+    // Turns the plane of focus around the x and y axes of the camera, to
+    // follow a receding floor, or to cut across it for a miniature look.
+    pub tilt: (Float, Float),
     transform_inv: Matrix<4>,
 }
 
@@ -42,23 +137,64 @@ impl Camera {
         Camera {
             focal_length,
             aperture: 0.0,
+            focus_distance: focal_length,
             hsize,
             vsize,
             half_width,
             half_height,
             pixel_size: half_width * 2.0 / hsize as Float,
             blur_oversampling: 1,
+            max_sample_value: Float::INFINITY,
+            exposure: 0.0,
+            white_balance: None,
+            color_space: ColorSpace::Linear,
+            differentials: false,
             oversampling: 2,
             render_depth: 5,
             threads: 1,
+            shift: (0.0, 0.0),
+            roll: 0.0,
+            distortion: (0.0, 0.0),
+            tilt: (0.0, 0.0),
             transform_inv: transform.inverse(),
         }
     }
 
+    pub fn with_physical_exposure(self, settings: PhysicalExposure) -> Camera {
+        Camera {
+            exposure: self.exposure + settings.stops(),
+            ..self
+        }
+    }
+
+    // Moves the camera back along `direction` until the bounding sphere of the
+    // scene fits in its narrowest field of view.
+    pub fn frame(self, world: &mut World, direction: Tuple) -> Camera {
+        world.prepare();
+        let bounds = world.bounds();
+        if !bounds.is_finite() {
+            return self;
+        }
+        let center = bounds.centroid();
+        let radius = ((bounds.max - bounds.min).magnitude() / 2.0).max(EPSILON);
+        let half_view = (self.half_width.min(self.half_height) / self.focal_length).atan();
+        let direction = direction.normalize();
+        let from = center - direction * (radius / half_view.sin());
+        let up = if direction.cross(Tuple::vector(0.0, 1.0, 0.0)).magnitude() < EPSILON {
+            Tuple::vector(0.0, 0.0, 1.0)
+        } else {
+            Tuple::vector(0.0, 1.0, 0.0)
+        };
+        Camera {
+            transform_inv: view_transform(from, center, up).inverse(),
+            ..self
+        }
+    }
+
     fn rays_for_coordinates(&self, x_offset: Float, y_offset: Float, rays: &mut Vec<Ray>) {
         let lens_x = self.half_width - x_offset;
         let lens_y = self.half_height - y_offset;
-        let pixel = self.transform_inv * Tuple::point(lens_x, lens_y, -self.focal_length);
+        let pixel = self.transform_inv * self.focus_point(lens_x, lens_y);
         let aperture = self.focal_length * self.aperture;
         for _ in 0..self.blur_oversampling {
             let lens_origin = Tuple::point(0.0, 0.0, 0.0)
@@ -69,8 +205,45 @@ impl Camera {
                 };
             let origin = self.transform_inv * lens_origin;
             let direction = (pixel - origin).normalize();
-            rays.push(Ray::new(origin, direction));
+            let ray = Ray::new(origin, direction).with_spread(self.spread());
+            if self.differentials {
+                let step = self.pixel_size / self.oversampling.max(1) as Float;
+                let offset = |dx: Float, dy: Float| {
+                    let pixel = self.transform_inv * self.focus_point(lens_x - dx, lens_y - dy);
+                    (pixel - origin).normalize() - direction
+                };
+                rays.push(ray.with_differentials(RayDifferentials::new(
+                    offset(step, 0.0),
+                    offset(0.0, step),
+                )));
+            } else {
+                rays.push(ray);
+            }
+        }
+    }
+    // The point in focus seen through (lens_x, lens_y) of the image plane,
+    // in camera space. The x axis points to the left of the image.
+    fn focus_point(&self, lens_x: Float, lens_y: Float) -> Tuple {
+        let x = lens_x - self.shift.0 * 2.0 * self.half_width;
+        let y = lens_y + self.shift.1 * 2.0 * self.half_height;
+        let r2 = (x * x + y * y) / (self.focal_length * self.focal_length);
+        let scale = (1.0 + self.distortion.0 * r2 + self.distortion.1 * r2 * r2).max(0.0);
+        let (x, y) = (x * scale, y * scale);
+        let (sin, cos) = self.roll.sin_cos();
+        let direction = Tuple::vector(x * cos + y * sin, y * cos - x * sin, -self.focal_length);
+        let mut t = self.focus_distance / self.focal_length;
+        if self.tilt != (0.0, 0.0) {
+            let normal =
+                rotation_x(self.tilt.0) * rotation_y(self.tilt.1) * Tuple::vector(0.0, 0.0, 1.0);
+            let tilted = -self.focus_distance * normal.z() / normal.dot(direction);
+            if tilted.is_finite() && tilted > 0.0 {
+                t = tilted;
+            }
         }
+        Tuple::point(0.0, 0.0, 0.0) + direction * t
+    }
+    fn spread(&self) -> Float {
+        self.pixel_size / self.focal_length / self.oversampling.max(1) as Float
     }
     fn rays_for_pixel(&self, x: usize, y: usize) -> Vec<Ray> {
         let mut rays = Vec::with_capacity(self.oversampling.pow(2) * self.blur_oversampling);
@@ -86,52 +259,471 @@ impl Camera {
         rays
     }
 
+    fn rays_for_pass(&self, x: usize, y: usize) -> Vec<Ray> {
+        let mut rays = Vec::with_capacity(self.blur_oversampling);
+        let x_offset = (x as Float + 0.5 + rand(0.5)) * self.pixel_size;
+        let y_offset = (y as Float + 0.5 + rand(0.5)) * self.pixel_size;
+        self.rays_for_coordinates(x_offset, y_offset, &mut rays);
+        rays
+    }
+
+    fn render_row<F>(
+        &self,
+        world: &World,
+        y: usize,
+        x0: usize,
+        x1: usize,
+        rays_for: F,
+    ) -> Vec<Color>
+    where
+        F: Fn(usize, usize) -> Vec<Ray>,
+    {
+        let pixels: Vec<(usize, usize)> = (x0..x1).map(|x| (x, y)).collect();
+        self.render_pixels(world, &pixels, rays_for)
+    }
+
+    fn render_pixels<F>(&self, world: &World, pixels: &[(usize, usize)], rays_for: F) -> Vec<Color>
+    where
+        F: Fn(usize, usize) -> Vec<Ray>,
+    {
+        // Pixels may not all take the same number of samples.
+        let mut rays = vec![];
+        let mut ends = Vec::with_capacity(pixels.len());
+        for &(x, y) in pixels {
+            rays.extend(rays_for(x, y));
+            ends.push(rays.len());
+        }
+        let colors = world.color_at_batch(&rays, self.render_depth);
+        let mut start = 0;
+        ends.into_iter()
+            .map(|end| {
+                let samples = &colors[start..end];
+                start = end;
+                samples
+                    .iter()
+                    .fold(BLACK, |a, b| a + b.clamped(self.max_sample_value))
+                    * (1.0 / samples.len().max(1) as Float)
+            })
+            .collect()
+    }
+
+    pub fn debug_pixel(self, world: &mut World, x: usize, y: usize) -> Vec<RayTrace> {
+        world.prepare();
+        self.rays_for_pixel(x, y)
+            .iter()
+            .map(|ray| world.debug_ray(ray, self.render_depth))
+            .collect()
+    }
+
+    // The world must have been prepared first.
+    pub fn pick<'a>(&self, world: &'a World, x: usize, y: usize) -> Option<PickResult<'a>> {
+        if x >= self.hsize || y >= self.vsize {
+            return None;
+        }
+        let pinhole = Camera {
+            blur_oversampling: 1,
+            differentials: false,
+            ..*self
+        };
+        let mut rays = vec![];
+        pinhole.rays_for_coordinates(
+            (x as Float + 0.5) * self.pixel_size,
+            (y as Float + 0.5) * self.pixel_size,
+            &mut rays,
+        );
+        let hit = world.trace(&rays[0], 0)?;
+        let id = world.objects.iter().position(|o| o.includes(hit.object))?;
+        Some(PickResult {
+            id,
+            name: hit
+                .object
+                .name
+                .as_deref()
+                .or(world.objects[id].name.as_deref()),
+            object: hit.object,
+            point: hit.point,
+            normal: hit.normal,
+            t: hit.t,
+        })
+    }
+
     pub fn render(self, world: &mut World) -> Canvas {
-        let mut world = world.clone();
+        self.render_region(world, 0, 0, self.hsize, self.vsize)
+    }
+
+    // Renders with the threads of `context` instead of starting new ones.
+    // The rows whose jobs panicked are left out of the render.
+    pub fn render_in(self, world: &mut World, context: &RenderContext) -> PartialRender {
         world.prepare();
-        let world = Arc::new(world);
-        let image = Arc::new(Mutex::new(Canvas::new(self.hsize, self.vsize)));
-        let mut handles = Vec::with_capacity(self.threads);
-        let chunk_size = self.vsize / self.threads;
-        let pb = Arc::new(Mutex::new(ProgressBar::new(self.vsize as u64)));
-        for i in 0..self.threads {
-            let pb = Arc::clone(&pb);
-            let world = Arc::clone(&world);
-            let image = Arc::clone(&image);
-            let handle = thread::spawn(move || {
-                for y in chunk_size * i..chunk_size * (i + 1) {
-                    for x in 0..self.hsize {
-                        let rays = self.rays_for_pixel(x, y);
-                        let color = rays
-                            .iter()
-                            .map(|ray| world.color_at(ray, self.render_depth))
-                            .reduce(|a, b| a + b)
-                            .unwrap()
-                            * (1.0 / rays.len() as Float);
-                        image.lock().unwrap().write_pixel(x, y, color);
-                    }
-                    pb.lock().unwrap().inc(1);
+        let shared = Arc::new(world.clone());
+        let (sender, receiver) = mpsc::channel();
+        for y in context.row_order.rows(self.vsize) {
+            let (world, sender) = (Arc::clone(&shared), sender.clone());
+            context.execute(move || {
+                let colors =
+                    self.render_row(&world, y, 0, self.hsize, |x, y| self.rays_for_pixel(x, y));
+                sender.send((y, colors)).unwrap();
+            });
+        }
+        drop(sender);
+        let pb = ProgressBar::new(self.vsize as u64);
+        let mut image = Canvas::new(self.hsize, self.vsize);
+        let mut done = vec![false; self.hsize * self.vsize];
+        for (y, colors) in receiver {
+            for (x, color) in colors.into_iter().enumerate() {
+                image.write_pixel(x, y, color);
+            }
+            done[y * self.hsize..(y + 1) * self.hsize].fill(true);
+            pb.inc(1);
+        }
+        pb.finish();
+        PartialRender {
+            image: self.develop(image),
+            done,
+        }
+    }
+
+    // Pixels take from `oversampling` up to `max_oversampling` squared
+    // samples, following the luminance of the `importance` map stretched
+    // over the image, e.g. a hand painted mask or the saliency of a draft.
+    // An empty map marks no pixel.
+    pub fn render_importance(
+        self,
+        world: &mut World,
+        importance: &Canvas,
+        max_oversampling: usize,
+    ) -> Canvas {
+        world.prepare();
+        let max_oversampling = max_oversampling.max(self.oversampling);
+        let pb = ProgressBar::new(self.vsize as u64);
+        let render = self.render_rows(
+            world,
+            (0, 0, self.hsize, self.vsize),
+            Some(&pb),
+            None,
+            |x, y| {
+                if importance.width() == 0 || importance.height() == 0 {
+                    return self.rays_for_pixel(x, y);
                 }
+                let weight = importance
+                    .pixel_at(
+                        x * importance.width() / self.hsize,
+                        y * importance.height() / self.vsize,
+                    )
+                    .to_xyz()[1]
+                    .clamp(0.0, 1.0);
+                let extra = (max_oversampling - self.oversampling) as Float * weight;
+                let camera = Camera {
+                    oversampling: self.oversampling + extra.round() as usize,
+                    ..self
+                };
+                camera.rays_for_pixel(x, y)
+            },
+        );
+        pb.finish();
+        render.image
+    }
+
+    pub fn render_clay(self, world: &mut World) -> Canvas {
+        let previous = world.material_override.replace(Material::clay());
+        let image = self.render(world);
+        world.material_override = previous;
+        image
+    }
+
+    pub fn render_wireframe(self, world: &mut World, isolines: Float, color: Color) -> Canvas {
+        let mut image = self.render(world);
+        image.over(&self.wireframe(world, isolines, color), 0, 0);
+        image
+    }
+
+    // Lines are drawn on pixels whose surface cell differs from the one on
+    // their right or below, so they stay one pixel wide at any distance.
+    pub fn wireframe(&self, world: &mut World, isolines: Float, color: Color) -> Canvas {
+        world.prepare();
+        let pinhole = Camera {
+            blur_oversampling: 1,
+            differentials: false,
+            ..*self
+        };
+        let mut rays = vec![];
+        let cells: Vec<_> = (0..self.vsize)
+            .flat_map(|y| (0..self.hsize).map(move |x| (x, y)))
+            .map(|(x, y)| {
+                rays.clear();
+                let x_offset = (x as Float + 0.5) * self.pixel_size;
+                let y_offset = (y as Float + 0.5) * self.pixel_size;
+                pinhole.rays_for_coordinates(x_offset, y_offset, &mut rays);
+                world.wire_cell(&rays[0], isolines)
+            })
+            .collect();
+        let mut image = Canvas::new(self.hsize, self.vsize);
+        for y in 0..self.vsize {
+            for x in 0..self.hsize {
+                let cell = cells[y * self.hsize + x];
+                let edge = (x + 1 < self.hsize && cells[y * self.hsize + x + 1] != cell)
+                    || (y + 1 < self.vsize && cells[(y + 1) * self.hsize + x] != cell);
+                image.write_pixel(x, y, color);
+                image.write_alpha(x, y, if edge { 1.0 } else { 0.0 });
+            }
+        }
+        image
+    }
+
+    pub fn render_region(
+        self,
+        world: &mut World,
+        x0: usize,
+        y0: usize,
+        x1: usize,
+        y1: usize,
+    ) -> Canvas {
+        assert!(
+            x0 <= x1 && x1 <= self.hsize && y0 <= y1 && y1 <= self.vsize,
+            "Render region is outside of the camera image"
+        );
+        world.prepare();
+        let pb = ProgressBar::new((y1 - y0) as u64);
+        let render = self.render_rows(world, (x0, y0, x1, y1), Some(&pb), None, |x, y| {
+            self.rays_for_pixel(x, y)
+        });
+        pb.finish();
+        render.image
+    }
+
+    // Renders tile by tile into a canvas mapped from `path`, so that only
+    // one tile of the image is in memory at a time.
+    pub fn render_tiled(
+        self,
+        world: &mut World,
+        path: &Path,
+        tile_size: usize,
+    ) -> Result<TiledCanvas, String> {
+        world.prepare();
+        let mut canvas = TiledCanvas::create(path, self.hsize, self.vsize, tile_size)?;
+        let tiles = canvas.tiles();
+        let pb = ProgressBar::new(tiles.len() as u64);
+        for (x0, y0, x1, y1) in tiles {
+            let tile = self.render_rows(world, (x0, y0, x1, y1), None, None, |x, y| {
+                self.rays_for_pixel(x, y)
             });
-            handles.push(handle);
+            canvas.write_tile(x0, y0, &tile.image);
+            pb.inc(1);
+        }
+        pb.finish();
+        canvas.flush()?;
+        Ok(canvas)
+    }
+
+    // Stops taking new rows once `cancel` is set, e.g. by another thread,
+    // and returns the rows finished so far.
+    pub fn render_cancellable(self, world: &mut World, cancel: &AtomicBool) -> PartialRender {
+        world.prepare();
+        self.render_rows(
+            world,
+            (0, 0, self.hsize, self.vsize),
+            None,
+            Some(cancel),
+            |x, y| self.rays_for_pixel(x, y),
+        )
+    }
+
+    pub fn render_pass(&self, world: &World) -> Canvas {
+        self.render_rows(world, (0, 0, self.hsize, self.vsize), None, None, |x, y| {
+            self.rays_for_pass(x, y)
+        })
+        .image
+    }
+
+    pub fn render_progressive<F>(
+        self,
+        world: &mut World,
+        max_passes: usize,
+        mut on_pass: F,
+    ) -> Canvas
+    where
+        F: FnMut(&Canvas, usize) -> bool,
+    {
+        world.prepare();
+        let mut accumulator = Accumulator::new(self.hsize, self.vsize);
+        for _ in 0..max_passes {
+            accumulator.add(&self.render_pass(world));
+            if !on_pass(&self.develop(accumulator.average()), accumulator.passes()) {
+                break;
+            }
+        }
+        self.develop(accumulator.average())
+    }
+
+    // Renders one pixel out of 8 in each direction first, then halves the
+    // spacing down to every pixel. Coarser levels render the same pixels as
+    // the full image, so the whole pyramid costs a single render. `on_level`
+    // sees each level blown up to the image size, with its spacing, and
+    // stops the render early by returning false.
+    pub fn render_pyramid<F>(self, world: &mut World, mut on_level: F) -> Canvas
+    where
+        F: FnMut(&Canvas, usize) -> bool,
+    {
+        world.prepare();
+        let mut image = Canvas::new(self.hsize, self.vsize);
+        let mut preview = image.clone();
+        for spacing in PYRAMID_SPACINGS {
+            let coarser = spacing * 2;
+            let pixels: Vec<(usize, usize)> = (0..self.vsize)
+                .step_by(spacing)
+                .flat_map(|y| (0..self.hsize).step_by(spacing).map(move |x| (x, y)))
+                .filter(|&(x, y)| {
+                    spacing == PYRAMID_SPACINGS[0] || x % coarser != 0 || y % coarser != 0
+                })
+                .collect();
+            let chunk_size = pixels.len().div_ceil(self.threads.max(1)).max(1);
+            let colors: Vec<Color> = thread::scope(|scope| {
+                let workers: Vec<_> = pixels
+                    .chunks(chunk_size)
+                    .map(|chunk| {
+                        let world = &*world;
+                        scope.spawn(move || {
+                            self.render_pixels(world, chunk, |x, y| self.rays_for_pixel(x, y))
+                        })
+                    })
+                    .collect();
+                workers
+                    .into_iter()
+                    .flat_map(|w| w.join().unwrap())
+                    .collect()
+            });
+            for (&(x, y), color) in pixels.iter().zip(colors) {
+                image.write_pixel(x, y, color);
+            }
+            for y in 0..self.vsize {
+                for x in 0..self.hsize {
+                    preview.write_pixel(x, y, image.pixel_at(x - x % spacing, y - y % spacing));
+                }
+            }
+            if !on_level(&self.develop(preview.clone()), spacing) {
+                break;
+            }
         }
-        for handle in handles {
-            handle.join().unwrap();
+        self.develop(preview)
+    }
+
+    fn develop(&self, image: Canvas) -> Canvas {
+        let image = image
+            .with_exposure(self.exposure)
+            .with_color_space(self.color_space);
+        match self.white_balance {
+            Some(kelvin) => image.with_white_balance(kelvin),
+            None => image,
         }
-        pb.lock().unwrap().finish();
-        match Arc::try_unwrap(image) {
-            Ok(image) => image.into_inner().unwrap(),
-            Err(_) => unreachable!(),
+    }
+
+    fn render_rows<F>(
+        &self,
+        world: &World,
+        (x0, y0, x1, y1): (usize, usize, usize, usize),
+        pb: Option<&ProgressBar>,
+        cancel: Option<&AtomicBool>,
+        rays_for: F,
+    ) -> PartialRender
+    where
+        F: Fn(usize, usize) -> Vec<Ray> + Sync,
+    {
+        let render = Mutex::new(PartialRender {
+            image: Canvas::new(x1 - x0, y1 - y0),
+            done: vec![false; (x1 - x0) * (y1 - y0)],
+        });
+        // Each thread takes the next row left, so that any thread count
+        // renders every row and fast rows do not leave threads idle.
+        let next_row = AtomicUsize::new(y0);
+        thread::scope(|scope| {
+            for _ in 0..self.threads.max(1) {
+                let (render, next_row) = (&render, &next_row);
+                let rays_for = &rays_for;
+                scope.spawn(move || loop {
+                    if cancel.is_some_and(|c| c.load(Ordering::Relaxed)) {
+                        break;
+                    }
+                    let y = next_row.fetch_add(1, Ordering::Relaxed);
+                    if y >= y1 {
+                        break;
+                    }
+                    let colors = self.render_row(world, y, x0, x1, rays_for);
+                    let mut render = render.lock().unwrap();
+                    for (x, color) in (x0..x1).zip(colors) {
+                        render.image.write_pixel(x - x0, y - y0, color);
+                    }
+                    let row = (y - y0) * (x1 - x0);
+                    render.done[row..row + x1 - x0].fill(true);
+                    if let Some(pb) = pb {
+                        pb.inc(1);
+                    }
+                });
+            }
+        });
+        let render = render.into_inner().unwrap();
+        PartialRender {
+            image: self.develop(render.image),
+            ..render
         }
     }
 }
 
+// Renders several views of the same world, e.g. front, side and top views,
+// preparing it and building its acceleration structure only once.
+pub fn render_all(cameras: &[Camera], world: &mut World) -> Vec<Canvas> {
+    world.prepare();
+    let pb = ProgressBar::new(cameras.iter().map(|c| c.vsize as u64).sum());
+    let images = cameras
+        .iter()
+        .map(|camera| {
+            camera
+                .render_rows(
+                    world,
+                    (0, 0, camera.hsize, camera.vsize),
+                    Some(&pb),
+                    None,
+                    |x, y| camera.rays_for_pixel(x, y),
+                )
+                .image
+        })
+        .collect();
+    pb.finish();
+    images
+}
+
+// Renders the world with its materials and in clay, with each number of
+// `samples`, one row per material mode, one column per number of samples.
+pub fn render_contact_sheet(camera: Camera, world: &mut World, samples: &[usize]) -> Canvas {
+    let cameras: Vec<Camera> = samples
+        .iter()
+        .map(|&samples| Camera {
+            oversampling: samples,
+            ..camera
+        })
+        .collect();
+    let previous = world.material_override.take();
+    let mut cells = vec![];
+    for (name, material) in [("materials", None), ("clay", Some(Material::clay()))] {
+        world.material_override = material;
+        let images = render_all(&cameras, world);
+        cells.extend(
+            samples
+                .iter()
+                .zip(images)
+                .map(|(samples, image)| (format!("{} {}x{}", name, samples, samples), image)),
+        );
+    }
+    world.material_override = previous;
+    contact_sheet(&cells, samples.len())
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::colors::Color;
-    use crate::floats::{equals, PI, SQRT_2};
-    use crate::transformations::{rotation_y, translation, view_transform};
+    use crate::colors::WHITE;
+    use crate::floats::{equals, PI, SQRT_2, SQRT_3};
+    use crate::render_contexts::RowOrder;
+    use crate::transformations::{rotation_y, scaling, translation, view_transform};
     use crate::worlds::tests::default_world;
 
     #[test]
@@ -192,6 +784,86 @@ mod tests {
         assert_eq!(r.direction, Tuple::vector(SQRT_2 / 2.0, 0.0, -SQRT_2 / 2.0));
     }
 
+    #[test]
+    fn constructing_rays_with_differentials() {
+        let mut c = Camera::new(201, 101, 1.0, PI / 2.0, Matrix::identity());
+        c.oversampling = 1;
+        assert_eq!(c.rays_for_pixel(100, 50)[0].differentials, None);
+        c.differentials = true;
+        let rs = c.rays_for_pixel(100, 50);
+        let d = rs[0].differentials.unwrap();
+        assert_eq!(d.dpdx, Tuple::vector(0.0, 0.0, 0.0));
+        assert!(equals(d.dddx.x(), -0.01));
+        assert!(equals(d.dddy.y(), -0.01));
+        assert!(equals(d.dddx.y(), 0.0));
+    }
+
+    #[test]
+    fn shifting_and_rolling_the_image() {
+        let mut c = Camera::new(201, 101, 1.0, PI / 2.0, Matrix::identity());
+        c.oversampling = 1;
+        c.shift = (0.0, 0.25);
+        let r = &c.rays_for_pixel(100, 50)[0];
+        assert_eq!(
+            r.direction,
+            Tuple::vector(0.0, c.half_height / 2.0, -1.0).normalize()
+        );
+        c.shift = (0.25, 0.0);
+        let r = &c.rays_for_pixel(100, 50)[0];
+        assert_eq!(r.direction, Tuple::vector(-0.5, 0.0, -1.0).normalize());
+        c.shift = (0.0, 0.0);
+        c.roll = PI / 2.0;
+        // The top of the image turns to the left of the camera.
+        let top = c.half_height - c.pixel_size / 2.0;
+        let r = &c.rays_for_pixel(100, 0)[0];
+        assert_eq!(r.direction, Tuple::vector(top, 0.0, -1.0).normalize());
+    }
+
+    #[test]
+    fn distorting_the_image_radially() {
+        let mut c = Camera::new(201, 101, 1.0, PI / 2.0, Matrix::identity());
+        c.distortion = (0.5, 0.25);
+        assert_eq!(c.focus_point(0.0, 0.0), Tuple::point(0.0, 0.0, -1.0));
+        assert_eq!(
+            c.focus_point(0.0, 0.5),
+            Tuple::point(0.0, 0.5 * 1.140625, -1.0)
+        );
+        assert_eq!(c.focus_point(-1.0, 0.0), Tuple::point(-1.75, 0.0, -1.0));
+        c.distortion = (-0.5, 0.0);
+        assert_eq!(c.focus_point(1.0, 0.0), Tuple::point(0.5, 0.0, -1.0));
+    }
+
+    #[test]
+    fn tilting_the_plane_of_focus() {
+        let mut c = Camera::new(201, 101, 1.0, PI / 2.0, Matrix::identity());
+        c.tilt = (-PI / 4.0, 0.0);
+        // In the middle, the plane of focus stays at the focal length.
+        assert_eq!(c.focus_point(0.0, 0.0), Tuple::point(0.0, 0.0, -1.0));
+        // Above, it recedes as a floor seen from above would.
+        let top = c.focus_point(0.0, 0.5);
+        assert!(equals(top.y() / top.z(), -0.5));
+        assert!(equals(top.y() + top.z(), -1.0));
+        assert!(equals(top.z(), -2.0));
+        // A pinhole camera sees the same image.
+        c.oversampling = 1;
+        let top = c.half_height - c.pixel_size / 2.0;
+        let r = &c.rays_for_pixel(100, 0)[0];
+        assert_eq!(r.direction, Tuple::vector(0.0, top, -1.0).normalize());
+    }
+
+    #[test]
+    fn focusing_without_changing_the_field_of_view() {
+        let mut c = Camera::new(201, 101, 1.0, PI / 2.0, Matrix::identity());
+        c.oversampling = 1;
+        let direction = c.rays_for_pixel(0, 0)[0].direction;
+        c.focus_distance = 3.0;
+        assert_eq!(c.focus_point(0.0, 0.0), Tuple::point(0.0, 0.0, -3.0));
+        assert_eq!(c.focus_point(0.5, 0.0), Tuple::point(1.5, 0.0, -3.0));
+        assert_eq!(c.rays_for_pixel(0, 0)[0].direction, direction);
+        c.tilt = (-PI / 4.0, 0.0);
+        assert_eq!(c.focus_point(0.0, 0.0), Tuple::point(0.0, 0.0, -3.0));
+    }
+
     #[test]
     fn rendering_a_world_with_a_camera() {
         let mut w = default_world();
@@ -204,4 +876,420 @@ mod tests {
         let image = c.render(&mut w);
         assert_eq!(image.pixel_at(5, 5), Color::new(0.38066, 0.47583, 0.2855));
     }
+
+    #[test]
+    fn rendering_several_views_of_a_world() {
+        let mut w = default_world();
+        let up = Tuple::vector(0.0, 1.0, 0.0);
+        let cameras: Vec<Camera> = [
+            Tuple::point(0.0, 0.0, -5.0),
+            Tuple::point(5.0, 0.0, 0.0),
+            Tuple::point(0.0, 5.0, 0.1),
+        ]
+        .into_iter()
+        .map(|from| {
+            let mut c = Camera::new(
+                5,
+                4,
+                1.0,
+                PI / 2.0,
+                view_transform(from, Tuple::point(0.0, 0.0, 0.0), up),
+            );
+            c.oversampling = 1;
+            c
+        })
+        .collect();
+        let images = render_all(&cameras, &mut w);
+        assert_eq!(images.len(), 3);
+        for (camera, image) in cameras.into_iter().zip(images) {
+            let expected = camera.render(&mut w);
+            for y in 0..4 {
+                for x in 0..5 {
+                    assert_eq!(image.output_color(x, y), expected.output_color(x, y));
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn clamping_bright_samples() {
+        let mut w = default_world();
+        Arc::make_mut(&mut w.objects[0]).material.ambient = 10.0;
+        let from = Tuple::point(0.0, 0.0, -5.0);
+        let to = Tuple::point(0.0, 0.0, 0.0);
+        let up = Tuple::vector(0.0, 1.0, 0.0);
+        let mut c = Camera::new(11, 11, 1.0, PI / 2.0, view_transform(from, to, up));
+        c.oversampling = 1;
+        c.render_depth = 1;
+        c.max_sample_value = 2.0;
+        let image = c.render(&mut w);
+        assert_eq!(image.pixel_at(5, 5).green(), 2.0);
+    }
+
+    #[test]
+    fn a_physical_exposure_sets_the_camera_exposure() {
+        let settings = PhysicalExposure {
+            iso: 100.0,
+            shutter: 1.0,
+            f_number: 1.0,
+        };
+        assert!(equals(settings.ev100(), 0.0));
+        let c =
+            Camera::new(10, 10, 1.0, PI / 2.0, Matrix::identity()).with_physical_exposure(settings);
+        assert!(equals(c.exposure.exp2(), 1.0 / 1.2));
+        let faster = PhysicalExposure {
+            iso: 400.0,
+            shutter: 0.5,
+            f_number: 2.0,
+        };
+        assert!(equals(faster.stops() - settings.stops(), -1.0));
+        assert!(equals(PhysicalExposure::default().ev100(), 14.64386));
+    }
+
+    #[test]
+    fn exposure_is_applied_when_the_image_is_written() {
+        let mut w = default_world();
+        let from = Tuple::point(0.0, 0.0, -5.0);
+        let to = Tuple::point(0.0, 0.0, 0.0);
+        let up = Tuple::vector(0.0, 1.0, 0.0);
+        let mut c = Camera::new(11, 11, 1.0, PI / 2.0, view_transform(from, to, up));
+        c.oversampling = 1;
+        c.render_depth = 1;
+        c.exposure = 1.0;
+        let image = c.render(&mut w);
+        assert_eq!(image.pixel_at(5, 5), Color::new(0.38066, 0.47583, 0.2855));
+        assert_eq!(
+            image.output_color(5, 5),
+            Color::new(0.76132, 0.95166, 0.571)
+        );
+    }
+
+    #[test]
+    fn rendering_a_clay_image() {
+        let mut w = default_world();
+        let from = Tuple::point(0.0, 0.0, -5.0);
+        let to = Tuple::point(0.0, 0.0, 0.0);
+        let up = Tuple::vector(0.0, 1.0, 0.0);
+        let mut c = Camera::new(11, 11, 1.0, PI / 2.0, view_transform(from, to, up));
+        c.oversampling = 1;
+        c.render_depth = 1;
+        let image = c.render_clay(&mut w);
+        let pixel = image.pixel_at(5, 5);
+        assert_eq!(pixel.red(), pixel.green());
+        assert_eq!(pixel.green(), pixel.blue());
+        assert!(w.material_override.is_none());
+    }
+
+    #[test]
+    fn rendering_a_contact_sheet() {
+        let mut w = default_world();
+        w.material_override = Some(Material::glass());
+        let from = Tuple::point(0.0, 0.0, -5.0);
+        let to = Tuple::point(0.0, 0.0, 0.0);
+        let up = Tuple::vector(0.0, 1.0, 0.0);
+        let mut c = Camera::new(11, 11, 1.0, PI / 2.0, view_transform(from, to, up));
+        c.render_depth = 1;
+        let sheet = render_contact_sheet(c, &mut w, &[1, 2]);
+        assert!(sheet.width() > 2 * c.hsize && sheet.height() > 2 * c.vsize);
+        assert!(w.material_override.unwrap().identical(&Material::glass()));
+    }
+
+    #[test]
+    fn framing_the_whole_scene() {
+        let mut w = World::new();
+        w.add_object(Object::new_plane());
+        w.add_object(
+            Object::new_sphere()
+                .with_transform(translation(10.0, 0.0, 0.0) * scaling(2.0, 2.0, 2.0)),
+        );
+        let mut c = Camera::new(11, 11, 1.0, PI / 2.0, Matrix::identity())
+            .frame(&mut w, Tuple::vector(0.0, 0.0, 1.0));
+        c.oversampling = 1;
+        let center = c.rays_for_pixel(5, 5)[0];
+        let radius = SQRT_3 * 2.0;
+        assert_eq!(center.origin, Tuple::point(10.0, 0.0, -radius * SQRT_2));
+        assert_eq!(center.direction, Tuple::vector(0.0, 0.0, 1.0));
+        let corner = c.rays_for_pixel(0, 0)[0];
+        let mut xs = vec![];
+        w.objects[1].intersect(&corner, &mut xs);
+        assert!(xs.is_empty());
+    }
+
+    #[test]
+    fn framing_an_empty_scene_keeps_the_camera() {
+        let c = Camera::new(11, 11, 1.0, PI / 2.0, translation(1.0, 2.0, 3.0));
+        let framed = c.frame(&mut World::new(), Tuple::vector(0.0, -1.0, 0.0));
+        assert_eq!(framed.transform_inv, c.transform_inv);
+    }
+
+    #[test]
+    fn drawing_the_wireframe_of_a_world() {
+        let mut w = default_world();
+        let from = Tuple::point(0.0, 0.0, -5.0);
+        let to = Tuple::point(0.0, 0.0, 0.0);
+        let up = Tuple::vector(0.0, 1.0, 0.0);
+        let c = Camera::new(11, 11, 1.0, PI / 2.0, view_transform(from, to, up));
+        let wires = c.wireframe(&mut w, 0.0, WHITE);
+        assert_eq!(wires.alpha_at(0, 0), 0.0);
+        assert_eq!(wires.alpha_at(5, 5), 0.0);
+        assert_eq!(wires.alpha_at(6, 5), 1.0);
+        assert_eq!(wires.alpha_at(5, 6), 1.0);
+        let image = c.render_wireframe(&mut w, 0.0, WHITE);
+        assert_eq!(image.pixel_at(6, 5), WHITE);
+        assert_ne!(image.pixel_at(5, 5), WHITE);
+    }
+
+    #[test]
+    fn picking_the_object_under_a_pixel() {
+        let mut w = World::new();
+        let mut group = Object::new_group().with_name("spheres");
+        group.as_mut_group().add_child(
+            Object::new_sphere()
+                .with_name("left")
+                .with_transform(translation(-2.0, 0.0, 0.0)),
+        );
+        group
+            .as_mut_group()
+            .add_child(Object::new_sphere().with_transform(translation(2.0, 0.0, 0.0)));
+        w.add_object(Object::new_plane().with_transform(translation(0.0, -1.0, 0.0)));
+        w.add_object(group);
+        w.prepare();
+        let from = Tuple::point(0.0, 0.0, -5.0);
+        let to = Tuple::point(0.0, 0.0, 0.0);
+        let up = Tuple::vector(0.0, 1.0, 0.0);
+        let c = Camera::new(101, 51, 1.0, PI / 2.0, view_transform(from, to, up));
+        let left = c.pick(&w, 30, 25).unwrap();
+        assert_eq!((left.id, left.name), (1, Some("left")));
+        let center = Tuple::point(-2.0, 0.0, 0.0);
+        assert!(equals((left.point - center).magnitude(), 1.0));
+        assert_eq!(left.normal, (left.point - center).normalize());
+        assert!(equals(left.t, (left.point - from).magnitude()));
+        let right = c.pick(&w, 70, 25).unwrap();
+        assert_eq!((right.id, right.name), (1, Some("spheres")));
+        assert!(right.object.name.is_none());
+        let floor = c.pick(&w, 50, 50).unwrap();
+        assert_eq!((floor.id, floor.name), (0, None));
+        assert_eq!(floor.normal, Tuple::vector(0.0, 1.0, 0.0));
+        assert!(c.pick(&w, 50, 0).is_none());
+        assert!(c.pick(&w, 101, 25).is_none());
+    }
+
+    #[test]
+    fn rendering_a_region_of_the_image() {
+        let mut w = default_world();
+        let from = Tuple::point(0.0, 0.0, -5.0);
+        let to = Tuple::point(0.0, 0.0, 0.0);
+        let up = Tuple::vector(0.0, 1.0, 0.0);
+        let mut c = Camera::new(11, 11, 1.0, PI / 2.0, view_transform(from, to, up));
+        c.oversampling = 1;
+        c.render_depth = 1;
+        let image = c.render_region(&mut w, 4, 3, 7, 8);
+        assert_eq!(image.width(), 3);
+        assert_eq!(image.height(), 5);
+        assert_eq!(image.pixel_at(1, 2), Color::new(0.38066, 0.47583, 0.2855));
+    }
+
+    #[test]
+    fn an_importance_map_gives_more_samples_to_marked_pixels() {
+        let mut w = default_world();
+        let from = Tuple::point(0.0, 0.0, -5.0);
+        let to = Tuple::point(0.0, 0.0, 0.0);
+        let up = Tuple::vector(0.0, 1.0, 0.0);
+        let mut c = Camera::new(11, 11, 1.0, PI / 2.0, view_transform(from, to, up));
+        c.oversampling = 1;
+        c.render_depth = 1;
+        let coarse = c.render(&mut w);
+        c.oversampling = 3;
+        let fine = c.render(&mut w);
+        c.oversampling = 1;
+        // The right half of the map is marked, at a lower resolution.
+        let mut importance = Canvas::new(2, 1);
+        importance.write_pixel(1, 0, WHITE);
+        let image = c.render_importance(&mut w, &importance, 3);
+        for y in 0..11 {
+            for x in 0..11 {
+                let expected = if x < 6 { &coarse } else { &fine };
+                assert_eq!(image.pixel_at(x, y), expected.pixel_at(x, y));
+            }
+        }
+    }
+
+    #[test]
+    fn an_empty_importance_map_marks_no_pixel() {
+        let mut w = default_world();
+        let from = Tuple::point(0.0, 0.0, -5.0);
+        let to = Tuple::point(0.0, 0.0, 0.0);
+        let up = Tuple::vector(0.0, 1.0, 0.0);
+        let mut c = Camera::new(5, 5, 1.0, PI / 2.0, view_transform(from, to, up));
+        c.oversampling = 1;
+        c.render_depth = 1;
+        let coarse = c.render(&mut w);
+        for importance in [Canvas::new(0, 3), Canvas::new(3, 0)] {
+            let image = c.render_importance(&mut w, &importance, 3);
+            for y in 0..5 {
+                for x in 0..5 {
+                    assert_eq!(image.pixel_at(x, y), coarse.pixel_at(x, y));
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn rendering_progressively_until_stopped() {
+        let mut w = default_world();
+        // Flat shaded, so that the jitter of the passes cannot change the
+        // center pixel, which stays on the sphere.
+        let outer = std::sync::Arc::make_mut(&mut w.objects[0]);
+        outer.material.ambient = 1.0;
+        outer.material.diffuse = 0.0;
+        outer.material.specular = 0.0;
+        let from = Tuple::point(0.0, 0.0, -5.0);
+        let to = Tuple::point(0.0, 0.0, 0.0);
+        let up = Tuple::vector(0.0, 1.0, 0.0);
+        let mut c = Camera::new(11, 11, 1.0, PI / 2.0, view_transform(from, to, up));
+        c.render_depth = 1;
+        let mut passes = vec![];
+        let image = c.render_progressive(&mut w, 10, |image, pass| {
+            passes.push(pass);
+            assert_eq!(image.width(), 11);
+            pass < 3
+        });
+        assert_eq!(passes, vec![1, 2, 3]);
+        assert_eq!(image.pixel_at(5, 5), Color::new(0.8, 1.0, 0.6));
+        assert_eq!(image.pixel_at(0, 0), BLACK);
+    }
+
+    #[test]
+    fn rendering_with_more_threads_than_rows_divide_into() {
+        let mut w = default_world();
+        let from = Tuple::point(0.0, 0.0, -1.2);
+        let to = Tuple::point(0.0, 0.0, 0.0);
+        let up = Tuple::vector(0.0, 1.0, 0.0);
+        let mut c = Camera::new(7, 11, 1.0, PI / 2.0, view_transform(from, to, up));
+        c.oversampling = 1;
+        c.render_depth = 1;
+        let expected = c.render(&mut w);
+        for threads in [2, 3, 4, 16] {
+            c.threads = threads;
+            let image = c.render(&mut w);
+            for y in 0..11 {
+                for x in 0..7 {
+                    assert_eq!(image.pixel_at(x, y), expected.pixel_at(x, y));
+                }
+            }
+        }
+        // The sphere fills the image, so missing rows would stay black.
+        assert_ne!(expected.pixel_at(3, 10), BLACK);
+    }
+
+    #[test]
+    fn cancelling_a_render_keeps_the_finished_rows() {
+        let mut w = default_world();
+        let from = Tuple::point(0.0, 0.0, -5.0);
+        let to = Tuple::point(0.0, 0.0, 0.0);
+        let up = Tuple::vector(0.0, 1.0, 0.0);
+        let mut c = Camera::new(11, 11, 1.0, PI / 2.0, view_transform(from, to, up));
+        c.oversampling = 1;
+        c.render_depth = 1;
+        c.threads = 2;
+        let cancel = AtomicBool::new(false);
+        let render = c.render_cancellable(&mut w, &cancel);
+        assert!(render.is_complete());
+        assert_eq!(render.image.pixel_at(5, 5), c.render(&mut w).pixel_at(5, 5));
+        cancel.store(true, Ordering::Relaxed);
+        let render = c.render_cancellable(&mut w, &cancel);
+        assert!(!render.is_complete());
+        assert!(!render.is_done(5, 5));
+        assert_eq!(render.image.pixel_at(5, 5), BLACK);
+    }
+
+    #[test]
+    fn rendering_into_a_tiled_canvas() {
+        let mut w = default_world();
+        let from = Tuple::point(0.0, 0.0, -5.0);
+        let to = Tuple::point(0.0, 0.0, 0.0);
+        let up = Tuple::vector(0.0, 1.0, 0.0);
+        let mut c = Camera::new(11, 9, 1.0, PI / 2.0, view_transform(from, to, up));
+        c.oversampling = 1;
+        c.render_depth = 1;
+        c.exposure = 0.5;
+        let path =
+            std::env::temp_dir().join(format!("rt_rust_tiled_render_{}", std::process::id()));
+        let tiled = c.render_tiled(&mut w, &path, 4).unwrap();
+        let mut ppm = vec![];
+        tiled.write_ppm(&mut ppm).unwrap();
+        assert_eq!(String::from_utf8(ppm).unwrap(), c.render(&mut w).to_ppm());
+        drop(tiled);
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn rendering_a_resolution_pyramid() {
+        let mut w = default_world();
+        let from = Tuple::point(0.0, 0.0, -5.0);
+        let to = Tuple::point(0.0, 0.0, 0.0);
+        let up = Tuple::vector(0.0, 1.0, 0.0);
+        let mut c = Camera::new(11, 11, 1.0, PI / 2.0, view_transform(from, to, up));
+        c.oversampling = 1;
+        c.render_depth = 1;
+        c.threads = 3;
+        let mut levels = vec![];
+        let image = c.render_pyramid(&mut w, |preview, spacing| {
+            if spacing == 8 {
+                assert_eq!(preview.pixel_at(7, 7), preview.pixel_at(0, 0));
+                assert_eq!(preview.pixel_at(10, 10), preview.pixel_at(8, 8));
+            }
+            levels.push(spacing);
+            true
+        });
+        assert_eq!(levels, vec![8, 4, 2, 1]);
+        let full = c.render(&mut w);
+        for y in 0..11 {
+            for x in 0..11 {
+                assert_eq!(image.pixel_at(x, y), full.pixel_at(x, y));
+            }
+        }
+        let coarse = c.render_pyramid(&mut w, |_, spacing| spacing > 4);
+        assert_eq!(coarse.pixel_at(5, 5), image.pixel_at(4, 4));
+    }
+
+    #[test]
+    fn rendering_with_a_reused_render_context() {
+        let mut w = default_world();
+        let from = Tuple::point(0.0, 0.0, -5.0);
+        let to = Tuple::point(0.0, 0.0, 0.0);
+        let up = Tuple::vector(0.0, 1.0, 0.0);
+        let mut c = Camera::new(11, 7, 1.0, PI / 2.0, view_transform(from, to, up));
+        c.oversampling = 1;
+        c.render_depth = 1;
+        let expected = c.render(&mut w);
+        let context = RenderContext::new(4).with_row_order(RowOrder::CenterOut);
+        for _ in 0..2 {
+            let render = c.render_in(&mut w, &context);
+            assert!(render.is_complete());
+            let image = render.image;
+            for y in 0..7 {
+                for x in 0..11 {
+                    assert_eq!(image.pixel_at(x, y), expected.pixel_at(x, y));
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn debugging_a_pixel() {
+        let mut w = default_world();
+        let from = Tuple::point(0.0, 0.0, -5.0);
+        let to = Tuple::point(0.0, 0.0, 0.0);
+        let up = Tuple::vector(0.0, 1.0, 0.0);
+        let mut c = Camera::new(11, 11, 1.0, PI / 2.0, view_transform(from, to, up));
+        c.render_depth = 1;
+        let traces = c.debug_pixel(&mut w, 5, 5);
+        assert_eq!(traces.len(), 4);
+        c.oversampling = 1;
+        let traces = c.debug_pixel(&mut w, 5, 5);
+        assert_eq!(traces.len(), 1);
+        assert_eq!(traces[0].color, Color::new(0.38066, 0.47583, 0.2855));
+        assert_eq!(traces[0].hit.as_ref().unwrap().t, 4.0);
+    }
 }
@@ -1,10 +1,25 @@
+pub mod font;
+mod gif;
+mod png;
+mod tiles;
+
+pub use tiles::TiledCanvas;
+
 use crate::colors;
 use crate::floats::Float;
+use std::time::Duration;
+
+const SHEET_MARGIN: usize = 4;
 
+#[derive(Clone)]
 pub struct Canvas {
     width: usize,
     height: usize,
     pixels: Vec<colors::Color>,
+    alphas: Vec<Float>,
+    color_space: colors::ColorSpace,
+    exposure: Float,
+    white_balance: Option<Float>,
 }
 
 impl Canvas {
@@ -13,9 +28,75 @@ impl Canvas {
             width,
             height,
             pixels: vec![colors::BLACK; width * height],
+            alphas: vec![1.0; width * height],
+            color_space: colors::ColorSpace::Linear,
+            exposure: 0.0,
+            white_balance: None,
+        }
+    }
+
+    pub fn with_exposure(self, stops: Float) -> Canvas {
+        Canvas {
+            exposure: stops,
+            ..self
+        }
+    }
+
+    // The encoding of the output colors, the pixels stay linear.
+    pub fn with_color_space(self, color_space: colors::ColorSpace) -> Canvas {
+        Canvas {
+            color_space,
+            ..self
         }
     }
 
+    pub fn with_white_balance(self, kelvin: Float) -> Canvas {
+        Canvas {
+            white_balance: Some(kelvin),
+            ..self
+        }
+    }
+
+    pub fn from_ppm(ppm: &str) -> Result<Canvas, String> {
+        let tokens: Vec<&str> = ppm
+            .lines()
+            .map(|line| line.split('#').next().unwrap_or(""))
+            .flat_map(|line| line.split_whitespace())
+            .collect();
+        let mut tokens = tokens.iter();
+        if tokens.next() != Some(&"P3") {
+            return Err(String::from("missing P3 magic number"));
+        }
+        let width = next_ppm_number(&mut tokens, "width")?;
+        let height = next_ppm_number(&mut tokens, "height")?;
+        let scale = next_ppm_number(&mut tokens, "scale")? as Float;
+        if scale <= 0.0 {
+            return Err(String::from("invalid scale 0"));
+        }
+        // The size is checked against the file before allocating the
+        // canvas, so that a corrupted header does not exhaust the memory.
+        let size = width
+            .checked_mul(height)
+            .filter(|size| size.checked_mul(3).is_some_and(|n| n <= tokens.len()))
+            .ok_or("unexpected end of file reading pixel")?;
+        let mut canvas = Canvas::new(width, height);
+        for i in 0..size {
+            let red = next_ppm_number(&mut tokens, "pixel")? as Float / scale;
+            let green = next_ppm_number(&mut tokens, "pixel")? as Float / scale;
+            let blue = next_ppm_number(&mut tokens, "pixel")? as Float / scale;
+            canvas.pixels[i] = colors::Color::new(red, green, blue);
+        }
+        Ok(canvas)
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
     fn position_to_index(&self, x: usize, y: usize) -> usize {
         y * self.width + x
     }
@@ -24,15 +105,228 @@ impl Canvas {
         self.pixels[self.position_to_index(x, y)]
     }
 
+    pub fn output_color(&self, x: usize, y: usize) -> colors::Color {
+        let color = self.pixel_at(x, y) * self.exposure.exp2();
+        let color = match self.white_balance {
+            Some(kelvin) => color.adapt(colors::Color::from_kelvin(kelvin), colors::WHITE),
+            None => color,
+        };
+        self.color_space.encode(color)
+    }
+
     pub fn write_pixel(&mut self, x: usize, y: usize, color: colors::Color) {
         let index = self.position_to_index(x, y);
         self.pixels[index] = color;
     }
 
+    pub fn alpha_at(&self, x: usize, y: usize) -> Float {
+        self.alphas[self.position_to_index(x, y)]
+    }
+
+    pub fn write_alpha(&mut self, x: usize, y: usize, alpha: Float) {
+        let index = self.position_to_index(x, y);
+        self.alphas[index] = alpha.clamp(0.0, 1.0);
+    }
+
+    pub fn blit(&mut self, src: &Canvas, x: isize, y: isize) {
+        self.composite(src, x, y, |_, _, c, a| (c, a));
+    }
+
+    pub fn over(&mut self, src: &Canvas, x: isize, y: isize) {
+        self.composite(src, x, y, |dst, dst_alpha, c, a| {
+            (c * a + dst * (1.0 - a), a + dst_alpha * (1.0 - a))
+        });
+    }
+
+    pub fn add(&mut self, src: &Canvas) {
+        self.combine(src, |a, b| a + b);
+    }
+
+    pub fn combine<F>(&mut self, src: &Canvas, f: F)
+    where
+        F: Fn(colors::Color, colors::Color) -> colors::Color,
+    {
+        assert_eq!(
+            (self.width, self.height),
+            (src.width, src.height),
+            "canvas sizes differ"
+        );
+        for (dst, c) in self.pixels.iter_mut().zip(src.pixels.iter()) {
+            *dst = f(*dst, *c);
+        }
+    }
+
+    pub fn map<F>(&mut self, f: F)
+    where
+        F: Fn(colors::Color) -> colors::Color,
+    {
+        for c in self.pixels.iter_mut() {
+            *c = f(*c);
+        }
+    }
+
+    // A grayscale map of local contrast, 1 where the luminance changes the
+    // most from a pixel to its neighbours, e.g. to guide where a next
+    // render spends its samples.
+    pub fn saliency(&self) -> Canvas {
+        let luminance = |x: usize, y: usize| self.pixel_at(x, y).to_xyz()[1];
+        let mut contrasts = Canvas::new(self.width, self.height);
+        let mut max: Float = 0.0;
+        for y in 0..self.height {
+            for x in 0..self.width {
+                let center = luminance(x, y);
+                let contrast = [
+                    (x.wrapping_sub(1), y),
+                    (x + 1, y),
+                    (x, y.wrapping_sub(1)),
+                    (x, y + 1),
+                ]
+                .into_iter()
+                .filter(|&(nx, ny)| nx < self.width && ny < self.height)
+                .map(|(nx, ny)| (luminance(nx, ny) - center).abs())
+                .fold(0.0, Float::max);
+                max = max.max(contrast);
+                contrasts.write_pixel(x, y, colors::WHITE * contrast);
+            }
+        }
+        if max > 0.0 {
+            contrasts.map(|c| c * (1.0 / max));
+        }
+        contrasts
+    }
+
+    pub fn draw_line(&mut self, x0: isize, y0: isize, x1: isize, y1: isize, color: colors::Color) {
+        let dx = (x1 - x0).abs();
+        let dy = -(y1 - y0).abs();
+        let sx = if x0 < x1 { 1 } else { -1 };
+        let sy = if y0 < y1 { 1 } else { -1 };
+        let (mut x, mut y, mut err) = (x0, y0, dx + dy);
+        loop {
+            self.put_pixel(x, y, color);
+            if x == x1 && y == y1 {
+                break;
+            }
+            let e2 = 2 * err;
+            if e2 >= dy {
+                err += dy;
+                x += sx;
+            }
+            if e2 <= dx {
+                err += dx;
+                y += sy;
+            }
+        }
+    }
+
+    pub fn draw_rect(
+        &mut self,
+        x: isize,
+        y: isize,
+        width: usize,
+        height: usize,
+        color: colors::Color,
+    ) {
+        if width == 0 || height == 0 {
+            return;
+        }
+        let (x1, y1) = (x + width as isize - 1, y + height as isize - 1);
+        self.draw_line(x, y, x1, y, color);
+        self.draw_line(x, y1, x1, y1, color);
+        self.draw_line(x, y, x, y1, color);
+        self.draw_line(x1, y, x1, y1, color);
+    }
+
+    pub fn fill_rect(
+        &mut self,
+        x: isize,
+        y: isize,
+        width: usize,
+        height: usize,
+        color: colors::Color,
+    ) {
+        for py in y..y + height as isize {
+            for px in x..x + width as isize {
+                self.put_pixel(px, py, color);
+            }
+        }
+    }
+
+    pub fn draw_text(
+        &mut self,
+        x: isize,
+        y: isize,
+        text: &str,
+        scale: usize,
+        color: colors::Color,
+    ) {
+        let scale = scale.max(1) as isize;
+        let (mut cx, mut cy) = (x, y);
+        for c in text.chars() {
+            if c == '\n' {
+                cx = x;
+                cy += (font::GLYPH_HEIGHT as isize + 1) * scale;
+                continue;
+            }
+            for (row, bits) in font::glyph(c).iter().enumerate() {
+                for col in 0..font::GLYPH_WIDTH {
+                    if bits & (1 << (font::GLYPH_WIDTH - 1 - col)) != 0 {
+                        self.fill_rect(
+                            cx + col as isize * scale,
+                            cy + row as isize * scale,
+                            scale as usize,
+                            scale as usize,
+                            color,
+                        );
+                    }
+                }
+            }
+            cx += (font::GLYPH_WIDTH as isize + 1) * scale;
+        }
+    }
+
+    fn put_pixel(&mut self, x: isize, y: isize, color: colors::Color) {
+        if x < 0 || y < 0 || x >= self.width as isize || y >= self.height as isize {
+            return;
+        }
+        self.write_pixel(x as usize, y as usize, color);
+    }
+
+    fn composite<F>(&mut self, src: &Canvas, x: isize, y: isize, f: F)
+    where
+        F: Fn(colors::Color, Float, colors::Color, Float) -> (colors::Color, Float),
+    {
+        for sy in 0..src.height {
+            let dy = y + sy as isize;
+            if dy < 0 || dy >= self.height as isize {
+                continue;
+            }
+            for sx in 0..src.width {
+                let dx = x + sx as isize;
+                if dx < 0 || dx >= self.width as isize {
+                    continue;
+                }
+                let src_index = src.position_to_index(sx, sy);
+                let index = self.position_to_index(dx as usize, dy as usize);
+                let (color, alpha) = f(
+                    self.pixels[index],
+                    self.alphas[index],
+                    src.pixels[src_index],
+                    src.alphas[src_index],
+                );
+                self.pixels[index] = color;
+                self.alphas[index] = alpha;
+            }
+        }
+    }
+
     pub fn to_ppm(&self) -> String {
         [self.ppm_header(), self.ppm_pixels(), String::from("")].join("\n")
     }
 
+    pub fn to_png(&self) -> Vec<u8> {
+        png::encode(self)
+    }
+
     fn ppm_header(&self) -> String {
         ["P3", &format!("{} {}", self.width, self.height), "255"].join("\n")
     }
@@ -42,7 +336,7 @@ impl Canvas {
         for y in 0..self.height {
             let mut line: Vec<String> = Vec::with_capacity(3 * self.width);
             for x in 0..self.width {
-                let pixel = self.pixel_at(x, y);
+                let pixel = self.output_color(x, y);
                 line.push(ppm_clamp_color(pixel.red()).to_string());
                 line.push(ppm_clamp_color(pixel.green()).to_string());
                 line.push(ppm_clamp_color(pixel.blue()).to_string());
@@ -64,6 +358,76 @@ impl Canvas {
     }
 }
 
+pub struct Accumulator {
+    sum: Canvas,
+    passes: usize,
+}
+
+impl Accumulator {
+    pub fn new(width: usize, height: usize) -> Accumulator {
+        Accumulator {
+            sum: Canvas::new(width, height),
+            passes: 0,
+        }
+    }
+
+    pub fn passes(&self) -> usize {
+        self.passes
+    }
+
+    pub fn add(&mut self, pass: &Canvas) {
+        self.sum.add(pass);
+        self.passes += 1;
+    }
+
+    pub fn average(&self) -> Canvas {
+        let mut average = self.sum.clone();
+        let scale = 1.0 / self.passes.max(1) as Float;
+        average.map(|c| c * scale);
+        average
+    }
+}
+
+// Tiles labeled images into a grid of `columns` columns, each label under
+// its image, e.g. to compare render settings side by side. The sheet holds
+// the developed colors of each image.
+pub fn contact_sheet(cells: &[(String, Canvas)], columns: usize) -> Canvas {
+    let columns = columns.clamp(1, cells.len().max(1));
+    let rows = cells.len().div_ceil(columns);
+    let cell_width = cells.iter().map(|(_, c)| c.width).max().unwrap_or(0);
+    let image_height = cells.iter().map(|(_, c)| c.height).max().unwrap_or(0);
+    let cell_height = image_height + font::GLYPH_HEIGHT + SHEET_MARGIN;
+    let mut sheet = Canvas::new(
+        columns * (cell_width + SHEET_MARGIN) + SHEET_MARGIN,
+        rows * (cell_height + SHEET_MARGIN) + SHEET_MARGIN,
+    );
+    for (i, (label, image)) in cells.iter().enumerate() {
+        let x0 = SHEET_MARGIN + (i % columns) * (cell_width + SHEET_MARGIN);
+        let y0 = SHEET_MARGIN + (i / columns) * (cell_height + SHEET_MARGIN);
+        for y in 0..image.height {
+            for x in 0..image.width {
+                sheet.write_pixel(x0 + x, y0 + y, image.output_color(x, y));
+            }
+        }
+        let label_y = y0 + image_height + SHEET_MARGIN / 2;
+        sheet.draw_text(x0 as isize, label_y as isize, label, 1, colors::WHITE);
+    }
+    sheet
+}
+
+pub fn encode_gif(frames: &[Canvas], delay: Duration) -> Vec<u8> {
+    gif::encode(frames, delay)
+}
+
+fn next_ppm_number(tokens: &mut std::slice::Iter<&str>, name: &str) -> Result<usize, String> {
+    let token = tokens
+        .next()
+        .ok_or_else(|| format!("unexpected end of file reading {}", name))?;
+    token
+        .parse()
+        .map_err(|_| format!("invalid {} '{}'", name, token))
+}
+
 fn ppm_clamp_color(v: Float) -> u8 {
     (v * 255.0).clamp(0.0, 255.0).round() as u8
 }
@@ -71,6 +435,7 @@ fn ppm_clamp_color(v: Float) -> u8 {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::testing::assert_images_close;
 
     #[test]
     fn creating_a_canvas() {
@@ -122,6 +487,32 @@ mod tests {
         );
     }
 
+    #[test]
+    fn srgb_output_encodes_the_linear_pixels() {
+        let mut c = Canvas::new(1, 1).with_color_space(colors::ColorSpace::Srgb);
+        c.write_pixel(0, 0, colors::Color::new(0.31855, 0.0, 1.0));
+        assert_eq!(c.pixel_at(0, 0), colors::Color::new(0.31855, 0.0, 1.0));
+        assert_eq!(c.output_color(0, 0), colors::Color::new(0.6, 0.0, 1.0));
+        assert_eq!(c.to_ppm().lines().nth(3), Some("153 0 255"));
+    }
+
+    #[test]
+    fn exposure_scales_the_written_pixels_by_stops() {
+        let mut c = Canvas::new(1, 1).with_exposure(-1.0);
+        c.write_pixel(0, 0, colors::Color::new(1.0, 0.5, 2.0));
+        assert_eq!(c.pixel_at(0, 0), colors::Color::new(1.0, 0.5, 2.0));
+        assert_eq!(c.output_color(0, 0), colors::Color::new(0.5, 0.25, 1.0));
+        assert_eq!(c.to_ppm().lines().nth(3), Some("128 64 255"));
+    }
+
+    #[test]
+    fn white_balance_neutralizes_the_reference_white() {
+        let warm = colors::Color::from_kelvin(3000.0);
+        let mut c = Canvas::new(1, 1).with_white_balance(3000.0);
+        c.write_pixel(0, 0, warm);
+        assert_eq!(c.output_color(0, 0), colors::WHITE);
+    }
+
     #[test]
     fn splitting_long_lines_in_ppm_files() {
         let mut c = Canvas::new(10, 2);
@@ -143,6 +534,217 @@ mod tests {
         );
     }
 
+    fn filled(width: usize, height: usize, color: colors::Color) -> Canvas {
+        let mut c = Canvas::new(width, height);
+        c.map(|_| color);
+        c
+    }
+
+    #[test]
+    fn blitting_a_canvas_clips_to_the_destination() {
+        let red = colors::Color::new(1.0, 0.0, 0.0);
+        let mut c = Canvas::new(4, 4);
+        c.blit(&filled(2, 2, red), 3, -1);
+        assert_eq!(c.pixel_at(3, 0), red);
+        assert_eq!(c.pixel_at(3, 1), colors::BLACK);
+        assert_eq!(c.pixel_at(2, 0), colors::BLACK);
+    }
+
+    #[test]
+    fn compositing_a_canvas_over_another() {
+        let red = colors::Color::new(1.0, 0.0, 0.0);
+        let mut top = filled(2, 1, red);
+        top.write_alpha(0, 0, 0.25);
+        top.write_alpha(1, 0, 0.0);
+        let mut c = filled(2, 1, colors::WHITE);
+        c.write_alpha(0, 0, 0.5);
+        c.over(&top, 0, 0);
+        assert_eq!(c.pixel_at(0, 0), colors::Color::new(1.0, 0.75, 0.75));
+        assert_eq!(c.alpha_at(0, 0), 0.625);
+        assert_eq!(c.pixel_at(1, 0), colors::WHITE);
+        assert_eq!(c.alpha_at(1, 0), 1.0);
+    }
+
+    #[test]
+    fn adding_and_mapping_canvases() {
+        let mut c = filled(2, 2, colors::Color::new(0.25, 0.5, 0.0));
+        c.add(&filled(2, 2, colors::Color::new(0.25, 0.0, 1.0)));
+        assert_eq!(c.pixel_at(1, 1), colors::Color::new(0.5, 0.5, 1.0));
+        c.map(|p| p * 2.0);
+        assert_eq!(c.pixel_at(0, 1), colors::Color::new(1.0, 1.0, 2.0));
+        c.combine(&filled(2, 2, colors::Color::new(0.5, 0.5, 0.5)), |a, b| {
+            a * b
+        });
+        assert_eq!(c.pixel_at(1, 0), colors::Color::new(0.5, 0.5, 1.0));
+    }
+
+    #[test]
+    #[should_panic(expected = "canvas sizes differ")]
+    fn adding_canvases_of_different_sizes() {
+        Canvas::new(2, 2).add(&Canvas::new(3, 2));
+    }
+
+    fn lit_pixels(c: &Canvas) -> Vec<(usize, usize)> {
+        let mut lit = vec![];
+        for y in 0..c.height {
+            for x in 0..c.width {
+                if c.pixel_at(x, y) != colors::BLACK {
+                    lit.push((x, y));
+                }
+            }
+        }
+        lit
+    }
+
+    #[test]
+    fn drawing_lines() {
+        let mut c = Canvas::new(5, 5);
+        c.draw_line(0, 0, 4, 2, colors::WHITE);
+        assert_eq!(lit_pixels(&c), vec![(0, 0), (1, 1), (2, 1), (3, 2), (4, 2)]);
+        let mut c = Canvas::new(3, 3);
+        c.draw_line(-2, 1, 10, 1, colors::WHITE);
+        assert_eq!(lit_pixels(&c), vec![(0, 1), (1, 1), (2, 1)]);
+    }
+
+    #[test]
+    fn drawing_and_filling_rectangles() {
+        let mut c = Canvas::new(4, 4);
+        c.draw_rect(0, 0, 3, 3, colors::WHITE);
+        assert_eq!(lit_pixels(&c).len(), 8);
+        assert_eq!(c.pixel_at(1, 1), colors::BLACK);
+        c.fill_rect(2, 2, 5, 5, colors::WHITE);
+        assert_eq!(c.pixel_at(3, 3), colors::WHITE);
+        assert_eq!(c.pixel_at(1, 1), colors::BLACK);
+    }
+
+    #[test]
+    fn tiling_labeled_images_into_a_contact_sheet() {
+        let gray = colors::Color::new(0.25, 0.25, 0.25);
+        let mut image = Canvas::new(2, 2).with_exposure(1.0);
+        image.map(|_| gray);
+        let cells = vec![
+            (String::from("-"), image.clone()),
+            (String::from(""), Canvas::new(1, 1)),
+            (String::from(""), image),
+        ];
+        let sheet = contact_sheet(&cells, 2);
+        assert_eq!((sheet.width(), sheet.height()), (16, 38));
+        let half = colors::Color::new(0.5, 0.5, 0.5);
+        for (x, y) in [(4, 4), (5, 5), (4, 21), (5, 22)] {
+            assert_eq!(sheet.pixel_at(x, y), half);
+        }
+        assert_eq!(sheet.pixel_at(6, 4), colors::BLACK);
+        assert_eq!(sheet.pixel_at(10, 4), colors::BLACK);
+        // The label is written under the tallest image of the sheet.
+        let label: Vec<_> = lit_pixels(&sheet)
+            .into_iter()
+            .filter(|&(_, y)| y < 17 && y > 6)
+            .collect();
+        assert_eq!(label, vec![(4, 11), (5, 11), (6, 11), (7, 11), (8, 11)]);
+    }
+
+    #[test]
+    fn drawing_text() {
+        let mut c = Canvas::new(12, 16);
+        c.draw_text(0, 0, "1\n-", 1, colors::WHITE);
+        assert_eq!(c.pixel_at(2, 0), colors::WHITE);
+        assert_eq!(c.pixel_at(1, 1), colors::WHITE);
+        assert_eq!(c.pixel_at(0, 0), colors::BLACK);
+        assert_eq!(
+            lit_pixels(&c)
+                .into_iter()
+                .filter(|p| p.1 >= 8)
+                .collect::<Vec<_>>(),
+            vec![(0, 11), (1, 11), (2, 11), (3, 11), (4, 11)]
+        );
+        let mut c = Canvas::new(12, 14);
+        c.draw_text(0, 0, "1", 2, colors::WHITE);
+        assert_eq!(lit_pixels(&c).len(), 4 * 10);
+    }
+
+    #[test]
+    fn reading_a_ppm_with_the_wrong_magic_number() {
+        assert!(Canvas::from_ppm("P32\n1 1\n255\n0 0 0\n").is_err());
+    }
+
+    #[test]
+    fn reading_a_ppm_returns_a_canvas_of_the_right_size() {
+        assert_eq!(
+            Canvas::from_ppm("P3\n10 2\n255\n").err(),
+            Some(String::from("unexpected end of file reading pixel"))
+        );
+        assert_eq!(
+            Canvas::from_ppm("P3\n4294967296 4294967296 255\n0 0 0\n").err(),
+            Some(String::from("unexpected end of file reading pixel"))
+        );
+        let ppm = format!("P3\n10 2\n255\n{}", "0 0 0\n".repeat(20));
+        let c = Canvas::from_ppm(&ppm).unwrap();
+        assert_eq!(c.width(), 10);
+        assert_eq!(c.height(), 2);
+    }
+
+    #[test]
+    fn reading_pixel_data_from_a_ppm_file() {
+        let c = Canvas::from_ppm(
+            "P3
+# a comment
+4 3
+255
+255 127 0  0 127 255  127 255 0  255 255 255
+0 0 0  255 0 0  0 255 0  0 0 255
+255 255 0  0 255 255  255 0 255  127
+127 127
+",
+        )
+        .unwrap();
+        assert_eq!(c.pixel_at(0, 0), colors::Color::new(1.0, 0.49804, 0.0));
+        assert_eq!(c.pixel_at(3, 1), colors::Color::new(0.0, 0.0, 1.0));
+        assert_eq!(
+            c.pixel_at(3, 2),
+            colors::Color::new(0.49804, 0.49804, 0.49804)
+        );
+    }
+
+    #[test]
+    fn reading_a_ppm_respects_the_scale_setting() {
+        let c = Canvas::from_ppm("P3\n2 2\n100\n100 100 100  50 50 50\n75 50 25  0 0 0\n").unwrap();
+        assert_eq!(c.pixel_at(0, 1), colors::Color::new(0.75, 0.5, 0.25));
+    }
+
+    #[test]
+    fn round_tripping_a_canvas_through_ppm() {
+        let mut c = Canvas::new(3, 2);
+        c.write_pixel(1, 1, colors::Color::new(0.2, 0.4, 0.6));
+        let read = Canvas::from_ppm(&c.to_ppm()).unwrap();
+        assert_images_close(&c, &read, 1.0 / 255.0);
+    }
+
+    #[test]
+    fn accumulating_passes_averages_them() {
+        let mut acc = Accumulator::new(2, 1);
+        assert_eq!(acc.average().pixel_at(0, 0), colors::BLACK);
+        acc.add(&filled(2, 1, colors::Color::new(1.0, 0.5, 0.0)));
+        acc.add(&filled(2, 1, colors::Color::new(0.0, 0.5, 1.0)));
+        assert_eq!(acc.passes(), 2);
+        assert_eq!(
+            acc.average().pixel_at(1, 0),
+            colors::Color::new(0.5, 0.5, 0.5)
+        );
+    }
+
+    #[test]
+    fn the_saliency_of_an_image_marks_its_edges() {
+        let mut c = Canvas::new(4, 1);
+        c.write_pixel(2, 0, colors::WHITE);
+        c.write_pixel(3, 0, colors::WHITE);
+        let saliency = c.saliency();
+        assert_eq!(saliency.pixel_at(0, 0), colors::BLACK);
+        assert_eq!(saliency.pixel_at(1, 0), colors::WHITE);
+        assert_eq!(saliency.pixel_at(2, 0), colors::WHITE);
+        assert_eq!(saliency.pixel_at(3, 0), colors::BLACK);
+        assert_eq!(Canvas::new(2, 2).saliency().pixel_at(1, 1), colors::BLACK);
+    }
+
     //  Scenario: PPM files are terminated by a newline character
     #[test]
     fn ppm_files_are_terminated_by_a_newline_character() {
@@ -0,0 +1,251 @@
+use crate::canvas::Canvas;
+use crate::floats::Float;
+use std::collections::HashMap;
+use std::time::Duration;
+
+const MIN_CODE_SIZE: u8 = 8;
+const MAX_CODE_SIZE: u8 = 12;
+const MAX_CODES: u16 = 1 << MAX_CODE_SIZE;
+const CLEAR_CODE: u16 = 1 << MIN_CODE_SIZE;
+const END_CODE: u16 = CLEAR_CODE + 1;
+const BAYER: [[u8; 4]; 4] = [[0, 8, 2, 10], [12, 4, 14, 6], [3, 11, 1, 9], [15, 7, 13, 5]];
+
+// Looping animated GIF, with the frames quantized to a fixed 3-3-2 bits palette
+// through an ordered dither, so that still areas do not flicker between frames.
+pub fn encode(frames: &[Canvas], delay: Duration) -> Vec<u8> {
+    assert!(!frames.is_empty(), "A GIF needs at least one frame");
+    let (width, height) = (frames[0].width(), frames[0].height());
+    assert!(
+        frames
+            .iter()
+            .all(|f| f.width() == width && f.height() == height),
+        "All the frames of a GIF must have the same size"
+    );
+    let mut out = b"GIF89a".to_vec();
+    push_u16(&mut out, width);
+    push_u16(&mut out, height);
+    // Global 256 colors table, 8 bits per primary.
+    out.extend_from_slice(&[0xf7, 0, 0]);
+    for i in 0..=255u8 {
+        out.push(((i >> 5) as u16 * 255 / 7) as u8);
+        out.push((((i >> 2) & 7) as u16 * 255 / 7) as u8);
+        out.push(((i & 3) as u16 * 255 / 3) as u8);
+    }
+    // Loop forever.
+    out.extend_from_slice(b"\x21\xff\x0bNETSCAPE2.0\x03\x01\x00\x00\x00");
+    let centiseconds = (delay.as_millis() / 10).min(u16::MAX as u128) as usize;
+    for frame in frames {
+        out.extend_from_slice(&[0x21, 0xf9, 0x04, 0x04]);
+        push_u16(&mut out, centiseconds);
+        out.extend_from_slice(&[0, 0, 0x2c, 0, 0, 0, 0]);
+        push_u16(&mut out, width);
+        push_u16(&mut out, height);
+        out.extend_from_slice(&[0, MIN_CODE_SIZE]);
+        for block in compress(&palette_indices(frame)).chunks(255) {
+            out.push(block.len() as u8);
+            out.extend_from_slice(block);
+        }
+        out.push(0);
+    }
+    out.push(0x3b);
+    out
+}
+
+fn push_u16(out: &mut Vec<u8>, value: usize) {
+    out.extend_from_slice(&(value as u16).to_le_bytes());
+}
+
+fn palette_indices(frame: &Canvas) -> Vec<u8> {
+    let mut indices = Vec::with_capacity(frame.width() * frame.height());
+    for y in 0..frame.height() {
+        for x in 0..frame.width() {
+            let threshold = (BAYER[y % 4][x % 4] as Float + 0.5) / 16.0;
+            let quantize = |v: Float, levels: u8| {
+                let max = (levels - 1) as Float;
+                (v.clamp(0.0, 1.0) * max + threshold).floor().min(max) as u8
+            };
+            let color = frame.output_color(x, y);
+            indices.push(
+                (quantize(color.red(), 8) << 5)
+                    | (quantize(color.green(), 8) << 2)
+                    | quantize(color.blue(), 4),
+            );
+        }
+    }
+    indices
+}
+
+struct BitWriter {
+    bytes: Vec<u8>,
+    buffer: u32,
+    bits: u8,
+}
+
+impl BitWriter {
+    fn write(&mut self, code: u16, size: u8) {
+        self.buffer |= (code as u32) << self.bits;
+        self.bits += size;
+        while self.bits >= 8 {
+            self.bytes.push(self.buffer as u8);
+            self.buffer >>= 8;
+            self.bits -= 8;
+        }
+    }
+
+    fn finish(mut self) -> Vec<u8> {
+        if self.bits > 0 {
+            self.bytes.push(self.buffer as u8);
+        }
+        self.bytes
+    }
+}
+
+// Variable length LZW: the code size grows one code after the table outgrows
+// it, because the decoder builds each entry one code late.
+fn compress(indices: &[u8]) -> Vec<u8> {
+    let mut writer = BitWriter {
+        bytes: vec![],
+        buffer: 0,
+        bits: 0,
+    };
+    let mut table: HashMap<(u16, u8), u16> = HashMap::new();
+    let mut next_code = END_CODE + 1;
+    let mut size = MIN_CODE_SIZE + 1;
+    writer.write(CLEAR_CODE, size);
+    let Some((&first, rest)) = indices.split_first() else {
+        writer.write(END_CODE, size);
+        return writer.finish();
+    };
+    let mut prefix = first as u16;
+    for &k in rest {
+        if let Some(&code) = table.get(&(prefix, k)) {
+            prefix = code;
+            continue;
+        }
+        writer.write(prefix, size);
+        if next_code >= 1 << size && size < MAX_CODE_SIZE {
+            size += 1;
+        }
+        if next_code == MAX_CODES {
+            writer.write(CLEAR_CODE, size);
+            table.clear();
+            next_code = END_CODE + 1;
+            size = MIN_CODE_SIZE + 1;
+        } else {
+            table.insert((prefix, k), next_code);
+            next_code += 1;
+        }
+        prefix = k as u16;
+    }
+    writer.write(prefix, size);
+    if next_code >= 1 << size && size < MAX_CODE_SIZE {
+        size += 1;
+    }
+    writer.write(END_CODE, size);
+    writer.finish()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::colors::{Color, WHITE};
+
+    fn decompress(bytes: &[u8]) -> Vec<u8> {
+        let mut table: Vec<Vec<u8>> = vec![];
+        let reset = |table: &mut Vec<Vec<u8>>| {
+            *table = (0..=255u8).map(|i| vec![i]).collect();
+            table.push(vec![]);
+            table.push(vec![]);
+        };
+        reset(&mut table);
+        let (mut size, mut position) = (MIN_CODE_SIZE + 1, 0);
+        let mut previous: Option<Vec<u8>> = None;
+        let mut out = vec![];
+        loop {
+            let mut code = 0u16;
+            for i in 0..size as usize {
+                let bit = (bytes[(position + i) / 8] >> ((position + i) % 8)) & 1;
+                code |= (bit as u16) << i;
+            }
+            position += size as usize;
+            if code == CLEAR_CODE {
+                reset(&mut table);
+                size = MIN_CODE_SIZE + 1;
+                previous = None;
+                continue;
+            }
+            if code == END_CODE {
+                return out;
+            }
+            let entry = match (table.get(code as usize), &previous) {
+                (Some(entry), _) => entry.clone(),
+                (None, Some(p)) => [p.clone(), vec![p[0]]].concat(),
+                (None, None) => panic!("invalid first code"),
+            };
+            if let Some(p) = previous
+                && table.len() < MAX_CODES as usize
+            {
+                table.push([p, vec![entry[0]]].concat());
+                if table.len() == 1 << size && size < MAX_CODE_SIZE {
+                    size += 1;
+                }
+            }
+            out.extend_from_slice(&entry);
+            previous = Some(entry);
+        }
+    }
+
+    #[test]
+    fn compressed_indices_decompress_to_the_same_indices() {
+        let short = vec![1, 1, 1, 2, 1, 1, 1, 2, 2, 2];
+        assert_eq!(decompress(&compress(&short)), short);
+        let mut seed = 7u32;
+        let long: Vec<u8> = (0..20000)
+            .map(|i| {
+                seed = seed.wrapping_mul(1664525).wrapping_add(1013904223);
+                if i % 3 == 0 {
+                    (seed >> 24) as u8
+                } else {
+                    (i / 50) as u8
+                }
+            })
+            .collect();
+        assert_eq!(decompress(&compress(&long)), long);
+        assert!(decompress(&compress(&[])).is_empty());
+    }
+
+    #[test]
+    fn primary_colors_use_the_extremes_of_the_palette() {
+        let mut frame = Canvas::new(2, 1);
+        frame.write_pixel(0, 0, WHITE);
+        frame.write_pixel(1, 0, Color::new(1.0, 0.0, 0.0));
+        assert_eq!(palette_indices(&frame), vec![0xff, 0xe0]);
+    }
+
+    #[test]
+    fn dithering_mixes_the_nearest_palette_colors() {
+        let mut frame = Canvas::new(4, 4);
+        for y in 0..4 {
+            for x in 0..4 {
+                frame.write_pixel(x, y, Color::new(0.0, 0.0, 0.5));
+            }
+        }
+        let indices = palette_indices(&frame);
+        assert_eq!(indices.iter().filter(|&&i| i == 1).count(), 8);
+        assert_eq!(indices.iter().filter(|&&i| i == 2).count(), 8);
+    }
+
+    #[test]
+    fn encoding_an_animated_gif() {
+        let frames = vec![Canvas::new(3, 2), Canvas::new(3, 2)];
+        let gif = encode(&frames, Duration::from_millis(40));
+        assert!(gif.starts_with(b"GIF89a\x03\x00\x02\x00\xf7"));
+        assert_eq!(gif.last(), Some(&0x3b));
+        let delays = gif
+            .windows(6)
+            .filter(|w| w[..4] == [0x21, 0xf9, 0x04, 0x04])
+            .map(|w| u16::from_le_bytes([w[4], w[5]]))
+            .collect::<Vec<_>>();
+        assert_eq!(delays, vec![4, 4]);
+    }
+}
@@ -0,0 +1,135 @@
+use crate::canvas::{ppm_clamp_color, Canvas};
+
+const SIGNATURE: [u8; 8] = [0x89, b'P', b'N', b'G', b'\r', b'\n', 0x1a, b'\n'];
+const MAX_STORED_BLOCK: usize = 0xffff;
+
+// 8 bits RGB PNG. The pixels are not filtered and the zlib stream only uses
+// stored deflate blocks: the files are larger than needed, but any viewer
+// reads them without an external compression library.
+pub fn encode(canvas: &Canvas) -> Vec<u8> {
+    let (width, height) = (canvas.width(), canvas.height());
+    let mut header = Vec::with_capacity(13);
+    header.extend_from_slice(&(width as u32).to_be_bytes());
+    header.extend_from_slice(&(height as u32).to_be_bytes());
+    // 8 bits per sample, RGB, deflate, no filter, no interlace.
+    header.extend_from_slice(&[8, 2, 0, 0, 0]);
+    let mut raw = Vec::with_capacity(height * (3 * width + 1));
+    for y in 0..height {
+        raw.push(0);
+        for x in 0..width {
+            let pixel = canvas.output_color(x, y);
+            raw.push(ppm_clamp_color(pixel.red()));
+            raw.push(ppm_clamp_color(pixel.green()));
+            raw.push(ppm_clamp_color(pixel.blue()));
+        }
+    }
+    let mut out = SIGNATURE.to_vec();
+    push_chunk(&mut out, b"IHDR", &header);
+    push_chunk(&mut out, b"IDAT", &zlib_stored(&raw));
+    push_chunk(&mut out, b"IEND", &[]);
+    out
+}
+
+fn push_chunk(out: &mut Vec<u8>, kind: &[u8; 4], data: &[u8]) {
+    out.extend_from_slice(&(data.len() as u32).to_be_bytes());
+    let start = out.len();
+    out.extend_from_slice(kind);
+    out.extend_from_slice(data);
+    let crc = crc32(&out[start..]);
+    out.extend_from_slice(&crc.to_be_bytes());
+}
+
+fn zlib_stored(data: &[u8]) -> Vec<u8> {
+    let blocks = data.len().div_ceil(MAX_STORED_BLOCK).max(1);
+    let mut out = Vec::with_capacity(data.len() + 5 * blocks + 6);
+    // Deflate with a 32K window, no preset dictionary.
+    out.extend_from_slice(&[0x78, 0x01]);
+    for i in 0..blocks {
+        let block = &data[i * MAX_STORED_BLOCK..((i + 1) * MAX_STORED_BLOCK).min(data.len())];
+        out.push((i + 1 == blocks) as u8);
+        out.extend_from_slice(&(block.len() as u16).to_le_bytes());
+        out.extend_from_slice(&(!(block.len() as u16)).to_le_bytes());
+        out.extend_from_slice(block);
+    }
+    out.extend_from_slice(&adler32(data).to_be_bytes());
+    out
+}
+
+fn crc32(bytes: &[u8]) -> u32 {
+    let mut crc = !0u32;
+    for &byte in bytes {
+        crc ^= byte as u32;
+        for _ in 0..8 {
+            crc = if crc & 1 == 1 {
+                (crc >> 1) ^ 0xedb8_8320
+            } else {
+                crc >> 1
+            };
+        }
+    }
+    !crc
+}
+
+fn adler32(bytes: &[u8]) -> u32 {
+    let (mut a, mut b) = (1u32, 0u32);
+    for &byte in bytes {
+        a = (a + byte as u32) % 65521;
+        b = (b + a) % 65521;
+    }
+    (b << 16) | a
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::colors::Color;
+
+    fn inflate_stored(zlib: &[u8]) -> Vec<u8> {
+        let mut data = vec![];
+        let mut i = 2;
+        loop {
+            let last = zlib[i] & 1 == 1;
+            let len = u16::from_le_bytes([zlib[i + 1], zlib[i + 2]]) as usize;
+            assert_eq!(!len as u16, u16::from_le_bytes([zlib[i + 3], zlib[i + 4]]));
+            data.extend_from_slice(&zlib[i + 5..i + 5 + len]);
+            i += 5 + len;
+            if last {
+                break;
+            }
+        }
+        assert_eq!(zlib[i..], adler32(&data).to_be_bytes());
+        data
+    }
+
+    #[test]
+    fn checksums_of_known_values() {
+        assert_eq!(crc32(b"IEND"), 0xae42_6082);
+        assert_eq!(adler32(b"Wikipedia"), 0x11e6_0398);
+    }
+
+    #[test]
+    fn large_data_is_split_in_stored_blocks() {
+        let data: Vec<u8> = (0..150_000).map(|i| (i % 251) as u8).collect();
+        let zlib = zlib_stored(&data);
+        assert_eq!(zlib.len(), data.len() + 3 * 5 + 6);
+        assert_eq!(inflate_stored(&zlib), data);
+        assert_eq!(inflate_stored(&zlib_stored(&[])), Vec::<u8>::new());
+    }
+
+    #[test]
+    fn encoding_a_canvas() {
+        let mut c = Canvas::new(2, 1);
+        c.write_pixel(0, 0, Color::new(1.0, 0.5, 0.0));
+        c.write_pixel(1, 0, Color::new(0.0, 0.0, 1.5));
+        let png = encode(&c);
+        assert_eq!(png[..8], SIGNATURE);
+        assert_eq!(png[8..16], *b"\x00\x00\x00\x0dIHDR");
+        assert_eq!(png[16..29], [0, 0, 0, 2, 0, 0, 0, 1, 8, 2, 0, 0, 0]);
+        assert_eq!(png[29..33], crc32(&png[12..29]).to_be_bytes());
+        let length = u32::from_be_bytes([png[33], png[34], png[35], png[36]]) as usize;
+        assert_eq!(png[37..41], *b"IDAT");
+        let pixels = inflate_stored(&png[41..41 + length]);
+        assert_eq!(pixels, vec![0, 255, 128, 0, 0, 0, 255]);
+        assert!(png.ends_with(b"\x00\x00\x00\x00IEND\xae\x42\x60\x82"));
+    }
+}
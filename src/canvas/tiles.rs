@@ -0,0 +1,211 @@
+use crate::canvas::Canvas;
+use crate::colors::Color;
+use crate::floats::Float;
+use memmap2::MmapMut;
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::path::Path;
+
+const CHANNEL_BYTES: usize = size_of::<Float>();
+const PIXEL_BYTES: usize = 3 * CHANNEL_BYTES;
+
+// An image kept in a memory mapped file instead of RAM, so that very large
+// renders only need one tile in memory at a time. It stores output colors,
+// with the exposure and white balance of the written tiles already applied.
+pub struct TiledCanvas {
+    width: usize,
+    height: usize,
+    tile_size: usize,
+    pixels: MmapMut,
+}
+
+impl TiledCanvas {
+    pub fn create(
+        path: &Path,
+        width: usize,
+        height: usize,
+        tile_size: usize,
+    ) -> Result<TiledCanvas, String> {
+        if tile_size == 0 {
+            return Err(String::from("Tiles must be at least one pixel wide"));
+        }
+        let error = |e: std::io::Error| format!("{}: {}", path.display(), e);
+        let size = width
+            .checked_mul(height)
+            .and_then(|pixels| pixels.checked_mul(PIXEL_BYTES))
+            .ok_or_else(|| {
+                format!(
+                    "{}: {}x{} pixels is too large",
+                    path.display(),
+                    width,
+                    height
+                )
+            })?;
+        let file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .open(path)
+            .map_err(error)?;
+        file.set_len(size.max(1) as u64).map_err(error)?;
+        // SAFETY: the file was just created for this canvas, nothing else
+        // is expected to resize or write it while it is mapped.
+        let pixels = unsafe { MmapMut::map_mut(&file) }.map_err(error)?;
+        Ok(TiledCanvas {
+            width,
+            height,
+            tile_size,
+            pixels,
+        })
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    // The (x0, y0, x1, y1) regions of the tiles, row by row.
+    pub fn tiles(&self) -> Vec<(usize, usize, usize, usize)> {
+        (0..self.height)
+            .step_by(self.tile_size)
+            .flat_map(|y0| {
+                (0..self.width).step_by(self.tile_size).map(move |x0| {
+                    (
+                        x0,
+                        y0,
+                        (x0 + self.tile_size).min(self.width),
+                        (y0 + self.tile_size).min(self.height),
+                    )
+                })
+            })
+            .collect()
+    }
+
+    pub fn pixel_at(&self, x: usize, y: usize) -> Color {
+        let offset = self.offset(x, y);
+        let channel = |i: usize| {
+            let start = offset + i * CHANNEL_BYTES;
+            Float::from_le_bytes(
+                self.pixels[start..start + CHANNEL_BYTES]
+                    .try_into()
+                    .unwrap(),
+            )
+        };
+        Color::new(channel(0), channel(1), channel(2))
+    }
+
+    // Copies the output colors of `tile` with its top left corner at (x0, y0).
+    pub fn write_tile(&mut self, x0: usize, y0: usize, tile: &Canvas) {
+        for y in 0..tile.height().min(self.height.saturating_sub(y0)) {
+            for x in 0..tile.width().min(self.width.saturating_sub(x0)) {
+                let color = tile.output_color(x, y);
+                let offset = self.offset(x0 + x, y0 + y);
+                for (i, v) in [color.red(), color.green(), color.blue()]
+                    .into_iter()
+                    .enumerate()
+                {
+                    let start = offset + i * CHANNEL_BYTES;
+                    self.pixels[start..start + CHANNEL_BYTES].copy_from_slice(&v.to_le_bytes());
+                }
+            }
+        }
+    }
+
+    pub fn flush(&self) -> Result<(), String> {
+        self.pixels.flush().map_err(|e| e.to_string())
+    }
+
+    // Writes the same PPM as `Canvas::to_ppm`, one row at a time.
+    pub fn write_ppm<W: Write>(&self, out: &mut W) -> std::io::Result<()> {
+        writeln!(out, "P3\n{} {}\n255", self.width, self.height)?;
+        let mut row = Canvas::new(self.width, 1);
+        for y in 0..self.height {
+            for x in 0..self.width {
+                row.write_pixel(x, 0, self.pixel_at(x, y));
+            }
+            writeln!(out, "{}", row.ppm_pixels())?;
+        }
+        Ok(())
+    }
+
+    fn offset(&self, x: usize, y: usize) -> usize {
+        assert!(
+            x < self.width && y < self.height,
+            "Pixel ({}, {}) is outside of the canvas",
+            x,
+            y
+        );
+        (y * self.width + x) * PIXEL_BYTES
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::colors::WHITE;
+
+    #[test]
+    fn a_tiled_canvas_covers_the_image_with_tiles() {
+        let path =
+            std::env::temp_dir().join(format!("rt_rust_tiled_canvas_tiles_{}", std::process::id()));
+        let canvas = TiledCanvas::create(&path, 5, 3, 2).unwrap();
+        assert_eq!(
+            canvas.tiles(),
+            vec![
+                (0, 0, 2, 2),
+                (2, 0, 4, 2),
+                (4, 0, 5, 2),
+                (0, 2, 2, 3),
+                (2, 2, 4, 3),
+                (4, 2, 5, 3)
+            ]
+        );
+        assert_eq!(canvas.pixel_at(4, 2), Color::new(0.0, 0.0, 0.0));
+        drop(canvas);
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn a_tiled_canvas_too_large_to_address_is_an_error() {
+        let path = std::env::temp_dir().join(format!(
+            "rt_rust_tiled_canvas_too_large_{}",
+            std::process::id()
+        ));
+        let error = TiledCanvas::create(&path, usize::MAX, 2, 16).err().unwrap();
+        assert!(error.ends_with("too large"));
+        assert!(!path.exists());
+        let error = TiledCanvas::create(&path, 4, 2, 0).err().unwrap();
+        assert_eq!(error, "Tiles must be at least one pixel wide");
+        assert!(!path.exists());
+    }
+
+    #[test]
+    fn tiles_are_stored_in_the_file_and_exported_as_ppm() {
+        let path =
+            std::env::temp_dir().join(format!("rt_rust_tiled_canvas_ppm_{}", std::process::id()));
+        let mut canvas = TiledCanvas::create(&path, 3, 2, 2).unwrap();
+        let mut corner = Canvas::new(2, 2);
+        corner.write_pixel(0, 0, WHITE);
+        canvas.write_tile(0, 0, &corner);
+        // The right tile is cut at the image edge, its exposure is applied.
+        let mut edge = Canvas::new(2, 2).with_exposure(1.0);
+        edge.write_pixel(0, 1, Color::new(0.25, 0.5, 0.0));
+        edge.write_pixel(1, 1, WHITE);
+        canvas.write_tile(2, 0, &edge);
+        assert_eq!(canvas.pixel_at(0, 0), WHITE);
+        assert_eq!(canvas.pixel_at(2, 1), Color::new(0.5, 1.0, 0.0));
+        canvas.flush().unwrap();
+        let mut expected = Canvas::new(3, 2);
+        expected.write_pixel(0, 0, WHITE);
+        expected.write_pixel(2, 1, Color::new(0.5, 1.0, 0.0));
+        let mut ppm = vec![];
+        canvas.write_ppm(&mut ppm).unwrap();
+        assert_eq!(String::from_utf8(ppm).unwrap(), expected.to_ppm());
+        drop(canvas);
+        std::fs::remove_file(&path).unwrap();
+    }
+}
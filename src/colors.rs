@@ -20,6 +20,125 @@ impl Color {
     pub fn blue(&self) -> Float {
         self.2
     }
+
+    pub fn from_kelvin(kelvin: Float) -> Color {
+        let t = kelvin.clamp(1667.0, 25000.0);
+        let x = if t <= 4000.0 {
+            -0.2661239e9 / t.powi(3) - 0.2343589e6 / t.powi(2) + 0.8776956e3 / t + 0.179910
+        } else {
+            -3.0258469e9 / t.powi(3) + 2.107038e6 / t.powi(2) + 0.2226347e3 / t + 0.240390
+        };
+        let y = if t <= 2222.0 {
+            -1.1063814 * x.powi(3) - 1.3481102 * x.powi(2) + 2.185558 * x - 0.2021968
+        } else if t <= 4000.0 {
+            -0.9549476 * x.powi(3) - 1.374186 * x.powi(2) + 2.09137 * x - 0.1674887
+        } else {
+            3.081758 * x.powi(3) - 5.873387 * x.powi(2) + 3.75113 * x - 0.3700148
+        };
+        let color = Color::from_xyz([x / y, 1.0, (1.0 - x - y) / y]);
+        let max = color.red().max(color.green()).max(color.blue());
+        color * (1.0 / max)
+    }
+
+    pub fn clamped(&self, max: Float) -> Color {
+        let brightest = self.red().max(self.green()).max(self.blue());
+        if brightest > max {
+            *self * (max / brightest)
+        } else {
+            *self
+        }
+    }
+
+    pub fn from_xyz(xyz: [Float; 3]) -> Color {
+        let [r, g, b] = mul3(&XYZ_TO_RGB, xyz);
+        Color(r, g, b)
+    }
+
+    pub fn to_xyz(&self) -> [Float; 3] {
+        mul3(&RGB_TO_XYZ, [self.red(), self.green(), self.blue()])
+    }
+
+    fn map<F: Fn(Float) -> Float>(&self, f: F) -> Color {
+        Color(f(self.0), f(self.1), f(self.2))
+    }
+
+    pub fn adapt(&self, source_white: Color, target_white: Color) -> Color {
+        let [sl, sm, ss] = mul3(&BRADFORD, source_white.to_xyz());
+        let [tl, tm, ts] = mul3(&BRADFORD, target_white.to_xyz());
+        let [l, m, s] = mul3(&BRADFORD, self.to_xyz());
+        let lms = [l * tl / sl, m * tm / sm, s * ts / ss];
+        Color::from_xyz(mul3(&BRADFORD_INVERSE, lms))
+    }
+}
+
+// How color values are encoded: linear light for rendering, or the sRGB
+// transfer curve of most images and color pickers.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ColorSpace {
+    Linear,
+    Srgb,
+}
+
+impl ColorSpace {
+    // From linear light to this space.
+    pub fn encode(self, color: Color) -> Color {
+        match self {
+            ColorSpace::Linear => color,
+            ColorSpace::Srgb => color.map(|v| {
+                if v <= 0.0031308 {
+                    v * 12.92
+                } else {
+                    1.055 * v.powf(1.0 / 2.4) - 0.055
+                }
+            }),
+        }
+    }
+
+    // From this space to linear light.
+    pub fn decode(self, color: Color) -> Color {
+        match self {
+            ColorSpace::Linear => color,
+            ColorSpace::Srgb => color.map(|v| {
+                if v <= 0.04045 {
+                    v / 12.92
+                } else {
+                    ((v + 0.055) / 1.055).powf(2.4)
+                }
+            }),
+        }
+    }
+}
+
+const RGB_TO_XYZ: [[Float; 3]; 3] = [
+    [0.4124564, 0.3575761, 0.1804375],
+    [0.2126729, 0.7151522, 0.072175],
+    [0.0193339, 0.119192, 0.9503041],
+];
+
+const XYZ_TO_RGB: [[Float; 3]; 3] = [
+    [3.2404542, -1.5371385, -0.4985314],
+    [-0.969266, 1.8760108, 0.041556],
+    [0.0556434, -0.2040259, 1.0572252],
+];
+
+const BRADFORD: [[Float; 3]; 3] = [
+    [0.8951, 0.2664, -0.1614],
+    [-0.7502, 1.7135, 0.0367],
+    [0.0389, -0.0685, 1.0296],
+];
+
+const BRADFORD_INVERSE: [[Float; 3]; 3] = [
+    [0.9869929, -0.1470543, 0.1599627],
+    [0.4323053, 0.5183603, 0.0492912],
+    [-0.0085287, 0.0400428, 0.9684867],
+];
+
+fn mul3(m: &[[Float; 3]; 3], v: [Float; 3]) -> [Float; 3] {
+    [
+        m[0][0] * v[0] + m[0][1] * v[1] + m[0][2] * v[2],
+        m[1][0] * v[0] + m[1][1] * v[1] + m[1][2] * v[2],
+        m[2][0] * v[0] + m[2][1] * v[1] + m[2][2] * v[2],
+    ]
 }
 
 impl cmp::PartialEq for Color {
@@ -82,6 +201,21 @@ impl ops::Mul<Color> for Color {
 mod tests {
     use super::*;
 
+    #[test]
+    fn encoding_and_decoding_srgb() {
+        let srgb = ColorSpace::Srgb;
+        let mid_gray = srgb.decode(Color(0.5, 0.5, 0.5));
+        assert_eq!(mid_gray, Color(0.21404, 0.21404, 0.21404));
+        assert_eq!(srgb.encode(mid_gray), Color(0.5, 0.5, 0.5));
+        assert_eq!(
+            srgb.encode(Color(0.0, 0.001, 1.0)),
+            Color(0.0, 0.01292, 1.0)
+        );
+        let c = Color(0.2, 0.4, 0.8);
+        assert_eq!(ColorSpace::Linear.encode(c), c);
+        assert_eq!(srgb.decode(srgb.encode(c)), c);
+    }
+
     #[test]
     fn colors_are_tuples() {
         let c = Color(-0.5, 0.4, 1.7);
@@ -116,4 +250,44 @@ mod tests {
         let c2 = Color(0.9, 1.0, 0.1);
         assert_eq!(c1 * c2, Color(0.9, 0.2, 0.04));
     }
+
+    #[test]
+    fn converting_a_color_to_xyz_and_back() {
+        let c = Color(0.9, 0.6, 0.75);
+        assert_eq!(Color::from_xyz(c.to_xyz()), c);
+    }
+
+    #[test]
+    fn the_color_of_a_color_temperature() {
+        let warm = Color::from_kelvin(2700.0);
+        let cold = Color::from_kelvin(10000.0);
+        assert!(equals(warm.red(), 1.0));
+        assert!(warm.red() > warm.green() && warm.green() > warm.blue());
+        assert!(equals(cold.blue(), 1.0));
+        assert!(cold.blue() > cold.red());
+        let daylight = Color::from_kelvin(6500.0);
+        assert!(daylight.blue() > 0.9 && daylight.green() > 0.9);
+    }
+
+    #[test]
+    fn adapting_the_source_white_gives_the_target_white() {
+        let source = Color::from_kelvin(3000.0);
+        assert_eq!(source.adapt(source, WHITE), WHITE);
+        assert_eq!(WHITE.adapt(WHITE, source), source);
+    }
+
+    #[test]
+    fn adapting_between_identical_whites_is_the_identity() {
+        let white = Color::from_kelvin(4500.0);
+        let c = Color(0.2, 0.5, 0.8);
+        assert_eq!(c.adapt(white, white), c);
+    }
+
+    #[test]
+    fn clamping_a_color_keeps_its_hue() {
+        let c = Color(4.0, 2.0, 1.0);
+        assert_eq!(c.clamped(2.0), Color(2.0, 1.0, 0.5));
+        assert_eq!(c.clamped(Float::INFINITY), c);
+        assert_eq!(Color(0.2, 0.5, 0.8).clamped(1.0), Color(0.2, 0.5, 0.8));
+    }
 }
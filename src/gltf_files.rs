@@ -0,0 +1,230 @@
+use crate::cameras::Camera;
+use crate::colors::Color;
+use crate::floats::Float;
+use crate::lights::Light;
+use crate::matrices::Matrix;
+use crate::objects::Object;
+use crate::tuples::Tuple;
+use crate::worlds::World;
+use gltf::khr_lights_punctual::Kind;
+use std::sync::Arc;
+
+pub struct GltfCamera {
+    pub y_field_of_view: Float,
+    pub view: Matrix<4>,
+}
+
+impl GltfCamera {
+    pub fn camera(&self, hsize: usize, vsize: usize) -> Camera {
+        let aspect = hsize as Float / vsize as Float;
+        let field_of_view = if aspect >= 1.0 {
+            2.0 * ((self.y_field_of_view / 2.0).tan() * aspect).atan()
+        } else {
+            self.y_field_of_view
+        };
+        Camera::new(hsize, vsize, 1.0, field_of_view, self.view)
+    }
+}
+
+pub struct GltfFile {
+    pub world: World,
+    pub cameras: Vec<GltfCamera>,
+}
+
+pub fn parse_gltf_file_path(path: &str) -> Result<GltfFile, String> {
+    let (document, buffers, _) = gltf::import(path).map_err(|e| format!("{}: {}", path, e))?;
+    parse_gltf(&document, &buffers)
+}
+
+pub fn parse_gltf_slice(data: &[u8]) -> Result<GltfFile, String> {
+    let (document, buffers, _) = gltf::import_slice(data).map_err(|e| e.to_string())?;
+    parse_gltf(&document, &buffers)
+}
+
+fn parse_gltf(
+    document: &gltf::Document,
+    buffers: &[gltf::buffer::Data],
+) -> Result<GltfFile, String> {
+    let meshes = document
+        .meshes()
+        .map(|mesh| parse_mesh(&mesh, buffers))
+        .collect::<Result<Vec<_>, _>>()?;
+    let mut file = GltfFile {
+        world: World::new(),
+        cameras: vec![],
+    };
+    let scene = document
+        .default_scene()
+        .or_else(|| document.scenes().next())
+        .ok_or("no scene in glTF file")?;
+    for node in scene.nodes() {
+        let object = parse_node(&node, Matrix::identity(), &meshes, &mut file);
+        file.world.add_object(object);
+    }
+    Ok(file)
+}
+
+fn parse_node(
+    node: &gltf::Node,
+    parent: Matrix<4>,
+    meshes: &[Vec<Arc<Object>>],
+    file: &mut GltfFile,
+) -> Object {
+    let transform = Matrix::new(node.transform().matrix()).transpose();
+    let world = parent * transform;
+    let mut group = Object::new_group().with_transform(transform);
+    if let Some(mesh) = node.mesh() {
+        for primitive in &meshes[mesh.index()] {
+            let instance = Object::new_instance(Arc::clone(primitive), Matrix::identity());
+            group.as_mut_group().add_child(instance);
+        }
+    }
+    if let Some(camera) = node.camera()
+        && let gltf::camera::Projection::Perspective(perspective) = camera.projection()
+    {
+        file.cameras.push(GltfCamera {
+            y_field_of_view: perspective.yfov(),
+            view: world.inverse(),
+        });
+    }
+    if let Some(light) = node.light()
+        && let Kind::Point = light.kind()
+    {
+        let [r, g, b] = light.color();
+        file.world.lights.push(Light::new_point(
+            world * Tuple::point(0.0, 0.0, 0.0),
+            Color::new(r, g, b) * light.intensity(),
+        ));
+    }
+    for child in node.children() {
+        let child = parse_node(&child, world, meshes, file);
+        group.as_mut_group().add_child(child);
+    }
+    group
+}
+
+fn parse_mesh(
+    mesh: &gltf::Mesh,
+    buffers: &[gltf::buffer::Data],
+) -> Result<Vec<Arc<Object>>, String> {
+    let mut primitives = vec![];
+    for primitive in mesh.primitives() {
+        if primitive.mode() != gltf::mesh::Mode::Triangles {
+            continue;
+        }
+        let reader = primitive.reader(|buffer| buffers.get(buffer.index()).map(|d| &d.0[..]));
+        let positions: Vec<Tuple> = reader
+            .read_positions()
+            .ok_or("mesh primitive without positions")?
+            .map(|[x, y, z]| Tuple::point(x, y, z))
+            .collect();
+        let normals: Option<Vec<Tuple>> = reader
+            .read_normals()
+            .map(|ns| ns.map(|[x, y, z]| Tuple::vector(x, y, z)).collect());
+        let indices: Vec<usize> = match reader.read_indices() {
+            Some(indices) => indices.into_u32().map(|i| i as usize).collect(),
+            None => (0..positions.len()).collect(),
+        };
+        if indices.iter().any(|&i| i >= positions.len()) {
+            return Err(String::from("mesh index out of range"));
+        }
+        let mut group = Object::new_group();
+        let [r, g, b, _] = primitive
+            .material()
+            .pbr_metallic_roughness()
+            .base_color_factor();
+        group.material.color = Color::new(r, g, b);
+        for face in indices.chunks_exact(3) {
+            let [p1, p2, p3] = [0, 1, 2].map(|i| positions[face[i]]);
+            let triangle = match &normals {
+                Some(normals) => {
+                    let [n1, n2, n3] = [0, 1, 2].map(|i| normals[face[i]]);
+                    Object::new_smooth_triangle(p1, p2, p3, n1, n2, n3)
+                }
+                None => Object::new_triangle(p1, p2, p3),
+            };
+            group
+                .as_mut_group()
+                .add_child(triangle.with_inherited_material());
+        }
+        primitives.push(group.into_shared());
+    }
+    Ok(primitives)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::floats::{equals, PI};
+    use crate::rays::Ray;
+    use crate::transformations::translation;
+
+    // One triangle in the z = 0 plane, lit by a point light and seen by a
+    // camera that is moved 5 units back along +z.
+    const TRIANGLE: &str = r#"{
+        "asset": { "version": "2.0" },
+        "extensionsUsed": ["KHR_lights_punctual"],
+        "extensions": {
+            "KHR_lights_punctual": {
+                "lights": [{ "type": "point", "color": [1, 0.5, 0.25], "intensity": 2 }]
+            }
+        },
+        "scene": 0,
+        "scenes": [{ "nodes": [0, 1, 2] }],
+        "nodes": [
+            { "mesh": 0, "translation": [0, 0, -1] },
+            { "camera": 0, "translation": [0, 0, 5] },
+            { "extensions": { "KHR_lights_punctual": { "light": 0 } }, "translation": [0, 10, 0] }
+        ],
+        "cameras": [{ "type": "perspective", "perspective": { "yfov": 1.0471976, "znear": 0.1 } }],
+        "materials": [{ "pbrMetallicRoughness": { "baseColorFactor": [0.8, 0.2, 0.1, 1] } }],
+        "meshes": [{ "primitives": [{ "attributes": { "POSITION": 0 }, "material": 0 }] }],
+        "accessors": [{
+            "bufferView": 0, "componentType": 5126, "count": 3, "type": "VEC3",
+            "min": [-1, -1, 0], "max": [1, 1, 0]
+        }],
+        "bufferViews": [{ "buffer": 0, "byteLength": 36 }],
+        "buffers": [{
+            "byteLength": 36,
+            "uri": "data:application/octet-stream;base64,AACAvwAAgL8AAAAAAACAPwAAgL8AAAAAAAAAAAAAgD8AAAAA"
+        }]
+    }"#;
+
+    #[test]
+    fn loading_meshes_and_materials() {
+        let mut file = parse_gltf_slice(TRIANGLE.as_bytes()).unwrap();
+        file.world.prepare();
+        let r = Ray::new(Tuple::point(0.0, 0.0, 5.0), Tuple::vector(0.0, 0.0, -1.0));
+        let mut xs = vec![];
+        for object in &file.world.objects {
+            object.intersect(&r, &mut xs);
+        }
+        assert_eq!(xs.len(), 1);
+        assert!(equals(xs[0].t, 6.0));
+        assert_eq!(
+            xs[0].shading_object().material.color,
+            Color::new(0.8, 0.2, 0.1)
+        );
+    }
+
+    #[test]
+    fn loading_point_lights() {
+        let file = parse_gltf_slice(TRIANGLE.as_bytes()).unwrap();
+        assert_eq!(file.world.lights.len(), 1);
+        assert_eq!(file.world.lights[0].position, Tuple::point(0.0, 10.0, 0.0));
+        assert_eq!(file.world.lights[0].intensity, Color::new(2.0, 1.0, 0.5));
+    }
+
+    #[test]
+    fn loading_cameras() {
+        let file = parse_gltf_slice(TRIANGLE.as_bytes()).unwrap();
+        assert_eq!(file.cameras.len(), 1);
+        assert!(equals(file.cameras[0].y_field_of_view, PI / 3.0));
+        assert_eq!(file.cameras[0].view, translation(0.0, 0.0, -5.0));
+    }
+
+    #[test]
+    fn invalid_files_are_reported() {
+        assert!(parse_gltf_slice(b"not a gltf file").is_err());
+    }
+}
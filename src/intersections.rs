@@ -1,6 +1,6 @@
 use crate::floats::{Float, EPSILON};
 use crate::objects::Object;
-use crate::rays::Ray;
+use crate::rays::{Ray, RayDifferentials};
 use crate::tuples::Tuple;
 use std::ptr;
 
@@ -9,9 +9,13 @@ pub struct Intersection<'a> {
     pub u: Float,
     pub v: Float,
     pub object: &'a Object,
+    pub instance: Option<&'a Object>,
 }
 
-pub struct IntersectionComputations {
+pub struct IntersectionComputations<'a> {
+    pub t: Float,
+    // The object whose material shades the hit: its instance when there is one.
+    pub object: &'a Object,
     pub point: Tuple,
     pub over_point: Tuple,
     pub under_point: Tuple,
@@ -21,6 +25,9 @@ pub struct IntersectionComputations {
     pub reflectv: Tuple,
     pub n1: Float,
     pub n2: Float,
+    pub footprint: Float,
+    pub differentials: Option<RayDifferentials>,
+    pub attributes: Vec<Tuple>,
 }
 
 impl<'a> Intersection<'a> {
@@ -30,28 +37,74 @@ impl<'a> Intersection<'a> {
             u: 0.0,
             v: 0.0,
             object,
+            instance: None,
         }
     }
 
     pub fn new_with_uv(t: Float, object: &'a Object, u: Float, v: Float) -> Intersection<'a> {
-        Intersection { t, u, v, object }
+        Intersection {
+            t,
+            u,
+            v,
+            object,
+            instance: None,
+        }
+    }
+
+    pub fn shading_object(&self) -> &'a Object {
+        self.instance.unwrap_or(self.object)
+    }
+
+    pub fn surface_coordinates(&self, point: Tuple) -> (Float, Float) {
+        let point = match self.instance {
+            Some(instance) => instance.world_to_object(point),
+            None => point,
+        };
+        self.object.surface_coordinates(point, self)
     }
 
     pub fn prepare_computations(
-        &'a self,
+        &self,
+        ray: &Ray,
+        xs: &Vec<Intersection<'a>>,
+    ) -> IntersectionComputations<'a> {
+        self.prepare_computations_with_bias(ray, xs, EPSILON)
+    }
+
+    pub fn prepare_computations_with_bias(
+        &self,
         ray: &Ray,
-        xs: &'a Vec<Intersection<'a>>,
-    ) -> IntersectionComputations {
+        xs: &Vec<Intersection<'a>>,
+        shadow_bias: Float,
+    ) -> IntersectionComputations<'a> {
         let point = ray.position(self.t);
         let eyev = -ray.direction;
-        let normalv = self.object.normal_at(point, self);
+        let normalv = match self.instance {
+            Some(instance) => {
+                let local_point = instance.world_to_object(point);
+                instance.normal_to_world(self.object.normal_at(local_point, self))
+            }
+            None => self.object.normal_at(point, self),
+        };
         let inside = normalv.dot(eyev) < 0.0;
         let normalv = if inside { -normalv } else { normalv };
-        let over_point = point + normalv * EPSILON;
-        let under_point = point - normalv * EPSILON;
+        let over_point = point + normalv * shadow_bias;
+        let under_point = point - normalv * shadow_bias;
         let reflectv = ray.direction.reflect(normalv);
         let (n1, n2) = self.find_refraction_indices(xs);
+        let differentials = ray
+            .differentials
+            .map(|d| d.transfer(ray.direction, self.t, normalv));
+        let footprint = match &differentials {
+            Some(d) => d.footprint(),
+            None => {
+                let distance = self.t * ray.direction.magnitude();
+                ray.spread * distance / (eyev.normalize().dot(normalv)).abs().max(0.05)
+            }
+        };
         IntersectionComputations {
+            t: self.t,
+            object: self.shading_object(),
             point,
             over_point,
             under_point,
@@ -61,26 +114,53 @@ impl<'a> Intersection<'a> {
             reflectv,
             n1,
             n2,
+            footprint,
+            differentials,
+            attributes: self.object.vertex_attributes(self),
         }
     }
 
+    fn same_surface(&self, other: &Intersection) -> bool {
+        ptr::eq(self.object, other.object)
+            && self.instance.map(ptr::from_ref) == other.instance.map(ptr::from_ref)
+    }
+
     fn find_refraction_indices(&self, xs: &Vec<Intersection>) -> (Float, Float) {
-        let mut containers: Vec<&Object> = Vec::with_capacity(xs.len());
+        let listed = xs.iter().any(|x| ptr::eq(x, self));
+        let is_self = |x: &Intersection| {
+            if listed {
+                ptr::eq(x, self)
+            } else {
+                x.t == self.t && x.same_surface(self)
+            }
+        };
+        let mut containers: Vec<&Intersection> = Vec::with_capacity(xs.len());
         let mut n1 = 1.0;
         let mut n2 = 1.0;
         for x in xs {
-            if x.t == self.t && !containers.is_empty() {
-                n1 = containers.last().unwrap().material.refractive_index;
+            let current = is_self(x);
+            if current && !containers.is_empty() {
+                n1 = containers
+                    .last()
+                    .unwrap()
+                    .shading_object()
+                    .material
+                    .refractive_index;
             }
-            let i = containers.iter().position(|c| ptr::eq(*c, x.object));
+            let i = containers.iter().position(|c| c.same_surface(x));
             if let Some(i) = i {
                 containers.remove(i);
             } else {
-                containers.push(x.object);
+                containers.push(x);
             }
-            if x.t == self.t {
+            if current {
                 if !containers.is_empty() {
-                    n2 = containers.last().unwrap().material.refractive_index;
+                    n2 = containers
+                        .last()
+                        .unwrap()
+                        .shading_object()
+                        .material
+                        .refractive_index;
                 }
                 break;
             }
@@ -90,8 +170,14 @@ impl<'a> Intersection<'a> {
 }
 
 pub fn hit<'a>(xs: &'a Vec<Intersection<'a>>) -> Option<&'a Intersection<'a>> {
+    hit_after(xs, 0.0)
+}
+
+// Unlike the ray range, this keeps the closer intersections in the list, so
+// that refraction indices still know which objects contain the ray origin.
+pub fn hit_after<'a, 'b>(xs: &'b [Intersection<'a>], t_min: Float) -> Option<&'b Intersection<'a>> {
     xs.iter()
-        .filter(|i| i.t >= 0.0)
+        .filter(|i| i.t >= t_min)
         .min_by(|i1, i2| i1.t.total_cmp(&i2.t))
 }
 
@@ -110,11 +196,27 @@ pub fn schlick(comps: &IntersectionComputations) -> Float {
     r0 + (1.0 - r0) * (1.0 - cos).powi(5)
 }
 
+// Exact Fresnel reflectances for light polarized perpendicular (s) and
+// parallel (p) to the plane of incidence.
+pub fn fresnel_sp(comps: &IntersectionComputations) -> (Float, Float) {
+    let (n1, n2) = (comps.n1, comps.n2);
+    let cos_i = comps.eyev.dot(comps.normalv).clamp(0.0, 1.0);
+    let sin2_t = (n1 / n2).powi(2) * (1.0 - cos_i.powi(2));
+    if sin2_t > 1.0 {
+        return (1.0, 1.0);
+    }
+    let cos_t = (1.0 - sin2_t).sqrt();
+    let rs = (n1 * cos_i - n2 * cos_t) / (n1 * cos_i + n2 * cos_t);
+    let rp = (n1 * cos_t - n2 * cos_i) / (n1 * cos_t + n2 * cos_i);
+    (rs.powi(2), rp.powi(2))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
     use crate::floats::{equals, SQRT_2};
     use crate::matrices::Matrix;
+    use crate::shapes::csg::Operation;
     use crate::transformations::{scaling, translation};
 
     #[test]
@@ -166,6 +268,18 @@ mod tests {
         assert_eq!(i.t, 2.0);
     }
 
+    #[test]
+    fn the_hit_after_a_minimum_t_skips_closer_intersections() {
+        let s = Object::new_sphere();
+        let xs = vec![
+            Intersection::new(-1.0, &s),
+            Intersection::new(0.001, &s),
+            Intersection::new(3.0, &s),
+        ];
+        assert_eq!(hit_after(&xs, 0.01).unwrap().t, 3.0);
+        assert!(hit_after(&xs, 5.0).is_none());
+    }
+
     #[test]
     fn precomputing_the_state_of_an_intersection() {
         let r = Ray::new(Tuple::point(0.0, 0.0, -5.0), Tuple::vector(0.0, 0.0, 1.0));
@@ -175,6 +289,54 @@ mod tests {
         assert_eq!(comps.point, Tuple::point(0.0, 0.0, -1.0));
         assert_eq!(comps.eyev, Tuple::vector(0.0, 0.0, -1.0));
         assert_eq!(comps.normalv, Tuple::vector(0.0, 0.0, -1.0));
+        assert_eq!(comps.t, 4.0);
+        assert!(ptr::eq(comps.object, &object));
+    }
+
+    #[test]
+    fn the_computations_of_an_instance_hit_refer_to_the_instance() {
+        let r = Ray::new(Tuple::point(0.0, 0.0, -5.0), Tuple::vector(0.0, 0.0, 1.0));
+        let instance = Object::new_instance(Object::new_sphere().into_shared(), Matrix::identity());
+        let mut i = Intersection::new(4.0, &instance.as_instance().object);
+        i.instance = Some(&instance);
+        let comps = i.prepare_computations(&r, &vec![]);
+        assert!(ptr::eq(comps.object, &instance));
+    }
+
+    #[test]
+    fn the_footprint_grows_with_distance_and_grazing_angle() {
+        let object = Object::new_plane();
+        let r =
+            Ray::new(Tuple::point(0.0, 10.0, 0.0), Tuple::vector(0.0, -1.0, 0.0)).with_spread(0.01);
+        let i = Intersection::new(10.0, &object);
+        assert!(equals(i.prepare_computations(&r, &vec![]).footprint, 0.1));
+        let r = Ray::new(
+            Tuple::point(0.0, 1.0, -1.0),
+            Tuple::vector(0.0, -SQRT_2 / 2.0, SQRT_2 / 2.0),
+        )
+        .with_spread(0.01);
+        let i = Intersection::new(SQRT_2, &object);
+        assert!(equals(i.prepare_computations(&r, &vec![]).footprint, 0.02));
+        let r = Ray::new(Tuple::point(0.0, 10.0, 0.0), Tuple::vector(0.0, -1.0, 0.0));
+        let i = Intersection::new(10.0, &object);
+        assert_eq!(i.prepare_computations(&r, &vec![]).footprint, 0.0);
+    }
+
+    #[test]
+    fn the_footprint_uses_ray_differentials_when_present() {
+        let object = Object::new_plane();
+        let r = Ray::new(Tuple::point(0.0, 10.0, 0.0), Tuple::vector(0.0, -1.0, 0.0))
+            .with_spread(0.01)
+            .with_differentials(RayDifferentials::new(
+                Tuple::vector(0.002, 0.0, 0.0),
+                Tuple::vector(0.0, 0.0, 0.001),
+            ));
+        let i = Intersection::new(10.0, &object);
+        let comps = i.prepare_computations(&r, &vec![]);
+        assert!(equals(comps.footprint, 0.02));
+        let d = comps.differentials.unwrap();
+        assert_eq!(d.dpdx, Tuple::vector(0.02, 0.0, 0.0));
+        assert_eq!(d.dpdy, Tuple::vector(0.0, 0.0, 0.01));
     }
 
     #[test]
@@ -208,6 +370,16 @@ mod tests {
         assert!(comps.point.z() > comps.over_point.z());
     }
 
+    #[test]
+    fn the_hit_offset_uses_the_shadow_bias() {
+        let r = Ray::new(Tuple::point(0.0, 0.0, -5.0), Tuple::vector(0.0, 0.0, 1.0));
+        let object = Object::new_sphere();
+        let i = Intersection::new(4.0, &object);
+        let comps = i.prepare_computations_with_bias(&r, &vec![], 0.5);
+        assert_eq!(comps.over_point, Tuple::point(0.0, 0.0, -1.5));
+        assert_eq!(comps.under_point, Tuple::point(0.0, 0.0, -0.5));
+    }
+
     #[test]
     fn precomputing_the_reflection_vector() {
         let object = Object::new_plane();
@@ -274,6 +446,71 @@ mod tests {
         )
     }
 
+    fn glass_sphere(transform: Matrix<4>, refractive_index: Float) -> Object {
+        let mut s = Object::new_sphere()
+            .made_of_glass()
+            .with_transform(transform);
+        s.material.refractive_index = refractive_index;
+        s
+    }
+
+    #[test]
+    fn finding_n1_and_n2_at_touching_surfaces() {
+        let a = glass_sphere(Matrix::identity(), 1.5);
+        let b = glass_sphere(translation(0.0, 0.0, 2.0), 2.0);
+        let r = Ray::new(Tuple::point(0.0, 0.0, -4.0), Tuple::vector(0.0, 0.0, 1.0));
+        let xs = vec![
+            Intersection::new(3.0, &a),
+            Intersection::new(5.0, &a),
+            Intersection::new(5.0, &b),
+            Intersection::new(7.0, &b),
+        ];
+        assert_eq!(
+            xs.iter()
+                .map(|x| x.prepare_computations(&r, &xs))
+                .map(|comps| (comps.n1, comps.n2))
+                .collect::<Vec<_>>(),
+            vec![(1.0, 1.5), (1.5, 1.0), (1.0, 2.0), (2.0, 1.0)]
+        )
+    }
+
+    #[test]
+    fn finding_n1_and_n2_at_coincident_nested_surfaces() {
+        let a = glass_sphere(scaling(2.0, 2.0, 2.0), 1.5);
+        let b = glass_sphere(translation(0.0, 0.0, 1.0), 2.0);
+        let r = Ray::new(Tuple::point(0.0, 0.0, -4.0), Tuple::vector(0.0, 0.0, 1.0));
+        let xs = vec![
+            Intersection::new(2.0, &a),
+            Intersection::new(4.0, &b),
+            Intersection::new(6.0, &b),
+            Intersection::new(6.0, &a),
+        ];
+        assert_eq!(
+            xs.iter()
+                .map(|x| x.prepare_computations(&r, &xs))
+                .map(|comps| (comps.n1, comps.n2))
+                .collect::<Vec<_>>(),
+            vec![(1.0, 1.5), (1.5, 2.0), (2.0, 1.5), (1.5, 1.0)]
+        )
+    }
+
+    #[test]
+    fn finding_n1_and_n2_at_touching_surfaces_in_a_csg() {
+        let a = glass_sphere(Matrix::identity(), 1.5);
+        let b = glass_sphere(translation(0.0, 0.0, 2.0), 2.0);
+        let c = Object::new_csg(Operation::Union, a, b);
+        let r = Ray::new(Tuple::point(0.0, 0.0, -4.0), Tuple::vector(0.0, 0.0, 1.0));
+        let mut xs = Vec::new();
+        c.intersect(&r, &mut xs);
+        assert_eq!(
+            xs.iter()
+                .map(|x| x.prepare_computations(&r, &xs))
+                .map(|comps| (comps.n1, comps.n2))
+                .collect::<Vec<_>>(),
+            vec![(1.0, 1.5), (1.5, 1.0), (1.0, 2.0), (2.0, 1.0)]
+        )
+    }
+
     #[test]
     fn the_schlick_approximation_under_total_internal_reflection() {
         let object = Object::new_sphere()
@@ -319,6 +556,25 @@ mod tests {
         assert!(equals(reflectance, 0.48873067));
     }
 
+    #[test]
+    fn the_fresnel_equations_split_s_and_p_reflectances() {
+        let glass = Object::new_plane().made_of_glass();
+        let reflectances = |angle: Float| {
+            let direction = Tuple::vector(angle.sin(), -angle.cos(), 0.0);
+            let r = Ray::new(Tuple::point(0.0, 0.0, 0.0) - direction, direction);
+            let xs = vec![Intersection::new(1.0, &glass)];
+            fresnel_sp(&xs[0].prepare_computations(&r, &xs))
+        };
+        let (rs, rp) = reflectances(0.0);
+        assert!(equals(rs, 0.04) && equals(rp, 0.04));
+        // Nothing polarized in the plane of incidence reflects at Brewster's angle.
+        let (rs, rp) = reflectances(1.5_f32.atan());
+        assert!(equals(rs, 0.14793));
+        assert!(equals(rp, 0.0));
+        let (rs, rp) = reflectances(1.5);
+        assert!(rs > 0.75 && rp > 0.5 && rs > rp);
+    }
+
     #[test]
     fn an_intersection_can_encapsulate_u_and_v() {
         let s = Object::new_triangle(
@@ -0,0 +1,135 @@
+use crate::bounds::Bounds;
+use crate::floats::{Float, PI};
+use crate::matrices::Matrix;
+use crate::objects::Object;
+use crate::transformations::{rotation_y, translation};
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+
+fn placed(object: Object, placement: Matrix<4>) -> Object {
+    let transform = placement * object.transform;
+    object.with_transform(transform)
+}
+
+pub fn grid_of<F>(mut factory: F, nx: usize, nz: usize, spacing: Float) -> Object
+where
+    F: FnMut(usize, usize) -> Object,
+{
+    let mut group = Object::new_group();
+    let offset = |n: usize, i: usize| (i as Float - (n as Float - 1.0) / 2.0) * spacing;
+    for i in 0..nx {
+        for j in 0..nz {
+            let t = translation(offset(nx, i), 0.0, offset(nz, j));
+            group.as_mut_group().add_child(placed(factory(i, j), t));
+        }
+    }
+    group
+}
+
+pub fn ring_of<F>(mut factory: F, n: usize, radius: Float) -> Object
+where
+    F: FnMut(usize) -> Object,
+{
+    let mut group = Object::new_group();
+    for i in 0..n {
+        let angle = 2.0 * PI * i as Float / n as Float;
+        let t = translation(radius * angle.cos(), 0.0, radius * angle.sin()) * rotation_y(-angle);
+        group.as_mut_group().add_child(placed(factory(i), t));
+    }
+    group
+}
+
+pub fn scatter_on_plane<F>(mut factory: F, n: usize, seed: u64, bounds: &Bounds) -> Object
+where
+    F: FnMut(usize) -> Object,
+{
+    let mut rng = StdRng::seed_from_u64(seed);
+    let mut group = Object::new_group();
+    for i in 0..n {
+        let x = bounds.min.x() + rng.random::<Float>() * (bounds.max.x() - bounds.min.x());
+        let z = bounds.min.z() + rng.random::<Float>() * (bounds.max.z() - bounds.min.z());
+        let angle = rng.random::<Float>() * 2.0 * PI;
+        let t = translation(x, bounds.min.y(), z) * rotation_y(angle);
+        group.as_mut_group().add_child(placed(factory(i), t));
+    }
+    group
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::transformations::scaling;
+    use crate::tuples::Tuple;
+
+    fn positions(group: &Object) -> Vec<Tuple> {
+        group
+            .as_group()
+            .children
+            .iter()
+            .map(|c| c.transform * Tuple::point(0.0, 0.0, 0.0))
+            .collect()
+    }
+
+    #[test]
+    fn a_grid_is_centered_on_the_origin() {
+        let g = grid_of(|_, _| Object::new_sphere(), 3, 2, 2.0);
+        assert_eq!(
+            positions(&g),
+            vec![
+                Tuple::point(-2.0, 0.0, -1.0),
+                Tuple::point(-2.0, 0.0, 1.0),
+                Tuple::point(0.0, 0.0, -1.0),
+                Tuple::point(0.0, 0.0, 1.0),
+                Tuple::point(2.0, 0.0, -1.0),
+                Tuple::point(2.0, 0.0, 1.0),
+            ]
+        );
+    }
+
+    #[test]
+    fn a_grid_keeps_the_transform_of_each_object() {
+        let g = grid_of(
+            |_, _| Object::new_sphere().with_transform(scaling(0.5, 0.5, 0.5)),
+            2,
+            1,
+            4.0,
+        );
+        let child = &g.as_group().children[1];
+        assert_eq!(
+            child.transform,
+            translation(2.0, 0.0, 0.0) * scaling(0.5, 0.5, 0.5)
+        );
+    }
+
+    #[test]
+    fn a_ring_places_objects_around_the_origin() {
+        let g = ring_of(|_| Object::new_cube(), 4, 3.0);
+        assert_eq!(
+            positions(&g),
+            vec![
+                Tuple::point(3.0, 0.0, 0.0),
+                Tuple::point(0.0, 0.0, 3.0),
+                Tuple::point(-3.0, 0.0, 0.0),
+                Tuple::point(0.0, 0.0, -3.0),
+            ]
+        );
+    }
+
+    #[test]
+    fn scattering_is_deterministic_and_stays_within_bounds() {
+        let bounds = Bounds {
+            min: Tuple::point(-5.0, 1.0, -2.0),
+            max: Tuple::point(5.0, 1.0, 2.0),
+        };
+        let a = scatter_on_plane(|_| Object::new_sphere(), 20, 7, &bounds);
+        let b = scatter_on_plane(|_| Object::new_sphere(), 20, 7, &bounds);
+        let c = scatter_on_plane(|_| Object::new_sphere(), 20, 8, &bounds);
+        assert_eq!(positions(&a), positions(&b));
+        assert_ne!(positions(&a), positions(&c));
+        for p in positions(&a) {
+            assert!((-5.0..=5.0).contains(&p.x()));
+            assert_eq!(p.y(), 1.0);
+            assert!((-2.0..=2.0).contains(&p.z()));
+        }
+    }
+}
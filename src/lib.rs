@@ -1,17 +1,30 @@
+pub mod accelerators;
 pub mod bounds;
 pub mod cameras;
 pub mod canvas;
 pub mod colors;
 pub mod floats;
+#[cfg(feature = "gltf")]
+pub mod gltf_files;
 pub mod intersections;
+pub mod layout;
 pub mod lights;
 pub mod materials;
 pub mod matrices;
+pub mod media;
+pub mod meshes;
 pub mod obj_files;
 pub mod objects;
 pub mod patterns;
+#[cfg(feature = "polarization")]
+pub mod polarization;
 pub mod rays;
+pub mod render_contexts;
+pub mod scene_files;
+pub mod sequences;
 pub mod shapes;
+pub mod skies;
+pub mod testing;
 pub mod transformations;
 pub mod tuples;
 pub mod worlds;
@@ -1,25 +1,35 @@
-use crate::colors::Color;
-use crate::floats::Float;
+use crate::colors::{Color, WHITE};
+use crate::floats::{Float, PI};
+use crate::matrices::Matrix;
+use crate::patterns::Pattern;
 use crate::rays::Ray;
 use crate::tuples::Tuple;
 
 mod cube_lights;
+mod gels;
+mod panel_lights;
 mod point_lights;
+mod sampling;
 mod sphere_lights;
 mod spot_lights;
 
-#[derive(Debug, Clone, Copy, PartialEq)]
+pub use gels::GelMapping;
+
+#[derive(Debug, Clone, PartialEq)]
 enum Lights {
     Cube(cube_lights::CubeLight),
-    Point,
+    Panel(panel_lights::PanelLight),
+    Point(point_lights::PointLight),
     Sphere(sphere_lights::SphereLight),
     Spot(spot_lights::SpotLight),
 }
 
-#[derive(Debug, Clone, Copy, PartialEq)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct Light {
     pub position: Tuple,
     pub intensity: Color,
+    pub temperature: Option<Float>,
+    gel: Option<gels::Gel>,
     light: Lights,
 }
 
@@ -28,6 +38,8 @@ impl Light {
         Light {
             position,
             intensity,
+            temperature: None,
+            gel: None,
             light,
         }
     }
@@ -38,8 +50,25 @@ impl Light {
             intensity,
         )
     }
+    pub fn new_panel(
+        position: Tuple,
+        intensity: Color,
+        u: Tuple,
+        v: Tuple,
+        samples: usize,
+    ) -> Light {
+        Light::new(
+            Lights::Panel(panel_lights::PanelLight::new(u, v, samples)),
+            position,
+            intensity,
+        )
+    }
     pub fn new_point(position: Tuple, intensity: Color) -> Light {
-        Light::new(Lights::Point, position, intensity)
+        Light::new(
+            Lights::Point(point_lights::PointLight::new()),
+            position,
+            intensity,
+        )
     }
     pub fn new_sphere(position: Tuple, intensity: Color, size: Float, samples: usize) -> Light {
         Light::new(
@@ -62,26 +91,134 @@ impl Light {
         )
     }
 
-    pub fn shadowed<T>(&self, point: Tuple, hit_fn: T) -> Light
+    pub fn describe(&self) -> String {
+        let kind = match self.light {
+            Lights::Cube(_) => "cube",
+            Lights::Panel(_) => "panel",
+            Lights::Point(_) => "point",
+            Lights::Sphere(_) => "sphere",
+            Lights::Spot(_) => "spot",
+        };
+        format!(
+            "{} light at ({}, {}, {}) intensity ({}, {}, {})",
+            kind,
+            self.position.x(),
+            self.position.y(),
+            self.position.z(),
+            self.intensity.red(),
+            self.intensity.green(),
+            self.intensity.blue()
+        )
+    }
+
+    // Moves the light along with objects, e.g. those of a merged world.
+    // Cube and sphere lights keep their size.
+    pub fn transformed(self, transform: Matrix<4>) -> Light {
+        let light = match self.light {
+            Lights::Panel(panel) => Lights::Panel(panel.transformed(transform)),
+            Lights::Spot(spot) => Lights::Spot(spot.transformed(transform)),
+            light => light,
+        };
+        Light {
+            position: transform * self.position,
+            light,
+            ..self
+        }
+    }
+
+    pub fn with_temperature(self, kelvin: Float) -> Light {
+        Light {
+            intensity: self.intensity * Color::from_kelvin(kelvin),
+            temperature: Some(kelvin),
+            ..self
+        }
+    }
+
+    // Physical units: the color of the light is scaled by its luminous
+    // intensity, so that it gives the illuminance in lux at one unit away.
+    pub fn with_candela(self, candela: Float) -> Light {
+        Light {
+            intensity: self.intensity * candela,
+            ..self
+        }
+    }
+
+    // The luminous flux is spread over the solid angle the light shines in.
+    pub fn with_lumens(self, lumens: Float) -> Light {
+        let solid_angle = match &self.light {
+            // A lambertian emitter, brightest along its normal.
+            Lights::Panel(_) => PI,
+            Lights::Spot(spot) => spot.solid_angle(),
+            _ => 4.0 * PI,
+        };
+        self.with_candela(lumens / solid_angle)
+    }
+
+    pub fn with_gel(self, pattern: Pattern, mapping: GelMapping) -> Light {
+        Light {
+            gel: Some(gels::Gel::new(pattern, mapping)),
+            ..self
+        }
+    }
+
+    pub fn with_texture(self, texture: Pattern) -> Light {
+        match self.light {
+            Lights::Panel(panel) => Light {
+                light: Lights::Panel(panel.with_texture(texture)),
+                ..self
+            },
+            _ => panic!("Only panel lights can be textured !"),
+        }
+    }
+
+    pub fn with_softness(self, softness: Float, samples: usize) -> Light {
+        match self.light {
+            Lights::Point(point) => Light {
+                light: Lights::Point(point.with_softness(softness, samples)),
+                ..self
+            },
+            _ => panic!("Only point lights can be softened !"),
+        }
+    }
+
+    pub fn white_balanced(&self, white_balance: Option<Float>) -> Light {
+        match (self.temperature, white_balance) {
+            (Some(_), Some(kelvin)) => Light {
+                intensity: self.intensity.adapt(Color::from_kelvin(kelvin), WHITE),
+                ..self.clone()
+            },
+            _ => self.clone(),
+        }
+    }
+
+    pub fn shadowed<T>(&self, point: Tuple, occluded_fn: T) -> Light
     where
-        T: Fn(&Ray) -> Option<Float>,
+        T: Fn(&Ray, Float) -> bool,
     {
+        let filter = self
+            .gel
+            .as_ref()
+            .map_or(WHITE, |g| g.filter(self.position, point));
+        let intensity = match &self.light {
+            Lights::Cube(cube) => {
+                cube.shadowed_intensity(self.position, self.intensity, point, occluded_fn)
+            }
+            Lights::Panel(panel) => {
+                panel.shadowed_intensity(self.position, self.intensity, point, occluded_fn)
+            }
+            Lights::Point(light) => {
+                light.shadowed_intensity(self.position, self.intensity, point, occluded_fn)
+            }
+            Lights::Sphere(sphere) => {
+                sphere.shadowed_intensity(self.position, self.intensity, point, occluded_fn)
+            }
+            Lights::Spot(spot) => {
+                spot.shadowed_intensity(self.position, self.intensity, point, occluded_fn)
+            }
+        };
         Light {
-            intensity: match self.light {
-                Lights::Cube(cube) => {
-                    cube.shadowed_intensity(self.position, self.intensity, point, hit_fn)
-                }
-                Lights::Point => {
-                    point_lights::shadowed_intensity(self.position, self.intensity, point, hit_fn)
-                }
-                Lights::Sphere(sphere) => {
-                    sphere.shadowed_intensity(self.position, self.intensity, point, hit_fn)
-                }
-                Lights::Spot(spot) => {
-                    spot.shadowed_intensity(self.position, self.intensity, point, hit_fn)
-                }
-            },
-            ..*self
+            intensity: intensity * filter,
+            ..self.clone()
         }
     }
 }
@@ -98,4 +235,54 @@ mod tests {
         assert_eq!(light.position, position);
         assert_eq!(light.intensity, intensity);
     }
+
+    #[test]
+    fn a_light_with_a_color_temperature() {
+        let light =
+            Light::new_point(Tuple::point(0.0, 0.0, 0.0), WHITE * 2.0).with_temperature(3000.0);
+        assert_eq!(light.temperature, Some(3000.0));
+        assert_eq!(light.intensity, Color::from_kelvin(3000.0) * 2.0);
+    }
+
+    #[test]
+    fn white_balancing_a_light_at_the_white_point_makes_it_neutral() {
+        let light = Light::new_point(Tuple::point(0.0, 0.0, 0.0), WHITE).with_temperature(3000.0);
+        assert_eq!(light.white_balanced(Some(3000.0)).intensity, WHITE);
+        assert_eq!(light.white_balanced(None).intensity, light.intensity);
+    }
+
+    #[test]
+    fn a_gel_filters_the_light_reaching_a_point() {
+        let red = Color::new(1.0, 0.0, 0.0);
+        let light = Light::new_point(Tuple::point(0.0, 5.0, 0.0), WHITE * 2.0)
+            .with_gel(Pattern::new_stripe(red, WHITE), GelMapping::Direction);
+        let lit = |x| {
+            light
+                .shadowed(Tuple::point(x, 0.0, 0.0), |_, _| false)
+                .intensity
+        };
+        assert_eq!(lit(1.0), red * 2.0);
+        assert_eq!(lit(-1.0), WHITE * 2.0);
+        assert_eq!(light.intensity, WHITE * 2.0);
+    }
+
+    #[test]
+    fn light_intensities_in_physical_units() {
+        let origin = Tuple::point(0.0, 0.0, 0.0);
+        let color = Color::new(1.0, 0.5, 0.25);
+        let light = Light::new_point(origin, color).with_candela(100.0);
+        assert_eq!(light.intensity, color * 100.0);
+        let bulb = Light::new_point(origin, WHITE).with_lumens(800.0);
+        assert_eq!(bulb.intensity, WHITE * (800.0 / (4.0 * PI)));
+        let spot = Light::new_spot(origin, WHITE, Tuple::vector(0.0, -1.0, 0.0), PI / 3.0, 0.0)
+            .with_lumens(800.0);
+        assert_eq!(spot.intensity, WHITE * (800.0 / PI));
+    }
+
+    #[test]
+    fn white_balancing_ignores_lights_without_a_temperature() {
+        let intensity = Color::new(1.0, 0.5, 0.25);
+        let light = Light::new_point(Tuple::point(0.0, 0.0, 0.0), intensity);
+        assert_eq!(light.white_balanced(Some(3000.0)).intensity, intensity);
+    }
 }
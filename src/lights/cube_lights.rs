@@ -1,6 +1,6 @@
 use crate::colors::Color;
 use crate::floats::Float;
-use crate::lights::point_lights;
+use crate::lights::{point_lights, sampling};
 use crate::rays::Ray;
 use crate::tuples::Tuple;
 
@@ -20,15 +20,16 @@ impl CubeLight {
         light_position: Tuple,
         light_intensity: Color,
         point: Tuple,
-        hit_fn: T,
+        occluded_fn: T,
     ) -> Color
     where
-        T: Fn(&Ray) -> Option<Float>,
+        T: Fn(&Ray, Float) -> bool,
     {
+        let seed = sampling::seed(point);
         let mut n_shadowed = 0;
-        for _ in 0..self.samples {
-            let light_position = light_position + Tuple::random_vector(self.size);
-            n_shadowed += if point_lights::is_shadowed(light_position, point, &hit_fn) {
+        for i in 0..self.samples {
+            let light_position = light_position + self.sample(i, seed);
+            n_shadowed += if point_lights::is_shadowed(light_position, point, &occluded_fn) {
                 0
             } else {
                 1
@@ -36,4 +37,39 @@ impl CubeLight {
         }
         light_intensity * (n_shadowed as Float / (self.samples as Float))
     }
+
+    fn sample(&self, i: usize, seed: [Float; 3]) -> Tuple {
+        let (u, v, w) = sampling::r3(i, seed);
+        Tuple::vector(2.0 * u - 1.0, 2.0 * v - 1.0, 2.0 * w - 1.0) * self.size
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::colors::WHITE;
+
+    #[test]
+    fn cube_light_samples_lie_inside_it() {
+        let light = CubeLight::new(2.0, 16);
+        let seed = sampling::seed(Tuple::point(1.0, 0.0, 0.0));
+        for i in 0..16 {
+            let s = light.sample(i, seed);
+            assert!(s.x().abs() <= 2.0 && s.y().abs() <= 2.0 && s.z().abs() <= 2.0);
+        }
+    }
+
+    #[test]
+    fn a_cube_light_half_occluded_is_half_lit() {
+        let light = CubeLight::new(2.0, 64);
+        let position = Tuple::point(0.0, 10.0, 0.0);
+        let point = Tuple::point(0.0, 0.0, 0.0);
+        let occluded_fn = |r: &Ray, _| r.direction.x() > 0.0;
+        let intensity = light.shadowed_intensity(position, WHITE, point, occluded_fn);
+        assert!((intensity.red() - 0.5).abs() < 0.1);
+        assert_eq!(
+            intensity,
+            light.shadowed_intensity(position, WHITE, point, occluded_fn)
+        );
+    }
 }
@@ -0,0 +1,79 @@
+use crate::colors::Color;
+use crate::patterns::Pattern;
+use crate::tuples::Tuple;
+use std::sync::Arc;
+
+// How a gel pattern is read: on the unit sphere of directions leaving the
+// light, or along the x axis at the distance from the light.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum GelMapping {
+    Direction,
+    Distance,
+}
+
+#[derive(Debug, Clone)]
+pub struct Gel {
+    pattern: Arc<Pattern>,
+    mapping: GelMapping,
+}
+
+impl PartialEq for Gel {
+    fn eq(&self, other: &Gel) -> bool {
+        Arc::ptr_eq(&self.pattern, &other.pattern) && self.mapping == other.mapping
+    }
+}
+
+impl Gel {
+    pub fn new(pattern: Pattern, mapping: GelMapping) -> Gel {
+        Gel {
+            pattern: Arc::new(pattern),
+            mapping,
+        }
+    }
+
+    pub fn filter(&self, light_position: Tuple, point: Tuple) -> Color {
+        let v = point - light_position;
+        let pattern_point = match self.mapping {
+            GelMapping::Direction => {
+                let d = v.normalize();
+                Tuple::point(d.x(), d.y(), d.z())
+            }
+            GelMapping::Distance => Tuple::point(v.magnitude(), 0.0, 0.0),
+        };
+        self.pattern.color_at_pattern(pattern_point)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::colors::{BLACK, WHITE};
+    use crate::transformations::scaling;
+
+    #[test]
+    fn a_distance_gel_ramps_with_the_distance_to_the_light() {
+        let ramp = Pattern::new_gradient(WHITE, BLACK).with_transform(scaling(10.0, 1.0, 1.0));
+        let gel = Gel::new(ramp, GelMapping::Distance);
+        let light_position = Tuple::point(0.0, 5.0, 0.0);
+        assert_eq!(
+            gel.filter(light_position, Tuple::point(0.0, 5.0, 0.0)),
+            WHITE
+        );
+        assert_eq!(
+            gel.filter(light_position, Tuple::point(0.0, 0.0, 0.0)),
+            Color::new(0.5, 0.5, 0.5)
+        );
+    }
+
+    #[test]
+    fn a_direction_gel_projects_its_pattern_around_the_light() {
+        let red = Color::new(1.0, 0.0, 0.0);
+        let gel = Gel::new(Pattern::new_stripe(red, WHITE), GelMapping::Direction);
+        let light_position = Tuple::point(0.0, 5.0, 0.0);
+        assert_eq!(gel.filter(light_position, Tuple::point(1.0, 0.0, 0.0)), red);
+        assert_eq!(
+            gel.filter(light_position, Tuple::point(-1.0, 0.0, 0.0)),
+            WHITE
+        );
+    }
+}
@@ -0,0 +1,132 @@
+use crate::colors::{Color, BLACK, WHITE};
+use crate::floats::Float;
+use crate::lights::{point_lights, sampling};
+use crate::matrices::Matrix;
+use crate::patterns::Pattern;
+use crate::rays::Ray;
+use crate::tuples::Tuple;
+use std::sync::Arc;
+
+#[derive(Debug, Clone)]
+pub struct PanelLight {
+    u: Tuple,
+    v: Tuple,
+    samples: usize,
+    texture: Option<Arc<Pattern>>,
+}
+
+impl PartialEq for PanelLight {
+    fn eq(&self, other: &PanelLight) -> bool {
+        let same_texture = match (&self.texture, &other.texture) {
+            (Some(a), Some(b)) => Arc::ptr_eq(a, b),
+            (a, b) => a.is_none() && b.is_none(),
+        };
+        self.u == other.u && self.v == other.v && self.samples == other.samples && same_texture
+    }
+}
+
+impl PanelLight {
+    pub fn new(u: Tuple, v: Tuple, samples: usize) -> PanelLight {
+        PanelLight {
+            u,
+            v,
+            samples,
+            texture: None,
+        }
+    }
+
+    pub fn transformed(self, transform: Matrix<4>) -> PanelLight {
+        PanelLight {
+            u: transform * self.u,
+            v: transform * self.v,
+            ..self
+        }
+    }
+
+    pub fn with_texture(self, texture: Pattern) -> PanelLight {
+        PanelLight {
+            texture: Some(Arc::new(texture)),
+            ..self
+        }
+    }
+
+    pub fn shadowed_intensity<T>(
+        &self,
+        light_position: Tuple,
+        light_intensity: Color,
+        point: Tuple,
+        occluded_fn: T,
+    ) -> Color
+    where
+        T: Fn(&Ray, Float) -> bool,
+    {
+        let seed = sampling::seed(point);
+        let mut emitted = BLACK;
+        for i in 0..self.samples {
+            let (s, t) = sampling::r2(i, seed);
+            let light_position = light_position + self.u * (s - 0.5) + self.v * (t - 0.5);
+            if !point_lights::is_shadowed(light_position, point, &occluded_fn) {
+                emitted = emitted + self.emission(s, t);
+            }
+        }
+        light_intensity * emitted * (1.0 / self.samples as Float)
+    }
+
+    // The texture is read over the unit square of its pattern space, in the
+    // XZ plane, with x along u and z along v.
+    fn emission(&self, s: Float, t: Float) -> Color {
+        self.texture
+            .as_ref()
+            .map_or(WHITE, |p| p.color_at_pattern(Tuple::point(s, 0.0, t)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::floats::equals;
+    use crate::transformations::scaling;
+
+    fn panel() -> PanelLight {
+        PanelLight::new(
+            Tuple::vector(2.0, 0.0, 0.0),
+            Tuple::vector(0.0, 0.0, 2.0),
+            64,
+        )
+    }
+
+    #[test]
+    fn an_unoccluded_panel_light_is_fully_lit() {
+        let position = Tuple::point(0.0, 10.0, 0.0);
+        let point = Tuple::point(0.0, 0.0, 0.0);
+        let intensity = panel().shadowed_intensity(position, WHITE, point, |_, _| false);
+        assert_eq!(intensity, WHITE);
+    }
+
+    #[test]
+    fn a_textured_panel_light_emits_the_colors_of_its_texture() {
+        let stripes = Pattern::new_stripe(Color::new(1.0, 0.0, 0.0), Color::new(0.0, 0.0, 1.0))
+            .with_transform(scaling(0.5, 1.0, 1.0));
+        let light = panel().with_texture(stripes);
+        assert_eq!(light.emission(0.25, 0.5), Color::new(1.0, 0.0, 0.0));
+        let position = Tuple::point(0.0, 10.0, 0.0);
+        let point = Tuple::point(0.0, 0.0, 0.0);
+        let intensity = light.shadowed_intensity(position, WHITE, point, |_, _| false);
+        assert!(equals(intensity.red() + intensity.blue(), 1.0));
+        assert_eq!(intensity.green(), 0.0);
+    }
+
+    #[test]
+    fn occluding_half_a_panel_light_hides_its_texture() {
+        let light = panel().with_texture(Pattern::new_gradient(
+            Color::new(1.0, 0.0, 0.0),
+            Color::new(0.0, 0.0, 1.0),
+        ));
+        let position = Tuple::point(0.0, 10.0, 0.0);
+        let point = Tuple::point(0.0, 0.0, 0.0);
+        let intensity =
+            light.shadowed_intensity(position, WHITE, point, |r, _| r.direction.x() > 0.0);
+        assert!(intensity.red() > 0.3);
+        assert!(intensity.blue() < 0.2);
+    }
+}
@@ -1,36 +1,83 @@
 use crate::colors::{Color, BLACK};
-use crate::floats::Float;
+use crate::floats::{Float, PI};
+use crate::lights::sampling;
 use crate::rays::Ray;
 use crate::tuples::Tuple;
 
-pub fn is_shadowed<T>(light_position: Tuple, point: Tuple, hit_fn: &T) -> bool
+// Soft point lights keep a single light position for shading, and only
+// jitter their shadow rays over a disk of radius `softness` facing the point.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PointLight {
+    softness: Float,
+    samples: usize,
+}
+
+impl PointLight {
+    pub fn new() -> PointLight {
+        PointLight {
+            softness: 0.0,
+            samples: 1,
+        }
+    }
+
+    pub fn with_softness(self, softness: Float, samples: usize) -> PointLight {
+        PointLight {
+            softness,
+            samples: samples.max(1),
+        }
+    }
+
+    pub fn shadowed_intensity<T>(
+        &self,
+        light_position: Tuple,
+        light_intensity: Color,
+        point: Tuple,
+        occluded_fn: T,
+    ) -> Color
+    where
+        T: Fn(&Ray, Float) -> bool,
+    {
+        if self.softness <= 0.0 {
+            return shadowed_intensity(light_position, light_intensity, point, occluded_fn);
+        }
+        let seed = sampling::seed(point);
+        let (u, v, _) = (light_position - point).orthonormal_basis();
+        let mut n_lit = 0;
+        for i in 0..self.samples {
+            let (s, t) = sampling::r2(i, seed);
+            let r = self.softness * s.sqrt();
+            let phi = 2.0 * PI * t;
+            let sample = light_position + u * (r * phi.cos()) + v * (r * phi.sin());
+            if !is_shadowed(sample, point, &occluded_fn) {
+                n_lit += 1;
+            }
+        }
+        light_intensity * (n_lit as Float / self.samples as Float)
+    }
+}
+
+pub fn is_shadowed<T>(light_position: Tuple, point: Tuple, occluded_fn: &T) -> bool
 where
-    T: Fn(&Ray) -> Option<Float>,
+    T: Fn(&Ray, Float) -> bool,
 {
     let v = light_position - point;
     let distance = v.magnitude();
     let direction = v.normalize();
     let r = Ray::new(point, direction);
 
-    if let Some(hit) = hit_fn(&r)
-        && hit < distance
-    {
-        true
-    } else {
-        false
-    }
+    occluded_fn(&r, distance)
 }
 
 pub fn shadowed_intensity<T>(
     light_position: Tuple,
     light_intensity: Color,
     point: Tuple,
-    hit_fn: T,
+    occluded_fn: T,
 ) -> Color
 where
-    T: Fn(&Ray) -> Option<Float>,
+    T: Fn(&Ray, Float) -> bool,
 {
-    if is_shadowed(light_position, point, &hit_fn) {
+    if is_shadowed(light_position, point, &occluded_fn) {
         BLACK
     } else {
         light_intensity
@@ -40,25 +87,52 @@ where
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::colors::WHITE;
 
     #[test]
     fn there_is_no_shadow_when_nothing_is_collinear_with_point_and_light() {
         let light_position = Tuple::point(-10.0, 10.0, -10.0);
         let p = Tuple::point(0.0, 10.0, 0.0);
-        assert!(!is_shadowed(light_position, p, &|_| None));
+        assert!(!is_shadowed(light_position, p, &|_, _| false));
     }
 
     #[test]
     fn there_is_a_shadow_when_an_object_is_between_the_point_and_the_light() {
         let light_position = Tuple::point(-10.0, 10.0, -10.0);
         let p = Tuple::point(10.0, -10.0, 10.0);
-        assert!(is_shadowed(light_position, p, &|_| Some(1.0)));
+        assert!(is_shadowed(light_position, p, &|_, distance| 1.0 < distance));
     }
 
     #[test]
     fn there_is_no_shadow_when_an_object_is_behind_the_light() {
         let light_position = Tuple::point(-10.0, 10.0, -10.0);
         let p = Tuple::point(-20.0, 20.0, -20.);
-        assert!(!is_shadowed(light_position, p, &|_| Some(20.0)));
+        assert!(!is_shadowed(light_position, p, &|_, distance| 20.0 < distance));
+    }
+
+    #[test]
+    fn a_point_light_without_softness_casts_hard_shadows() {
+        let light = PointLight::new();
+        let position = Tuple::point(0.0, 10.0, 0.0);
+        let point = Tuple::point(0.0, 0.0, 0.0);
+        let occluded_fn = |r: &Ray, _| r.direction.x() > 0.0;
+        assert_eq!(
+            light.shadowed_intensity(position, WHITE, point, occluded_fn),
+            WHITE
+        );
+    }
+
+    #[test]
+    fn a_soft_point_light_casts_penumbras() {
+        let light = PointLight::new().with_softness(1.0, 32);
+        let position = Tuple::point(0.0, 10.0, 0.0);
+        let point = Tuple::point(0.0, 0.0, 0.0);
+        let occluded_fn = |r: &Ray, _| r.direction.x() > 0.0;
+        let intensity = light.shadowed_intensity(position, WHITE, point, occluded_fn);
+        assert!(intensity.red() > 0.3 && intensity.red() < 0.7);
+        assert_eq!(
+            intensity,
+            light.shadowed_intensity(position, WHITE, point, occluded_fn)
+        );
     }
 }
@@ -0,0 +1,72 @@
+use crate::floats::Float;
+use crate::tuples::Tuple;
+
+const R2: [Float; 2] = [0.7548777, 0.5698403];
+const R3: [Float; 3] = [0.8191725, 0.6710436, 0.5497005];
+
+pub fn seed(point: Tuple) -> [Float; 3] {
+    let mut h = point.x().to_bits()
+        ^ point.y().to_bits().rotate_left(11)
+        ^ point.z().to_bits().rotate_left(22);
+    [0, 1, 2].map(|_| {
+        h = mix(h);
+        (h >> 8) as Float / (1 << 24) as Float
+    })
+}
+
+pub fn r2(i: usize, seed: [Float; 3]) -> (Float, Float) {
+    let i = i as Float + 1.0;
+    ((seed[0] + i * R2[0]).fract(), (seed[1] + i * R2[1]).fract())
+}
+
+pub fn r3(i: usize, seed: [Float; 3]) -> (Float, Float, Float) {
+    let i = i as Float + 1.0;
+    (
+        (seed[0] + i * R3[0]).fract(),
+        (seed[1] + i * R3[1]).fract(),
+        (seed[2] + i * R3[2]).fract(),
+    )
+}
+
+fn mix(mut h: u32) -> u32 {
+    h = h.wrapping_add(0x9e3779b9);
+    h = (h ^ (h >> 16)).wrapping_mul(0x85ebca6b);
+    h = (h ^ (h >> 13)).wrapping_mul(0xc2b2ae35);
+    h ^ (h >> 16)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn the_seed_is_deterministic_per_point() {
+        let p = Tuple::point(1.0, 2.0, 3.0);
+        assert_eq!(seed(p), seed(p));
+        assert_ne!(seed(p), seed(Tuple::point(1.0, 2.0, 3.5)));
+        assert!(seed(p).iter().all(|s| (0.0..1.0).contains(s)));
+    }
+
+    #[test]
+    fn the_r2_sequence_covers_every_stratum() {
+        let seed = seed(Tuple::point(0.0, 0.0, 0.0));
+        let mut strata = [[0; 4]; 4];
+        for i in 0..64 {
+            let (u, v) = r2(i, seed);
+            strata[(u * 4.0) as usize][(v * 4.0) as usize] += 1;
+        }
+        assert!(strata.iter().flatten().all(|&n| (2..=6).contains(&n)));
+    }
+
+    #[test]
+    fn the_r3_sequence_covers_every_octant() {
+        let seed = seed(Tuple::point(0.0, 0.0, 0.0));
+        let mut octants = [0; 8];
+        for i in 0..64 {
+            let (u, v, w) = r3(i, seed);
+            let index = (u * 2.0) as usize * 4 + (v * 2.0) as usize * 2 + (w * 2.0) as usize;
+            octants[index] += 1;
+        }
+        assert!(octants.iter().all(|&n| (6..=10).contains(&n)));
+    }
+}
@@ -1,6 +1,6 @@
 use crate::colors::Color;
-use crate::floats::Float;
-use crate::lights::point_lights;
+use crate::floats::{Float, PI};
+use crate::lights::{point_lights, sampling};
 use crate::rays::Ray;
 use crate::tuples::Tuple;
 
@@ -20,15 +20,16 @@ impl SphereLight {
         light_position: Tuple,
         light_intensity: Color,
         point: Tuple,
-        hit_fn: T,
+        occluded_fn: T,
     ) -> Color
     where
-        T: Fn(&Ray) -> Option<Float>,
+        T: Fn(&Ray, Float) -> bool,
     {
+        let seed = sampling::seed(point);
         let mut n_shadowed = 0;
-        for _ in 0..self.samples {
-            let light_position = light_position + Tuple::random_vector(self.size).normalize();
-            n_shadowed += if point_lights::is_shadowed(light_position, point, &hit_fn) {
+        for i in 0..self.samples {
+            let light_position = light_position + self.sample(i, seed);
+            n_shadowed += if point_lights::is_shadowed(light_position, point, &occluded_fn) {
                 0
             } else {
                 1
@@ -36,4 +37,40 @@ impl SphereLight {
         }
         light_intensity * (n_shadowed as Float / (self.samples as Float))
     }
+
+    fn sample(&self, i: usize, seed: [Float; 3]) -> Tuple {
+        let (u, v) = sampling::r2(i, seed);
+        let y = 1.0 - 2.0 * u;
+        let r = (1.0 - y * y).max(0.0).sqrt();
+        let phi = 2.0 * PI * v;
+        Tuple::vector(r * phi.cos(), y, r * phi.sin()) * self.size
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::colors::WHITE;
+    use crate::floats::equals;
+
+    #[test]
+    fn sphere_light_samples_lie_on_its_surface() {
+        let light = SphereLight::new(2.0, 16);
+        let seed = sampling::seed(Tuple::point(1.0, 0.0, 0.0));
+        for i in 0..16 {
+            assert!(equals(light.sample(i, seed).magnitude(), 2.0));
+        }
+    }
+
+    #[test]
+    fn a_sphere_light_is_deterministic_for_a_point() {
+        let light = SphereLight::new(2.0, 16);
+        let position = Tuple::point(0.0, 10.0, 0.0);
+        let point = Tuple::point(0.0, 0.0, 0.0);
+        let occluded_fn = |r: &Ray, _| r.direction.x() > 0.0;
+        let first = light.shadowed_intensity(position, WHITE, point, occluded_fn);
+        let second = light.shadowed_intensity(position, WHITE, point, occluded_fn);
+        assert_eq!(first, second);
+        assert!(first.red() > 0.3 && first.red() < 0.7);
+    }
 }
@@ -1,6 +1,7 @@
 use crate::colors::{Color, BLACK};
-use crate::floats::Float;
+use crate::floats::{Float, PI};
 use crate::lights::point_lights;
+use crate::matrices::Matrix;
 use crate::rays::Ray;
 use crate::tuples::Tuple;
 
@@ -22,19 +23,30 @@ impl SpotLight {
         }
     }
 
+    pub fn transformed(self, transform: Matrix<4>) -> SpotLight {
+        SpotLight {
+            direction: (transform * self.direction).normalize(),
+            ..self
+        }
+    }
+
+    pub fn solid_angle(&self) -> Float {
+        2.0 * PI * (1.0 - self.width.cos())
+    }
+
     pub fn shadowed_intensity<T>(
         &self,
         light_position: Tuple,
         light_intensity: Color,
         point: Tuple,
-        hit_fn: T,
+        occluded_fn: T,
     ) -> Color
     where
-        T: Fn(&Ray) -> Option<Float>,
+        T: Fn(&Ray, Float) -> bool,
     {
         let light_to_point = point - light_position;
         let angle = self.direction.angle(light_to_point);
-        if point_lights::is_shadowed(light_position, point, &hit_fn) || angle > self.width {
+        if point_lights::is_shadowed(light_position, point, &occluded_fn) || angle > self.width {
             BLACK
         } else if angle > self.narrow_width {
             light_intensity * (1.0 - (angle - self.narrow_width) / (self.width - self.narrow_width))
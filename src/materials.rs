@@ -1,21 +1,86 @@
 use crate::colors::{Color, BLACK, WHITE};
-use crate::floats::Float;
+use crate::floats::{Float, EPSILON, PI};
 use crate::lights::Light;
+use crate::matrices::Matrix;
 use crate::objects::Object;
 use crate::patterns::Pattern;
 use crate::tuples::Tuple;
+use std::collections::HashMap;
 use std::fmt;
 
-#[derive(Clone, Copy)]
+const MIN_ROUGHNESS: Float = 0.05;
+const MAX_ANISOTROPY: Float = 0.95;
+
+// Perceptual roughness to Phong exponent, through the Beckmann distribution
+// width alpha = roughness^2 whose highlight matches 2 / alpha^2 - 2.
+pub fn shininess_from_roughness(roughness: Float) -> Float {
+    2.0 / roughness.clamp(MIN_ROUGHNESS, 1.0).powi(4) - 2.0
+}
+
+pub fn roughness_from_shininess(shininess: Float) -> Float {
+    (2.0 / (shininess.max(0.0) + 2.0)).powf(0.25)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ClearCoat {
+    pub reflective: Float,
+    pub refractive_index: Float,
+    pub roughness: Float,
+}
+
+impl ClearCoat {
+    pub fn new(reflective: Float, refractive_index: Float, roughness: Float) -> ClearCoat {
+        ClearCoat {
+            reflective,
+            refractive_index,
+            roughness,
+        }
+    }
+
+    pub fn base_reflectance(&self) -> Float {
+        ((self.refractive_index - 1.0) / (self.refractive_index + 1.0)).powi(2)
+    }
+
+    pub fn fresnel(&self, cos: Float) -> Float {
+        let r0 = self.base_reflectance();
+        self.reflective * (r0 + (1.0 - r0) * (1.0 - cos.clamp(0.0, 1.0)).powi(5))
+    }
+}
+
+// Where anisotropic highlights get their stretch direction, in object space.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Tangents {
+    Direction(Tuple),
+    // Around the object y axis, like a brushed disc.
+    Circular,
+    // A vertex attribute of the hit triangle, e.g. the mesh UV tangents.
+    Attribute(usize),
+}
+
+#[derive(Clone)]
 pub struct Material {
     pub pattern: Option<Pattern>,
     pub color: Color,
     pub ambient: Float,
+    pub anisotropy: Float,
+    pub attenuation: Color,
+    pub clear_coat: Option<ClearCoat>,
+    pub density: Float,
     pub diffuse: Float,
+    pub dispersion: Float,
+    pub fresnel: bool,
+    pub glossy_samples: usize,
+    pub metallic: bool,
+    pub portal: Option<Matrix<4>>,
     pub reflective: Float,
+    pub reflective_color: Option<Color>,
     pub refractive_index: Float,
+    pub roughness: Float,
     pub shininess: Float,
     pub specular: Float,
+    pub specular_roughness: Option<Float>,
+    pub tangents: Tangents,
+    pub translucency: Float,
     pub transparency: Float,
 }
 
@@ -25,31 +90,329 @@ impl Default for Material {
             pattern: None,
             color: WHITE,
             ambient: 0.1,
+            anisotropy: 0.0,
+            attenuation: WHITE,
+            clear_coat: None,
+            density: 0.0,
             diffuse: 0.9,
+            dispersion: 0.0,
+            fresnel: false,
+            glossy_samples: 1,
+            metallic: false,
+            portal: None,
             reflective: 0.0,
+            reflective_color: None,
             refractive_index: 1.0,
+            roughness: 0.0,
             shininess: 200.0,
             specular: 0.9,
+            specular_roughness: None,
+            tangents: Tangents::Direction(Tuple::vector(1.0, 0.0, 0.0)),
+            translucency: 0.0,
             transparency: 0.0,
         }
     }
 }
 
 impl Material {
+    pub fn clay() -> Material {
+        Material {
+            color: Color::new(0.8, 0.8, 0.8),
+            specular: 0.0,
+            ..Material::default()
+        }
+    }
+
     pub fn glass() -> Material {
         Material {
             pattern: None,
             color: WHITE,
             ambient: 0.0,
+            anisotropy: 0.0,
+            attenuation: WHITE,
+            clear_coat: None,
+            density: 0.0,
             diffuse: 0.588235,
+            dispersion: 0.0,
+            fresnel: false,
             specular: 0.9,
+            translucency: 0.0,
             transparency: 1.0,
+            glossy_samples: 1,
+            metallic: false,
+            portal: None,
             reflective: 0.08,
+            reflective_color: None,
             refractive_index: 1.5,
+            roughness: 0.0,
             shininess: 300.0,
+            specular_roughness: None,
+            tangents: Tangents::Direction(Tuple::vector(1.0, 0.0, 0.0)),
+        }
+    }
+
+    pub fn metal(color: Color, roughness: Float) -> Material {
+        Material {
+            color,
+            ambient: 0.1,
+            diffuse: 0.0,
+            specular: 1.0,
+            metallic: true,
+            reflective: 1.0,
+            roughness,
+            ..Material::default()
+        }
+    }
+
+    pub fn with_clear_coat(self, clear_coat: ClearCoat) -> Material {
+        Material {
+            clear_coat: Some(clear_coat),
+            ..self
+        }
+    }
+
+    // Rays hitting a portal leave again from `transform` times the hit point,
+    // see `Object::portal_to`. Its surface only shows when the bounces run out.
+    pub fn with_portal(self, transform: Matrix<4>) -> Material {
+        Material {
+            portal: Some(transform),
+            ..self
+        }
+    }
+
+    pub fn with_specular_roughness(self, roughness: Float) -> Material {
+        Material {
+            specular_roughness: Some(roughness.clamp(0.0, 1.0)),
+            ..self
+        }
+    }
+
+    // Highlights stretch along the tangents as the anisotropy goes from 0 to 1.
+    pub fn with_anisotropy(self, anisotropy: Float, tangents: Tangents) -> Material {
+        Material {
+            anisotropy: anisotropy.clamp(0.0, MAX_ANISOTROPY),
+            tangents,
+            ..self
+        }
+    }
+
+    // The world tangent at a point, projected onto the surface. Tangents go
+    // to world space like normals, which is exact up to non uniform scales.
+    pub fn tangent_at(
+        &self,
+        object: &Object,
+        position: Tuple,
+        normalv: Tuple,
+        attributes: &[Tuple],
+    ) -> Tuple {
+        let local = match self.tangents {
+            Tangents::Direction(direction) => direction,
+            Tangents::Circular => {
+                let p = object.world_to_object(position);
+                Tuple::vector(-p.z(), 0.0, p.x())
+            }
+            Tangents::Attribute(i) => attributes.get(i).map_or(Tuple::vector(1.0, 0.0, 0.0), |a| {
+                Tuple::vector(a.x(), a.y(), a.z())
+            }),
+        };
+        let mut tangent = object.object_to_world * local;
+        tangent.to_vector();
+        let tangent = tangent - normalv * tangent.dot(normalv);
+        if tangent.magnitude() < EPSILON {
+            return normalv.orthonormal_basis().0;
+        }
+        tangent.normalize()
+    }
+
+    // The Phong exponent of the highlight, from the roughness when it is set.
+    pub fn phong_exponent(&self) -> Float {
+        self.specular_roughness
+            .map_or(self.shininess, shininess_from_roughness)
+    }
+
+    // Opaque dielectrics also reflect more at grazing angles, as seen from
+    // outside through a surface of this refractive index.
+    pub fn with_fresnel(self, refractive_index: Float) -> Material {
+        Material {
+            fresnel: true,
+            refractive_index,
+            ..self
+        }
+    }
+
+    // Whether the reflections are weighted by the Schlick reflectance.
+    pub fn fresnel_weighted(&self) -> bool {
+        self.reflective > 0.0 && (self.transparency > 0.0 || self.fresnel)
+    }
+
+    pub fn coat_reflectance(&self, eyev: Tuple, normalv: Tuple) -> Float {
+        self.clear_coat
+            .map_or(0.0, |coat| coat.fresnel(eyev.dot(normalv)))
+    }
+
+    // Tinted mirrors reflect each channel by its own amount, scaled by `reflective`.
+    pub fn with_reflective_color(self, color: Color) -> Material {
+        Material {
+            reflective: 1.0,
+            reflective_color: Some(color),
+            ..self
+        }
+    }
+
+    pub fn reflection_tint(&self) -> Color {
+        if let Some(color) = self.reflective_color {
+            color * self.reflective
+        } else if self.metallic {
+            self.color * self.reflective
+        } else {
+            WHITE * self.reflective
         }
     }
 
+    // Red, green and blue each see a slightly different refractive index.
+    pub fn dispersed_indices(&self) -> [(Color, Float); 3] {
+        [
+            (
+                Color::new(1.0, 0.0, 0.0),
+                self.refractive_index - self.dispersion,
+            ),
+            (Color::new(0.0, 1.0, 0.0), self.refractive_index),
+            (
+                Color::new(0.0, 0.0, 1.0),
+                self.refractive_index + self.dispersion,
+            ),
+        ]
+    }
+
+    pub fn summary(&self) -> String {
+        let mut summary = format!(
+            "color ({}, {}, {}) ambient {} diffuse {} specular {} shininess {}",
+            self.color.red(),
+            self.color.green(),
+            self.color.blue(),
+            self.ambient,
+            self.diffuse,
+            self.specular,
+            self.shininess
+        );
+        if let Some(roughness) = self.specular_roughness {
+            summary.push_str(&format!(" specular-roughness {}", roughness));
+        }
+        if self.pattern.is_some() {
+            summary.push_str(" patterned");
+        }
+        if self.metallic {
+            summary.push_str(&format!(" metal roughness {}", self.roughness));
+        }
+        if self.reflective > 0.0 {
+            summary.push_str(&format!(" reflective {}", self.reflective));
+        }
+        if let Some(color) = self.reflective_color {
+            summary.push_str(&format!(
+                " reflective-color ({}, {}, {})",
+                color.red(),
+                color.green(),
+                color.blue()
+            ));
+        }
+        if self.transparency > 0.0 {
+            summary.push_str(&format!(
+                " transparency {} refractive index {}",
+                self.transparency, self.refractive_index
+            ));
+        }
+        if self.fresnel {
+            summary.push_str(&format!(" fresnel {}", self.refractive_index));
+        }
+        if self.dispersion > 0.0 {
+            summary.push_str(&format!(" dispersion {}", self.dispersion));
+        }
+        if self.translucency > 0.0 {
+            summary.push_str(&format!(" translucency {}", self.translucency));
+        }
+        if let Some(coat) = &self.clear_coat {
+            summary.push_str(&format!(
+                " clear coat {} refractive index {} roughness {}",
+                coat.reflective, coat.refractive_index, coat.roughness
+            ));
+        }
+        if self.density > 0.0 {
+            summary.push_str(&format!(
+                " attenuation ({}, {}, {}) density {}",
+                self.attenuation.red(),
+                self.attenuation.green(),
+                self.attenuation.blue(),
+                self.density
+            ));
+        }
+        summary
+    }
+
+    // Every field equal, unlike `==` that only compares the lighting terms.
+    // Fields are listed exhaustively so that new ones are not forgotten.
+    pub fn identical(&self, other: &Material) -> bool {
+        let Material {
+            pattern,
+            color,
+            ambient,
+            anisotropy,
+            attenuation,
+            clear_coat,
+            density,
+            diffuse,
+            dispersion,
+            fresnel,
+            glossy_samples,
+            metallic,
+            portal,
+            reflective,
+            reflective_color,
+            refractive_index,
+            roughness,
+            shininess,
+            specular,
+            specular_roughness,
+            tangents,
+            translucency,
+            transparency,
+        } = self;
+        *pattern == other.pattern
+            && *color == other.color
+            && *ambient == other.ambient
+            && *anisotropy == other.anisotropy
+            && *attenuation == other.attenuation
+            && *clear_coat == other.clear_coat
+            && *density == other.density
+            && *diffuse == other.diffuse
+            && *dispersion == other.dispersion
+            && *fresnel == other.fresnel
+            && *glossy_samples == other.glossy_samples
+            && *metallic == other.metallic
+            && *portal == other.portal
+            && *reflective == other.reflective
+            && *reflective_color == other.reflective_color
+            && *refractive_index == other.refractive_index
+            && *roughness == other.roughness
+            && *shininess == other.shininess
+            && *specular == other.specular
+            && *specular_roughness == other.specular_roughness
+            && *tangents == other.tangents
+            && *translucency == other.translucency
+            && *transparency == other.transparency
+    }
+
+    pub fn transmittance(&self, distance: Float) -> Color {
+        if self.density == 0.0 {
+            return WHITE;
+        }
+        let absorb = |c: Float| (-(1.0 - c) * self.density * distance).exp();
+        Color::new(
+            absorb(self.attenuation.red()),
+            absorb(self.attenuation.green()),
+            absorb(self.attenuation.blue()),
+        )
+    }
+
     pub fn lighting(
         &self,
         object: &Object,
@@ -59,11 +422,55 @@ impl Material {
         eyev: Tuple,
         normalv: Tuple,
     ) -> Color {
-        let color = if let Some(pattern) = &self.pattern {
-            pattern.color_at_object(object, position)
-        } else {
-            self.color
-        };
+        let (ambient, diffuse, specular) =
+            self.lighting_terms(object, ambient_light, lights, position, eyev, normalv);
+        ambient + diffuse + specular
+    }
+
+    pub fn color_at(
+        &self,
+        object: &Object,
+        position: Tuple,
+        normalv: Tuple,
+        eyev: Tuple,
+        footprint: Float,
+    ) -> Color {
+        match &self.pattern {
+            Some(pattern) => pattern.color_at_surface(object, position, normalv, eyev, footprint),
+            None => self.color,
+        }
+    }
+
+    pub fn lighting_terms(
+        &self,
+        object: &Object,
+        ambient_light: Color,
+        lights: &Vec<Light>,
+        position: Tuple,
+        eyev: Tuple,
+        normalv: Tuple,
+    ) -> (Color, Color, Color) {
+        let color = self.color_at(object, position, normalv, eyev, 0.0);
+        let tangent = self.tangent_at(object, position, normalv, &[]);
+        self.lighting_terms_with_color(
+            color,
+            ambient_light,
+            lights,
+            position,
+            eyev,
+            (normalv, tangent),
+        )
+    }
+
+    pub fn lighting_terms_with_color(
+        &self,
+        color: Color,
+        ambient_light: Color,
+        lights: &Vec<Light>,
+        position: Tuple,
+        eyev: Tuple,
+        (normalv, tangent): (Tuple, Tuple),
+    ) -> (Color, Color, Color) {
         let effective_color = color * ambient_light;
         let ambient = effective_color * self.ambient;
         let mut diffuse = BLACK;
@@ -75,16 +482,34 @@ impl Material {
             let lightv = (light.position - position).normalize();
             let light_dot_normal = lightv.dot(normalv);
             let (diffusel, specularl) = if light_dot_normal < 0.0 {
-                (BLACK, BLACK)
+                let effective_color = color * light.intensity;
+                let translucent =
+                    effective_color * self.diffuse * self.translucency * -light_dot_normal;
+                (translucent, BLACK)
             } else {
                 let effective_color = color * light.intensity;
                 let diffuse = effective_color * self.diffuse * light_dot_normal;
                 let reflectv = (-lightv).reflect(normalv);
                 let reflect_dot_eye = reflectv.dot(eyev);
-                if reflect_dot_eye <= 0.0 {
+                if self.metallic {
+                    let alphas = self.anisotropic_alphas(self.roughness);
+                    let specular = cook_torrance(
+                        color,
+                        alphas,
+                        (normalv, tangent),
+                        lightv,
+                        eyev,
+                        self.roughness,
+                    ) * light.intensity
+                        * self.specular;
+                    (diffuse, specular)
+                } else if self.anisotropy > 0.0 {
+                    let factor = self.anisotropic_phong(normalv, tangent, lightv, eyev);
+                    (diffuse, light.intensity * self.specular * factor)
+                } else if reflect_dot_eye <= 0.0 {
                     (diffuse, BLACK)
                 } else {
-                    let factor = reflect_dot_eye.powf(self.shininess);
+                    let factor = reflect_dot_eye.powf(self.phong_exponent());
                     let specular = light.intensity * self.specular * factor;
                     (diffuse, specular)
                 }
@@ -92,7 +517,132 @@ impl Material {
             diffuse = diffuse + diffusel;
             specular = specular + specularl;
         }
-        ambient + diffuse + specular
+        let Some(coat) = &self.clear_coat else {
+            return (ambient, diffuse, specular);
+        };
+        let base = 1.0 - self.coat_reflectance(eyev, normalv);
+        let mut highlight = BLACK;
+        for light in lights {
+            let lightv = (light.position - position).normalize();
+            let r0 = coat.base_reflectance();
+            let alpha = coat.roughness.clamp(MIN_ROUGHNESS, 1.0).powi(2);
+            highlight = highlight
+                + cook_torrance(
+                    WHITE * r0,
+                    (alpha, alpha),
+                    (normalv, tangent),
+                    lightv,
+                    eyev,
+                    coat.roughness,
+                ) * light.intensity
+                    * coat.reflective;
+        }
+        (ambient * base, diffuse * base, specular * base + highlight)
+    }
+
+    // Microfacet widths along the tangent and the bitangent (Burley 2012).
+    fn anisotropic_alphas(&self, roughness: Float) -> (Float, Float) {
+        let alpha = roughness.clamp(MIN_ROUGHNESS, 1.0).powi(2);
+        let aspect = (1.0 - 0.9 * self.anisotropy).sqrt();
+        (alpha / aspect, alpha * aspect)
+    }
+
+    // Half vector Phong lobe with a lower exponent along the tangent
+    // (Ashikhmin and Shirley 2000). The half vector turns half as much as
+    // the reflection vector, hence the exponents four times larger.
+    fn anisotropic_phong(
+        &self,
+        normalv: Tuple,
+        tangent: Tuple,
+        lightv: Tuple,
+        eyev: Tuple,
+    ) -> Float {
+        let halfv = (lightv + eyev).normalize();
+        let n_dot_h = normalv.dot(halfv);
+        if n_dot_h <= 0.0 {
+            return 0.0;
+        }
+        let sin2 = 1.0 - n_dot_h.powi(2);
+        if sin2 < EPSILON {
+            return 1.0;
+        }
+        let exponent = 4.0 * self.phong_exponent();
+        let bitangent = normalv.cross(tangent);
+        let exponent = (exponent * (1.0 - self.anisotropy) * halfv.dot(tangent).powi(2)
+            + exponent * halfv.dot(bitangent).powi(2))
+            / sin2;
+        n_dot_h.powf(exponent)
+    }
+}
+
+fn cook_torrance(
+    color: Color,
+    (alpha_t, alpha_b): (Float, Float),
+    (normalv, tangent): (Tuple, Tuple),
+    lightv: Tuple,
+    eyev: Tuple,
+    roughness: Float,
+) -> Color {
+    let n_dot_l = normalv.dot(lightv);
+    let n_dot_v = normalv.dot(eyev);
+    if n_dot_l <= 0.0 || n_dot_v <= 0.0 {
+        return BLACK;
+    }
+    let halfv = (lightv + eyev).normalize();
+    let n_dot_h = normalv.dot(halfv).max(0.0);
+    let v_dot_h = eyev.dot(halfv).max(0.0);
+    let bitangent = normalv.cross(tangent);
+    let stretch = (halfv.dot(tangent) / alpha_t).powi(2)
+        + (halfv.dot(bitangent) / alpha_b).powi(2)
+        + n_dot_h.powi(2);
+    let distribution = 1.0 / (PI * alpha_t * alpha_b * stretch.powi(2));
+    let k = (roughness + 1.0).powi(2) / 8.0;
+    let geometry = (n_dot_l / (n_dot_l * (1.0 - k) + k)) * (n_dot_v / (n_dot_v * (1.0 - k) + k));
+    let fresnel = color + (WHITE - color) * (1.0 - v_dot_h).powi(5);
+    fresnel * (distribution * geometry / (4.0 * n_dot_v))
+}
+
+// Materials shared by name, e.g. between the objects of a scene file.
+// Objects refer to them with `Object::with_material_ref`, and take their
+// current value each time the world is prepared, so editing a material
+// here changes every object using it.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct MaterialLibrary {
+    materials: HashMap<String, Material>,
+}
+
+impl MaterialLibrary {
+    pub fn new() -> MaterialLibrary {
+        MaterialLibrary::default()
+    }
+
+    // Returns the material previously known by this name, if any.
+    pub fn insert(&mut self, name: &str, material: Material) -> Option<Material> {
+        self.materials.insert(name.to_string(), material)
+    }
+
+    pub fn get(&self, name: &str) -> Option<&Material> {
+        self.materials.get(name)
+    }
+
+    pub fn get_mut(&mut self, name: &str) -> Option<&mut Material> {
+        self.materials.get_mut(name)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &Material)> {
+        self.materials.iter().map(|(name, m)| (name.as_str(), m))
+    }
+
+    pub fn contains(&self, name: &str) -> bool {
+        self.materials.contains_key(name)
+    }
+
+    pub fn len(&self) -> usize {
+        self.materials.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.materials.is_empty()
     }
 }
 
@@ -105,6 +655,7 @@ impl PartialEq for Material {
             && self.diffuse == other.diffuse
             && self.specular == other.specular
             && self.shininess == other.shininess
+            && self.specular_roughness == other.specular_roughness
     }
 }
 
@@ -116,6 +667,7 @@ impl fmt::Debug for Material {
             .field("diffuse", &self.diffuse)
             .field("specular", &self.specular)
             .field("shininess", &self.shininess)
+            .field("specular_roughness", &self.specular_roughness)
             .finish()
     }
 }
@@ -123,7 +675,22 @@ impl fmt::Debug for Material {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::floats::SQRT_2;
+    use crate::floats::{equals, SQRT_2};
+
+    #[test]
+    fn a_material_library_stores_materials_by_name() {
+        let mut library = MaterialLibrary::new();
+        assert!(library.is_empty());
+        assert_eq!(library.insert("steel", Material::glass()), None);
+        assert_eq!(
+            library.insert("steel", Material::default()),
+            Some(Material::glass())
+        );
+        library.get_mut("steel").unwrap().color = BLACK;
+        assert_eq!(library.get("steel").unwrap().color, BLACK);
+        assert!(library.contains("steel") && !library.contains("gold"));
+        assert_eq!(library.len(), 1);
+    }
 
     #[test]
     fn the_default_material() {
@@ -133,10 +700,96 @@ mod tests {
         assert_eq!(m.diffuse, 0.9);
         assert!(m.pattern.is_none());
         assert_eq!(m.reflective, 0.0);
+        assert_eq!(m.reflective_color, None);
         assert_eq!(m.refractive_index, 1.0);
         assert_eq!(m.shininess, 200.0);
         assert_eq!(m.specular, 0.9);
         assert_eq!(m.transparency, 0.0);
+        assert_eq!(m.translucency, 0.0);
+        assert_eq!(m.roughness, 0.0);
+        assert_eq!(m.glossy_samples, 1);
+        assert_eq!(m.attenuation, WHITE);
+        assert_eq!(m.density, 0.0);
+        assert_eq!(m.dispersion, 0.0);
+        assert!(!m.fresnel);
+        assert_eq!(m.specular_roughness, None);
+    }
+
+    #[test]
+    fn converting_between_roughness_and_shininess() {
+        assert!(equals(shininess_from_roughness(1.0), 0.0));
+        assert!(equals(shininess_from_roughness(0.5), 30.0));
+        assert!(shininess_from_roughness(0.0).is_finite());
+        for shininess in [0.0, 10.0, 200.0, 1000.0] {
+            let roughness = roughness_from_shininess(shininess);
+            assert!(
+                (shininess_from_roughness(roughness) - shininess).abs() < 0.01 * shininess + 0.01
+            );
+        }
+    }
+
+    #[test]
+    fn the_specular_roughness_replaces_the_shininess() {
+        let m = Material::default().with_specular_roughness(0.5);
+        assert_eq!(m.phong_exponent(), 30.0);
+        assert_eq!(Material::default().phong_exponent(), 200.0);
+        assert_eq!(
+            Material::default()
+                .with_specular_roughness(2.0)
+                .specular_roughness,
+            Some(1.0)
+        );
+        let position = Tuple::point(0.0, 0.0, 0.0);
+        let eyev = Tuple::vector(0.0, 0.0, -1.0);
+        let normalv = Tuple::vector(0.0, 0.0, -1.0);
+        let s = Object::new_sphere();
+        let light = vec![Light::new_point(Tuple::point(0.0, 10.0, -10.0), WHITE)];
+        let lit = |m: &Material| m.lighting(&s, WHITE, &light, position, eyev, normalv);
+        let equivalent = Material {
+            shininess: 30.0,
+            ..Material::default()
+        };
+        assert_eq!(lit(&m), lit(&equivalent));
+        let rough = Material::default().with_specular_roughness(0.9);
+        assert!(lit(&rough).red() > lit(&m).red());
+    }
+
+    #[test]
+    fn dispersion_spreads_the_refractive_index_per_channel() {
+        let m = Material {
+            dispersion: 0.02,
+            ..Material::glass()
+        };
+        let indices = m.dispersed_indices();
+        assert_eq!(indices[0], (Color::new(1.0, 0.0, 0.0), 1.48));
+        assert_eq!(indices[1], (Color::new(0.0, 1.0, 0.0), 1.5));
+        assert_eq!(indices[2], (Color::new(0.0, 0.0, 1.0), 1.52));
+    }
+
+    #[test]
+    fn the_transmittance_through_an_absorbing_material() {
+        let m = Material {
+            attenuation: Color::new(0.5, 1.0, 0.0),
+            density: 2.0,
+            ..Material::glass()
+        };
+        assert_eq!(m.transmittance(0.0), WHITE);
+        assert_eq!(m.transmittance(1.0), Color::new(0.36788, 1.0, 0.13534));
+        assert_eq!(m.transmittance(2.0), Color::new(0.13534, 1.0, 0.01832));
+        assert_eq!(Material::glass().transmittance(10.0), WHITE);
+    }
+
+    #[test]
+    fn summarizing_a_material() {
+        assert_eq!(
+            Material::default().summary(),
+            "color (1, 1, 1) ambient 0.1 diffuse 0.9 specular 0.9 shininess 200"
+        );
+        assert_eq!(
+            Material::glass().summary(),
+            "color (1, 1, 1) ambient 0 diffuse 0.588235 specular 0.9 shininess 300 \
+reflective 0.08 transparency 1 refractive index 1.5"
+        );
     }
 
     #[test]
@@ -199,6 +852,174 @@ mod tests {
         assert_eq!(result, Color::new(0.1, 0.1, 0.1));
     }
 
+    #[test]
+    fn lighting_a_translucent_surface_from_behind() {
+        let m = Material {
+            translucency: 0.5,
+            ..Material::default()
+        };
+        let position = Tuple::point(0.0, 0.0, 0.0);
+        let eyev = Tuple::vector(0.0, 0.0, -1.0);
+        let normalv = Tuple::vector(0.0, 0.0, -1.0);
+        let s = Object::new_sphere();
+        let light = Light::new_point(Tuple::point(0.0, 0.0, 10.0), WHITE);
+        let result = m.lighting(&s, WHITE, &vec![light], position, eyev, normalv);
+        assert_eq!(result, Color::new(0.55, 0.55, 0.55));
+        let light = Light::new_point(Tuple::point(0.0, 10.0, 10.0), WHITE);
+        let result = m.lighting(&s, WHITE, &vec![light], position, eyev, normalv);
+        assert_eq!(result, Color::new(0.41820, 0.41820, 0.41820));
+    }
+
+    #[test]
+    fn creating_a_metal_material() {
+        let m = Material::metal(Color::new(1.0, 0.5, 0.0), 0.3);
+        assert!(m.metallic);
+        assert_eq!(m.roughness, 0.3);
+        assert_eq!(m.diffuse, 0.0);
+        assert_eq!(m.reflection_tint(), Color::new(1.0, 0.5, 0.0));
+        assert_eq!(
+            Material::glass().reflection_tint(),
+            Color::new(0.08, 0.08, 0.08)
+        );
+    }
+
+    #[test]
+    fn a_tinted_mirror_reflects_each_channel_by_its_own_amount() {
+        let gold = Color::new(1.0, 0.78, 0.34);
+        let m = Material::default().with_reflective_color(gold);
+        assert_eq!(m.reflective, 1.0);
+        assert_eq!(m.reflection_tint(), gold);
+        let m = Material {
+            reflective: 0.5,
+            ..Material::metal(WHITE, 0.0).with_reflective_color(gold)
+        };
+        assert_eq!(m.reflection_tint(), Color::new(0.5, 0.39, 0.17));
+    }
+
+    #[test]
+    fn anisotropic_highlights_stretch_along_the_tangent() {
+        let position = Tuple::point(0.0, 0.0, 0.0);
+        let normalv = Tuple::vector(0.0, 0.0, -1.0);
+        let s = Object::new_sphere();
+        let light = vec![Light::new_point(Tuple::point(0.0, 0.0, -10.0), WHITE)];
+        let highlight = |m: &Material, eyev: Tuple| {
+            m.lighting_terms(&s, BLACK, &light, position, eyev.normalize(), normalv)
+                .2
+                .red()
+        };
+        let along = Tuple::vector(0.3, 0.0, -1.0);
+        let across = Tuple::vector(0.0, 0.3, -1.0);
+        for m in [Material::default(), Material::metal(WHITE, 0.3)] {
+            assert!(equals(highlight(&m, along), highlight(&m, across)));
+            let m = m.with_anisotropy(0.8, Tangents::Direction(Tuple::vector(1.0, 0.0, 0.0)));
+            assert!(highlight(&m, along) > highlight(&m, across));
+        }
+        let isotropic = Material::default().with_anisotropy(0.0, Tangents::Circular);
+        assert_eq!(
+            highlight(&isotropic, along),
+            highlight(&Material::default(), along)
+        );
+    }
+
+    #[test]
+    fn tangents_are_projected_onto_the_surface() {
+        let s = Object::new_sphere();
+        let m = Material::default().with_anisotropy(0.5, Tangents::Circular);
+        let tangent = m.tangent_at(
+            &s,
+            Tuple::point(1.0, 0.0, 0.0),
+            Tuple::vector(1.0, 0.0, 0.0),
+            &[],
+        );
+        assert_eq!(tangent, Tuple::vector(0.0, 0.0, 1.0));
+        let m = Material::default().with_anisotropy(0.5, Tangents::Attribute(0));
+        let normalv = Tuple::vector(0.0, 0.0, -1.0);
+        let position = Tuple::point(0.0, 0.0, -1.0);
+        let attributes = [Tuple::point(0.0, 2.0, -1.0)];
+        assert_eq!(
+            m.tangent_at(&s, position, normalv, &attributes),
+            Tuple::vector(0.0, 1.0, 0.0)
+        );
+        assert_eq!(
+            m.tangent_at(&s, position, normalv, &[]),
+            Tuple::vector(1.0, 0.0, 0.0)
+        );
+        let m = Material::default()
+            .with_anisotropy(0.5, Tangents::Direction(Tuple::vector(0.0, 0.0, 1.0)));
+        let tangent = m.tangent_at(&s, position, normalv, &[]);
+        assert!(equals(tangent.magnitude(), 1.0));
+        assert!(equals(tangent.dot(normalv), 0.0));
+    }
+
+    #[test]
+    fn lighting_a_metal_with_a_microfacet_highlight() {
+        let m = Material::metal(Color::new(1.0, 0.5, 0.0), 0.5);
+        let position = Tuple::point(0.0, 0.0, 0.0);
+        let eyev = Tuple::vector(0.0, 0.0, -1.0);
+        let normalv = Tuple::vector(0.0, 0.0, -1.0);
+        let s = Object::new_sphere();
+        let light = Light::new_point(Tuple::point(0.0, 0.0, -10.0), WHITE);
+        let result = m.lighting(&s, WHITE, &vec![light], position, eyev, normalv);
+        assert_eq!(result, Color::new(1.37324, 0.68662, 0.0));
+        let light = Light::new_point(Tuple::point(0.0, 0.0, 10.0), WHITE);
+        let result = m.lighting(&s, WHITE, &vec![light], position, eyev, normalv);
+        assert_eq!(result, Color::new(0.1, 0.05, 0.0));
+    }
+
+    #[test]
+    fn a_rougher_metal_has_a_wider_highlight() {
+        let position = Tuple::point(0.0, 0.0, 0.0);
+        let eyev = Tuple::vector(0.0, SQRT_2 / 2.0, -SQRT_2 / 2.0);
+        let normalv = Tuple::vector(0.0, 0.0, -1.0);
+        let s = Object::new_sphere();
+        let light = Light::new_point(Tuple::point(0.0, 0.0, -10.0), WHITE);
+        let smooth = Material::metal(WHITE, 0.1).lighting(
+            &s,
+            BLACK,
+            &vec![light.clone()],
+            position,
+            eyev,
+            normalv,
+        );
+        let rough =
+            Material::metal(WHITE, 0.8).lighting(&s, BLACK, &vec![light], position, eyev, normalv);
+        assert!(rough.red() > smooth.red());
+    }
+
+    #[test]
+    fn a_clear_coat_reflects_more_at_grazing_angles() {
+        let coat = ClearCoat::new(1.0, 1.5, 0.0);
+        assert!(equals(coat.base_reflectance(), 0.04));
+        assert!(equals(coat.fresnel(1.0), 0.04));
+        assert!(equals(coat.fresnel(0.0), 1.0));
+        assert!(equals(ClearCoat::new(0.5, 1.5, 0.0).fresnel(1.0), 0.02));
+        let m = Material::default().with_clear_coat(coat);
+        let normalv = Tuple::vector(0.0, 0.0, -1.0);
+        assert!(equals(
+            m.coat_reflectance(Tuple::vector(0.0, 0.0, -1.0), normalv),
+            0.04
+        ));
+        assert_eq!(
+            Material::default().coat_reflectance(Tuple::vector(0.0, 0.0, -1.0), normalv),
+            0.0
+        );
+    }
+
+    #[test]
+    fn lighting_a_clear_coated_surface() {
+        let m = Material::default().with_clear_coat(ClearCoat::new(1.0, 1.5, 0.2));
+        let position = Tuple::point(0.0, 0.0, 0.0);
+        let eyev = Tuple::vector(0.0, 0.0, -1.0);
+        let normalv = Tuple::vector(0.0, 0.0, -1.0);
+        let s = Object::new_sphere();
+        let light = Light::new_point(Tuple::point(0.0, 0.0, 10.0), WHITE);
+        let result = m.lighting(&s, WHITE, &vec![light], position, eyev, normalv);
+        assert_eq!(result, Color::new(0.096, 0.096, 0.096));
+        let light = Light::new_point(Tuple::point(0.0, 0.0, -10.0), WHITE);
+        let (_, _, specular) = m.lighting_terms(&s, WHITE, &vec![light], position, eyev, normalv);
+        assert!(specular.red() > 0.9 * 0.96);
+    }
+
     #[test]
     fn lighting_with_the_surface_in_shadow() {
         let m = Material::default();
@@ -212,6 +1033,7 @@ mod tests {
     }
 
     #[test]
+    #[allow(clippy::field_reassign_with_default)]
     fn lighting_with_a_pattern_applied() {
         let mut m = Material::default();
         m.pattern = Some(Pattern::new_stripe(WHITE, BLACK));
@@ -1,6 +1,6 @@
 use crate::floats::{equals, Float};
 use crate::tuples::Tuple;
-use std::{cmp, ops};
+use std::{cmp, fmt, ops};
 
 #[derive(Debug, Copy, Clone)]
 pub struct Matrix<const M: usize>([[Float; M]; M]);
@@ -58,7 +58,7 @@ impl Matrix<3> {
 
     fn cofactor(self, row: usize, column: usize) -> Float {
         let minor = self.minor(row, column);
-        if (row + column) % 2 == 0 {
+        if (row + column).is_multiple_of(2) {
             minor
         } else {
             -minor
@@ -95,7 +95,7 @@ impl Matrix<4> {
 
     fn cofactor(self, row: usize, column: usize) -> Float {
         let minor = self.minor(row, column);
-        if (row + column) % 2 == 0 {
+        if (row + column).is_multiple_of(2) {
             minor
         } else {
             -minor
@@ -109,8 +109,9 @@ impl Matrix<4> {
             + self.0[0][3] * self.cofactor(0, 3)
     }
 
-    fn is_invertible(self) -> bool {
-        !equals(self.determinant(), 0.0)
+    pub fn is_invertible(self) -> bool {
+        let determinant = self.determinant();
+        determinant.is_finite() && !equals(determinant, 0.0)
     }
 
     pub fn inverse(self) -> Matrix<4> {
@@ -196,6 +197,31 @@ impl ops::Mul<Tuple> for Matrix<4> {
     }
 }
 
+// One line per row, the columns right aligned. A precision, as in
+// `{:.2}`, applies to every element.
+impl<const M: usize> fmt::Display for Matrix<M> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let cells = self.0.map(|row| {
+            row.map(|v| match f.precision() {
+                Some(precision) => format!("{:.*}", precision, v),
+                None => v.to_string(),
+            })
+        });
+        let width = cells.iter().flatten().map(String::len).max().unwrap_or(0);
+        for (i, row) in cells.iter().enumerate() {
+            if i > 0 {
+                writeln!(f)?;
+            }
+            write!(f, "|")?;
+            for cell in row {
+                write!(f, " {:>width$}", cell, width = width)?;
+            }
+            write!(f, " |")?;
+        }
+        Ok(())
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -524,4 +550,14 @@ mod tests {
         let c = a * b;
         assert_eq!(c * b.inverse(), a);
     }
+
+    #[test]
+    fn displaying_a_matrix_as_an_aligned_grid() {
+        let a = Matrix::new([[1.0, -2.5], [10.0, 0.0]]);
+        assert_eq!(format!("{}", a), "|    1 -2.5 |\n|   10    0 |");
+        assert_eq!(
+            format!("{:.1}", Matrix::<2>::identity()),
+            "| 1.0 0.0 |\n| 0.0 1.0 |"
+        );
+    }
 }
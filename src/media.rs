@@ -0,0 +1,105 @@
+use crate::colors::Color;
+use crate::floats::Float;
+
+const MIN_TRANSMITTANCE: Float = 0.001;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Fog {
+    pub color: Color,
+    pub density: Float,
+    pub samples: usize,
+}
+
+impl Fog {
+    pub fn new(color: Color, density: Float) -> Fog {
+        Fog {
+            color,
+            density,
+            samples: 8,
+        }
+    }
+
+    pub fn with_samples(self, samples: usize) -> Fog {
+        Fog {
+            samples: samples.max(1),
+            ..self
+        }
+    }
+
+    // Also for infinite distances, that clear fog does not hide.
+    pub fn transmittance(&self, distance: Float) -> Float {
+        if self.density <= 0.0 {
+            return 1.0;
+        }
+        (-self.density * distance).exp()
+    }
+
+    pub fn max_distance(&self) -> Float {
+        if self.density <= 0.0 {
+            return 0.0;
+        }
+        -MIN_TRANSMITTANCE.ln() / self.density
+    }
+
+    pub fn sample_distances(&self, distance: Float) -> Vec<(Float, Float)> {
+        let distance = distance.min(self.max_distance());
+        let step = distance / self.samples as Float;
+        (0..self.samples)
+            .map(|i| {
+                let s = (i as Float + 0.5) * step;
+                (s, self.density * self.transmittance(s) * step)
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::colors::WHITE;
+    use crate::floats::equals;
+
+    #[test]
+    fn creating_a_fog() {
+        let fog = Fog::new(WHITE, 0.5);
+        assert_eq!(fog.color, WHITE);
+        assert_eq!(fog.density, 0.5);
+        assert_eq!(fog.samples, 8);
+        assert_eq!(fog.with_samples(0).samples, 1);
+    }
+
+    #[test]
+    fn the_transmittance_decays_with_distance() {
+        let fog = Fog::new(WHITE, 0.5);
+        assert_eq!(fog.transmittance(0.0), 1.0);
+        assert!(equals(fog.transmittance(2.0), 0.36788));
+        assert_eq!(Fog::new(WHITE, 0.0).transmittance(100.0), 1.0);
+        assert_eq!(Fog::new(WHITE, 0.0).transmittance(Float::INFINITY), 1.0);
+        assert_eq!(fog.transmittance(Float::INFINITY), 0.0);
+    }
+
+    #[test]
+    fn sampling_distances_along_a_ray() {
+        let fog = Fog::new(WHITE, 0.5).with_samples(2);
+        let samples = fog.sample_distances(4.0);
+        assert_eq!(samples.len(), 2);
+        assert!(equals(samples[0].0, 1.0));
+        assert!(equals(samples[1].0, 3.0));
+        assert!(equals(samples[0].1, 0.5 * 0.60653 * 2.0));
+        let total: Float = fog
+            .with_samples(512)
+            .sample_distances(4.0)
+            .iter()
+            .map(|s| s.1)
+            .sum();
+        assert!((total - (1.0 - fog.transmittance(4.0))).abs() < 0.001);
+    }
+
+    #[test]
+    fn sampling_distances_stops_where_the_fog_is_opaque() {
+        let fog = Fog::new(WHITE, 0.5).with_samples(1);
+        let samples = fog.sample_distances(Float::INFINITY);
+        assert!(equals(samples[0].0, fog.max_distance() / 2.0));
+        assert!(equals(fog.max_distance(), 13.81551));
+    }
+}
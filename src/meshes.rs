@@ -0,0 +1,357 @@
+use crate::floats::{Float, EPSILON};
+use crate::matrices::Matrix;
+use crate::objects::Object;
+use crate::tuples::Tuple;
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, HashMap};
+
+// An indexed triangle mesh, to process geometry, e.g. of an OBJ file, before
+// it is turned into triangle objects.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Mesh {
+    pub vertices: Vec<Tuple>,
+    pub triangles: Vec<[usize; 3]>,
+}
+
+impl Mesh {
+    pub fn new(vertices: Vec<Tuple>, triangles: Vec<[usize; 3]>) -> Mesh {
+        Mesh {
+            vertices,
+            triangles,
+        }
+    }
+
+    // Bakes `transform` into the vertices.
+    pub fn transform(&mut self, transform: Matrix<4>) {
+        for v in &mut self.vertices {
+            *v = transform * *v;
+        }
+    }
+
+    // Points the triangles at a single vertex for all the vertices closer
+    // than `epsilon`, e.g. for files that repeat the vertices of each face.
+    // The triangles are kept, even those that become degenerate.
+    pub fn merge_vertices(&mut self, epsilon: Float) {
+        let epsilon = epsilon.max(EPSILON);
+        let cell = |p: Tuple| [p.x(), p.y(), p.z()].map(|c| (c / epsilon).floor() as i64);
+        let mut cells: HashMap<[i64; 3], Vec<usize>> = HashMap::new();
+        let mut vertices: Vec<Tuple> = vec![];
+        let mut index = Vec::with_capacity(self.vertices.len());
+        for &position in &self.vertices {
+            let [x, y, z] = cell(position);
+            let merged = (-1..=1)
+                .flat_map(|dx| {
+                    (-1..=1).flat_map(move |dy| (-1..=1).map(move |dz| [x + dx, y + dy, z + dz]))
+                })
+                .filter_map(|c| cells.get(&c))
+                .flatten()
+                .copied()
+                .find(|&v| (vertices[v] - position).magnitude() <= epsilon);
+            index.push(merged.unwrap_or_else(|| {
+                vertices.push(position);
+                cells.entry([x, y, z]).or_default().push(vertices.len() - 1);
+                vertices.len() - 1
+            }));
+        }
+        for v in self.triangles.iter_mut().flatten() {
+            *v = index[*v];
+        }
+        self.vertices = vertices;
+    }
+
+    // Merges the vertices closer than `epsilon` and drops the triangles
+    // that collapse, so that neighbouring faces share their vertices.
+    pub fn weld(&mut self, epsilon: Float) {
+        self.merge_vertices(epsilon);
+        self.triangles
+            .retain(|&[a, b, c]| a != b && b != c && c != a);
+        self.remove_unused_vertices();
+    }
+
+    // Same orientation as the normal of a flat triangle, none for a
+    // degenerate one.
+    pub fn face_normal(&self, [a, b, c]: [usize; 3]) -> Option<Tuple> {
+        let normal =
+            (self.vertices[c] - self.vertices[a]).cross(self.vertices[b] - self.vertices[a]);
+        (normal.magnitude() >= EPSILON).then(|| normal.normalize())
+    }
+
+    // The normals at the corners of every triangle. A corner averages the
+    // normals of the faces around its vertex, weighted by their angle at the
+    // vertex so that the triangulation does not matter, and leaving out the
+    // faces beyond the crease angle. Degenerate triangles have none.
+    pub fn smooth_normals(&self, crease_angle: Float) -> Vec<Option<[Tuple; 3]>> {
+        let face_normals: Vec<Option<Tuple>> = self
+            .triangles
+            .iter()
+            .map(|&t| self.face_normal(t))
+            .collect();
+        let mut corners_around = vec![vec![]; self.vertices.len()];
+        for (face, &[a, b, c]) in self.triangles.iter().enumerate() {
+            let angle = |p: usize, q: usize, r: usize| {
+                (self.vertices[q] - self.vertices[p]).angle(self.vertices[r] - self.vertices[p])
+            };
+            corners_around[a].push((face, angle(a, b, c)));
+            corners_around[b].push((face, angle(b, c, a)));
+            corners_around[c].push((face, angle(c, a, b)));
+        }
+        let min_cos = crease_angle.cos() - EPSILON;
+        self.triangles
+            .iter()
+            .zip(&face_normals)
+            .map(|(triangle, normal)| {
+                let normal = (*normal)?;
+                Some(triangle.map(|vertex| {
+                    corners_around[vertex]
+                        .iter()
+                        .filter_map(|&(face, angle)| face_normals[face].map(|n| (n, angle)))
+                        .filter(|(n, _)| n.dot(normal) >= min_cos)
+                        .fold(Tuple::vector(0.0, 0.0, 0.0), |sum, (n, angle)| {
+                            sum + n * angle
+                        })
+                        .normalize()
+                }))
+            })
+            .collect()
+    }
+
+    // Collapses the shortest edges first, moving their two vertices to the
+    // middle, until at most `target_triangles` are left. Collapses that
+    // would flip a neighbouring triangle are skipped, so the result may stay
+    // above the target.
+    pub fn decimate(&mut self, target_triangles: usize) {
+        let mut alive = vec![true; self.triangles.len()];
+        let mut count = self.triangles.len();
+        let mut around = vec![vec![]; self.vertices.len()];
+        for (t, triangle) in self.triangles.iter().enumerate() {
+            for &v in triangle {
+                around[v].push(t);
+            }
+        }
+        // Lengths are positive, so their bits sort like them.
+        let mut edges = BinaryHeap::new();
+        let key = |mesh: &Mesh, a: usize, b: usize| {
+            Reverse((
+                (mesh.vertices[a] - mesh.vertices[b]).magnitude().to_bits(),
+                a,
+                b,
+            ))
+        };
+        for &[a, b, c] in &self.triangles {
+            for (p, q) in [(a, b), (b, c), (c, a)] {
+                edges.push(key(self, p.min(q), p.max(q)));
+            }
+        }
+        while count > target_triangles {
+            let Some(Reverse((length, a, b))) = edges.pop() else {
+                break;
+            };
+            // Entries left from before a collapse moved a vertex are stale.
+            if around[a].is_empty()
+                || around[b].is_empty()
+                || (self.vertices[a] - self.vertices[b]).magnitude().to_bits() != length
+            {
+                continue;
+            }
+            let middle = self.vertices[a] + (self.vertices[b] - self.vertices[a]) * 0.5;
+            if self.collapse_flips(&around, &alive, a, b, middle) {
+                continue;
+            }
+            self.vertices[a] = middle;
+            for t in std::mem::take(&mut around[b]) {
+                if !alive[t] {
+                    continue;
+                }
+                let triangle = &mut self.triangles[t];
+                if triangle.contains(&a) {
+                    alive[t] = false;
+                    count -= 1;
+                } else {
+                    triangle
+                        .iter_mut()
+                        .filter(|v| **v == b)
+                        .for_each(|v| *v = a);
+                    around[a].push(t);
+                }
+            }
+            around[a].retain(|&t| alive[t]);
+            for &t in &around[a] {
+                for &v in &self.triangles[t] {
+                    if v != a {
+                        edges.push(key(self, v.min(a), v.max(a)));
+                    }
+                }
+            }
+        }
+        let triangles = self
+            .triangles
+            .iter()
+            .zip(alive)
+            .filter_map(|(t, alive)| alive.then_some(*t))
+            .collect();
+        self.triangles = triangles;
+        self.remove_unused_vertices();
+    }
+
+    fn collapse_flips(
+        &self,
+        around: &[Vec<usize>],
+        alive: &[bool],
+        a: usize,
+        b: usize,
+        middle: Tuple,
+    ) -> bool {
+        around[a].iter().chain(&around[b]).any(|&t| {
+            let triangle = self.triangles[t];
+            if !alive[t] || (triangle.contains(&a) && triangle.contains(&b)) {
+                return false;
+            }
+            let Some(before) = self.face_normal(triangle) else {
+                return false;
+            };
+            let mut moved = self.corners(triangle);
+            for (i, &v) in triangle.iter().enumerate() {
+                if v == a || v == b {
+                    moved[i] = middle;
+                }
+            }
+            let after = (moved[2] - moved[0]).cross(moved[1] - moved[0]);
+            after.dot(before) <= 0.0
+        })
+    }
+
+    fn corners(&self, triangle: [usize; 3]) -> [Tuple; 3] {
+        triangle.map(|v| self.vertices[v])
+    }
+
+    fn remove_unused_vertices(&mut self) {
+        let mut used = vec![false; self.vertices.len()];
+        for &v in self.triangles.iter().flatten() {
+            used[v] = true;
+        }
+        let mut index = vec![0; self.vertices.len()];
+        let mut vertices = vec![];
+        for (v, &position) in self.vertices.iter().enumerate() {
+            if used[v] {
+                index[v] = vertices.len();
+                vertices.push(position);
+            }
+        }
+        for v in self.triangles.iter_mut().flatten() {
+            *v = index[*v];
+        }
+        self.vertices = vertices;
+    }
+
+    // A group of triangles, smooth ones when a crease angle is given.
+    pub fn to_group(&self, crease_angle: Option<Float>) -> Object {
+        let normals = crease_angle.map_or(vec![], |angle| self.smooth_normals(angle));
+        let mut group = Object::new_group();
+        for (i, &triangle) in self.triangles.iter().enumerate() {
+            let [p1, p2, p3] = self.corners(triangle);
+            let object = match normals.get(i).copied().flatten() {
+                Some([n1, n2, n3]) => Object::new_smooth_triangle(p1, p2, p3, n1, n2, n3),
+                None => Object::new_triangle(p1, p2, p3),
+            };
+            group
+                .as_mut_group()
+                .add_child(object.with_inherited_material());
+        }
+        group
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::floats::PI;
+    use crate::transformations::{scaling, translation};
+
+    // A flat square of the xz plane, split in n x n quads.
+    fn grid(n: usize) -> Mesh {
+        let vertices = (0..=n)
+            .flat_map(|z| (0..=n).map(move |x| Tuple::point(x as Float, 0.0, z as Float)))
+            .collect();
+        let index = |x: usize, z: usize| z * (n + 1) + x;
+        let triangles = (0..n)
+            .flat_map(|z| (0..n).map(move |x| (x, z)))
+            .flat_map(|(x, z)| {
+                [
+                    [index(x, z), index(x + 1, z), index(x + 1, z + 1)],
+                    [index(x, z), index(x + 1, z + 1), index(x, z + 1)],
+                ]
+            })
+            .collect();
+        Mesh::new(vertices, triangles)
+    }
+
+    #[test]
+    fn decimating_a_mesh_keeps_its_shape() {
+        let mut mesh = grid(10);
+        let normal = mesh.face_normal(mesh.triangles[0]).unwrap();
+        mesh.decimate(50);
+        assert!(mesh.triangles.len() <= 50 && mesh.triangles.len() > 10);
+        assert!(mesh.vertices.len() < 121);
+        for &triangle in &mesh.triangles {
+            assert_eq!(mesh.face_normal(triangle), Some(normal));
+        }
+        for v in &mesh.vertices {
+            assert_eq!(v.y(), 0.0);
+            assert!((0.0..=10.0).contains(&v.x()) && (0.0..=10.0).contains(&v.z()));
+        }
+    }
+
+    #[test]
+    fn decimating_to_more_triangles_changes_nothing() {
+        let mut mesh = grid(2);
+        mesh.decimate(8);
+        assert_eq!(mesh, grid(2));
+    }
+
+    #[test]
+    fn welding_merges_coincident_vertices() {
+        // Each triangle has its own vertices, and one is degenerate.
+        let mut mesh = Mesh::new(
+            vec![
+                Tuple::point(0.0, 0.0, 0.0),
+                Tuple::point(1.0, 0.0, 0.0),
+                Tuple::point(1.0, 0.0, 1.0),
+                Tuple::point(0.00001, 0.0, 0.0),
+                Tuple::point(1.0, 0.0, 1.00001),
+                Tuple::point(0.0, 0.0, 1.0),
+                Tuple::point(0.0, 0.0, 1.0),
+                Tuple::point(0.0, 0.0, 1.0),
+                Tuple::point(1.0, 0.0, 0.0),
+            ],
+            vec![[0, 1, 2], [3, 4, 5], [6, 7, 8]],
+        );
+        mesh.weld(0.001);
+        assert_eq!(mesh.triangles, vec![[0, 1, 2], [0, 2, 3]]);
+        assert_eq!(mesh.vertices.len(), 4);
+        assert_eq!(mesh.vertices[3], Tuple::point(0.0, 0.0, 1.0));
+        // The shared edge makes the normals smooth.
+        let normals = mesh.smooth_normals(PI);
+        assert_eq!(normals[0].unwrap()[0], normals[1].unwrap()[0]);
+    }
+
+    #[test]
+    fn baking_a_transform_into_a_mesh() {
+        let mut mesh = grid(1);
+        mesh.transform(translation(0.0, 2.0, 0.0) * scaling(2.0, 1.0, 1.0));
+        assert_eq!(mesh.vertices[3], Tuple::point(2.0, 2.0, 1.0));
+    }
+
+    #[test]
+    fn a_mesh_becomes_a_group_of_triangles() {
+        let mesh = grid(1);
+        let flat = mesh.to_group(None);
+        assert_eq!(flat.as_group().children.len(), 2);
+        assert_eq!(
+            flat.as_group().children[1].as_triangle().p3,
+            Tuple::point(0.0, 0.0, 1.0)
+        );
+        let smooth = mesh.to_group(Some(0.5));
+        let t = smooth.as_group().children[0].as_smooth_triangle();
+        assert_eq!(t.n1, mesh.face_normal(mesh.triangles[0]).unwrap());
+    }
+}
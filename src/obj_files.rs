@@ -1,5 +1,9 @@
+use crate::floats::{Float, EPSILON};
+use crate::meshes::Mesh;
 use crate::objects::Object;
 use crate::tuples::Tuple;
+use std::str::SplitWhitespace;
+use std::sync::Arc;
 
 pub struct ObjFile {
     pub default_group: Object,
@@ -9,58 +13,154 @@ pub struct ObjFile {
     vertices: Vec<Tuple>,
 }
 
-pub fn parse_obj_file_path(path: &str) -> ObjFile {
-    parse_obj_file(&std::fs::read_to_string(path).unwrap())
+pub fn parse_obj_file_path(path: &str) -> Result<ObjFile, String> {
+    parse_obj_file(&read(path)?).map_err(|e| format!("{}: {}", path, e))
 }
 
-fn parse_obj_file(lines: &str) -> ObjFile {
+// Faces without normals get smooth ones, except across edges sharper than
+// `crease_angle`, so that e.g. the corners of a cube stay hard.
+pub fn parse_smooth_obj_file_path(path: &str, crease_angle: Float) -> Result<ObjFile, String> {
+    parse_obj_file_with(&read(path)?, Some(crease_angle)).map_err(|e| format!("{}: {}", path, e))
+}
+
+fn read(path: &str) -> Result<String, String> {
+    std::fs::read_to_string(path).map_err(|e| format!("{}: {}", path, e))
+}
+
+// Malformed records, e.g. of a corrupted file, are errors instead of panics.
+pub fn parse_obj_file(lines: &str) -> Result<ObjFile, String> {
+    parse_obj_file_with(lines, None)
+}
+
+fn parse_obj_file_with(lines: &str, crease_angle: Option<Float>) -> Result<ObjFile, String> {
     let mut default_group = Object::new_group();
     let mut normals = vec![];
     let mut vertices = vec![];
+    // Faces that repeat their vertices are still smoothed together.
+    let mut smooth = match crease_angle {
+        Some(angle) => {
+            let mut mesh = parse_obj_mesh(lines)?;
+            mesh.merge_vertices(EPSILON);
+            mesh.smooth_normals(angle)
+        }
+        None => vec![],
+    }
+    .into_iter();
     let mut current_group = &mut default_group;
-    for line in lines.lines() {
+    for (i, line) in lines.lines().enumerate() {
+        let at = |e: String| format!("line {}: {}", i + 1, e);
         let mut words = line.split_whitespace();
         match words.next() {
             Some("v") => {
-                let x = words.next().unwrap().parse().unwrap();
-                let y = words.next().unwrap().parse().unwrap();
-                let z = words.next().unwrap().parse().unwrap();
+                let (x, y, z) = triple(&mut words).map_err(at)?;
                 vertices.push(Tuple::point(x, y, z));
             }
             Some("vn") => {
-                let x = words.next().unwrap().parse().unwrap();
-                let y = words.next().unwrap().parse().unwrap();
-                let z = words.next().unwrap().parse().unwrap();
+                let (x, y, z) = triple(&mut words).map_err(at)?;
                 normals.push(Tuple::vector(x, y, z));
             }
             Some("f") => {
-                let mut indices: Vec<usize> = vec![];
-                let mut normal_indices: Vec<usize> = vec![];
-                for word in words {
-                    if word.contains('/') {
-                        let mut ints = word.split('/');
-                        indices.push(ints.next().unwrap().parse().unwrap());
-                        ints.next();
-                        normal_indices.push(ints.next().unwrap().parse().unwrap());
-                    } else {
-                        indices.push(word.parse().unwrap());
-                    }
-                }
-                fan_triangulation(&vertices, &normals, indices, normal_indices, current_group);
+                let (indices, normal_indices) =
+                    face_indices(words, vertices.len(), normals.len()).map_err(at)?;
+                fan_triangulation(
+                    &vertices,
+                    &normals,
+                    indices,
+                    normal_indices,
+                    &mut smooth,
+                    current_group,
+                );
             }
             Some("g") => {
-                let new_group = Object::new_group();
+                let new_group = Object::new_group().with_inherited_material();
                 default_group.as_mut_group().add_child(new_group);
-                current_group = default_group.as_mut_group().children.last_mut().unwrap();
+                current_group =
+                    Arc::make_mut(default_group.as_mut_group().children.last_mut().unwrap());
             }
             _ => {}
         }
     }
-    ObjFile {
+    Ok(ObjFile {
         default_group,
         normals,
         vertices,
+    })
+}
+
+fn triple(words: &mut SplitWhitespace) -> Result<(Float, Float, Float), String> {
+    let mut next = || {
+        let word = words.next().ok_or("missing coordinate")?;
+        word.parse()
+            .ok()
+            .filter(|v: &Float| v.is_finite())
+            .ok_or_else(|| format!("invalid number '{}'", word))
+    };
+    Ok((next()?, next()?, next()?))
+}
+
+// The vertex and normal indices of a face, from 0 instead of 1 as in the
+// file, checked against the number of records read so far.
+fn face_indices(
+    words: SplitWhitespace,
+    vertex_count: usize,
+    normal_count: usize,
+) -> Result<(Vec<usize>, Vec<usize>), String> {
+    let index = |word: &str, count: usize| {
+        word.parse::<usize>()
+            .ok()
+            .filter(|i| (1..=count).contains(i))
+            .map(|i| i - 1)
+            .ok_or_else(|| format!("invalid index '{}'", word))
+    };
+    let mut indices: Vec<usize> = vec![];
+    let mut normal_indices: Vec<usize> = vec![];
+    for word in words {
+        let mut ints = word.split('/');
+        indices.push(index(ints.next().unwrap_or(""), vertex_count)?);
+        if let Some(normal) = ints.nth(1).filter(|n| !n.is_empty()) {
+            normal_indices.push(index(normal, normal_count)?);
+        }
+    }
+    if indices.len() < 3 {
+        return Err(String::from("a face needs at least 3 vertices"));
+    }
+    if !normal_indices.is_empty() && normal_indices.len() != indices.len() {
+        return Err(String::from("some vertices of the face have no normal"));
     }
+    Ok((indices, normal_indices))
+}
+
+// Every face of the file, fan triangulated, without its group or normals.
+pub fn parse_obj_mesh_path(path: &str) -> Result<Mesh, String> {
+    parse_obj_mesh(&read(path)?).map_err(|e| format!("{}: {}", path, e))
+}
+
+fn parse_obj_mesh(lines: &str) -> Result<Mesh, String> {
+    let mut vertices = vec![];
+    let mut normal_count = 0;
+    let mut triangles: Vec<[usize; 3]> = vec![];
+    for (i, line) in lines.lines().enumerate() {
+        let at = |e: String| format!("line {}: {}", i + 1, e);
+        let mut words = line.split_whitespace();
+        match words.next() {
+            Some("v") => {
+                let (x, y, z) = triple(&mut words).map_err(at)?;
+                vertices.push(Tuple::point(x, y, z));
+            }
+            Some("vn") => {
+                triple(&mut words).map_err(at)?;
+                normal_count += 1;
+            }
+            Some("f") => {
+                let (indices, _) = face_indices(words, vertices.len(), normal_count).map_err(at)?;
+                for i in 1..indices.len() - 1 {
+                    triangles.push([indices[0], indices[i], indices[i + 1]]);
+                }
+            }
+            _ => {}
+        }
+    }
+    Ok(Mesh::new(vertices, triangles))
 }
 
 fn fan_triangulation(
@@ -68,23 +168,29 @@ fn fan_triangulation(
     normals: &[Tuple],
     indices: Vec<usize>,
     normal_indices: Vec<usize>,
+    smooth: &mut std::vec::IntoIter<Option<[Tuple; 3]>>,
     group: &mut Object,
 ) {
     for i in 1..indices.len() - 1 {
-        let p1 = vertices[indices[0] - 1];
-        let p2 = vertices[indices[i] - 1];
-        let p3 = vertices[indices[i + 1] - 1];
+        let p1 = vertices[indices[0]];
+        let p2 = vertices[indices[i]];
+        let p3 = vertices[indices[i + 1]];
+        let smooth_normals = smooth.next().flatten();
         if !normal_indices.is_empty() {
-            let n1 = normals[normal_indices[0] - 1];
-            let n2 = normals[normal_indices[i] - 1];
-            let n3 = normals[normal_indices[i + 1] - 1];
-            group
-                .as_mut_group()
-                .add_child(Object::new_smooth_triangle(p1, p2, p3, n1, n2, n3));
+            let n1 = normals[normal_indices[0]];
+            let n2 = normals[normal_indices[i]];
+            let n3 = normals[normal_indices[i + 1]];
+            group.as_mut_group().add_child(
+                Object::new_smooth_triangle(p1, p2, p3, n1, n2, n3).with_inherited_material(),
+            );
+        } else if let Some([n1, n2, n3]) = smooth_normals {
+            group.as_mut_group().add_child(
+                Object::new_smooth_triangle(p1, p2, p3, n1, n2, n3).with_inherited_material(),
+            );
         } else {
             group
                 .as_mut_group()
-                .add_child(Object::new_triangle(p1, p2, p3));
+                .add_child(Object::new_triangle(p1, p2, p3).with_inherited_material());
         }
     }
 }
@@ -92,6 +198,9 @@ fn fan_triangulation(
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::floats::PI;
+    use crate::materials::Material;
+    use crate::shapes::triangles::Triangle;
 
     #[test]
     fn ignoring_unrecognized_lines() {
@@ -100,7 +209,40 @@ who traveled much faster than light.
 She set out one day
 in a relative way,
 and came back the previous night.";
-        parse_obj_file(gibberish);
+        assert!(parse_obj_file(gibberish).is_ok());
+    }
+
+    #[test]
+    fn malformed_records_are_errors() {
+        let error = |lines: &str| parse_obj_file(lines).err().unwrap();
+        let triangle = "v 0 0 0\nv 1 0 0\nv 0 1 0\n";
+        assert_eq!(error("v 1 2\n"), "line 1: missing coordinate");
+        assert_eq!(error("vn 1 nan 0\n"), "line 1: invalid number 'nan'");
+        assert_eq!(
+            error(&format!("{}f 1 2 4\n", triangle)),
+            "line 4: invalid index '4'"
+        );
+        assert_eq!(
+            error(&format!("{}f 0 1 2\n", triangle)),
+            "line 4: invalid index '0'"
+        );
+        assert_eq!(
+            error(&format!("{}f 1 2\n", triangle)),
+            "line 4: a face needs at least 3 vertices"
+        );
+        assert_eq!(
+            error(&format!("{}vn 0 0 1\nf 1//1 2 3//1\n", triangle)),
+            "line 5: some vertices of the face have no normal"
+        );
+        assert_eq!(
+            parse_obj_mesh(&format!("{}f 1 2 x\n", triangle))
+                .err()
+                .unwrap(),
+            "line 4: invalid index 'x'"
+        );
+        // Texture coordinates are skipped.
+        let obj = parse_obj_file(&format!("{}f 1/1 2/2 3/3\n", triangle)).unwrap();
+        assert_eq!(obj.default_group.as_group().children.len(), 1);
     }
 
     #[test]
@@ -109,7 +251,7 @@ and came back the previous night.";
 v -1.0000 0.5000 0.0000
 v 1 0 0
 v 1 1 0";
-        let obj = parse_obj_file(vertex_records);
+        let obj = parse_obj_file(vertex_records).unwrap();
         assert_eq!(
             obj.vertices,
             vec![
@@ -129,7 +271,7 @@ v 1 0 0
 v 1 1 0
 f 1 2 3
 f 1 3 4";
-        let obj = parse_obj_file(lines);
+        let obj = parse_obj_file(lines).unwrap();
         let g = obj.default_group;
         let t1 = g.as_group().children[0].as_triangle();
         let t2 = g.as_group().children[1].as_triangle();
@@ -149,7 +291,7 @@ v 1 0 0
 v 1 1 0
 v 0 2 0
 f 1 2 3 4 5";
-        let obj = parse_obj_file(lines);
+        let obj = parse_obj_file(lines).unwrap();
         let g = obj.default_group;
         let t1 = g.as_group().children[0].as_triangle();
         let t2 = g.as_group().children[1].as_triangle();
@@ -175,7 +317,7 @@ g FirstGroup
 f 1 2 3
 g SecondGroup
 f 1 3 4";
-        let obj = parse_obj_file(lines);
+        let obj = parse_obj_file(lines).unwrap();
         let g1 = obj.default_group.as_group().children[0].as_group();
         let g2 = obj.default_group.as_group().children[1].as_group();
         let t1 = g1.children[0].as_triangle();
@@ -193,12 +335,31 @@ f 1 3 4";
         let lines = "vn 0 0 1
 vn 0.707 0 -0.707
 vn 1 2 3";
-        let obj = parse_obj_file(lines);
+        let obj = parse_obj_file(lines).unwrap();
         assert_eq!(obj.normals[0], Tuple::vector(0.0, 0.0, 1.0));
         assert_eq!(obj.normals[1], Tuple::vector(0.707, 0.0, -0.707));
         assert_eq!(obj.normals[2], Tuple::vector(1.0, 2.0, 3.0));
     }
 
+    #[test]
+    fn faces_inherit_the_material_of_the_group() {
+        let lines = "v -1 1 0
+v -1 0 0
+v 1 0 0
+f 1 2 3
+g FirstGroup
+f 1 2 3";
+        let mut g = parse_obj_file(lines).unwrap().default_group;
+        g.material = Material::glass();
+        g.prepare();
+        let children = &g.as_group().children;
+        assert_eq!(children[0].material, Material::glass());
+        assert_eq!(
+            children[1].as_group().children[0].material,
+            Material::glass()
+        );
+    }
+
     #[test]
     fn faces_with_normals() {
         let lines = "v 0 1 0
@@ -209,7 +370,7 @@ vn 1 0 0
 vn 0 1 0
 f 1//3 2//1 3//2
 f 1/0/3 2/102/1 3/14/2";
-        let obj = parse_obj_file(lines);
+        let obj = parse_obj_file(lines).unwrap();
         let g = obj.default_group;
         let t1 = g.as_group().children[0].as_smooth_triangle();
         let t2 = g.as_group().children[1].as_smooth_triangle();
@@ -221,4 +382,71 @@ f 1/0/3 2/102/1 3/14/2";
         assert_eq!(t1.n3, obj.normals[1]);
         assert_eq!(t2, t1);
     }
+
+    const CUBE: &str = "v -1 -1 -1
+v 1 -1 -1
+v 1 1 -1
+v -1 1 -1
+v -1 -1 1
+v 1 -1 1
+v 1 1 1
+v -1 1 1
+f 1 2 3 4
+f 5 8 7 6
+f 1 5 6 2
+f 4 3 7 8
+f 1 4 8 5
+f 2 6 7 3";
+
+    #[test]
+    fn faces_with_their_own_vertices_are_smoothed_together() {
+        let lines = "v 0 0 0
+v 1 0 0
+v 0 0 1
+v 1 0 0
+v 1 1 1
+v 0 0 1
+f 1 2 3
+f 4 5 6";
+        let obj = parse_obj_file_with(lines, Some(PI / 2.0)).unwrap();
+        let g = obj.default_group;
+        let t1 = g.as_group().children[0].as_smooth_triangle();
+        let t2 = g.as_group().children[1].as_smooth_triangle();
+        assert_eq!(t1.n2, t2.n1);
+        assert_ne!(t1.n1, t1.n2);
+    }
+
+    #[test]
+    fn parsing_the_faces_as_a_mesh() {
+        let mesh = parse_obj_mesh(CUBE).unwrap();
+        assert_eq!(mesh.vertices.len(), 8);
+        assert_eq!(mesh.triangles.len(), 12);
+        assert_eq!(mesh.triangles[1], [0, 2, 3]);
+    }
+
+    #[test]
+    fn a_small_crease_angle_keeps_hard_edges() {
+        let obj = parse_obj_file_with(CUBE, Some(PI / 6.0)).unwrap();
+        for child in &obj.default_group.as_group().children {
+            let t = child.as_smooth_triangle();
+            let flat = Triangle::new(t.p1, t.p2, t.p3).normal;
+            assert_eq!((t.n1, t.n2, t.n3), (flat, flat, flat));
+        }
+    }
+
+    #[test]
+    fn a_wide_crease_angle_smooths_across_edges() {
+        let obj = parse_obj_file_with(CUBE, Some(PI * 0.75)).unwrap();
+        // Every corner points along the diagonal, whichever way the fan
+        // triangulation split the faces.
+        for child in &obj.default_group.as_group().children {
+            let t = child.as_smooth_triangle();
+            let flat = Triangle::new(t.p1, t.p2, t.p3).normal;
+            for (p, n) in [(t.p1, t.n1), (t.p2, t.n2), (t.p3, t.n3)] {
+                let diagonal = (p - Tuple::point(0.0, 0.0, 0.0)).normalize();
+                assert_eq!(n, diagonal * n.dot(diagonal).signum());
+                assert!(n.dot(flat) > 0.0);
+            }
+        }
+    }
 }
@@ -1,44 +1,73 @@
-use crate::bounds::Bounds;
+use crate::bounds::{BoundingSphere, Bounds};
+use crate::floats::{Float, PI};
 use crate::intersections::Intersection;
-use crate::materials::Material;
+use crate::materials::{Material, MaterialLibrary};
 use crate::matrices::Matrix;
+use crate::patterns::Pattern;
 use crate::rays::Ray;
+use crate::scene_files::parse_scene_file_path;
+use crate::shapes::capsules::Capsule;
 use crate::shapes::cones::Cone;
 use crate::shapes::csg::{Csg, Operation};
 use crate::shapes::cubes::Cube;
+use crate::shapes::curves::Curve;
 use crate::shapes::cylinders::Cylinder;
+use crate::shapes::displaced_surfaces::{DisplacedSurface, DisplacementBase};
 use crate::shapes::groups::Group;
+use crate::shapes::instances::Instance;
+use crate::shapes::lods::Lod;
 use crate::shapes::planes::Plane;
+use crate::shapes::prisms::Prism;
+use crate::shapes::quads::Quad;
+use crate::shapes::rounded_cubes::RoundedCube;
 use crate::shapes::smooth_triangles::SmoothTriangle;
 use crate::shapes::spheres::Sphere;
-use crate::shapes::triangles::Triangle;
+use crate::shapes::triangles::{interpolate_attributes, Triangle};
 use crate::shapes::Shapes;
+use crate::transformations::rotation_x;
 use crate::tuples::Tuple;
+use std::fmt::{self, Write};
 use std::ptr;
+use std::sync::Arc;
 
 #[derive(Debug, Clone, PartialEq)]
 pub struct Object {
+    pub name: Option<String>,
     pub material: Material,
+    // The name of a material of the world library, that replaces
+    // `material` when the world is prepared.
+    pub material_ref: Option<String>,
+    pub inherit_material: bool,
+    pub single_sided: bool,
     pub transform: Matrix<4>,
     pub transform_inverse: Matrix<4>,
     pub world_to_object: Matrix<4>,
     pub object_to_world: Matrix<4>,
     pub bounds: Bounds,
+    pub bounding_sphere: Option<BoundingSphere>,
     shape: Shapes,
 }
 
 impl Object {
     fn new(shape: Shapes) -> Object {
         Object {
+            name: None,
             material: Material::default(),
+            material_ref: None,
+            inherit_material: false,
+            single_sided: false,
             transform: Matrix::identity(),
             transform_inverse: Matrix::identity(),
             world_to_object: Matrix::identity(),
             object_to_world: Matrix::identity(),
             bounds: Bounds::default(),
+            bounding_sphere: None,
             shape,
         }
     }
+    pub fn new_capsule(p0: Tuple, p1: Tuple, radius: Float) -> Object {
+        Object::new(Shapes::Capsule(Capsule::new(p0, p1, radius)))
+    }
     pub fn new_cone() -> Object {
         Object::new(Shapes::Cone(Cone::new()))
     }
@@ -48,15 +77,46 @@ impl Object {
     pub fn new_cube() -> Object {
         Object::new(Shapes::Cube(Cube::new()))
     }
+    pub fn new_curve(points: [Tuple; 4], radius0: Float, radius1: Float) -> Object {
+        Object::new(Shapes::Curve(Curve::new(points, radius0, radius1)))
+    }
     pub fn new_cylinder() -> Object {
         Object::new(Shapes::Cylinder(Cylinder::new()))
     }
+    pub fn new_displaced_surface(base: DisplacementBase, height: Pattern, scale: Float) -> Object {
+        Object::new(Shapes::DisplacedSurface(DisplacedSurface::new(
+            base, height, scale,
+        )))
+    }
     pub fn new_group() -> Object {
         Object::new(Shapes::Group(Group::new()))
     }
+    pub fn new_instance(object: Arc<Object>, transform: Matrix<4>) -> Object {
+        let material = object.material.clone();
+        Object {
+            material,
+            ..Object::new(Shapes::Instance(Instance::new(object)))
+        }
+        .with_transform(transform)
+    }
+    pub fn new_lod(object: Object) -> Object {
+        Object::new(Shapes::Lod(Lod::new(object)))
+    }
     pub fn new_plane() -> Object {
         Object::new(Shapes::Plane(Plane::new()))
     }
+    pub fn new_prism(sides: usize, height: Float) -> Object {
+        Object::new(Shapes::Prism(Prism::new_regular(sides, height)))
+    }
+    pub fn new_extrusion(polygon: Vec<(Float, Float)>, height: Float) -> Object {
+        Object::new(Shapes::Prism(Prism::new(polygon, height)))
+    }
+    pub fn new_quad(width: Float, height: Float) -> Object {
+        Object::new(Shapes::Quad(Quad::new(width, height)))
+    }
+    pub fn new_rounded_cube(radius: Float) -> Object {
+        Object::new(Shapes::RoundedCube(RoundedCube::new(radius)))
+    }
     pub fn new_sphere() -> Object {
         Object::new(Shapes::Sphere(Sphere::new()))
     }
@@ -72,10 +132,22 @@ impl Object {
             p1, p2, p3, n1, n2, n3,
         )))
     }
+    pub fn new_tube(radius: Float, height: Float) -> Object {
+        let mut tube = Object::new_cylinder();
+        tube.as_mut_cylinder().resize(radius, radius);
+        tube.as_mut_cylinder().truncate(0.0, height, false);
+        tube
+    }
     pub fn new_triangle(p1: Tuple, p2: Tuple, p3: Tuple) -> Object {
         Object::new(Shapes::Triangle(Triangle::new(p1, p2, p3)))
     }
 
+    pub fn as_capsule(&self) -> &Capsule {
+        match &self.shape {
+            Shapes::Capsule(capsule) => capsule,
+            _ => panic!("This object is not a capsule !"),
+        }
+    }
     pub fn as_cone(&self) -> &Cone {
         match &self.shape {
             Shapes::Cone(cone) => cone,
@@ -100,6 +172,12 @@ impl Object {
             _ => panic!("This object is not a cube !"),
         }
     }
+    pub fn as_curve(&self) -> &Curve {
+        match &self.shape {
+            Shapes::Curve(curve) => curve,
+            _ => panic!("This object is not a curve !"),
+        }
+    }
     pub fn as_cylinder(&self) -> &Cylinder {
         match &self.shape {
             Shapes::Cylinder(cylinder) => cylinder,
@@ -112,6 +190,12 @@ impl Object {
             _ => panic!("This object is not a cylinder !"),
         }
     }
+    pub fn as_displaced_surface(&self) -> &DisplacedSurface {
+        match &self.shape {
+            Shapes::DisplacedSurface(surface) => surface,
+            _ => panic!("This object is not a displaced surface !"),
+        }
+    }
     pub fn as_group(&self) -> &Group {
         match &self.shape {
             Shapes::Group(group) => group,
@@ -124,12 +208,51 @@ impl Object {
             _ => panic!("This object is not a group !"),
         }
     }
+    pub fn as_instance(&self) -> &Instance {
+        match &self.shape {
+            Shapes::Instance(instance) => instance,
+            _ => panic!("This object is not an instance !"),
+        }
+    }
+    pub fn as_lod(&self) -> &Lod {
+        match &self.shape {
+            Shapes::Lod(lod) => lod,
+            _ => panic!("This object is not a lod !"),
+        }
+    }
+    pub fn as_mut_lod(&mut self) -> &mut Lod {
+        match &mut self.shape {
+            Shapes::Lod(lod) => lod,
+            _ => panic!("This object is not a lod !"),
+        }
+    }
+    pub fn is_plane(&self) -> bool {
+        matches!(self.shape, Shapes::Plane(_))
+    }
     pub fn as_plane(&self) -> &Plane {
         match &self.shape {
             Shapes::Plane(plane) => plane,
             _ => panic!("This object is not a plane !"),
         }
     }
+    pub fn as_prism(&self) -> &Prism {
+        match &self.shape {
+            Shapes::Prism(prism) => prism,
+            _ => panic!("This object is not a prism !"),
+        }
+    }
+    pub fn as_quad(&self) -> &Quad {
+        match &self.shape {
+            Shapes::Quad(quad) => quad,
+            _ => panic!("This object is not a quad !"),
+        }
+    }
+    pub fn as_rounded_cube(&self) -> &RoundedCube {
+        match &self.shape {
+            Shapes::RoundedCube(cube) => cube,
+            _ => panic!("This object is not a rounded cube !"),
+        }
+    }
     pub fn as_sphere(&self) -> &Sphere {
         match &self.shape {
             Shapes::Sphere(sphere) => sphere,
@@ -149,6 +272,35 @@ impl Object {
         }
     }
 
+    pub fn with_vertex_attribute(mut self, values: [Tuple; 3]) -> Object {
+        match &mut self.shape {
+            Shapes::SmoothTriangle(triangle) => triangle.attributes.push(values),
+            Shapes::Triangle(triangle) => triangle.attributes.push(values),
+            _ => panic!("This object is not a triangle !"),
+        }
+        self
+    }
+
+    // The objects of a scene file in a group, e.g. a chair to place in a
+    // larger scene, with the materials of its library. The camera and the
+    // lights of the file are left out, see `World::merge` to keep them.
+    pub fn from_scene_file(path: &str) -> Result<Object, String> {
+        let scene = parse_scene_file_path(path)?;
+        let mut group = Object::new_group();
+        for mut o in scene.world.objects {
+            resolve_shared(&mut o, &scene.world.materials);
+            group.as_mut_group().add_shared_child(o);
+        }
+        Ok(group)
+    }
+
+    pub fn with_name(self, name: &str) -> Object {
+        Object {
+            name: Some(name.to_string()),
+            ..self
+        }
+    }
+
     pub fn made_of_glass(self) -> Object {
         Object {
             material: Material::glass(),
@@ -156,6 +308,72 @@ impl Object {
         }
     }
 
+    pub fn with_material_ref(self, name: &str) -> Object {
+        Object {
+            material_ref: Some(name.to_string()),
+            ..self
+        }
+    }
+
+    pub fn with_inherited_material(self) -> Object {
+        Object {
+            inherit_material: true,
+            ..self
+        }
+    }
+
+    pub fn with_single_sided(mut self) -> Object {
+        self.set_single_sided_recursive();
+        self
+    }
+
+    fn set_single_sided_recursive(&mut self) {
+        self.single_sided = true;
+        match &mut self.shape {
+            Shapes::Csg(csg) => {
+                for c in &mut csg.children {
+                    c.set_single_sided_recursive();
+                }
+            }
+            Shapes::Group(group) => {
+                for c in &mut group.children {
+                    Arc::make_mut(c).set_single_sided_recursive();
+                }
+            }
+            Shapes::Lod(lod) => {
+                for (_, c) in &mut lod.levels {
+                    Arc::make_mut(c).set_single_sided_recursive();
+                }
+            }
+            _ => (),
+        }
+    }
+
+    pub fn set_material_recursive(&mut self, material: &Material) {
+        self.material = material.clone();
+        match &mut self.shape {
+            Shapes::Csg(csg) => {
+                for c in &mut csg.children {
+                    c.set_material_recursive(material);
+                }
+            }
+            Shapes::Group(group) => group.set_material_recursive(material),
+            Shapes::Lod(lod) => lod.set_material_recursive(material),
+            _ => (),
+        }
+    }
+
+    pub fn into_shared(mut self) -> Arc<Object> {
+        self.prepare();
+        Arc::new(self)
+    }
+
+    pub fn with_bounding_sphere(self) -> Object {
+        Object {
+            bounding_sphere: Some(BoundingSphere::from_bounds(&self.bounds)),
+            ..self
+        }
+    }
     pub fn with_transform(self, transform: Matrix<4>) -> Object {
         let transform_inverse = transform.inverse();
         Object {
@@ -167,22 +385,236 @@ impl Object {
         }
     }
 
+    // Portal transform re-emitting the rays that cross this object from its
+    // +y side out of the +y side of `partner`, for top level objects.
+    pub fn portal_to(&self, partner: &Object) -> Matrix<4> {
+        partner.transform * rotation_x(PI) * self.transform_inverse
+    }
+
     pub fn prepare(&mut self) {
+        self.prepare_materials();
         self.prepare_bounds();
         self.prepare_transform();
     }
+    pub fn prepare_materials(&mut self) {
+        let material = &self.material;
+        let inherit = |c: &mut Object| {
+            if c.inherit_material {
+                c.material = material.clone();
+            }
+            c.prepare_materials();
+        };
+        match &mut self.shape {
+            Shapes::Csg(csg) => csg.children.iter_mut().for_each(inherit),
+            Shapes::Group(group) => group
+                .children
+                .iter_mut()
+                .for_each(|c| update_shared(c, |c| c.inherits_new_material(material), inherit)),
+            Shapes::Lod(lod) => lod.levels.iter_mut().for_each(|(_, c)| {
+                update_shared(c, |c| c.inherits_new_material(material), inherit)
+            }),
+            _ => (),
+        }
+    }
+    // Unknown names leave the material of the object as it is.
+    pub fn resolve_materials(&mut self, library: &MaterialLibrary) {
+        if let Some(material) = self.material_ref.as_deref().and_then(|n| library.get(n)) {
+            self.material = material.clone();
+        }
+        match &mut self.shape {
+            Shapes::Csg(csg) => csg
+                .children
+                .iter_mut()
+                .for_each(|c| c.resolve_materials(library)),
+            Shapes::Group(group) => group
+                .children
+                .iter_mut()
+                .for_each(|c| resolve_shared(c, library)),
+            Shapes::Lod(lod) => lod
+                .levels
+                .iter_mut()
+                .for_each(|(_, c)| resolve_shared(c, library)),
+            _ => (),
+        }
+    }
     pub fn prepare_bounds(&mut self) {
         self.shape.prepare_bounds(&mut self.bounds);
+        if self.bounding_sphere.is_some() {
+            self.bounding_sphere = Some(self.enclosing_sphere());
+        }
+    }
+    fn enclosing_sphere(&self) -> BoundingSphere {
+        match &self.shape {
+            Shapes::Sphere(_) => BoundingSphere {
+                center: Tuple::point(0.0, 0.0, 0.0),
+                radius: 1.0,
+            },
+            Shapes::Group(group) if !group.children.is_empty() => {
+                let center = self.bounds.centroid();
+                let radius = group
+                    .children
+                    .iter()
+                    .map(|c| {
+                        let sphere = c
+                            .bounding_sphere
+                            .unwrap_or_else(|| BoundingSphere::from_bounds(&c.bounds))
+                            .transform(&c.transform);
+                        (sphere.center - center).magnitude() + sphere.radius
+                    })
+                    .fold(0.0, Float::max);
+                BoundingSphere { center, radius }
+            }
+            _ => BoundingSphere::from_bounds(&self.bounds),
+        }
+    }
+    pub fn parent_bounds(&self) -> Bounds {
+        let bounds = self.bounds.transform(&self.transform);
+        match &self.bounding_sphere {
+            Some(sphere) => bounds.clip(&sphere.transform(&self.transform).to_bounds()),
+            None => bounds,
+        }
+    }
+    pub fn divide(&mut self, threshold: usize) {
+        match &mut self.shape {
+            Shapes::Csg(csg) => {
+                for c in &mut csg.children {
+                    c.divide(threshold);
+                }
+            }
+            Shapes::Group(group) => group.divide(threshold),
+            _ => (),
+        }
+    }
+    pub fn collect_faces(&self, faces: &mut Vec<[Tuple; 3]>) {
+        match &self.shape {
+            Shapes::Group(group) => {
+                for c in &group.children {
+                    c.collect_faces(faces);
+                }
+            }
+            Shapes::SmoothTriangle(t) => faces.push([t.p1, t.p2, t.p3]),
+            Shapes::Triangle(t) => faces.push([t.p1, t.p2, t.p3]),
+            _ => (),
+        }
+    }
+    pub fn flip_faces(&mut self, flips: &mut impl Iterator<Item = bool>) {
+        match &mut self.shape {
+            Shapes::Group(group) => {
+                for c in &mut group.children {
+                    Arc::make_mut(c).flip_faces(flips);
+                }
+            }
+            Shapes::SmoothTriangle(t) if flips.next() == Some(true) => t.flip(),
+            Shapes::Triangle(t) if flips.next() == Some(true) => t.flip(),
+            _ => (),
+        }
     }
     pub fn prepare_transform(&mut self) {
         self.shape
             .prepare_transform(&self.world_to_object, &self.object_to_world);
     }
 
+    // Whether inheriting the `parent` material changes this object or one of
+    // its descendants.
+    pub fn inherits_new_material(&self, parent: &Material) -> bool {
+        (self.inherit_material && !self.material.identical(parent))
+            || self.children_inherit_new_material()
+    }
+    pub fn children_inherit_new_material(&self) -> bool {
+        self.children()
+            .any(|c| c.inherits_new_material(&self.material))
+    }
+    // Whether resolving the material references changes this object or one
+    // of its descendants.
+    pub fn resolves_new_material(&self, library: &MaterialLibrary) -> bool {
+        self.material_ref
+            .as_deref()
+            .and_then(|n| library.get(n))
+            .is_some_and(|m| !m.identical(&self.material))
+            || self.children().any(|c| c.resolves_new_material(library))
+    }
+    // Whether `prepare_transform` changes the world transforms of one of the
+    // descendants of this object.
+    pub fn transforms_changed(&self) -> bool {
+        self.children().any(|c| {
+            c.world_to_object != c.transform_inverse * self.world_to_object
+                || c.transforms_changed()
+        })
+    }
+    // Children prepared along with this object, instances excluded.
+    fn children(&self) -> Box<dyn Iterator<Item = &Object> + '_> {
+        match &self.shape {
+            Shapes::Csg(csg) => Box::new(csg.children.iter()),
+            Shapes::Group(group) => Box::new(group.children.iter().map(|c| &**c)),
+            Shapes::Lod(lod) => Box::new(lod.levels.iter().map(|(_, c)| &**c)),
+            _ => Box::new(std::iter::empty()),
+        }
+    }
+
+    pub fn tree_string(&self) -> String {
+        let mut out = String::new();
+        self.write_tree(&mut out, 0);
+        out
+    }
+
+    fn write_tree(&self, out: &mut String, depth: usize) {
+        write!(out, "{}{}", "  ".repeat(depth), self.shape.describe()).unwrap();
+        if let Some(name) = &self.name {
+            write!(out, " '{}'", name).unwrap();
+        }
+        if self.transform != Matrix::identity() {
+            write!(out, " transform {}", describe_matrix(&self.transform)).unwrap();
+        }
+        match &self.shape {
+            Shapes::Csg(csg) => {
+                out.push('\n');
+                for c in &csg.children {
+                    c.write_tree(out, depth + 1);
+                }
+            }
+            Shapes::Group(group) => {
+                out.push('\n');
+                for c in &group.children {
+                    c.write_tree(out, depth + 1);
+                }
+            }
+            Shapes::Lod(lod) => {
+                out.push('\n');
+                for (_, c) in &lod.levels {
+                    c.write_tree(out, depth + 1);
+                }
+            }
+            Shapes::Instance(instance) => {
+                writeln!(out, " material {}", self.material.summary()).unwrap();
+                instance.object.write_tree(out, depth + 1);
+            }
+            _ => writeln!(out, " material {}", self.material.summary()).unwrap(),
+        }
+    }
+
+    // The world bounds of this object and all its descendants, with their
+    // depth in the tree. `parent` is the transform of the enclosing group.
+    pub fn bounds_tree(&self, parent: &Matrix<4>, depth: usize, out: &mut Vec<(usize, Bounds)>) {
+        let transform = *parent * self.transform;
+        out.push((depth, self.bounds.transform(&transform)));
+        let children: Vec<&Object> = match &self.shape {
+            Shapes::Csg(csg) => csg.children.iter().collect(),
+            Shapes::Group(group) => group.children.iter().map(|c| c.as_ref()).collect(),
+            Shapes::Lod(lod) => lod.levels.iter().map(|(_, c)| c.as_ref()).collect(),
+            Shapes::Instance(instance) => vec![instance.object.as_ref()],
+            _ => vec![],
+        };
+        for c in children {
+            c.bounds_tree(&transform, depth + 1, out);
+        }
+    }
+
     pub fn includes(&self, object: &Object) -> bool {
         match self.shape {
             Shapes::Csg(ref csg) => csg.includes(object),
             Shapes::Group(ref group) => group.includes(object),
+            Shapes::Instance(ref instance) => instance.includes(object),
+            Shapes::Lod(ref lod) => lod.includes(object),
             _ => ptr::eq(self, object),
         }
     }
@@ -199,7 +631,26 @@ impl Object {
 
     pub fn intersect<'b>(&'b self, ray: &Ray, xs: &mut Vec<Intersection<'b>>) {
         let local_ray = ray.transform(self.transform_inverse);
-        self.shape.local_intersect(&local_ray, self, xs)
+        if let Some(sphere) = &self.bounding_sphere
+            && !sphere.intersect(&local_ray)
+        {
+            return;
+        }
+        if !self.single_sided || self.shape.is_aggregate() {
+            return self.shape.local_intersect(&local_ray, self, xs);
+        }
+        let start = xs.len();
+        self.shape.local_intersect(&local_ray, self, xs);
+        let mut i = start;
+        while i < xs.len() {
+            let point = local_ray.position(xs[i].t);
+            let normal = self.shape.local_normal_at(point, &xs[i]);
+            if normal.dot(local_ray.direction) > 0.0 {
+                xs.swap_remove(i);
+            } else {
+                i += 1;
+            }
+        }
     }
 
     pub fn normal_at(&self, world_point: Tuple, hit: &Intersection) -> Tuple {
@@ -207,12 +658,97 @@ impl Object {
         let local_normal = self.shape.local_normal_at(local_point, hit);
         self.normal_to_world(local_normal)
     }
+
+    pub fn vertex_attributes(&self, hit: &Intersection) -> Vec<Tuple> {
+        match &self.shape {
+            Shapes::SmoothTriangle(triangle) => {
+                interpolate_attributes(&triangle.attributes, hit.u, hit.v)
+            }
+            Shapes::Triangle(triangle) => {
+                interpolate_attributes(&triangle.attributes, hit.u, hit.v)
+            }
+            _ => vec![],
+        }
+    }
+
+    // Parametric coordinates used to draw isolines: angle and height around
+    // round shapes, one unit square per face on cubes, uv on quads.
+    pub fn surface_coordinates(&self, world_point: Tuple, hit: &Intersection) -> (Float, Float) {
+        let p = self.world_to_object(world_point);
+        let angle = p.x().atan2(p.z()) / (2.0 * PI) + 0.5;
+        match &self.shape {
+            Shapes::Sphere(_) | Shapes::RoundedCube(_) => {
+                let polar = (p.y() / p.magnitude()).clamp(-1.0, 1.0).acos() / PI;
+                (angle, polar)
+            }
+            Shapes::Cone(_) | Shapes::Cylinder(_) => (angle, p.y()),
+            Shapes::Cube(_) => {
+                let n = Cube::new().local_normal_at(p);
+                let (face, a, b) = match n {
+                    n if n.x() != 0.0 => (0.0, p.y(), p.z()),
+                    n if n.y() != 0.0 => (2.0, p.x(), p.z()),
+                    _ => (4.0, p.x(), p.y()),
+                };
+                let side = if n.x() + n.y() + n.z() < 0.0 {
+                    1.0
+                } else {
+                    0.0
+                };
+                (face + side + (a + 1.0) / 2.0, (b + 1.0) / 2.0)
+            }
+            Shapes::Plane(_) => (p.x(), p.z()),
+            Shapes::Quad(_) => (hit.u, hit.v),
+            _ => (0.0, 0.0),
+        }
+    }
+}
+
+// Updates a child in place. A child shared with other parents is only copied
+// when `changes` tells that the update would change it.
+pub fn update_shared(
+    child: &mut Arc<Object>,
+    changes: impl FnOnce(&Object) -> bool,
+    update: impl FnOnce(&mut Object),
+) {
+    if let Some(c) = Arc::get_mut(child) {
+        update(c);
+    } else if changes(child) {
+        update(Arc::make_mut(child));
+    }
+}
+
+fn resolve_shared(child: &mut Arc<Object>, library: &MaterialLibrary) {
+    update_shared(
+        child,
+        |c| c.resolves_new_material(library),
+        |c| c.resolve_materials(library),
+    );
+}
+
+// The shape tree, one indented line per object, see `tree_string`.
+impl fmt::Display for Object {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.tree_string())
+    }
+}
+
+fn describe_matrix(m: &Matrix<4>) -> String {
+    let rows = (0..4)
+        .map(|i| {
+            (0..4)
+                .map(|j| m[(i, j)].to_string())
+                .collect::<Vec<_>>()
+                .join(" ")
+        })
+        .collect::<Vec<_>>();
+    format!("[{}]", rows.join(" | "))
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::floats::{PI, SQRT_2, SQRT_3};
+    use crate::colors::Color;
+    use crate::floats::{equals, PI, SQRT_2, SQRT_3};
     use crate::shapes::TestShape;
     use crate::transformations::{rotation_y, scaling, translation};
 
@@ -255,6 +791,78 @@ mod tests {
         assert_eq!(n, Tuple::vector(0.0, SQRT_2 / 2.0, -SQRT_2 / 2.0));
     }
 
+    #[test]
+    fn importing_the_objects_of_a_scene_file() {
+        let path = std::env::temp_dir().join("rt_rust_prefab.scene");
+        std::fs::write(
+            &path,
+            "camera size 10 10
+light point at 0 5 0 color 1 1 1
+material wood color 0.6 0.4 0.2
+cube material wood scale 1 0.1 1
+sphere translate 0 1 0
+",
+        )
+        .unwrap();
+        let prefab = Object::from_scene_file(&path.to_string_lossy()).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        let children = &prefab.as_group().children;
+        assert_eq!(children.len(), 2);
+        assert_eq!(children[0].material.color, Color::new(0.6, 0.4, 0.2));
+        assert_eq!(children[1].transform, translation(0.0, 1.0, 0.0));
+        assert!(Object::from_scene_file("missing.scene")
+            .unwrap_err()
+            .starts_with("missing.scene: "));
+    }
+
+    #[test]
+    fn children_inherit_the_parent_material_when_prepared() {
+        let red = Material {
+            color: Color::new(1.0, 0.0, 0.0),
+            ..Material::default()
+        };
+        let mut inner = Object::new_group().with_inherited_material();
+        inner
+            .as_mut_group()
+            .add_child(Object::new_sphere().with_inherited_material());
+        let mut g = Object::new_group();
+        g.material = red.clone();
+        g.as_mut_group()
+            .add_child(Object::new_sphere().with_inherited_material());
+        g.as_mut_group().add_child(Object::new_sphere());
+        g.as_mut_group().add_child(inner);
+        g.prepare();
+        let children = &g.as_group().children;
+        assert_eq!(children[0].material, red);
+        assert_eq!(children[1].material, Material::default());
+        assert_eq!(children[2].as_group().children[0].material, red);
+    }
+
+    #[test]
+    fn printing_the_tree_of_a_group() {
+        let mut g = Object::new_group().with_transform(translation(1.0, 2.0, 3.0));
+        g.as_mut_group().add_child(Object::new_sphere());
+        let csg = Object::new_csg(Operation::Union, Object::new_cube(), Object::new_plane());
+        g.as_mut_group().add_child(csg);
+        assert_eq!(
+            g.tree_string(),
+            "group (2 children) transform [1 0 0 1 | 0 1 0 2 | 0 0 1 3 | 0 0 0 1]
+  sphere material color (1, 1, 1) ambient 0.1 diffuse 0.9 specular 0.9 shininess 200
+  csg union
+    cube material color (1, 1, 1) ambient 0.1 diffuse 0.9 specular 0.9 shininess 200
+    plane material color (1, 1, 1) ambient 0.1 diffuse 0.9 specular 0.9 shininess 200
+"
+        );
+    }
+
+    #[test]
+    fn displaying_an_object_prints_its_tree() {
+        let mut g = Object::new_group();
+        g.as_mut_group().add_child(Object::new_cube());
+        assert_eq!(format!("{}", g), g.tree_string());
+        assert!(format!("{}", g).starts_with("group (1 children)\n  cube material"));
+    }
+
     #[test]
     fn converting_a_point_from_world_to_object_space() {
         let s = Object::new_sphere().with_transform(translation(5.0, 0.0, 0.0));
@@ -294,4 +902,123 @@ mod tests {
         let v = s.normal_at(Tuple::point(1.7321, 1.1547, -5.5774), &hit);
         assert_eq!(v, Tuple::vector(0.28571427, 0.42857143, -0.8571));
     }
+
+    #[test]
+    fn a_tube_is_an_open_cylinder_with_a_radius_and_height() {
+        let mut tube = Object::new_tube(0.25, 2.0);
+        tube.prepare();
+        let cyl = tube.as_cylinder();
+        assert_eq!((cyl.radius_x, cyl.radius_z), (0.25, 0.25));
+        assert_eq!((cyl.minimum, cyl.maximum, cyl.closed), (0.0, 2.0, false));
+        assert_eq!(tube.bounds.min, Tuple::point(-0.25, 0.0, -0.25));
+        assert_eq!(tube.bounds.max, Tuple::point(0.25, 2.0, 0.25));
+    }
+
+    #[test]
+    fn a_single_sided_shape_ignores_back_facing_hits() {
+        let mut s = Object::new_sphere()
+            .with_single_sided()
+            .with_transform(scaling(2.0, 2.0, 2.0));
+        s.prepare();
+        let r = Ray::new(Tuple::point(0.0, 0.0, -5.0), Tuple::vector(0.0, 0.0, 1.0));
+        let mut xs = Vec::new();
+        s.intersect(&r, &mut xs);
+        assert_eq!(xs.iter().map(|x| x.t).collect::<Vec<_>>(), vec![3.0]);
+        let r = Ray::new(Tuple::point(0.0, 0.0, 0.0), Tuple::vector(0.0, 0.0, 1.0));
+        let mut xs = Vec::new();
+        s.intersect(&r, &mut xs);
+        assert_eq!(xs.iter().map(|x| x.t).collect::<Vec<_>>(), vec![-2.0]);
+    }
+
+    #[test]
+    fn single_sided_groups_apply_to_their_children() {
+        let mut g = Object::new_group();
+        g.as_mut_group().add_child(Object::new_plane());
+        let mut g = g.with_single_sided();
+        g.prepare();
+        let mut xs = Vec::new();
+        let r = Ray::new(Tuple::point(0.0, 1.0, 0.0), Tuple::vector(0.0, -1.0, 0.0));
+        g.intersect(&r, &mut xs);
+        assert_eq!(xs.len(), 1);
+        let r = Ray::new(Tuple::point(0.0, -1.0, 0.0), Tuple::vector(0.0, 1.0, 0.0));
+        xs.clear();
+        g.intersect(&r, &mut xs);
+        assert!(xs.is_empty());
+    }
+
+    #[test]
+    fn a_bounding_sphere_rejects_rays_before_the_shape() {
+        let mut cube = Object::new_cube()
+            .with_bounding_sphere()
+            .with_transform(scaling(2.0, 2.0, 2.0));
+        cube.prepare();
+        assert_eq!(cube.bounding_sphere.unwrap().radius, SQRT_3);
+        let r = Ray::new(Tuple::point(0.0, 1.0, -5.0), Tuple::vector(0.0, 0.0, 1.0));
+        let mut xs = Vec::new();
+        cube.intersect(&r, &mut xs);
+        assert_eq!(xs.iter().map(|x| x.t).collect::<Vec<_>>(), vec![3.0, 7.0]);
+        let r = Ray::new(Tuple::point(0.0, 4.0, -5.0), Tuple::vector(0.0, 0.0, 1.0));
+        let mut xs = Vec::new();
+        cube.intersect(&r, &mut xs);
+        assert!(xs.is_empty());
+    }
+
+    #[test]
+    fn surface_coordinates_of_a_sphere() {
+        let s = Object::new_sphere().with_transform(scaling(2.0, 2.0, 2.0));
+        let i = Intersection::new(1.0, &s);
+        let (u, v) = s.surface_coordinates(Tuple::point(2.0, 0.0, 0.0), &i);
+        assert!(equals(u, 0.75));
+        assert!(equals(v, 0.5));
+        let (_, v) = s.surface_coordinates(Tuple::point(0.0, 2.0, 0.0), &i);
+        assert!(equals(v, 0.0));
+    }
+
+    #[test]
+    fn surface_coordinates_of_cube_faces_do_not_overlap() {
+        let c = Object::new_cube();
+        let i = Intersection::new(1.0, &c);
+        let faces: Vec<Float> = [
+            Tuple::point(1.0, 0.0, 0.0),
+            Tuple::point(-1.0, 0.0, 0.0),
+            Tuple::point(0.0, 1.0, 0.0),
+            Tuple::point(0.0, -1.0, 0.0),
+            Tuple::point(0.0, 0.0, 1.0),
+            Tuple::point(0.0, 0.0, -1.0),
+        ]
+        .iter()
+        .map(|p| c.surface_coordinates(*p, &i).0.floor())
+        .collect();
+        assert_eq!(faces, vec![0.0, 1.0, 2.0, 3.0, 4.0, 5.0]);
+    }
+
+    #[test]
+    fn intersecting_with_a_ray_range() {
+        let s = Object::new_sphere();
+        let r = Ray::new(Tuple::point(0.0, 0.0, -5.0), Tuple::vector(0.0, 0.0, 1.0));
+        let mut xs = vec![];
+        s.intersect(&r.with_range(0.0, 5.0), &mut xs);
+        assert_eq!(xs.len(), 1);
+        assert_eq!(xs[0].t, 4.0);
+        let mut g = Object::new_group();
+        g.as_mut_group().add_child(Object::new_sphere());
+        g.prepare();
+        xs.clear();
+        g.intersect(&r.with_range(7.0, 10.0), &mut xs);
+        assert!(xs.is_empty());
+    }
+
+    #[test]
+    fn a_ray_range_does_not_change_csg_results() {
+        let c = Object::new_csg(
+            Operation::Difference,
+            Object::new_sphere(),
+            Object::new_sphere().with_transform(translation(0.0, 0.0, -0.5)),
+        );
+        let r = Ray::new(Tuple::point(0.0, 0.0, -5.0), Tuple::vector(0.0, 0.0, 1.0));
+        let mut xs = vec![];
+        c.intersect(&r.with_range(5.25, 10.0), &mut xs);
+        let ts: Vec<Float> = xs.iter().map(|x| x.t).collect();
+        assert_eq!(ts, vec![5.5, 6.0]);
+    }
 }
@@ -1,16 +1,35 @@
-use crate::colors::Color;
+use crate::colors::{Color, BLACK, WHITE};
+use crate::floats::{Float, EPSILON, SQRT_3};
 use crate::matrices::Matrix;
 use crate::objects::Object;
 use crate::tuples::Tuple;
+use std::sync::Arc;
 
+mod blends;
 mod checkers;
 mod gradients;
+mod radial_gradients;
 mod rings;
+mod spirals;
 mod stripes;
 
-#[derive(Debug, Clone, Copy)]
+// Where a surface pattern is evaluated: the normal spaces put the world
+// normal, or the facing ratio along x, in place of the point. Triplanar
+// blends three projections of the object space point on the x/z plane,
+// weighted by the normal components raised to the sharpness.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PatternSpace {
+    Object,
+    World,
+    Normal,
+    FacingRatio,
+    Triplanar(Float),
+}
+
+#[derive(Debug, Clone, PartialEq)]
 pub struct Pattern {
     pattern: Patterns,
+    space: PatternSpace,
     transform_inverse: Matrix<4>,
 }
 
@@ -18,22 +37,62 @@ impl Pattern {
     fn new(pattern: Patterns) -> Pattern {
         Pattern {
             pattern,
+            space: PatternSpace::Object,
             transform_inverse: Matrix::identity(),
         }
     }
 
+    pub fn new_solid(color: Color) -> Pattern {
+        Pattern::new(Patterns::Solid(color))
+    }
+
+    pub fn blend(a: Pattern, b: Pattern, factor: Pattern) -> Pattern {
+        Pattern::new(Patterns::Blend(Arc::new(blends::BlendPattern::new(
+            a, b, factor,
+        ))))
+    }
+
+    pub fn new_nested_checker(a: Pattern, b: Pattern) -> Pattern {
+        Pattern::blend(a, b, Pattern::new_checker(BLACK, WHITE))
+    }
+    pub fn new_nested_gradient(a: Pattern, b: Pattern) -> Pattern {
+        Pattern::blend(a, b, Pattern::new_gradient(BLACK, WHITE))
+    }
+    pub fn new_nested_ring(a: Pattern, b: Pattern) -> Pattern {
+        Pattern::blend(a, b, Pattern::new_ring(BLACK, WHITE))
+    }
+    pub fn new_nested_stripe(a: Pattern, b: Pattern) -> Pattern {
+        Pattern::blend(a, b, Pattern::new_stripe(BLACK, WHITE))
+    }
+
     pub fn new_checker(a: Color, b: Color) -> Pattern {
         Pattern::new(Patterns::Checker(checkers::CheckerPattern::new(a, b)))
     }
+    pub fn new_filtered_checker(a: Color, b: Color) -> Pattern {
+        Pattern::new(Patterns::Checker(checkers::CheckerPattern::new_filtered(
+            a, b,
+        )))
+    }
     pub fn new_gradient(a: Color, b: Color) -> Pattern {
         Pattern::new(Patterns::Gradient(gradients::GradientPattern::new(a, b)))
     }
+    pub fn new_radial_gradient(a: Color, b: Color) -> Pattern {
+        Pattern::new(Patterns::RadialGradient(
+            radial_gradients::RadialGradientPattern::new(a, b),
+        ))
+    }
     pub fn new_ring(a: Color, b: Color) -> Pattern {
         Pattern::new(Patterns::Ring(rings::RingPattern::new(a, b)))
     }
+    pub fn new_spiral(a: Color, b: Color) -> Pattern {
+        Pattern::new(Patterns::Spiral(spirals::SpiralPattern::new(a, b)))
+    }
     pub fn new_stripe(a: Color, b: Color) -> Pattern {
         Pattern::new(Patterns::Stripe(stripes::StripePattern::new(a, b)))
     }
+    pub fn new_filtered_stripe(a: Color, b: Color) -> Pattern {
+        Pattern::new(Patterns::Stripe(stripes::StripePattern::new_filtered(a, b)))
+    }
     pub fn new_test() -> Pattern {
         Pattern::new(Patterns::Test(TestPattern))
     }
@@ -45,18 +104,142 @@ impl Pattern {
         }
     }
 
+    pub fn with_space(self, space: PatternSpace) -> Pattern {
+        Pattern { space, ..self }
+    }
+
     pub fn color_at_object(&self, object: &Object, world_point: Tuple) -> Color {
-        let object_point = object.world_to_object(world_point);
-        let pattern_point = self.transform_inverse * object_point;
-        self.pattern.color_at(pattern_point)
+        let zero = Tuple::vector(0.0, 0.0, 0.0);
+        let space_point = self.space_point(object, world_point, zero, zero);
+        self.pattern.color_at(self.transform_inverse * space_point)
+    }
+
+    fn space_point(
+        &self,
+        object: &Object,
+        world_point: Tuple,
+        normalv: Tuple,
+        eyev: Tuple,
+    ) -> Tuple {
+        match self.space {
+            PatternSpace::Object => object.world_to_object(world_point),
+            // Without a normal, the projection from above.
+            PatternSpace::Triplanar(_) => {
+                let p = object.world_to_object(world_point);
+                Tuple::point(p.x(), 0.0, p.z())
+            }
+            PatternSpace::World => world_point,
+            PatternSpace::Normal => Tuple::point(normalv.x(), normalv.y(), normalv.z()),
+            // Kept below 1 so that gradients end on their second color.
+            PatternSpace::FacingRatio => {
+                Tuple::point(eyev.dot(normalv).abs().min(1.0 - EPSILON / 2.0), 0.0, 0.0)
+            }
+        }
+    }
+
+    pub fn color_at_pattern(&self, point: Tuple) -> Color {
+        self.pattern.color_at(self.transform_inverse * point)
+    }
+
+    pub fn color_at_pattern_filtered(&self, point: Tuple, width: Float) -> Color {
+        let diagonal = Tuple::vector(1.0, 1.0, 1.0) * (width / SQRT_3);
+        let width = (self.transform_inverse * diagonal).magnitude();
+        self.pattern
+            .color_at_filtered(self.transform_inverse * point, width)
+    }
+
+    pub fn color_at_object_filtered(
+        &self,
+        object: &Object,
+        world_point: Tuple,
+        footprint: Float,
+    ) -> Color {
+        let zero = Tuple::vector(0.0, 0.0, 0.0);
+        self.color_at_surface(object, world_point, zero, zero, footprint)
+    }
+
+    pub fn color_at_surface(
+        &self,
+        object: &Object,
+        world_point: Tuple,
+        normalv: Tuple,
+        eyev: Tuple,
+        footprint: Float,
+    ) -> Color {
+        if let PatternSpace::Triplanar(sharpness) = self.space
+            && let Some(color) = self.triplanar(object, world_point, normalv, sharpness, footprint)
+        {
+            return color;
+        }
+        let space_point = self.space_point(object, world_point, normalv, eyev);
+        let pattern_point = self.transform_inverse * space_point;
+        let diagonal = Tuple::vector(1.0, 1.0, 1.0) * (footprint / SQRT_3);
+        let diagonal = match self.space {
+            PatternSpace::Object | PatternSpace::Triplanar(_) => object.world_to_object(diagonal),
+            PatternSpace::World => diagonal,
+            PatternSpace::Normal | PatternSpace::FacingRatio => Tuple::vector(0.0, 0.0, 0.0),
+        };
+        let width = (self.transform_inverse * diagonal).magnitude();
+        self.pattern.color_at_filtered(pattern_point, width)
+    }
+
+    fn triplanar(
+        &self,
+        object: &Object,
+        world_point: Tuple,
+        normalv: Tuple,
+        sharpness: Float,
+        footprint: Float,
+    ) -> Option<Color> {
+        let p = object.world_to_object(world_point);
+        // Only the rough facing matters, so the normal goes back to object
+        // space like a vector.
+        let n = object.world_to_object(normalv);
+        let weights = [n.x(), n.y(), n.z()].map(|c| c.abs().powf(sharpness));
+        let total: Float = weights.iter().sum();
+        if total < EPSILON {
+            return None;
+        }
+        let projections = [
+            Tuple::point(p.z(), 0.0, p.y()),
+            Tuple::point(p.x(), 0.0, p.z()),
+            Tuple::point(p.x(), 0.0, p.y()),
+        ];
+        let diagonal = Tuple::vector(1.0, 1.0, 1.0) * (footprint / SQRT_3);
+        let width = (self.transform_inverse * object.world_to_object(diagonal)).magnitude();
+        Some(
+            projections
+                .iter()
+                .zip(weights)
+                .filter(|(_, w)| *w > 0.0)
+                .fold(BLACK, |color, (point, w)| {
+                    color
+                        + self
+                            .pattern
+                            .color_at_filtered(self.transform_inverse * *point, width)
+                            * (w / total)
+                }),
+        )
     }
 }
 
-#[derive(Debug, Clone, Copy)]
+pub fn filtered_square_wave(x: Float, width: Float) -> Float {
+    if width < EPSILON {
+        return if x.rem_euclid(2.0) < 1.0 { 1.0 } else { -1.0 };
+    }
+    let integral = |x: Float| 1.0 - (x.rem_euclid(2.0) - 1.0).abs();
+    (integral(x + width / 2.0) - integral(x - width / 2.0)) / width
+}
+
+#[derive(Debug, Clone, PartialEq)]
 enum Patterns {
+    Blend(Arc<blends::BlendPattern>),
     Checker(checkers::CheckerPattern),
     Gradient(gradients::GradientPattern),
+    RadialGradient(radial_gradients::RadialGradientPattern),
     Ring(rings::RingPattern),
+    Solid(Color),
+    Spiral(spirals::SpiralPattern),
     Stripe(stripes::StripePattern),
     Test(TestPattern),
 }
@@ -64,16 +247,29 @@ enum Patterns {
 impl Patterns {
     fn color_at(&self, point: Tuple) -> Color {
         match *self {
+            Patterns::Blend(ref pattern) => pattern.color_at(point),
             Patterns::Checker(ref pattern) => pattern.color_at(point),
             Patterns::Stripe(ref pattern) => pattern.color_at(point),
             Patterns::Gradient(ref pattern) => pattern.color_at(point),
+            Patterns::RadialGradient(ref pattern) => pattern.color_at(point),
             Patterns::Ring(ref pattern) => pattern.color_at(point),
+            Patterns::Solid(color) => color,
+            Patterns::Spiral(ref pattern) => pattern.color_at(point),
             Patterns::Test(ref pattern) => pattern.color_at(point),
         }
     }
+
+    fn color_at_filtered(&self, point: Tuple, width: Float) -> Color {
+        match *self {
+            Patterns::Blend(ref pattern) => pattern.color_at_filtered(point, width),
+            Patterns::Checker(ref pattern) => pattern.color_at_filtered(point, width),
+            Patterns::Stripe(ref pattern) => pattern.color_at_filtered(point, width),
+            _ => self.color_at(point),
+        }
+    }
 }
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq)]
 struct TestPattern;
 
 impl TestPattern {
@@ -113,4 +309,88 @@ pub mod tests {
         let c = pattern.color_at_object(&object, Tuple::point(2.5, 3.0, 3.5));
         assert_eq!(c, Color::new(0.75, 0.5, 0.25));
     }
+
+    #[test]
+    fn a_world_space_pattern_ignores_the_object_transformation() {
+        let object = Object::new_sphere().with_transform(scaling(2.0, 2.0, 2.0));
+        let pattern = Pattern::new_test().with_space(PatternSpace::World);
+        let c = pattern.color_at_object(&object, Tuple::point(2.0, 3.0, 4.0));
+        assert_eq!(c, Color::new(2.0, 3.0, 4.0));
+    }
+
+    #[test]
+    fn normal_space_patterns_follow_the_surface_normal() {
+        let object = Object::new_sphere().with_transform(scaling(2.0, 2.0, 2.0));
+        let point = Tuple::point(0.0, 0.0, -2.0);
+        let normalv = Tuple::vector(0.0, 0.6, -0.8);
+        let pattern = Pattern::new_test().with_space(PatternSpace::Normal);
+        let c = pattern.color_at_surface(&object, point, normalv, normalv, 0.0);
+        assert_eq!(c, Color::new(0.0, 0.6, -0.8));
+        let rim = Pattern::new_gradient(BLACK, WHITE).with_space(PatternSpace::FacingRatio);
+        let facing = rim.color_at_surface(&object, point, normalv, normalv, 0.0);
+        assert_eq!(facing, WHITE);
+        let grazing = Tuple::vector(1.0, 0.0, 0.0);
+        let c = rim.color_at_surface(&object, point, normalv, grazing, 0.0);
+        assert_eq!(c, BLACK);
+        let c = rim.color_at_surface(&object, point, normalv, -normalv, 0.0);
+        assert_eq!(c, WHITE);
+    }
+
+    #[test]
+    fn triplanar_patterns_project_along_the_dominant_axis() {
+        let object = Object::new_cube();
+        let pattern = Pattern::new_test().with_space(PatternSpace::Triplanar(4.0));
+        let point = Tuple::point(1.0, 0.25, 0.5);
+        let c = pattern.color_at_surface(&object, point, Tuple::vector(1.0, 0.0, 0.0), point, 0.0);
+        assert_eq!(c, Color::new(0.5, 0.0, 0.25));
+        let point = Tuple::point(0.25, 1.0, 0.5);
+        let c = pattern.color_at_surface(&object, point, Tuple::vector(0.0, 1.0, 0.0), point, 0.0);
+        assert_eq!(c, Color::new(0.25, 0.0, 0.5));
+        let point = Tuple::point(0.25, 0.5, -1.0);
+        let c = pattern.color_at_surface(&object, point, Tuple::vector(0.0, 0.0, -1.0), point, 0.0);
+        assert_eq!(c, Color::new(0.25, 0.0, 0.5));
+    }
+
+    #[test]
+    fn triplanar_projections_blend_on_slanted_surfaces() {
+        let object = Object::new_sphere();
+        // The x projection reads z = 1.5, black, and the y projection x = 0.5, white.
+        let point = Tuple::point(0.5, 0.0, 1.5);
+        let normalv = Tuple::vector(1.0, 2.0, 0.0).normalize();
+        let stripes = |sharpness| {
+            Pattern::new_stripe(WHITE, BLACK)
+                .with_space(PatternSpace::Triplanar(sharpness))
+                .color_at_surface(&object, point, normalv, normalv, 0.0)
+        };
+        assert_eq!(stripes(2.0), Color::new(0.8, 0.8, 0.8));
+        assert!(stripes(8.0).red() > 0.99);
+    }
+
+    #[test]
+    fn a_spiral_with_a_pattern_transformation() {
+        let object = Object::new_sphere();
+        let pattern = Pattern::new_spiral(WHITE, BLACK).with_transform(scaling(2.0, 2.0, 2.0));
+        let c = pattern.color_at_object(&object, Tuple::point(1.0, 0.0, 0.0));
+        assert_eq!(c, WHITE);
+        let c = pattern.color_at_object(&object, Tuple::point(3.0, 0.0, 0.0));
+        assert_eq!(c, BLACK);
+        let pattern = Pattern::new_radial_gradient(WHITE, BLACK)
+            .with_transform(translation(1.0, 0.0, 0.0) * scaling(4.0, 4.0, 4.0));
+        let c = pattern.color_at_object(&object, Tuple::point(2.0, 0.0, 0.0));
+        assert_eq!(c, Color::new(0.75, 0.75, 0.75));
+    }
+
+    #[test]
+    fn stripes_of_gradients() {
+        let object = Object::new_sphere();
+        let pattern = Pattern::new_nested_stripe(
+            Pattern::new_gradient(WHITE, BLACK).with_transform(scaling(4.0, 1.0, 1.0)),
+            Pattern::new_solid(Color::new(1.0, 0.0, 0.0)),
+        )
+        .with_transform(scaling(2.0, 2.0, 2.0));
+        let c = pattern.color_at_object(&object, Tuple::point(1.0, 0.0, 0.0));
+        assert_eq!(c, Color::new(0.875, 0.875, 0.875));
+        let c = pattern.color_at_object(&object, Tuple::point(3.0, 0.0, 0.0));
+        assert_eq!(c, Color::new(1.0, 0.0, 0.0));
+    }
 }
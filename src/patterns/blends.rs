@@ -0,0 +1,66 @@
+use crate::colors::Color;
+use crate::floats::Float;
+use crate::patterns::Pattern;
+use crate::tuples::Tuple;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct BlendPattern {
+    a: Pattern,
+    b: Pattern,
+    factor: Pattern,
+}
+
+impl BlendPattern {
+    pub fn new(a: Pattern, b: Pattern, factor: Pattern) -> BlendPattern {
+        BlendPattern { a, b, factor }
+    }
+
+    pub fn color_at(&self, point: Tuple) -> Color {
+        let t = weight(self.factor.color_at_pattern(point));
+        self.a.color_at_pattern(point) * (1.0 - t) + self.b.color_at_pattern(point) * t
+    }
+
+    pub fn color_at_filtered(&self, point: Tuple, width: Float) -> Color {
+        let t = weight(self.factor.color_at_pattern_filtered(point, width));
+        self.a.color_at_pattern_filtered(point, width) * (1.0 - t)
+            + self.b.color_at_pattern_filtered(point, width) * t
+    }
+}
+
+fn weight(factor: Color) -> Float {
+    ((factor.red() + factor.green() + factor.blue()) / 3.0).clamp(0.0, 1.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::colors::{BLACK, WHITE};
+    use crate::transformations::scaling;
+
+    #[test]
+    fn blending_two_patterns_with_a_factor_pattern() {
+        let red = Color::new(1.0, 0.0, 0.0);
+        let blue = Color::new(0.0, 0.0, 1.0);
+        let pattern = BlendPattern::new(
+            Pattern::new_solid(red),
+            Pattern::new_solid(blue),
+            Pattern::new_gradient(BLACK, WHITE),
+        );
+        assert_eq!(pattern.color_at(Tuple::point(0.0, 0.0, 0.0)), red);
+        assert_eq!(
+            pattern.color_at(Tuple::point(0.25, 0.0, 0.0)),
+            Color::new(0.75, 0.0, 0.25)
+        );
+    }
+
+    #[test]
+    fn nested_patterns_apply_their_own_transform() {
+        let pattern = BlendPattern::new(
+            Pattern::new_stripe(WHITE, BLACK).with_transform(scaling(0.5, 0.5, 0.5)),
+            Pattern::new_solid(BLACK),
+            Pattern::new_solid(BLACK),
+        );
+        assert_eq!(pattern.color_at(Tuple::point(0.25, 0.0, 0.0)), WHITE);
+        assert_eq!(pattern.color_at(Tuple::point(0.75, 0.0, 0.0)), BLACK);
+    }
+}
@@ -1,15 +1,39 @@
 use crate::colors::Color;
+use crate::floats::Float;
+use crate::patterns::filtered_square_wave;
 use crate::tuples::Tuple;
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub struct CheckerPattern {
     a: Color,
     b: Color,
+    filtered: bool,
 }
 
 impl CheckerPattern {
     pub fn new(a: Color, b: Color) -> CheckerPattern {
-        CheckerPattern { a, b }
+        CheckerPattern {
+            a,
+            b,
+            filtered: false,
+        }
+    }
+
+    pub fn new_filtered(a: Color, b: Color) -> CheckerPattern {
+        CheckerPattern {
+            filtered: true,
+            ..CheckerPattern::new(a, b)
+        }
+    }
+
+    pub fn color_at_filtered(&self, point: Tuple, width: Float) -> Color {
+        if !self.filtered {
+            return self.color_at(point);
+        }
+        let parity = filtered_square_wave(point.x(), width)
+            * filtered_square_wave(point.y(), width)
+            * filtered_square_wave(point.z(), width);
+        self.a * ((1.0 + parity) / 2.0) + self.b * ((1.0 - parity) / 2.0)
     }
 
     pub fn color_at(&self, point: Tuple) -> Color {
@@ -50,4 +74,38 @@ mod tests {
         assert_eq!(pattern.color_at(Tuple::point(0.0, 0.0, 0.99)), WHITE);
         assert_eq!(pattern.color_at(Tuple::point(0.0, 0.0, 1.01)), BLACK);
     }
+
+    #[test]
+    fn filtered_checkers_match_checkers_for_a_tiny_footprint() {
+        let pattern = CheckerPattern::new_filtered(WHITE, BLACK);
+        assert_eq!(
+            pattern.color_at_filtered(Tuple::point(0.5, 0.5, 0.5), 0.0),
+            WHITE
+        );
+        assert_eq!(
+            pattern.color_at_filtered(Tuple::point(1.5, 0.5, 0.5), 0.01),
+            BLACK
+        );
+    }
+
+    #[test]
+    fn filtered_checkers_blend_across_an_edge() {
+        let pattern = CheckerPattern::new_filtered(WHITE, BLACK);
+        let c = pattern.color_at_filtered(Tuple::point(1.0, 0.5, 0.5), 0.5);
+        assert_eq!(c, Color::new(0.5, 0.5, 0.5));
+        let c = pattern.color_at_filtered(Tuple::point(0.9, 0.5, 0.5), 0.4);
+        assert_eq!(c, Color::new(0.75, 0.75, 0.75));
+    }
+
+    #[test]
+    fn filtered_checkers_fade_to_grey_in_the_distance() {
+        let pattern = CheckerPattern::new_filtered(WHITE, BLACK);
+        let c = pattern.color_at_filtered(Tuple::point(13.3, 0.5, 7.1), 40.0);
+        assert!((c.red() - 0.5).abs() < 0.05);
+        let unfiltered = CheckerPattern::new(WHITE, BLACK);
+        assert_eq!(
+            unfiltered.color_at_filtered(Tuple::point(0.5, 0.5, 0.5), 40.0),
+            WHITE
+        );
+    }
 }
@@ -0,0 +1,45 @@
+use crate::colors::Color;
+use crate::tuples::Tuple;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RadialGradientPattern {
+    a: Color,
+    b: Color,
+}
+
+impl RadialGradientPattern {
+    pub fn new(a: Color, b: Color) -> RadialGradientPattern {
+        RadialGradientPattern { a, b }
+    }
+
+    pub fn color_at(&self, point: Tuple) -> Color {
+        let r = (point.x().powi(2) + point.z().powi(2)).sqrt();
+        self.a + (self.b - self.a) * r.fract()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::colors::{BLACK, WHITE};
+    use crate::floats::SQRT_2;
+
+    #[test]
+    fn a_radial_gradient_interpolates_with_the_distance_from_the_axis() {
+        let pattern = RadialGradientPattern::new(WHITE, BLACK);
+        assert_eq!(pattern.color_at(Tuple::point(0.0, 0.0, 0.0)), WHITE);
+        assert_eq!(
+            pattern.color_at(Tuple::point(0.25, 0.0, 0.0)),
+            Color::new(0.75, 0.75, 0.75)
+        );
+        assert_eq!(
+            pattern.color_at(Tuple::point(0.0, 5.0, -0.5)),
+            Color::new(0.5, 0.5, 0.5)
+        );
+        assert_eq!(
+            pattern.color_at(Tuple::point(SQRT_2 / 4.0, 0.0, SQRT_2 / 4.0)),
+            Color::new(0.5, 0.5, 0.5)
+        );
+        assert_eq!(pattern.color_at(Tuple::point(1.0, 0.0, 0.0)), WHITE);
+    }
+}
@@ -1,7 +1,7 @@
 use crate::colors::Color;
 use crate::tuples::Tuple;
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub struct RingPattern {
     a: Color,
     b: Color,
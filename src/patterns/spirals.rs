@@ -0,0 +1,48 @@
+use crate::colors::Color;
+use crate::floats::PI;
+use crate::tuples::Tuple;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SpiralPattern {
+    a: Color,
+    b: Color,
+}
+
+impl SpiralPattern {
+    pub fn new(a: Color, b: Color) -> SpiralPattern {
+        SpiralPattern { a, b }
+    }
+
+    pub fn color_at(&self, point: Tuple) -> Color {
+        let r = (point.x().powi(2) + point.z().powi(2)).sqrt();
+        let turn = point.z().atan2(point.x()) / (2.0 * PI);
+        if (r + turn).floor() as i32 % 2 == 0 {
+            self.a
+        } else {
+            self.b
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::colors::{BLACK, WHITE};
+
+    #[test]
+    fn a_spiral_alternates_along_a_radius() {
+        let pattern = SpiralPattern::new(WHITE, BLACK);
+        assert_eq!(pattern.color_at(Tuple::point(0.5, 0.0, 0.0)), WHITE);
+        assert_eq!(pattern.color_at(Tuple::point(1.5, 0.0, 0.0)), BLACK);
+        assert_eq!(pattern.color_at(Tuple::point(2.5, 3.0, 0.0)), WHITE);
+    }
+
+    #[test]
+    fn a_spiral_winds_around_the_y_axis() {
+        let pattern = SpiralPattern::new(WHITE, BLACK);
+        assert_eq!(pattern.color_at(Tuple::point(0.0, 0.0, 0.9)), BLACK);
+        assert_eq!(pattern.color_at(Tuple::point(0.0, 0.0, 0.7)), WHITE);
+        assert_eq!(pattern.color_at(Tuple::point(-0.4, 0.0, 0.0)), WHITE);
+        assert_eq!(pattern.color_at(Tuple::point(-0.6, 0.0, 0.0)), BLACK);
+    }
+}
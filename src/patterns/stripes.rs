@@ -1,15 +1,37 @@
 use crate::colors::Color;
+use crate::floats::Float;
+use crate::patterns::filtered_square_wave;
 use crate::tuples::Tuple;
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub struct StripePattern {
     a: Color,
     b: Color,
+    filtered: bool,
 }
 
 impl StripePattern {
     pub fn new(a: Color, b: Color) -> StripePattern {
-        StripePattern { a, b }
+        StripePattern {
+            a,
+            b,
+            filtered: false,
+        }
+    }
+
+    pub fn new_filtered(a: Color, b: Color) -> StripePattern {
+        StripePattern {
+            filtered: true,
+            ..StripePattern::new(a, b)
+        }
+    }
+
+    pub fn color_at_filtered(&self, point: Tuple, width: Float) -> Color {
+        if !self.filtered {
+            return self.color_at(point);
+        }
+        let parity = filtered_square_wave(point.x(), width);
+        self.a * ((1.0 + parity) / 2.0) + self.b * ((1.0 - parity) / 2.0)
     }
 
     pub fn color_at(&self, point: Tuple) -> Color {
@@ -52,4 +74,17 @@ mod tests {
         assert_eq!(pattern.color_at(Tuple::point(-1.0, 0.0, 0.0)), BLACK);
         assert_eq!(pattern.color_at(Tuple::point(-1.1, 0.0, 0.0)), WHITE);
     }
+
+    #[test]
+    fn a_filtered_stripe_pattern_blends_near_edges() {
+        let pattern = StripePattern::new_filtered(WHITE, BLACK);
+        assert_eq!(
+            pattern.color_at_filtered(Tuple::point(0.5, 0.0, 0.0), 0.5),
+            WHITE
+        );
+        assert_eq!(
+            pattern.color_at_filtered(Tuple::point(-0.2, 0.0, 0.0), 0.8),
+            Color::new(0.25, 0.25, 0.25)
+        );
+    }
 }
@@ -0,0 +1,130 @@
+use crate::floats::{Float, EPSILON};
+use crate::intersections::{fresnel_sp, IntersectionComputations};
+use crate::tuples::Tuple;
+
+// Incoherent polarization state of a ray: the fractions of its intensity
+// along `axis` and along the perpendicular axis, both across the ray. Phases
+// are not tracked, which is enough for dielectric reflections and refractions.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Polarization {
+    axis: Tuple,
+    along: Float,
+    across: Float,
+}
+
+impl Polarization {
+    pub fn unpolarized() -> Polarization {
+        Polarization {
+            axis: Tuple::vector(1.0, 0.0, 0.0),
+            along: 0.5,
+            across: 0.5,
+        }
+    }
+
+    pub fn linear(axis: Tuple) -> Polarization {
+        Polarization {
+            axis: axis.normalize(),
+            along: 1.0,
+            across: 0.0,
+        }
+    }
+
+    // 0 for unpolarized light, 1 when it is fully polarized.
+    pub fn degree(&self) -> Float {
+        (self.along - self.across).abs() / (self.along + self.across)
+    }
+
+    // The fractions of the intensity polarized perpendicular (s) and parallel
+    // (p) to the plane of incidence of the hit.
+    pub fn split(&self, comps: &IntersectionComputations) -> (Float, Float) {
+        let s_axis = s_axis(comps).unwrap_or(self.axis);
+        let cos2 = self.axis.dot(s_axis).powi(2).min(1.0);
+        let total = self.along + self.across;
+        (
+            (self.along * cos2 + self.across * (1.0 - cos2)) / total,
+            (self.along * (1.0 - cos2) + self.across * cos2) / total,
+        )
+    }
+
+    pub fn reflectance(&self, comps: &IntersectionComputations) -> Float {
+        let (s, p) = self.split(comps);
+        let (rs, rp) = fresnel_sp(comps);
+        s * rs + p * rp
+    }
+
+    pub fn reflected(&self, comps: &IntersectionComputations) -> Polarization {
+        let (rs, rp) = fresnel_sp(comps);
+        self.filtered(comps, rs, rp)
+    }
+
+    pub fn refracted(&self, comps: &IntersectionComputations) -> Polarization {
+        let (rs, rp) = fresnel_sp(comps);
+        self.filtered(comps, 1.0 - rs, 1.0 - rp)
+    }
+
+    fn filtered(&self, comps: &IntersectionComputations, ts: Float, tp: Float) -> Polarization {
+        let (s, p) = self.split(comps);
+        let (along, across) = (s * ts, p * tp);
+        if along + across < EPSILON {
+            return *self;
+        }
+        Polarization {
+            axis: s_axis(comps).unwrap_or(self.axis),
+            along: along / (along + across),
+            across: across / (along + across),
+        }
+    }
+}
+
+// Perpendicular to the plane of incidence, so across both the incoming and
+// the outgoing rays. There is none at normal incidence.
+fn s_axis(comps: &IntersectionComputations) -> Option<Tuple> {
+    let axis = comps.normalv.cross(comps.eyev);
+    (axis.magnitude() > EPSILON).then(|| axis.normalize())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::floats::equals;
+    use crate::intersections::Intersection;
+    use crate::objects::Object;
+    use crate::rays::Ray;
+
+    fn glass_hit(glass: &Object, angle: Float, test: impl Fn(&IntersectionComputations)) {
+        let direction = Tuple::vector(angle.sin(), -angle.cos(), 0.0);
+        let r = Ray::new(Tuple::point(0.0, 0.0, 0.0) - direction, direction);
+        let xs = vec![Intersection::new(1.0, glass)];
+        test(&xs[0].prepare_computations(&r, &xs));
+    }
+
+    #[test]
+    fn light_reflected_at_brewster_angle_is_fully_polarized() {
+        let glass = Object::new_plane().made_of_glass();
+        glass_hit(&glass, 1.5_f32.atan(), |comps| {
+            let light = Polarization::unpolarized();
+            assert!(equals(light.reflectance(comps), 0.14793 / 2.0));
+            let reflected = light.reflected(comps);
+            assert!(equals(reflected.degree(), 1.0));
+            let (s, p) = reflected.split(comps);
+            assert!(equals(s, 1.0) && equals(p, 0.0));
+            assert!(light.refracted(comps).degree() > 0.0);
+        });
+    }
+
+    #[test]
+    fn polarized_light_reflects_depending_on_its_orientation() {
+        let glass = Object::new_plane().made_of_glass();
+        glass_hit(&glass, 1.5_f32.atan(), |comps| {
+            let s = Polarization::linear(Tuple::vector(0.0, 0.0, 1.0));
+            let p = Polarization::linear(Tuple::vector(1.0, 1.0, 0.0));
+            assert!(equals(s.reflectance(comps), 0.14793));
+            assert!(equals(p.reflectance(comps), 0.0));
+        });
+        glass_hit(&glass, 0.0, |comps| {
+            let p = Polarization::linear(Tuple::vector(1.0, 0.0, 0.0));
+            assert!(equals(p.reflectance(comps), 0.04));
+            assert_eq!(p.reflected(comps), p);
+        });
+    }
+}
@@ -1,16 +1,128 @@
-use crate::floats::Float;
+use crate::floats::{Float, EPSILON};
 use crate::matrices::Matrix;
 use crate::tuples::Tuple;
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub struct Ray {
     pub origin: Tuple,
     pub direction: Tuple,
+    pub spread: Float,
+    pub differentials: Option<RayDifferentials>,
+    pub t_min: Float,
+    pub t_max: Float,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RayDifferentials {
+    pub dpdx: Tuple,
+    pub dpdy: Tuple,
+    pub dddx: Tuple,
+    pub dddy: Tuple,
+}
+
+impl RayDifferentials {
+    pub fn new(dddx: Tuple, dddy: Tuple) -> RayDifferentials {
+        let zero = Tuple::vector(0.0, 0.0, 0.0);
+        RayDifferentials {
+            dpdx: zero,
+            dpdy: zero,
+            dddx,
+            dddy,
+        }
+    }
+
+    pub fn transfer(&self, direction: Tuple, t: Float, normal: Tuple) -> RayDifferentials {
+        let d_dot_n = direction.dot(normal);
+        let transfer = |dp: Tuple, dd: Tuple| {
+            let dp = dp + dd * t;
+            if d_dot_n.abs() < EPSILON {
+                return dp;
+            }
+            dp - direction * (dp.dot(normal) / d_dot_n)
+        };
+        RayDifferentials {
+            dpdx: transfer(self.dpdx, self.dddx),
+            dpdy: transfer(self.dpdy, self.dddy),
+            ..*self
+        }
+    }
+
+    pub fn reflect(&self, normal: Tuple) -> RayDifferentials {
+        RayDifferentials {
+            dddx: self.dddx.reflect(normal),
+            dddy: self.dddy.reflect(normal),
+            ..*self
+        }
+    }
+
+    pub fn refract(
+        &self,
+        direction: Tuple,
+        refracted: Tuple,
+        normal: Tuple,
+        n_ratio: Float,
+    ) -> RayDifferentials {
+        let t_dot_n = refracted.dot(normal);
+        let factor = if t_dot_n.abs() < EPSILON {
+            0.0
+        } else {
+            n_ratio - n_ratio.powi(2) * direction.dot(normal) / t_dot_n
+        };
+        let refract = |dd: Tuple| dd * n_ratio - normal * (factor * dd.dot(normal));
+        RayDifferentials {
+            dddx: refract(self.dddx),
+            dddy: refract(self.dddy),
+            ..*self
+        }
+    }
+
+    pub fn footprint(&self) -> Float {
+        self.dpdx.magnitude().max(self.dpdy.magnitude())
+    }
+
+    fn transform(&self, m: Matrix<4>) -> RayDifferentials {
+        RayDifferentials {
+            dpdx: m * self.dpdx,
+            dpdy: m * self.dpdy,
+            dddx: m * self.dddx,
+            dddy: m * self.dddy,
+        }
+    }
 }
 
 impl Ray {
     pub fn new(origin: Tuple, direction: Tuple) -> Ray {
-        Ray { origin, direction }
+        Ray {
+            origin,
+            direction,
+            spread: 0.0,
+            differentials: None,
+            t_min: Float::NEG_INFINITY,
+            t_max: Float::INFINITY,
+        }
+    }
+
+    pub fn with_differentials(self, differentials: RayDifferentials) -> Ray {
+        Ray {
+            differentials: Some(differentials),
+            ..self
+        }
+    }
+
+    pub fn with_spread(self, spread: Float) -> Ray {
+        Ray { spread, ..self }
+    }
+
+    pub fn with_range(self, t_min: Float, t_max: Float) -> Ray {
+        Ray {
+            t_min,
+            t_max,
+            ..self
+        }
+    }
+
+    pub fn in_range(&self, t: Float) -> bool {
+        self.t_min <= t && t <= self.t_max
     }
 
     pub fn position(&self, t: Float) -> Tuple {
@@ -21,6 +133,9 @@ impl Ray {
         Ray {
             origin: t * self.origin,
             direction: t * self.direction,
+            spread: self.spread,
+            differentials: self.differentials.map(|d| d.transform(t)),
+            ..*self
         }
     }
 }
@@ -28,6 +143,7 @@ impl Ray {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::floats::{equals, SQRT_2};
     use crate::transformations::{scaling, translation};
 
     #[test]
@@ -65,4 +181,66 @@ mod tests {
         assert_eq!(r2.origin, Tuple::point(2.0, 6.0, 12.0));
         assert_eq!(r2.direction, Tuple::vector(0.0, 3.0, 0.0));
     }
+
+    fn differentials() -> RayDifferentials {
+        RayDifferentials::new(Tuple::vector(0.01, 0.0, 0.0), Tuple::vector(0.0, 0.0, 0.01))
+    }
+
+    #[test]
+    fn transforming_ray_differentials() {
+        let r = Ray::new(Tuple::point(1.0, 2.0, 3.0), Tuple::vector(0.0, -1.0, 0.0))
+            .with_differentials(differentials());
+        let r2 = r.transform(scaling(2.0, 3.0, 4.0) * translation(1.0, 1.0, 1.0));
+        let d = r2.differentials.unwrap();
+        assert_eq!(d.dddx, Tuple::vector(0.02, 0.0, 0.0));
+        assert_eq!(d.dddy, Tuple::vector(0.0, 0.0, 0.04));
+        assert_eq!(d.dpdx, Tuple::vector(0.0, 0.0, 0.0));
+    }
+
+    #[test]
+    fn transferring_ray_differentials_to_a_surface() {
+        let normal = Tuple::vector(0.0, 1.0, 0.0);
+        let d = differentials().transfer(Tuple::vector(0.0, -1.0, 0.0), 10.0, normal);
+        assert_eq!(d.dpdx, Tuple::vector(0.1, 0.0, 0.0));
+        assert_eq!(d.dpdy, Tuple::vector(0.0, 0.0, 0.1));
+        assert!(equals(d.footprint(), 0.1));
+        let direction = Tuple::vector(0.0, -SQRT_2 / 2.0, SQRT_2 / 2.0);
+        let tilted = RayDifferentials::new(
+            Tuple::vector(0.0, 0.0, 0.0),
+            Tuple::vector(0.0, SQRT_2 / 200.0, SQRT_2 / 200.0),
+        );
+        let d = tilted.transfer(direction, 10.0, normal);
+        assert_eq!(d.dpdy, Tuple::vector(0.0, 0.0, SQRT_2 / 10.0));
+    }
+
+    #[test]
+    fn reflecting_ray_differentials() {
+        let normal = Tuple::vector(0.0, 1.0, 0.0);
+        let d = RayDifferentials::new(
+            Tuple::vector(0.01, -0.01, 0.0),
+            Tuple::vector(0.0, 0.0, 0.0),
+        )
+        .reflect(normal);
+        assert_eq!(d.dddx, Tuple::vector(0.01, 0.01, 0.0));
+    }
+
+    #[test]
+    fn refracting_ray_differentials() {
+        let normal = Tuple::vector(0.0, 1.0, 0.0);
+        let direction = Tuple::vector(0.0, -1.0, 0.0);
+        let d = differentials().refract(direction, direction, normal, 1.0);
+        assert_eq!(d, differentials());
+        let d = differentials().refract(direction, direction, normal, 0.5);
+        assert_eq!(d.dddx, Tuple::vector(0.005, 0.0, 0.0));
+    }
+
+    #[test]
+    fn a_ray_range_survives_transformations() {
+        let r = Ray::new(Tuple::point(1.0, 2.0, 3.0), Tuple::vector(0.0, 1.0, 0.0))
+            .with_range(0.5, 4.0);
+        let r2 = r.transform(scaling(2.0, 3.0, 4.0));
+        assert_eq!((r2.t_min, r2.t_max), (0.5, 4.0));
+        assert!(r2.in_range(4.0));
+        assert!(!r2.in_range(0.25));
+    }
 }
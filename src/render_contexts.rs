@@ -0,0 +1,130 @@
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+
+type Job = Box<dyn FnOnce() + Send>;
+
+// The order in which the rows of an image are handed to the threads.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum RowOrder {
+    TopDown,
+    // From the middle row outward, so that the subject shows up first.
+    CenterOut,
+}
+
+impl RowOrder {
+    pub fn rows(self, height: usize) -> Vec<usize> {
+        let mut rows: Vec<usize> = (0..height).collect();
+        if self == RowOrder::CenterOut {
+            let middle = height / 2;
+            rows.sort_by_key(|&y| (y.abs_diff(middle), y));
+        }
+        rows
+    }
+}
+
+// Render threads kept alive between renders, e.g. for the frames of an
+// animation, so that each render does not start its own threads.
+pub struct RenderContext {
+    pub row_order: RowOrder,
+    sender: Option<Sender<Job>>,
+    workers: Vec<JoinHandle<()>>,
+}
+
+impl RenderContext {
+    pub fn new(threads: usize) -> RenderContext {
+        let (sender, receiver) = mpsc::channel::<Job>();
+        let receiver = Arc::new(Mutex::new(receiver));
+        let workers = (0..threads.max(1))
+            .map(|_| {
+                let receiver = Arc::clone(&receiver);
+                thread::spawn(move || work(&receiver))
+            })
+            .collect();
+        RenderContext {
+            row_order: RowOrder::TopDown,
+            sender: Some(sender),
+            workers,
+        }
+    }
+
+    pub fn with_row_order(mut self, row_order: RowOrder) -> RenderContext {
+        self.row_order = row_order;
+        self
+    }
+
+    pub fn threads(&self) -> usize {
+        self.workers.len()
+    }
+
+    pub fn execute<F>(&self, job: F)
+    where
+        F: FnOnce() + Send + 'static,
+    {
+        self.sender
+            .as_ref()
+            .unwrap()
+            .send(Box::new(job))
+            .expect("The render threads have stopped");
+    }
+}
+
+fn work(receiver: &Mutex<Receiver<Job>>) {
+    loop {
+        // The lock is released before running the job.
+        let job = receiver.lock().unwrap().recv();
+        match job {
+            // A panicking job must not take the thread down with it, the
+            // caller finds out from the results that never come back.
+            Ok(job) => {
+                let _ = panic::catch_unwind(AssertUnwindSafe(job));
+            }
+            Err(_) => return,
+        }
+    }
+}
+
+impl Drop for RenderContext {
+    fn drop(&mut self) {
+        drop(self.sender.take());
+        for worker in self.workers.drain(..) {
+            let _ = worker.join();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn the_threads_run_every_job() {
+        let context = RenderContext::new(3);
+        assert_eq!(context.threads(), 3);
+        let (sender, receiver) = mpsc::channel();
+        for i in 0..20 {
+            let sender = sender.clone();
+            context.execute(move || sender.send(i).unwrap());
+        }
+        drop(sender);
+        let mut done: Vec<usize> = receiver.iter().collect();
+        done.sort();
+        assert_eq!(done, (0..20).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn the_threads_survive_a_panicking_job() {
+        let context = RenderContext::new(1);
+        context.execute(|| panic!("job failed"));
+        let (sender, receiver) = mpsc::channel();
+        context.execute(move || sender.send(1).unwrap());
+        assert_eq!(receiver.recv(), Ok(1));
+    }
+
+    #[test]
+    fn ordering_the_rows() {
+        assert_eq!(RowOrder::TopDown.rows(4), vec![0, 1, 2, 3]);
+        assert_eq!(RowOrder::CenterOut.rows(5), vec![2, 1, 3, 0, 4]);
+    }
+}
@@ -0,0 +1,923 @@
+use crate::cameras::{Camera, PhysicalExposure};
+use crate::colors::{Color, ColorSpace};
+use crate::floats::{Float, PI};
+use crate::lights::{GelMapping, Light};
+use crate::materials::{ClearCoat, Material, MaterialLibrary, Tangents};
+use crate::matrices::Matrix;
+use crate::obj_files::{parse_obj_file_path, parse_obj_mesh_path, parse_smooth_obj_file_path};
+use crate::objects::Object;
+use crate::patterns::{Pattern, PatternSpace};
+use crate::shapes::displaced_surfaces::DisplacementBase;
+use crate::skies::Sky;
+use crate::transformations::{
+    rotation_x, rotation_y, rotation_z, scaling, translation, view_transform,
+};
+use crate::tuples::Tuple;
+use crate::worlds::World;
+use std::path::Path;
+use std::str::SplitWhitespace;
+
+const MAX_PRISM_SIDES: usize = 1 << 16;
+
+pub struct SceneFile {
+    pub world: World,
+    pub width: usize,
+    pub height: usize,
+    pub field_of_view: Float,
+    pub view: Matrix<4>,
+    pub exposure: Float,
+    pub white_balance: Option<Float>,
+    // Set by any of the iso, shutter or f-number camera properties.
+    pub physical_exposure: Option<PhysicalExposure>,
+    pub shift: (Float, Float),
+    pub roll: Float,
+    pub distortion: (Float, Float),
+    // Material and pattern colors of the file are decoded from the input
+    // space. Light intensities are always linear.
+    pub input_color_space: ColorSpace,
+    pub output_color_space: ColorSpace,
+}
+
+impl SceneFile {
+    pub fn camera(&self) -> Camera {
+        let mut camera = Camera::new(self.width, self.height, 1.0, self.field_of_view, self.view);
+        camera.exposure = self.exposure;
+        camera.white_balance = self.white_balance;
+        camera.color_space = self.output_color_space;
+        camera.shift = self.shift;
+        camera.roll = self.roll;
+        camera.distortion = self.distortion;
+        match self.physical_exposure {
+            Some(settings) => camera.with_physical_exposure(settings),
+            None => camera,
+        }
+    }
+}
+
+pub fn parse_scene_file_path(path: &str) -> Result<SceneFile, String> {
+    let text = std::fs::read_to_string(path).map_err(|e| format!("{}: {}", path, e))?;
+    let base = Path::new(path).parent().unwrap_or(Path::new(""));
+    parse_scene_file(&text, base)
+}
+
+pub fn parse_scene_file(text: &str, base: &Path) -> Result<SceneFile, String> {
+    let mut scene = SceneFile {
+        world: World::new(),
+        width: 400,
+        height: 200,
+        field_of_view: PI / 3.0,
+        view: Matrix::identity(),
+        exposure: 0.0,
+        white_balance: None,
+        physical_exposure: None,
+        shift: (0.0, 0.0),
+        roll: 0.0,
+        distortion: (0.0, 0.0),
+        input_color_space: ColorSpace::Linear,
+        output_color_space: ColorSpace::Linear,
+    };
+    for (i, line) in text.lines().enumerate() {
+        let line = line.split('#').next().unwrap_or("");
+        let mut words = Words {
+            words: line.split_whitespace(),
+            color_space: scene.input_color_space,
+        };
+        let Some(directive) = words.next() else {
+            continue;
+        };
+        let result = match directive {
+            "camera" => parse_camera(&mut words, &mut scene),
+            "input-color-space" => {
+                parse_color_space(&mut words).map(|c| scene.input_color_space = c)
+            }
+            "output-color-space" => {
+                parse_color_space(&mut words).map(|c| scene.output_color_space = c)
+            }
+            "ambient" => words.color().map(|c| scene.world.ambient_light = c),
+            "light" => parse_light(&mut words).map(|l| scene.world.lights.push(l)),
+            "sky" => parse_sky(&mut words).map(|s| scene.world.sky = Some(s)),
+            "material" => parse_named_material(&mut words).map(|(name, material)| {
+                scene.world.materials.insert(name, material);
+            }),
+            "obj" => parse_obj(&mut words, base, &scene.world.materials)
+                .map(|o| scene.world.add_object(o)),
+            shape => parse_shape(shape, &mut words, &scene.world.materials)
+                .map(|o| scene.world.add_object(o)),
+        };
+        result.map_err(|e| format!("line {}: {}", i + 1, e))?;
+    }
+    Ok(scene)
+}
+
+struct Words<'a> {
+    words: SplitWhitespace<'a>,
+    color_space: ColorSpace,
+}
+
+impl<'a> Words<'a> {
+    fn next(&mut self) -> Option<&'a str> {
+        self.words.next()
+    }
+
+    fn float(&mut self) -> Result<Float, String> {
+        let word = self.next().ok_or("missing number")?;
+        word.parse()
+            .ok()
+            .filter(|v: &Float| v.is_finite())
+            .ok_or_else(|| format!("invalid number '{}'", word))
+    }
+
+    fn usize(&mut self) -> Result<usize, String> {
+        let word = self.next().ok_or("missing integer")?;
+        word.parse()
+            .map_err(|_| format!("invalid integer '{}'", word))
+    }
+
+    fn triple(&mut self) -> Result<(Float, Float, Float), String> {
+        Ok((self.float()?, self.float()?, self.float()?))
+    }
+
+    fn point(&mut self) -> Result<Tuple, String> {
+        let (x, y, z) = self.triple()?;
+        Ok(Tuple::point(x, y, z))
+    }
+
+    fn vector(&mut self) -> Result<Tuple, String> {
+        let (x, y, z) = self.triple()?;
+        Ok(Tuple::vector(x, y, z))
+    }
+
+    fn color(&mut self) -> Result<Color, String> {
+        let (r, g, b) = self.triple()?;
+        Ok(Color::new(r, g, b))
+    }
+
+    // Reflectance colors, e.g. of materials and patterns, in the input color
+    // space of the file.
+    fn albedo(&mut self) -> Result<Color, String> {
+        Ok(self.color_space.decode(self.color()?))
+    }
+
+    fn degrees(&mut self) -> Result<Float, String> {
+        Ok(self.float()? * PI / 180.0)
+    }
+}
+
+fn parse_color_space(words: &mut Words) -> Result<ColorSpace, String> {
+    match words.next() {
+        Some("linear") => Ok(ColorSpace::Linear),
+        Some("srgb") => Ok(ColorSpace::Srgb),
+        _ => Err(String::from("color space must be linear or srgb")),
+    }
+}
+
+fn parse_camera(words: &mut Words, scene: &mut SceneFile) -> Result<(), String> {
+    let mut from = Tuple::point(0.0, 0.0, 0.0);
+    let mut to = Tuple::point(0.0, 0.0, -1.0);
+    let mut up = Tuple::vector(0.0, 1.0, 0.0);
+    while let Some(property) = words.next() {
+        match property {
+            "size" => {
+                scene.width = words.usize()?;
+                scene.height = words.usize()?;
+                if scene.width == 0 || scene.height == 0 {
+                    return Err(String::from("camera size must not be zero"));
+                }
+            }
+            "fov" => scene.field_of_view = words.degrees()?,
+            "from" => from = words.point()?,
+            "to" => to = words.point()?,
+            "up" => up = words.vector()?,
+            "shift" => scene.shift = (words.float()?, words.float()?),
+            "roll" => scene.roll = words.degrees()?,
+            "distortion" => scene.distortion = (words.float()?, words.float()?),
+            "exposure" => scene.exposure = words.float()?,
+            "white-balance" => scene.white_balance = Some(words.float()?),
+            "iso" => physical(scene).iso = words.float()?,
+            "shutter" => physical(scene).shutter = words.float()?,
+            "f-number" => physical(scene).f_number = words.float()?,
+            "reflection-depth" => scene.world.max_reflection_depth = words.usize()?,
+            "refraction-depth" => scene.world.max_refraction_depth = words.usize()?,
+            "t-min" => scene.world.camera_t_min = words.float()?,
+            "shadow-t-min" => scene.world.shadow_t_min = words.float()?,
+            _ => return Err(format!("unknown camera property '{}'", property)),
+        }
+    }
+    scene.view = invertible(view_transform(from, to, up))?;
+    Ok(())
+}
+
+fn physical(scene: &mut SceneFile) -> &mut PhysicalExposure {
+    scene.physical_exposure.get_or_insert_with(Default::default)
+}
+
+fn parse_light(words: &mut Words) -> Result<Light, String> {
+    let kind = words.next().ok_or("missing light type")?;
+    let mut position = Tuple::point(0.0, 0.0, 0.0);
+    let mut intensity = Color::new(1.0, 1.0, 1.0);
+    let mut size = 1.0;
+    let mut samples = 16;
+    let mut softness = 0.0;
+    let mut texture = Material::default();
+    let mut gel = None;
+    let mut candela = None;
+    let mut lumens = None;
+    while let Some(property) = words.next() {
+        match property {
+            "at" => position = words.point()?,
+            "color" => intensity = words.color()?,
+            "candela" => candela = Some(words.float()?),
+            "lumens" => lumens = Some(words.float()?),
+            "size" => size = words.float()?,
+            "samples" => samples = words.usize()?,
+            "softness" => softness = words.float()?,
+            "gel" => gel = Some(parse_gel(words)?),
+            _ if kind == "panel" && parse_material(property, words, &mut texture)? => {}
+            _ => return Err(format!("unknown light property '{}'", property)),
+        }
+    }
+    let light = match kind {
+        "point" if softness > 0.0 => {
+            Ok(Light::new_point(position, intensity).with_softness(softness, samples))
+        }
+        "point" => Ok(Light::new_point(position, intensity)),
+        "sphere" => Ok(Light::new_sphere(position, intensity, size, samples)),
+        "cube" => Ok(Light::new_cube(position, intensity, size, samples)),
+        "panel" => {
+            let u = Tuple::vector(size, 0.0, 0.0);
+            let v = Tuple::vector(0.0, 0.0, size);
+            let light = Light::new_panel(position, intensity, u, v, samples);
+            Ok(match texture.pattern {
+                Some(pattern) => light.with_texture(pattern),
+                None => light,
+            })
+        }
+        _ => Err(format!("unknown light type '{}'", kind)),
+    }?;
+    let light = match (candela, lumens) {
+        (Some(candela), _) => light.with_candela(candela),
+        (None, Some(lumens)) => light.with_lumens(lumens),
+        (None, None) => light,
+    };
+    Ok(match gel {
+        Some((pattern, mapping)) => light.with_gel(pattern, mapping),
+        None => light,
+    })
+}
+
+fn parse_gel(words: &mut Words) -> Result<(Pattern, GelMapping), String> {
+    let mapping = match words.next() {
+        Some("direction") => GelMapping::Direction,
+        Some("distance") => GelMapping::Distance,
+        _ => return Err(String::from("gel mapping must be direction or distance")),
+    };
+    Ok((
+        parse_pattern(words).map_err(|e| format!("gel: {}", e))?,
+        mapping,
+    ))
+}
+
+fn parse_pattern(words: &mut Words) -> Result<Pattern, String> {
+    let mut material = Material::default();
+    let property = words.next().ok_or("missing pattern")?;
+    parse_material(property, words, &mut material)?;
+    material.pattern.ok_or(String::from("missing pattern"))
+}
+
+fn parse_displaced(words: &mut Words) -> Result<Object, String> {
+    let base = match words.next() {
+        Some("plane") => DisplacementBase::Plane,
+        Some("sphere") => DisplacementBase::Sphere,
+        _ => return Err(String::from("displaced base must be plane or sphere")),
+    };
+    let scale = words.float()?;
+    // The pattern colors are heights, they are read as they are.
+    let color_space = std::mem::replace(&mut words.color_space, ColorSpace::Linear);
+    let height = parse_pattern(words).map_err(|e| format!("displaced: {}", e))?;
+    words.color_space = color_space;
+    Ok(Object::new_displaced_surface(base, height, scale))
+}
+
+fn parse_tangents(words: &mut Words) -> Result<Tangents, String> {
+    match words.next() {
+        Some("direction") => Ok(Tangents::Direction(words.vector()?)),
+        Some("circular") => Ok(Tangents::Circular),
+        Some("attribute") => Ok(Tangents::Attribute(words.usize()?)),
+        _ => Err(String::from(
+            "anisotropy tangents must be direction, circular or attribute",
+        )),
+    }
+}
+
+fn parse_sky(words: &mut Words) -> Result<Sky, String> {
+    let mut sky = Sky::new(Tuple::vector(0.0, 1.0, 0.0), 3.0);
+    while let Some(property) = words.next() {
+        match property {
+            "sun" => sky.sun_direction = words.vector()?.normalize(),
+            "turbidity" => sky.turbidity = words.float()?.max(1.0),
+            "intensity" => sky.intensity = words.float()?,
+            "sun-intensity" => sky.sun_intensity = words.float()?,
+            _ => return Err(format!("unknown sky property '{}'", property)),
+        }
+    }
+    Ok(sky)
+}
+
+fn parse_obj(
+    words: &mut Words,
+    base: &Path,
+    materials: &MaterialLibrary,
+) -> Result<Object, String> {
+    let file = words.next().ok_or("missing obj file path")?;
+    let path = base.join(file);
+    if !path.is_file() {
+        return Err(format!("obj file '{}' not found", path.display()));
+    }
+    let mut transform = Matrix::identity();
+    let mut material = Material::default();
+    let mut crease_angle = None;
+    let mut weld = None;
+    let mut target_triangles = None;
+    let mut material_ref = None;
+    while let Some(property) = words.next() {
+        match property {
+            "material" => material_ref = Some(parse_material_ref(words, materials)?),
+            "smooth" => crease_angle = Some(words.degrees()?),
+            "weld" => weld = Some(words.float()?),
+            "decimate" => target_triangles = Some(words.usize()?),
+            _ if parse_transform(property, words, &mut transform)?
+                || parse_material(property, words, &mut material)? => {}
+            _ => return Err(format!("unknown obj property '{}'", property)),
+        }
+    }
+    invertible(transform)?;
+    let path = path.to_string_lossy();
+    // Welding and decimating merge the groups of the file, and bake the
+    // transform so that distances are measured in the scene.
+    let mut group = if weld.is_some() || target_triangles.is_some() {
+        let mut mesh = parse_obj_mesh_path(&path)?;
+        mesh.transform(transform);
+        transform = Matrix::identity();
+        if let Some(epsilon) = weld {
+            mesh.weld(epsilon);
+        }
+        if let Some(target) = target_triangles {
+            mesh.decimate(target);
+        }
+        mesh.to_group(crease_angle)
+    } else {
+        match crease_angle {
+            Some(angle) => parse_smooth_obj_file_path(&path, angle),
+            None => parse_obj_file_path(&path),
+        }?
+        .default_group
+    };
+    group.material = material;
+    group.material_ref = material_ref;
+    Ok(group.with_transform(transform))
+}
+
+fn parse_shape(
+    shape: &str,
+    words: &mut Words,
+    materials: &MaterialLibrary,
+) -> Result<Object, String> {
+    let mut object = match shape {
+        "sphere" => Object::new_sphere(),
+        "plane" => Object::new_plane(),
+        "quad" => Object::new_quad(words.float()?, words.float()?),
+        "cube" => Object::new_cube(),
+        "cylinder" => Object::new_cylinder(),
+        "cone" => Object::new_cone(),
+        "prism" => {
+            let sides = words.usize()?;
+            if sides < 3 {
+                return Err(String::from("a prism has at least 3 sides"));
+            }
+            if sides > MAX_PRISM_SIDES {
+                return Err(format!("a prism has at most {} sides", MAX_PRISM_SIDES));
+            }
+            Object::new_prism(sides, words.float()?)
+        }
+        "displaced" => parse_displaced(words)?,
+        "curve" => {
+            let points = [
+                words.point()?,
+                words.point()?,
+                words.point()?,
+                words.point()?,
+            ];
+            Object::new_curve(points, words.float()?, words.float()?)
+        }
+        _ => return Err(format!("unknown directive '{}'", shape)),
+    };
+    let mut transform = Matrix::identity();
+    let (mut minimum, mut maximum, mut closed) = (Float::NEG_INFINITY, Float::INFINITY, false);
+    while let Some(property) = words.next() {
+        if parse_transform(property, words, &mut transform)?
+            || parse_material(property, words, &mut object.material)?
+        {
+            continue;
+        }
+        match property {
+            "min" => minimum = words.float()?,
+            "max" => maximum = words.float()?,
+            "closed" => closed = true,
+            "single-sided" => object.single_sided = true,
+            "material" => object.material_ref = Some(parse_material_ref(words, materials)?),
+            "name" => object.name = Some(words.next().ok_or("missing name")?.to_string()),
+            _ => return Err(format!("unknown {} property '{}'", shape, property)),
+        }
+    }
+    match shape {
+        "cylinder" => object.as_mut_cylinder().truncate(minimum, maximum, closed),
+        "cone" => object.as_mut_cone().truncate(minimum, maximum, closed),
+        _ => {}
+    }
+    Ok(object.with_transform(invertible(transform)?))
+}
+
+// Transforms are inverted to render, so flat ones are errors here.
+fn invertible(transform: Matrix<4>) -> Result<Matrix<4>, String> {
+    if transform.is_invertible() {
+        Ok(transform)
+    } else {
+        Err(String::from("transform is not invertible"))
+    }
+}
+
+fn parse_transform(
+    property: &str,
+    words: &mut Words,
+    transform: &mut Matrix<4>,
+) -> Result<bool, String> {
+    let m = match property {
+        "translate" => {
+            let (x, y, z) = words.triple()?;
+            translation(x, y, z)
+        }
+        "scale" => {
+            let (x, y, z) = words.triple()?;
+            scaling(x, y, z)
+        }
+        "rotate-x" => rotation_x(words.degrees()?),
+        "rotate-y" => rotation_y(words.degrees()?),
+        "rotate-z" => rotation_z(words.degrees()?),
+        _ => return Ok(false),
+    };
+    *transform = m * *transform;
+    Ok(true)
+}
+
+// A material of the library, that objects refer to by name.
+fn parse_named_material<'a>(words: &mut Words<'a>) -> Result<(&'a str, Material), String> {
+    let name = words.next().ok_or("missing material name")?;
+    let mut material = Material::default();
+    while let Some(property) = words.next() {
+        if !parse_material(property, words, &mut material)? {
+            return Err(format!("unknown material property '{}'", property));
+        }
+    }
+    Ok((name, material))
+}
+
+// Materials must be defined before the objects using them.
+fn parse_material_ref(words: &mut Words, materials: &MaterialLibrary) -> Result<String, String> {
+    let name = words.next().ok_or("missing material name")?;
+    if !materials.contains(name) {
+        return Err(format!("unknown material '{}'", name));
+    }
+    Ok(name.to_string())
+}
+
+fn parse_material(
+    property: &str,
+    words: &mut Words,
+    material: &mut Material,
+) -> Result<bool, String> {
+    match property {
+        "color" => material.color = words.albedo()?,
+        "ambient" => material.ambient = words.float()?,
+        "diffuse" => material.diffuse = words.float()?,
+        "specular" => material.specular = words.float()?,
+        "shininess" => material.shininess = words.float()?,
+        "specular-roughness" => material.specular_roughness = Some(words.float()?.clamp(0.0, 1.0)),
+        "reflective" => material.reflective = words.float()?,
+        "reflective-color" => {
+            material.reflective = 1.0;
+            material.reflective_color = Some(words.albedo()?);
+        }
+        "anisotropy" => {
+            let anisotropy = words.float()?;
+            *material = material
+                .clone()
+                .with_anisotropy(anisotropy, parse_tangents(words)?);
+        }
+        "pattern-space" => {
+            let space =
+                match words.next() {
+                    Some("object") => PatternSpace::Object,
+                    Some("world") => PatternSpace::World,
+                    Some("normal") => PatternSpace::Normal,
+                    Some("facing-ratio") => PatternSpace::FacingRatio,
+                    Some("triplanar") => PatternSpace::Triplanar(words.float()?),
+                    _ => return Err(String::from(
+                        "pattern space must be object, world, normal, facing-ratio or triplanar",
+                    )),
+                };
+            let pattern = material
+                .pattern
+                .take()
+                .ok_or("pattern-space needs a pattern")?;
+            material.pattern = Some(pattern.with_space(space));
+        }
+        "fresnel" => {
+            material.fresnel = true;
+            material.refractive_index = words.float()?;
+        }
+        "transparency" => material.transparency = words.float()?,
+        "refractive-index" => material.refractive_index = words.float()?,
+        "dispersion" => material.dispersion = words.float()?,
+        "glass" => *material = Material::glass(),
+        "clear-coat" => {
+            let (reflective, refractive_index, roughness) = words.triple()?;
+            material.clear_coat = Some(ClearCoat::new(reflective, refractive_index, roughness));
+        }
+        "checker" | "stripe" | "gradient" | "ring" | "radial-gradient" | "spiral" => {
+            let (a, b) = (words.albedo()?, words.albedo()?);
+            let scale = words.float()?;
+            let pattern = match property {
+                "checker" => Pattern::new_checker(a, b),
+                "stripe" => Pattern::new_stripe(a, b),
+                "gradient" => Pattern::new_gradient(a, b),
+                "radial-gradient" => Pattern::new_radial_gradient(a, b),
+                "spiral" => Pattern::new_spiral(a, b),
+                _ => Pattern::new_ring(a, b),
+            };
+            material.pattern =
+                Some(pattern.with_transform(invertible(scaling(scale, scale, scale))?));
+        }
+        _ => return Ok(false),
+    }
+    Ok(true)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::floats::equals;
+
+    fn parse(text: &str) -> Result<SceneFile, String> {
+        parse_scene_file(text, Path::new(""))
+    }
+
+    #[test]
+    fn corrupted_scenes_are_errors() {
+        let error = |text: &str| parse(text).err().unwrap();
+        assert_eq!(
+            error("sphere scale 1 0 1\n"),
+            "line 1: transform is not invertible"
+        );
+        assert_eq!(
+            error("cube checker 0 0 0 1 1 1 0\n"),
+            "line 1: transform is not invertible"
+        );
+        assert_eq!(
+            error("camera from 0 0 0 to 0 0 0\n"),
+            "line 1: transform is not invertible"
+        );
+        assert_eq!(
+            error("sphere translate NaN 0 0\n"),
+            "line 1: invalid number 'NaN'"
+        );
+        assert_eq!(
+            error("prism 100000000 1\n"),
+            "line 1: a prism has at most 65536 sides"
+        );
+        assert_eq!(
+            error("prism 2 1\n"),
+            "line 1: a prism has at least 3 sides"
+        );
+        assert_eq!(
+            error("camera size 0 0\n"),
+            "line 1: camera size must not be zero"
+        );
+    }
+
+    #[test]
+    fn parsing_the_camera() {
+        let scene = parse(
+            "# a comment
+camera size 100 50 fov 90 from 0 1.5 -5 to 0 1 0 up 0 1 0
+",
+        )
+        .unwrap();
+        assert_eq!(scene.width, 100);
+        assert_eq!(scene.height, 50);
+        assert!(equals(scene.field_of_view, PI / 2.0));
+        assert_eq!(
+            scene.view,
+            view_transform(
+                Tuple::point(0.0, 1.5, -5.0),
+                Tuple::point(0.0, 1.0, 0.0),
+                Tuple::vector(0.0, 1.0, 0.0)
+            )
+        );
+    }
+
+    #[test]
+    fn parsing_the_camera_exposure_and_white_balance() {
+        let scene = parse("camera exposure -1.5 white-balance 3200\n").unwrap();
+        let camera = scene.camera();
+        assert_eq!(camera.exposure, -1.5);
+        assert_eq!(camera.white_balance, Some(3200.0));
+    }
+
+    #[test]
+    fn parsing_the_camera_lens() {
+        let camera = parse("camera shift 0 0.2 roll 90 distortion -0.1 0.01\n")
+            .unwrap()
+            .camera();
+        assert_eq!(camera.shift, (0.0, 0.2));
+        assert!(equals(camera.roll, PI / 2.0));
+        assert_eq!(camera.distortion, (-0.1, 0.01));
+    }
+
+    #[test]
+    fn parsing_named_materials() {
+        let mut scene = parse(
+            "material steel color 0.5 0.5 0.6 specular 1 reflective 0.3
+sphere material steel translate 0 1 0
+cube material steel
+",
+        )
+        .unwrap();
+        let steel = scene.world.materials.get("steel").unwrap();
+        assert_eq!(steel.color, Color::new(0.5, 0.5, 0.6));
+        assert_eq!(steel.reflective, 0.3);
+        scene.world.materials.get_mut("steel").unwrap().specular = 0.5;
+        scene.world.prepare();
+        for o in &scene.world.objects {
+            assert_eq!(o.material_ref.as_deref(), Some("steel"));
+            assert_eq!(o.material.specular, 0.5);
+        }
+        assert_eq!(
+            parse("sphere material gold\n").err().unwrap(),
+            "line 1: unknown material 'gold'"
+        );
+        assert_eq!(
+            parse("material gold size 2\n").err().unwrap(),
+            "line 1: unknown material property 'size'"
+        );
+    }
+
+    #[test]
+    fn parsing_physical_light_units() {
+        let scene = parse(
+            "camera iso 400 shutter 0.5 f-number 2
+light point at 0 5 0 color 1 0.5 0.5 candela 100
+light panel at 0 5 0 lumens 800
+",
+        )
+        .unwrap();
+        let settings = PhysicalExposure {
+            iso: 400.0,
+            shutter: 0.5,
+            f_number: 2.0,
+        };
+        assert_eq!(scene.physical_exposure, Some(settings));
+        assert_eq!(scene.camera().exposure, settings.stops());
+        assert_eq!(
+            scene.world.lights[0].intensity,
+            Color::new(100.0, 50.0, 50.0)
+        );
+        assert_eq!(
+            scene.world.lights[1].intensity,
+            Color::new(1.0, 1.0, 1.0) * (800.0 / PI)
+        );
+        let scene = parse("camera exposure 1\n").unwrap();
+        assert_eq!(scene.physical_exposure, None);
+    }
+
+    #[test]
+    fn parsing_srgb_colors() {
+        let scene = parse(
+            "input-color-space srgb
+ambient 0.5 0.5 0.5
+light point at 0 5 0 color 0.5 0.5 0.5
+sphere color 0.5 0.5 0.5 checker 0.5 0.5 0.5 1 1 1 1
+displaced plane 1 gradient 0.5 0.5 0.5 0 0 0 1 color 0.5 0.5 0.5
+",
+        )
+        .unwrap();
+        let decoded = Color::new(0.21404, 0.21404, 0.21404);
+        let linear = Color::new(0.5, 0.5, 0.5);
+        assert_eq!(scene.world.ambient_light, linear);
+        assert_eq!(scene.world.lights[0].intensity, linear);
+        let sphere = &scene.world.objects[0];
+        assert_eq!(sphere.material.color, decoded);
+        let pattern = sphere.material.pattern.as_ref().unwrap();
+        assert_eq!(
+            pattern.color_at_pattern(Tuple::point(0.25, 0.25, 0.25)),
+            decoded
+        );
+        let displaced = &scene.world.objects[1];
+        let height = &displaced.as_displaced_surface().height;
+        assert_eq!(height.color_at_pattern(Tuple::point(0.0, 0.0, 0.0)), linear);
+        assert_eq!(displaced.material.color, decoded);
+        assert_eq!(scene.camera().color_space, ColorSpace::Linear);
+        assert!(parse("input-color-space cmyk\n").is_err());
+    }
+
+    #[test]
+    fn parsing_the_output_color_space() {
+        let scene = parse("output-color-space srgb\nsphere color 0.5 0.5 0.5\n").unwrap();
+        assert_eq!(scene.camera().color_space, ColorSpace::Srgb);
+        assert_eq!(
+            scene.world.objects[0].material.color,
+            Color::new(0.5, 0.5, 0.5)
+        );
+        assert!(parse("output-color-space cmyk\n").is_err());
+    }
+
+    #[test]
+    fn parsing_separate_reflection_and_refraction_depths() {
+        let scene = parse("camera reflection-depth 1 refraction-depth 8\n").unwrap();
+        assert_eq!(scene.world.max_reflection_depth, 1);
+        assert_eq!(scene.world.max_refraction_depth, 8);
+    }
+
+    #[test]
+    fn parsing_the_camera_and_shadow_t_min() {
+        let scene = parse("camera t-min 0.5 shadow-t-min 0.01\n").unwrap();
+        assert_eq!(scene.world.camera_t_min, 0.5);
+        assert_eq!(scene.world.shadow_t_min, 0.01);
+    }
+
+    #[test]
+    fn parsing_lights() {
+        let scene = parse(
+            "ambient 0.5 0.5 0.5
+light point at -10 10 -10 color 1 0.5 1
+light sphere at 0 5 0 size 0.5 samples 4
+light point at 0 5 0 softness 0.5 samples 8
+",
+        )
+        .unwrap();
+        assert_eq!(scene.world.ambient_light, Color::new(0.5, 0.5, 0.5));
+        assert_eq!(
+            scene.world.lights[0],
+            Light::new_point(Tuple::point(-10.0, 10.0, -10.0), Color::new(1.0, 0.5, 1.0))
+        );
+        assert_eq!(
+            scene.world.lights[1],
+            Light::new_sphere(
+                Tuple::point(0.0, 5.0, 0.0),
+                Color::new(1.0, 1.0, 1.0),
+                0.5,
+                4
+            )
+        );
+        assert_eq!(
+            scene.world.lights[2],
+            Light::new_point(Tuple::point(0.0, 5.0, 0.0), Color::new(1.0, 1.0, 1.0))
+                .with_softness(0.5, 8)
+        );
+    }
+
+    #[test]
+    fn parsing_textured_panel_lights() {
+        let scene = parse(
+            "light panel at 0 5 0 size 2 samples 4
+light panel at 0 5 0 size 2 checker 1 0 0 0 0 1 0.5
+",
+        )
+        .unwrap();
+        let panel = Light::new_panel(
+            Tuple::point(0.0, 5.0, 0.0),
+            Color::new(1.0, 1.0, 1.0),
+            Tuple::vector(2.0, 0.0, 0.0),
+            Tuple::vector(0.0, 0.0, 2.0),
+            4,
+        );
+        assert_eq!(scene.world.lights[0], panel);
+        assert_ne!(scene.world.lights[1], panel);
+        assert!(scene.world.lights[1].describe().starts_with("panel light"));
+        assert!(parse("light sphere at 0 5 0 checker 1 0 0 0 0 1 0.5\n").is_err());
+    }
+
+    #[test]
+    fn parsing_light_gels() {
+        let scene = parse("light point at 0 5 0 gel distance gradient 1 1 1 0 0 0 10\n").unwrap();
+        let light = scene.world.lights[0].shadowed(Tuple::point(0.0, 0.0, 0.0), |_, _| false);
+        assert_eq!(light.intensity, Color::new(0.5, 0.5, 0.5));
+        assert!(parse("light point at 0 5 0 gel sideways checker 1 0 0 0 0 1 1\n").is_err());
+        assert!(parse("light point at 0 5 0 gel direction color 1 0 0\n").is_err());
+    }
+
+    #[test]
+    fn parsing_a_sky() {
+        let scene = parse("sky sun 0 1 1 turbidity 4 intensity 0.5\n").unwrap();
+        let sky = scene.world.sky.unwrap();
+        assert_eq!(sky.sun_direction, Tuple::vector(0.0, 1.0, 1.0).normalize());
+        assert_eq!(sky.turbidity, 4.0);
+        assert_eq!(sky.intensity, 0.5);
+        assert_eq!(sky.sun_intensity, 1.0);
+        assert!(parse("sky moon 0 1 0\n").is_err());
+    }
+
+    #[test]
+    fn parsing_shapes() {
+        let scene = parse(
+            "sphere translate 0 1 0 scale 2 2 2 color 1 0 0 diffuse 0.7 reflective 0.1 fresnel 1.4
+plane checker 1 1 1 0 0 0 0.5 clear-coat 1 1.5 0.1 single-sided reflective-color 1 0.5 0 anisotropy 0.8 circular
+cylinder min 0 max 2 closed rotate-z 90
+quad 4 2 translate 0 3 0
+sphere gradient 0 0 0 1 1 1 1 pattern-space facing-ratio
+cube stripe 1 1 1 0 0 0 1 pattern-space triplanar 4
+displaced plane 0.5 stripe 1 1 1 0 0 0 2 translate 0 -1 0
+curve 0 0 0 0 1 0 1 2 0 2 2 0 0.05 0.01 color 0.4 0.3 0.1
+prism 6 0.5
+",
+        )
+        .unwrap();
+        let objects = &scene.world.objects;
+        assert_eq!(objects.len(), 9);
+        assert_eq!(
+            objects[0].transform,
+            scaling(2.0, 2.0, 2.0) * translation(0.0, 1.0, 0.0)
+        );
+        assert_eq!(objects[0].material.color, Color::new(1.0, 0.0, 0.0));
+        assert_eq!(objects[0].material.diffuse, 0.7);
+        assert_eq!(objects[0].material.reflective, 0.1);
+        assert!(objects[0].material.fresnel_weighted());
+        assert_eq!(objects[0].material.refractive_index, 1.4);
+        assert_eq!(
+            objects[1].material.clear_coat,
+            Some(ClearCoat::new(1.0, 1.5, 0.1))
+        );
+        assert!(objects[1].material.pattern.is_some());
+        assert_eq!(objects[1].material.anisotropy, 0.8);
+        assert_eq!(objects[1].material.tangents, Tangents::Circular);
+        assert_eq!(
+            objects[1].material.reflection_tint(),
+            Color::new(1.0, 0.5, 0.0)
+        );
+        assert!(objects[1].single_sided);
+        assert!(!objects[0].single_sided);
+        let cylinder = objects[2].as_cylinder();
+        assert_eq!(cylinder.minimum, 0.0);
+        assert_eq!(cylinder.maximum, 2.0);
+        assert!(cylinder.closed);
+        assert_eq!(objects[2].transform, rotation_z(PI / 2.0));
+        assert_eq!(objects[3].as_quad().width, 4.0);
+        assert_eq!(objects[3].as_quad().height, 2.0);
+        let pattern = objects[4].material.pattern.as_ref().unwrap();
+        let facing = Tuple::vector(0.0, 0.0, -1.0);
+        let c = pattern.color_at_surface(
+            &objects[4],
+            Tuple::point(0.0, 0.0, -1.0),
+            facing,
+            facing,
+            0.0,
+        );
+        assert_eq!(c, Color::new(1.0, 1.0, 1.0));
+        assert!(parse("sphere pattern-space world\n").is_err());
+        let pattern = objects[5].material.pattern.as_ref().unwrap();
+        let top = Tuple::vector(0.0, 1.0, 0.0);
+        let c = pattern.color_at_surface(&objects[5], Tuple::point(1.5, 1.0, 0.5), top, top, 0.0);
+        assert_eq!(c, Color::new(0.0, 0.0, 0.0));
+        let displaced = objects[6].as_displaced_surface();
+        assert_eq!(displaced.base, DisplacementBase::Plane);
+        assert_eq!(displaced.scale, 0.5);
+        assert_eq!(displaced.height_at(Tuple::point(3.0, 0.0, 0.0)), 0.0);
+        assert_eq!(objects[6].transform, translation(0.0, -1.0, 0.0));
+        assert!(parse("displaced cube 1 stripe 1 1 1 0 0 0 1\n").is_err());
+        assert_eq!(objects[7].as_curve().points[3], Tuple::point(2.0, 2.0, 0.0));
+        assert_eq!(objects[7].as_curve().radii, (0.05, 0.01));
+        assert_eq!(objects[7].material.color, Color::new(0.4, 0.3, 0.1));
+        assert_eq!(objects[8].as_prism().polygon.len(), 6);
+        assert_eq!(objects[8].as_prism().height, 0.5);
+        assert!(parse("displaced plane 1 color 1 0 0\n").is_err());
+    }
+
+    #[test]
+    fn reporting_errors_with_line_numbers() {
+        assert_eq!(
+            parse("sphere\nteapot\n").err(),
+            Some(String::from("line 2: unknown directive 'teapot'"))
+        );
+        assert_eq!(
+            parse("sphere color 1 0\n").err(),
+            Some(String::from("line 1: missing number"))
+        );
+        assert_eq!(
+            parse("cube diffuse high\n").err(),
+            Some(String::from("line 1: invalid number 'high'"))
+        );
+        assert_eq!(
+            parse("obj missing.obj\n").err(),
+            Some(String::from("line 1: obj file 'missing.obj' not found"))
+        );
+    }
+}
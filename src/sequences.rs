@@ -0,0 +1,152 @@
+use crate::cameras::{Camera, PartialRender};
+use crate::canvas::Canvas;
+use crate::render_contexts::RenderContext;
+use crate::worlds::World;
+use std::path::Path;
+use std::sync::mpsc;
+use std::thread;
+
+// Renders the frames of an animation, calling `update` on the world before
+// each frame so that it can move objects, lights or materials. All the frames
+// share the same render threads, and the rows of a frame whose render panicked
+// are left out of it.
+#[derive(Debug, Clone, Copy)]
+pub struct Sequence {
+    pub camera: Camera,
+    pub frames: usize,
+}
+
+impl Sequence {
+    pub fn new(camera: Camera, frames: usize) -> Sequence {
+        Sequence { camera, frames }
+    }
+
+    pub fn render_each<U, F>(&self, world: &mut World, mut update: U, mut on_frame: F)
+    where
+        U: FnMut(&mut World, usize),
+        F: FnMut(PartialRender, usize),
+    {
+        let context = RenderContext::new(self.camera.threads);
+        for frame in 0..self.frames {
+            update(world, frame);
+            on_frame(self.camera.render_in(world, &context), frame);
+        }
+    }
+
+    pub fn render<U>(&self, world: &mut World, update: U) -> Vec<PartialRender>
+    where
+        U: FnMut(&mut World, usize),
+    {
+        let mut images = Vec::with_capacity(self.frames);
+        self.render_each(world, update, |image, _| images.push(image));
+        images
+    }
+
+    // Frames are written to `frame_0000.png`, `frame_0001.png`... by a second
+    // thread, while the next frame is rendered. A frame with missing rows stops
+    // the sequence with an error.
+    pub fn write<U>(&self, world: &mut World, directory: &Path, mut update: U) -> Result<(), String>
+    where
+        U: FnMut(&mut World, usize),
+    {
+        let (sender, receiver) = mpsc::sync_channel::<(Canvas, usize)>(1);
+        thread::scope(|scope| {
+            let writer = scope.spawn(move || {
+                for (image, frame) in receiver {
+                    let path = directory.join(format!("frame_{:04}.png", frame));
+                    std::fs::write(&path, image.to_png())
+                        .map_err(|e| format!("{}: {}", path.display(), e))?;
+                }
+                Ok(())
+            });
+            let context = RenderContext::new(self.camera.threads);
+            let mut rendered = Ok(());
+            for frame in 0..self.frames {
+                update(world, frame);
+                let render = self.camera.render_in(world, &context);
+                if !render.is_complete() {
+                    rendered = Err(format!("frame {}: some rows failed to render", frame));
+                    break;
+                }
+                // A failed writer stops receiving, its error is reported below.
+                if sender.send((render.image, frame)).is_err() {
+                    break;
+                }
+            }
+            drop(sender);
+            writer.join().unwrap().and(rendered)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::colors::Color;
+    use crate::floats::Float;
+    use crate::transformations::view_transform;
+    use crate::tuples::Tuple;
+    use crate::worlds::tests::default_world;
+    use std::sync::Arc;
+
+    fn sequence(frames: usize) -> Sequence {
+        let from = Tuple::point(0.0, 0.0, -5.0);
+        let to = Tuple::point(0.0, 0.0, 0.0);
+        let up = Tuple::vector(0.0, 1.0, 0.0);
+        let mut camera = Camera::new(5, 5, 1.0, 0.5, view_transform(from, to, up));
+        camera.oversampling = 1;
+        Sequence::new(camera, frames)
+    }
+
+    fn redden(world: &mut World, frame: usize) {
+        let object = Arc::make_mut(&mut world.objects[0]);
+        object.material.color = Color::new(frame as Float * 0.5, 0.0, 0.0);
+    }
+
+    #[test]
+    fn a_sequence_updates_the_world_before_each_frame() {
+        let mut world = default_world();
+        let mut updated = vec![];
+        let images = sequence(3).render(&mut world, |w, frame| {
+            updated.push(frame);
+            redden(w, frame);
+        });
+        assert_eq!(updated, vec![0, 1, 2]);
+        assert_eq!(images.len(), 3);
+        assert!(images.iter().all(|f| f.is_complete()));
+        assert_eq!(images[0].image.pixel_at(2, 2).red(), 0.0);
+        assert!(images[2].image.pixel_at(2, 2).red() > images[1].image.pixel_at(2, 2).red());
+    }
+
+    #[test]
+    fn writing_a_sequence_numbers_the_frames() {
+        let directory =
+            std::env::temp_dir().join(format!("rt_rust_sequence_frames_{}", std::process::id()));
+        std::fs::create_dir_all(&directory).unwrap();
+        let mut world = default_world();
+        sequence(2).write(&mut world, &directory, redden).unwrap();
+        let first = std::fs::read(directory.join("frame_0000.png")).unwrap();
+        let second = std::fs::read(directory.join("frame_0001.png")).unwrap();
+        assert!(first.starts_with(b"\x89PNG\r\n\x1a\n"));
+        assert_ne!(first, second);
+        std::fs::remove_dir_all(&directory).unwrap();
+    }
+
+    #[test]
+    fn a_failed_writer_stops_the_rendering() {
+        let directory =
+            std::env::temp_dir().join(format!("rt_rust_missing_frames_{}", std::process::id()));
+        let mut world = default_world();
+        let mut updated = 0;
+        let result = sequence(10).write(&mut world, &directory, |_, _| updated += 1);
+        assert!(result.unwrap_err().contains("frame_0000.png"));
+        assert!(updated <= 3);
+    }
+
+    #[test]
+    fn writing_into_a_missing_directory_fails() {
+        let directory = std::env::temp_dir().join("rt_rust_missing_sequence_directory");
+        let mut world = default_world();
+        assert!(sequence(2).write(&mut world, &directory, redden).is_err());
+    }
+}
@@ -5,24 +5,40 @@ use crate::objects::Object;
 use crate::rays::Ray;
 use crate::tuples::Tuple;
 
+pub mod capsules;
 pub mod cones;
 pub mod csg;
 pub mod cubes;
+pub mod curves;
 pub mod cylinders;
+pub mod displaced_surfaces;
 pub mod groups;
+pub mod instances;
+pub mod lods;
 pub mod planes;
+pub mod prisms;
+pub mod quads;
+pub mod rounded_cubes;
 pub mod smooth_triangles;
 pub mod spheres;
 pub mod triangles;
 
 #[derive(Debug, Clone, PartialEq)]
 pub enum Shapes {
+    Capsule(capsules::Capsule),
     Cone(cones::Cone),
     Csg(csg::Csg),
     Cube(cubes::Cube),
+    Curve(curves::Curve),
     Cylinder(cylinders::Cylinder),
+    DisplacedSurface(displaced_surfaces::DisplacedSurface),
     Group(groups::Group),
+    Instance(instances::Instance),
+    Lod(lods::Lod),
     Plane(planes::Plane),
+    Prism(prisms::Prism),
+    Quad(quads::Quad),
+    RoundedCube(rounded_cubes::RoundedCube),
     SmoothTriangle(smooth_triangles::SmoothTriangle),
     Sphere(spheres::Sphere),
     Test(TestShape),
@@ -32,12 +48,20 @@ pub enum Shapes {
 impl Shapes {
     pub fn prepare_bounds(&mut self, bounds: &mut Bounds) {
         match self {
+            Shapes::Capsule(capsule) => capsule.prepare_bounds(bounds),
             Shapes::Cone(cone) => cone.prepare_bounds(bounds),
             Shapes::Csg(csg) => csg.prepare_bounds(bounds),
             Shapes::Cube(_) => (),
+            Shapes::Curve(curve) => curve.prepare_bounds(bounds),
             Shapes::Cylinder(cylinder) => cylinder.prepare_bounds(bounds),
+            Shapes::DisplacedSurface(surface) => surface.prepare_bounds(bounds),
             Shapes::Group(group) => group.prepare_bounds(bounds),
+            Shapes::Instance(instance) => instance.prepare_bounds(bounds),
+            Shapes::Lod(lod) => lod.prepare_bounds(bounds),
             Shapes::Plane(plane) => plane.prepare_bounds(bounds),
+            Shapes::Prism(prism) => prism.prepare_bounds(bounds),
+            Shapes::Quad(quad) => quad.prepare_bounds(bounds),
+            Shapes::RoundedCube(_) => (),
             Shapes::SmoothTriangle(triangle) => triangle.prepare_bounds(bounds),
             Shapes::Sphere(_) => (),
             Shapes::Test(_) => (),
@@ -49,10 +73,18 @@ impl Shapes {
         match self {
             Shapes::Csg(csg) => csg.prepare_transform(world_to_object, object_to_world),
             Shapes::Group(group) => group.prepare_transform(world_to_object, object_to_world),
+            Shapes::Lod(lod) => lod.prepare_transform(world_to_object, object_to_world),
             _ => (),
         }
     }
 
+    pub fn is_aggregate(&self) -> bool {
+        matches!(
+            self,
+            Shapes::Csg(_) | Shapes::Group(_) | Shapes::Instance(_) | Shapes::Lod(_)
+        )
+    }
+
     pub fn local_intersect<'a>(
         &'a self,
         ray: &Ray,
@@ -60,12 +92,20 @@ impl Shapes {
         xs: &mut Vec<Intersection<'a>>,
     ) {
         match self {
+            Shapes::Capsule(capsule) => capsule.local_intersect(ray, object, xs),
             Shapes::Cone(cone) => cone.local_intersect(ray, object, xs),
             Shapes::Csg(csg) => csg.local_intersect(ray, object, xs),
             Shapes::Cube(cube) => cube.local_intersect(ray, object, xs),
+            Shapes::Curve(curve) => curve.local_intersect(ray, object, xs),
             Shapes::Cylinder(cylinder) => cylinder.local_intersect(ray, object, xs),
+            Shapes::DisplacedSurface(surface) => surface.local_intersect(ray, object, xs),
             Shapes::Group(group) => group.local_intersect(ray, object, xs),
+            Shapes::Instance(instance) => instance.local_intersect(ray, object, xs),
+            Shapes::Lod(lod) => lod.local_intersect(ray, object, xs),
             Shapes::Plane(plane) => plane.local_intersect(ray, object, xs),
+            Shapes::Prism(prism) => prism.local_intersect(ray, object, xs),
+            Shapes::Quad(quad) => quad.local_intersect(ray, object, xs),
+            Shapes::RoundedCube(cube) => cube.local_intersect(ray, object, xs),
             Shapes::SmoothTriangle(triangle) => triangle.local_intersect(ray, object, xs),
             Shapes::Sphere(sphere) => sphere.local_intersect(ray, object, xs),
             Shapes::Test(test) => test.local_intersect(ray, object, xs),
@@ -73,14 +113,72 @@ impl Shapes {
         };
     }
 
+    pub fn describe(&self) -> String {
+        match self {
+            Shapes::Capsule(capsule) => format!(
+                "capsule ({}, {}, {}) to ({}, {}, {}) radius {}",
+                capsule.p0.x(),
+                capsule.p0.y(),
+                capsule.p0.z(),
+                capsule.p1.x(),
+                capsule.p1.y(),
+                capsule.p1.z(),
+                capsule.radius
+            ),
+            Shapes::Cone(cone) => {
+                format!(
+                    "cone [{}, {}]{}",
+                    cone.minimum,
+                    cone.maximum,
+                    closed(cone.closed)
+                )
+            }
+            Shapes::Csg(csg) => format!("csg {:?}", csg.operation).to_lowercase(),
+            Shapes::Cube(_) => String::from("cube"),
+            Shapes::Curve(curve) => format!("curve radius {} to {}", curve.radii.0, curve.radii.1),
+            Shapes::Cylinder(cylinder) => format!(
+                "cylinder [{}, {}]{}",
+                cylinder.minimum,
+                cylinder.maximum,
+                closed(cylinder.closed)
+            ),
+            Shapes::DisplacedSurface(surface) => {
+                format!("displaced {:?} scale {}", surface.base, surface.scale).to_lowercase()
+            }
+            Shapes::Group(group) => format!("group ({} children)", group.children.len()),
+            Shapes::Instance(_) => String::from("instance"),
+            Shapes::Lod(lod) => format!("lod ({} levels)", lod.levels.len()),
+            Shapes::Plane(_) => String::from("plane"),
+            Shapes::Prism(prism) => format!(
+                "prism ({} sides) height {}",
+                prism.polygon.len(),
+                prism.height
+            ),
+            Shapes::Quad(quad) => format!("quad {}x{}", quad.width, quad.height),
+            Shapes::RoundedCube(cube) => format!("rounded cube radius {}", cube.radius),
+            Shapes::SmoothTriangle(_) => String::from("smooth triangle"),
+            Shapes::Sphere(_) => String::from("sphere"),
+            Shapes::Test(_) => String::from("test"),
+            Shapes::Triangle(_) => String::from("triangle"),
+        }
+    }
+
     pub fn local_normal_at(&self, point: Tuple, hit: &Intersection) -> Tuple {
         match self {
+            Shapes::Capsule(capsule) => capsule.local_normal_at(point),
             Shapes::Cone(cone) => cone.local_normal_at(point),
             Shapes::Csg(csg) => csg.local_normal_at(point),
             Shapes::Cube(cube) => cube.local_normal_at(point),
+            Shapes::Curve(curve) => curve.local_normal_at(point),
             Shapes::Cylinder(cylinder) => cylinder.local_normal_at(point),
+            Shapes::DisplacedSurface(surface) => surface.local_normal_at(point),
             Shapes::Group(group) => group.local_normal_at(point),
+            Shapes::Instance(instance) => instance.local_normal_at(point),
+            Shapes::Lod(lod) => lod.local_normal_at(point),
             Shapes::Plane(plane) => plane.local_normal_at(point),
+            Shapes::Prism(prism) => prism.local_normal_at(point),
+            Shapes::Quad(quad) => quad.local_normal_at(point),
+            Shapes::RoundedCube(cube) => cube.local_normal_at(point),
             Shapes::SmoothTriangle(triangle) => triangle.local_normal_at(point, hit),
             Shapes::Sphere(sphere) => sphere.local_normal_at(point),
             Shapes::Test(test) => test.local_normal_at(point),
@@ -89,6 +187,14 @@ impl Shapes {
     }
 }
 
+fn closed(closed: bool) -> &'static str {
+    if closed {
+        " closed"
+    } else {
+        ""
+    }
+}
+
 #[derive(Debug, Clone, PartialEq)]
 pub struct TestShape;
 
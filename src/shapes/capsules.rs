@@ -0,0 +1,260 @@
+use crate::bounds::Bounds;
+use crate::floats::{Float, EPSILON};
+use crate::intersections::Intersection;
+use crate::objects::Object;
+use crate::rays::Ray;
+use crate::tuples::Tuple;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Capsule {
+    pub p0: Tuple,
+    pub p1: Tuple,
+    pub radius: Float,
+}
+
+impl Capsule {
+    pub fn new(p0: Tuple, p1: Tuple, radius: Float) -> Capsule {
+        Capsule { p0, p1, radius }
+    }
+
+    pub fn prepare_bounds(&mut self, bounds: &mut Bounds) {
+        let r = Tuple::vector(self.radius, self.radius, self.radius);
+        bounds.min = self.p0.min(self.p1) - r;
+        bounds.max = self.p0.max(self.p1) + r;
+    }
+
+    pub fn local_intersect<'a>(
+        &'a self,
+        ray: &Ray,
+        object: &'a Object,
+        xs: &mut Vec<Intersection<'a>>,
+    ) {
+        if let Some((t0, t1)) = self.span(ray) {
+            for t in [t0, t1] {
+                if ray.in_range(t) {
+                    xs.push(Intersection::new(t, object));
+                }
+            }
+        }
+    }
+
+    // Where the ray enters and leaves the capsule.
+    pub fn span(&self, ray: &Ray) -> Option<(Float, Float)> {
+        let mut ts = Vec::with_capacity(6);
+        self.intersect_body(ray, &mut ts);
+        self.intersect_cap(ray, self.p0, |h| h <= 0.0, &mut ts);
+        self.intersect_cap(ray, self.p1, |h| h >= 1.0, &mut ts);
+        if ts.is_empty() {
+            return None;
+        }
+        let t0 = ts.iter().cloned().fold(Float::INFINITY, Float::min);
+        let t1 = ts.iter().cloned().fold(Float::NEG_INFINITY, Float::max);
+        Some((t0, t1))
+    }
+
+    // Signed distance from the surface.
+    pub fn distance(&self, point: Tuple) -> Float {
+        self.local_normal_at(point).magnitude() - self.radius
+    }
+
+    pub fn local_normal_at(&self, local_point: Tuple) -> Tuple {
+        let closest = self.p0 + self.axis() * self.project(local_point).clamp(0.0, 1.0);
+        local_point - closest
+    }
+
+    fn axis(&self) -> Tuple {
+        self.p1 - self.p0
+    }
+
+    fn project(&self, point: Tuple) -> Float {
+        let axis = self.axis();
+        let length2 = axis.dot(axis);
+        if length2 < EPSILON {
+            return 0.0;
+        }
+        (point - self.p0).dot(axis) / length2
+    }
+
+    fn intersect_body(&self, ray: &Ray, ts: &mut Vec<Float>) {
+        let axis = self.axis();
+        let length2 = axis.dot(axis);
+        if length2 < EPSILON {
+            return;
+        }
+        let oa = ray.origin - self.p0;
+        let d_axis = ray.direction.dot(axis);
+        let o_axis = oa.dot(axis);
+        let a = length2 * ray.direction.dot(ray.direction) - d_axis * d_axis;
+        if a.abs() < EPSILON {
+            return;
+        }
+        let b = length2 * oa.dot(ray.direction) - o_axis * d_axis;
+        let c = length2 * (oa.dot(oa) - self.radius.powi(2)) - o_axis * o_axis;
+        let discriminant = b * b - a * c;
+        if discriminant < 0.0 {
+            return;
+        }
+        for t in [
+            (-b - discriminant.sqrt()) / a,
+            (-b + discriminant.sqrt()) / a,
+        ] {
+            let h = (o_axis + t * d_axis) / length2;
+            if (0.0..=1.0).contains(&h) {
+                ts.push(t);
+            }
+        }
+    }
+
+    fn intersect_cap(
+        &self,
+        ray: &Ray,
+        center: Tuple,
+        on_cap: impl Fn(Float) -> bool,
+        ts: &mut Vec<Float>,
+    ) {
+        let center_to_ray = ray.origin - center;
+        let a = ray.direction.dot(ray.direction);
+        let b = 2.0 * ray.direction.dot(center_to_ray);
+        let c = center_to_ray.dot(center_to_ray) - self.radius.powi(2);
+        let discriminant = b * b - 4.0 * a * c;
+        if discriminant < 0.0 {
+            return;
+        }
+        for t in [
+            (-b - discriminant.sqrt()) / (2.0 * a),
+            (-b + discriminant.sqrt()) / (2.0 * a),
+        ] {
+            if on_cap(self.project(ray.position(t))) {
+                ts.push(t);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::floats::equals;
+
+    fn capsule() -> Object {
+        Object::new_capsule(
+            Tuple::point(0.0, -1.0, 0.0),
+            Tuple::point(0.0, 1.0, 0.0),
+            0.5,
+        )
+    }
+
+    #[test]
+    fn a_ray_strikes_a_capsule() {
+        let shape = capsule();
+        let examples = [
+            (
+                Tuple::point(0.0, 0.0, -5.0),
+                Tuple::vector(0.0, 0.0, 1.0),
+                4.5,
+                5.5,
+            ),
+            (
+                Tuple::point(0.0, 5.0, 0.0),
+                Tuple::vector(0.0, -1.0, 0.0),
+                3.5,
+                6.5,
+            ),
+            (
+                Tuple::point(0.0, 1.25, -5.0),
+                Tuple::vector(0.0, 0.0, 1.0),
+                5.0 - 0.4330127,
+                5.0 + 0.4330127,
+            ),
+            (
+                Tuple::point(0.0, -1.5, -5.0),
+                Tuple::vector(0.0, 0.0, 1.0),
+                5.0,
+                5.0,
+            ),
+        ];
+        for (origin, direction, t0, t1) in examples {
+            let r = Ray::new(origin, direction);
+            let mut xs = Vec::new();
+            shape.as_capsule().local_intersect(&r, &shape, &mut xs);
+            assert_eq!(xs.len(), 2);
+            assert!(equals(xs[0].t, t0));
+            assert!(equals(xs[1].t, t1));
+        }
+    }
+
+    #[test]
+    fn a_ray_misses_a_capsule() {
+        let shape = capsule();
+        let examples = [
+            (Tuple::point(0.6, 0.0, -5.0), Tuple::vector(0.0, 0.0, 1.0)),
+            (Tuple::point(0.0, 1.6, -5.0), Tuple::vector(0.0, 0.0, 1.0)),
+            (Tuple::point(0.4, 1.4, -5.0), Tuple::vector(0.0, 0.0, 1.0)),
+        ];
+        for (origin, direction) in examples {
+            let r = Ray::new(origin, direction);
+            let mut xs = Vec::new();
+            shape.as_capsule().local_intersect(&r, &shape, &mut xs);
+            assert_eq!(xs.len(), 0);
+        }
+    }
+
+    #[test]
+    fn a_ray_inside_a_capsule() {
+        let shape = capsule();
+        let r = Ray::new(Tuple::point(0.0, 0.0, 0.0), Tuple::vector(0.0, 1.0, 0.0));
+        let mut xs = Vec::new();
+        shape.as_capsule().local_intersect(&r, &shape, &mut xs);
+        assert_eq!(xs.iter().map(|x| x.t).collect::<Vec<_>>(), vec![-1.5, 1.5]);
+    }
+
+    #[test]
+    fn intersecting_a_slanted_capsule() {
+        let shape = Object::new_capsule(
+            Tuple::point(-1.0, -1.0, 0.0),
+            Tuple::point(1.0, 1.0, 0.0),
+            0.5,
+        );
+        let r = Ray::new(
+            Tuple::point(2.0, -2.0, 0.0),
+            Tuple::vector(-1.0, 1.0, 0.0).normalize(),
+        );
+        let mut xs = Vec::new();
+        shape.as_capsule().local_intersect(&r, &shape, &mut xs);
+        assert_eq!(xs.len(), 2);
+        assert!(equals(xs[0].t, 8.0_f32.sqrt() - 0.5));
+        assert!(equals(xs[1].t, 8.0_f32.sqrt() + 0.5));
+    }
+
+    #[test]
+    fn the_normal_on_a_capsule() {
+        let shape = Capsule::new(
+            Tuple::point(0.0, -1.0, 0.0),
+            Tuple::point(0.0, 1.0, 0.0),
+            0.5,
+        );
+        let examples = [
+            (Tuple::point(0.5, 0.0, 0.0), Tuple::vector(0.5, 0.0, 0.0)),
+            (Tuple::point(0.0, 0.3, -0.5), Tuple::vector(0.0, 0.0, -0.5)),
+            (Tuple::point(0.0, 1.5, 0.0), Tuple::vector(0.0, 0.5, 0.0)),
+            (Tuple::point(0.0, -1.5, 0.0), Tuple::vector(0.0, -0.5, 0.0)),
+            (Tuple::point(0.3, 1.4, 0.0), Tuple::vector(0.3, 0.4, 0.0)),
+        ];
+        for (point, normal) in examples {
+            assert_eq!(shape.local_normal_at(point), normal);
+        }
+    }
+
+    #[test]
+    fn the_bounds_of_a_capsule() {
+        let mut shape = Capsule::new(
+            Tuple::point(-1.0, 2.0, 0.0),
+            Tuple::point(1.0, -2.0, 3.0),
+            0.5,
+        );
+        let mut bounds = Bounds::default();
+        shape.prepare_bounds(&mut bounds);
+        assert_eq!(bounds.min, Tuple::point(-1.5, -2.5, -0.5));
+        assert_eq!(bounds.max, Tuple::point(1.5, 2.5, 3.5));
+    }
+}
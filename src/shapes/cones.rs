@@ -10,6 +10,8 @@ pub struct Cone {
     pub minimum: Float,
     pub maximum: Float,
     pub closed: bool,
+    pub radius_x: Float,
+    pub radius_z: Float,
 }
 
 impl Cone {
@@ -18,9 +20,16 @@ impl Cone {
             minimum: -Float::INFINITY,
             maximum: Float::INFINITY,
             closed: false,
+            radius_x: 1.0,
+            radius_z: 1.0,
         }
     }
 
+    pub fn resize(&mut self, radius_x: Float, radius_z: Float) {
+        self.radius_x = radius_x;
+        self.radius_z = radius_z;
+    }
+
     pub fn truncate(&mut self, min: Float, max: Float, closed: bool) {
         self.minimum = min;
         self.maximum = max;
@@ -28,8 +37,13 @@ impl Cone {
     }
 
     pub fn prepare_bounds(&mut self, bounds: &mut Bounds) {
-        bounds.min = Tuple::point(-1.0, self.minimum, -1.0);
-        bounds.max = Tuple::point(1.0, self.maximum, 1.0);
+        let radius = self.minimum.abs().max(self.maximum.abs());
+        bounds.min = Tuple::point(
+            -radius * self.radius_x,
+            self.minimum,
+            -radius * self.radius_z,
+        );
+        bounds.max = Tuple::point(radius * self.radius_x, self.maximum, radius * self.radius_z);
     }
 
     pub fn local_intersect<'a>(
@@ -38,14 +52,19 @@ impl Cone {
         object: &'a Object,
         xs: &mut Vec<Intersection<'a>>,
     ) {
+        let ray = &self.to_unit_radius(ray);
         self.intersect_sides(ray, object, xs);
         self.intersect_caps(ray, object, xs);
     }
 
     pub fn local_normal_at(&self, local_point: Tuple) -> Tuple {
-        let y = (local_point.x().powi(2) + local_point.z().powi(2)).sqrt();
+        let (x, z) = (
+            local_point.x() / self.radius_x,
+            local_point.z() / self.radius_z,
+        );
+        let y = (x.powi(2) + z.powi(2)).sqrt();
         let y = if local_point.y() > 0.0 { -y } else { y };
-        let dist = local_point.x().powi(2) + local_point.z().powi(2);
+        let dist = x.powi(2) + z.powi(2);
         let rad2 = local_point.y().powi(2);
         if dist < rad2 && local_point.y() >= self.maximum - EPSILON {
             return Tuple::vector(0.0, 1.0, 0.0);
@@ -53,7 +72,20 @@ impl Cone {
         if dist < rad2 && local_point.y() <= self.minimum + EPSILON {
             return Tuple::vector(0.0, -1.0, 0.0);
         }
-        Tuple::vector(local_point.x(), y, local_point.z())
+        Tuple::vector(x / self.radius_x, y, z / self.radius_z)
+    }
+
+    fn to_unit_radius(&self, ray: &Ray) -> Ray {
+        let (rx, rz) = (self.radius_x, self.radius_z);
+        Ray {
+            origin: Tuple::point(ray.origin.x() / rx, ray.origin.y(), ray.origin.z() / rz),
+            direction: Tuple::vector(
+                ray.direction.x() / rx,
+                ray.direction.y(),
+                ray.direction.z() / rz,
+            ),
+            ..*ray
+        }
     }
 
     fn intersect_caps<'a>(&'a self, ray: &Ray, object: &'a Object, xs: &mut Vec<Intersection<'a>>) {
@@ -61,11 +93,11 @@ impl Cone {
             return;
         }
         let t = (self.minimum - ray.origin.y()) / ray.direction.y();
-        if check_cap(ray, t, self.minimum.abs()) {
+        if ray.in_range(t) && check_cap(ray, t, self.minimum.abs()) {
             xs.push(Intersection::new(t, object));
         }
         let t = (self.maximum - ray.origin.y()) / ray.direction.y();
-        if check_cap(ray, t, self.maximum.abs()) {
+        if ray.in_range(t) && check_cap(ray, t, self.maximum.abs()) {
             xs.push(Intersection::new(t, object));
         }
     }
@@ -82,7 +114,9 @@ impl Cone {
         let c = ray.origin.x().powi(2) - ray.origin.y().powi(2) + ray.origin.z().powi(2);
         if equals(a, 0.0) && !equals(b, 0.0) {
             let t = -c / (2.0 * b);
-            xs.push(Intersection::new(t, object));
+            if ray.in_range(t) {
+                xs.push(Intersection::new(t, object));
+            }
             return;
         }
         let disc = b.powi(2) - 4.0 * a * c;
@@ -94,11 +128,11 @@ impl Cone {
         let t1 = (-b + disc.sqrt()) / (2.0 * a);
         let (t0, t1) = (t0.min(t1), t0.max(t1));
         let y0 = ray.origin.y() + t0 * ray.direction.y();
-        if self.minimum - EPSILON < y0 && y0 < self.maximum + EPSILON {
+        if ray.in_range(t0) && self.minimum - EPSILON < y0 && y0 < self.maximum + EPSILON {
             xs.push(Intersection::new(t0, object));
         }
         let y1 = ray.origin.y() + t1 * ray.direction.y();
-        if self.minimum - EPSILON < y1 && y1 < self.maximum + EPSILON {
+        if ray.in_range(t1) && self.minimum - EPSILON < y1 && y1 < self.maximum + EPSILON {
             xs.push(Intersection::new(t1, object));
         }
     }
@@ -113,7 +147,7 @@ impl Default for Cone {
 fn check_cap(ray: &Ray, t: Float, radius: Float) -> bool {
     let x = ray.origin.x() + t * ray.direction.x();
     let z = ray.origin.z() + t * ray.direction.z();
-    x.powi(2) + z.powi(2) <= radius + EPSILON
+    x.powi(2) + z.powi(2) <= radius.powi(2) + EPSILON
 }
 
 #[cfg(test)]
@@ -205,4 +239,54 @@ mod tests {
             assert_eq!(n, normals[i]);
         }
     }
+
+    #[test]
+    fn intersecting_the_caps_of_a_small_cone() {
+        let mut shape = Object::new_cone();
+        shape.as_mut_cone().truncate(0.0, 0.5, true);
+        let r = Ray::new(Tuple::point(0.6, 5.0, 0.0), Tuple::vector(0.0, -1.0, 0.0));
+        let mut xs = Vec::new();
+        shape.as_cone().local_intersect(&r, &shape, &mut xs);
+        assert_eq!(xs.len(), 0);
+        let r = Ray::new(Tuple::point(0.4, 5.0, 0.0), Tuple::vector(0.0, -1.0, 0.0));
+        let mut xs = Vec::new();
+        shape.as_cone().local_intersect(&r, &shape, &mut xs);
+        assert_eq!(xs.len(), 2);
+    }
+
+    #[test]
+    fn intersecting_an_elliptical_cone() {
+        let mut shape = Object::new_cone();
+        shape.as_mut_cone().resize(2.0, 0.5);
+        let along_x = Ray::new(Tuple::point(-5.0, 1.0, 0.0), Tuple::vector(1.0, 0.0, 0.0));
+        let along_z = Ray::new(Tuple::point(0.0, 1.0, -5.0), Tuple::vector(0.0, 0.0, 1.0));
+        let mut xs = Vec::new();
+        shape.as_cone().local_intersect(&along_x, &shape, &mut xs);
+        assert_eq!(xs.iter().map(|x| x.t).collect::<Vec<_>>(), vec![3.0, 7.0]);
+        let mut xs = Vec::new();
+        shape.as_cone().local_intersect(&along_z, &shape, &mut xs);
+        assert_eq!(xs.iter().map(|x| x.t).collect::<Vec<_>>(), vec![4.5, 5.5]);
+    }
+
+    #[test]
+    fn an_elliptical_cone_keeps_the_ray_range() {
+        let mut shape = Object::new_cone();
+        shape.as_mut_cone().resize(2.0, 0.5);
+        let r = Ray::new(Tuple::point(-5.0, 1.0, 0.0), Tuple::vector(1.0, 0.0, 0.0))
+            .with_range(0.0, 5.0);
+        let mut xs = Vec::new();
+        shape.as_cone().local_intersect(&r, &shape, &mut xs);
+        assert_eq!(xs.iter().map(|x| x.t).collect::<Vec<_>>(), vec![3.0]);
+    }
+
+    #[test]
+    fn the_bounds_of_a_cone_with_radii() {
+        let mut shape = Cone::new();
+        shape.resize(2.0, 0.5);
+        shape.truncate(-1.0, 3.0, false);
+        let mut bounds = Bounds::default();
+        shape.prepare_bounds(&mut bounds);
+        assert_eq!(bounds.min, Tuple::point(-6.0, -1.0, -1.5));
+        assert_eq!(bounds.max, Tuple::point(6.0, 3.0, 1.5));
+    }
 }
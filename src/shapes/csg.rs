@@ -15,8 +15,8 @@ pub enum Operation {
 
 #[derive(Debug, Clone, PartialEq)]
 pub struct Csg {
-    operation: Operation,
-    children: Vec<Object>,
+    pub operation: Operation,
+    pub children: Vec<Object>,
 }
 
 impl Csg {
@@ -32,8 +32,7 @@ impl Csg {
         bounds.max = Tuple::point(-Float::INFINITY, -Float::INFINITY, -Float::INFINITY);
         for c in &mut self.children {
             c.prepare_bounds();
-            let transformed_bounds = c.bounds.transform(&c.transform);
-            bounds.merge(&transformed_bounds);
+            bounds.merge(&c.parent_bounds());
         }
     }
 
@@ -55,12 +54,19 @@ impl Csg {
         _object: &'a Object,
         xs: &mut Vec<Intersection<'a>>,
     ) {
+        // Every hit of the children tells when the ray is inside them, so
+        // the range only applies to the combined hits.
+        let line = ray.with_range(Float::NEG_INFINITY, Float::INFINITY);
         let mut xxs = Vec::with_capacity(self.children.len() * 2);
         for c in &self.children {
-            c.intersect(ray, &mut xxs);
+            c.intersect(&line, &mut xxs);
         }
         xxs.sort_by(|a, b| a.t.partial_cmp(&b.t).unwrap());
-        xs.extend(self.filter_intersections(xxs));
+        xs.extend(
+            self.filter_intersections(xxs)
+                .into_iter()
+                .filter(|x| ray.in_range(x.t)),
+        );
     }
 
     pub fn local_normal_at(&self, _point: Tuple) -> Tuple {
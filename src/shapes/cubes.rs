@@ -26,8 +26,11 @@ impl Cube {
         if tmin > tmax {
             return;
         }
-        xs.push(Intersection::new(tmin, object));
-        xs.push(Intersection::new(tmax, object));
+        for t in [tmin, tmax] {
+            if ray.in_range(t) {
+                xs.push(Intersection::new(t, object));
+            }
+        }
     }
 
     pub fn local_normal_at(&self, local_point: Tuple) -> Tuple {
@@ -51,7 +54,7 @@ impl Default for Cube {
     }
 }
 
-fn check_axis(origin: Float, direction: Float) -> (Float, Float) {
+pub fn check_axis(origin: Float, direction: Float) -> (Float, Float) {
     let tmin_numerator = -1.0 - origin;
     let tmax_numerator = 1.0 - origin;
 
@@ -0,0 +1,187 @@
+use crate::bounds::Bounds;
+use crate::floats::Float;
+use crate::intersections::Intersection;
+use crate::objects::Object;
+use crate::rays::Ray;
+use crate::shapes::capsules::Capsule;
+use crate::tuples::Tuple;
+
+const SEGMENTS: usize = 16;
+
+// Cubic Bezier curve swept by a sphere whose radius goes from `radii.0` to
+// `radii.1`, intersected as a chain of capsules.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Curve {
+    pub points: [Tuple; 4],
+    pub radii: (Float, Float),
+    segments: Vec<Capsule>,
+}
+
+impl Curve {
+    pub fn new(points: [Tuple; 4], radius0: Float, radius1: Float) -> Curve {
+        let mut curve = Curve {
+            points,
+            radii: (radius0, radius1),
+            segments: Vec::with_capacity(SEGMENTS),
+        };
+        for i in 0..SEGMENTS {
+            let (t0, t1) = (
+                i as Float / SEGMENTS as Float,
+                (i + 1) as Float / SEGMENTS as Float,
+            );
+            let radius = radius0 + (radius1 - radius0) * (t0 + t1) / 2.0;
+            curve
+                .segments
+                .push(Capsule::new(curve.point_at(t0), curve.point_at(t1), radius));
+        }
+        curve
+    }
+
+    pub fn point_at(&self, t: Float) -> Tuple {
+        let [p0, p1, p2, p3] = self.points;
+        let s = 1.0 - t;
+        let origin = Tuple::point(0.0, 0.0, 0.0);
+        origin
+            + (p0 - origin) * (s * s * s)
+            + (p1 - origin) * (3.0 * s * s * t)
+            + (p2 - origin) * (3.0 * s * t * t)
+            + (p3 - origin) * (t * t * t)
+    }
+
+    pub fn prepare_bounds(&mut self, bounds: &mut Bounds) {
+        *bounds = Bounds::empty();
+        for segment in &mut self.segments {
+            let mut segment_bounds = Bounds::empty();
+            segment.prepare_bounds(&mut segment_bounds);
+            bounds.merge(&segment_bounds);
+        }
+    }
+
+    // Overlapping capsules are merged, so that the ray only enters and
+    // leaves the curve where it crosses its outer surface.
+    pub fn local_intersect<'a>(
+        &'a self,
+        ray: &Ray,
+        object: &'a Object,
+        xs: &mut Vec<Intersection<'a>>,
+    ) {
+        let mut spans: Vec<(Float, Float)> =
+            self.segments.iter().filter_map(|s| s.span(ray)).collect();
+        spans.sort_by(|a, b| a.0.total_cmp(&b.0));
+        let mut push = |(m0, m1): (Float, Float)| {
+            for t in [m0, m1] {
+                if ray.in_range(t) {
+                    xs.push(Intersection::new(t, object));
+                }
+            }
+        };
+        let mut merged: Option<(Float, Float)> = None;
+        for (t0, t1) in spans {
+            merged = match merged {
+                Some((m0, m1)) if t0 <= m1 => Some((m0, m1.max(t1))),
+                Some(span) => {
+                    push(span);
+                    Some((t0, t1))
+                }
+                None => Some((t0, t1)),
+            };
+        }
+        if let Some(span) = merged {
+            push(span);
+        }
+    }
+
+    pub fn local_normal_at(&self, local_point: Tuple) -> Tuple {
+        self.segments
+            .iter()
+            .min_by(|a, b| {
+                let distance = |s: &Capsule| s.distance(local_point).abs();
+                distance(a).total_cmp(&distance(b))
+            })
+            .map_or(Tuple::vector(0.0, 1.0, 0.0), |s| {
+                s.local_normal_at(local_point)
+            })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::floats::equals;
+
+    fn arch() -> Object {
+        Object::new_curve(
+            [
+                Tuple::point(-2.0, 0.0, 0.0),
+                Tuple::point(-2.0, 2.0, 0.0),
+                Tuple::point(2.0, 2.0, 0.0),
+                Tuple::point(2.0, 0.0, 0.0),
+            ],
+            0.1,
+            0.1,
+        )
+    }
+
+    fn ts(object: &Object, ray: Ray) -> Vec<Float> {
+        let mut xs = vec![];
+        object.intersect(&ray, &mut xs);
+        xs.iter().map(|x| x.t).collect()
+    }
+
+    #[test]
+    fn a_curve_goes_through_its_end_points() {
+        let curve = arch();
+        let curve = curve.as_curve();
+        assert_eq!(curve.point_at(0.0), Tuple::point(-2.0, 0.0, 0.0));
+        assert_eq!(curve.point_at(1.0), Tuple::point(2.0, 0.0, 0.0));
+        assert_eq!(curve.point_at(0.5), Tuple::point(0.0, 1.5, 0.0));
+    }
+
+    #[test]
+    fn a_ray_crosses_the_thickness_of_the_curve() {
+        let curve = arch();
+        let down = Tuple::vector(0.0, -1.0, 0.0);
+        let xs = ts(&curve, Ray::new(Tuple::point(0.0, 5.0, 0.0), down));
+        assert_eq!(xs.len(), 2);
+        assert!((xs[0] - 3.4).abs() < 0.01);
+        assert!((xs[1] - 3.6).abs() < 0.01);
+        let across = Tuple::vector(1.0, 0.0, 0.0);
+        let xs = ts(&curve, Ray::new(Tuple::point(-5.0, 0.5, 0.0), across));
+        assert_eq!(xs.len(), 4);
+        let xs = ts(&curve, Ray::new(Tuple::point(0.0, 5.0, 1.0), down));
+        assert!(xs.is_empty());
+    }
+
+    #[test]
+    fn the_normal_points_away_from_the_curve() {
+        let curve = arch();
+        let n = curve
+            .as_curve()
+            .local_normal_at(Tuple::point(0.0, 1.6, 0.0))
+            .normalize();
+        assert!(equals(n.y(), 1.0));
+        let n = curve
+            .as_curve()
+            .local_normal_at(Tuple::point(0.0, 1.5, -0.1))
+            .normalize();
+        assert!(equals(n.z(), -1.0));
+    }
+
+    #[test]
+    fn the_bounds_of_a_curve_include_its_thickness() {
+        let mut curve = Object::new_curve(
+            [
+                Tuple::point(0.0, 0.0, 0.0),
+                Tuple::point(0.0, 1.0, 0.0),
+                Tuple::point(0.0, 2.0, 0.0),
+                Tuple::point(0.0, 3.0, 0.0),
+            ],
+            0.2,
+            0.0,
+        );
+        curve.prepare();
+        assert!(curve.bounds.min.y() < 0.0);
+        assert!(equals(curve.bounds.max.y(), 3.0 + 0.2 / 32.0));
+        assert!(curve.bounds.max.x() > 0.19);
+    }
+}
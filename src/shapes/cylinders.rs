@@ -10,6 +10,8 @@ pub struct Cylinder {
     pub closed: bool,
     pub minimum: Float,
     pub maximum: Float,
+    pub radius_x: Float,
+    pub radius_z: Float,
 }
 
 impl Cylinder {
@@ -18,16 +20,22 @@ impl Cylinder {
             closed: false,
             minimum: -Float::INFINITY,
             maximum: Float::INFINITY,
+            radius_x: 1.0,
+            radius_z: 1.0,
         }
     }
+    pub fn resize(&mut self, radius_x: Float, radius_z: Float) {
+        self.radius_x = radius_x;
+        self.radius_z = radius_z;
+    }
     pub fn truncate(&mut self, min: Float, max: Float, closed: bool) {
         self.minimum = min;
         self.maximum = max;
         self.closed = closed;
     }
     pub fn prepare_bounds(&mut self, bounds: &mut Bounds) {
-        bounds.min = Tuple::point(-1.0, self.minimum, -1.0);
-        bounds.max = Tuple::point(1.0, self.maximum, 1.0);
+        bounds.min = Tuple::point(-self.radius_x, self.minimum, -self.radius_z);
+        bounds.max = Tuple::point(self.radius_x, self.maximum, self.radius_z);
     }
     pub fn local_intersect<'a>(
         &'a self,
@@ -35,29 +43,46 @@ impl Cylinder {
         object: &'a Object,
         xs: &mut Vec<Intersection<'a>>,
     ) {
+        let ray = &self.to_unit_radius(ray);
         self.intersect_sides(ray, object, xs);
         self.intersect_caps(ray, object, xs);
     }
     pub fn local_normal_at(&self, local_point: Tuple) -> Tuple {
-        let dist = local_point.x().powi(2) + local_point.z().powi(2);
+        let (x, z) = (
+            local_point.x() / self.radius_x,
+            local_point.z() / self.radius_z,
+        );
+        let dist = x.powi(2) + z.powi(2);
         if dist < 1.0 && local_point.y() >= self.maximum - EPSILON {
             return Tuple::vector(0.0, 1.0, 0.0);
         }
         if dist < 1.0 && local_point.y() <= self.minimum + EPSILON {
             return Tuple::vector(0.0, -1.0, 0.0);
         }
-        Tuple::vector(local_point.x(), 0.0, local_point.z())
+        Tuple::vector(x / self.radius_x, 0.0, z / self.radius_z)
+    }
+    fn to_unit_radius(&self, ray: &Ray) -> Ray {
+        let (rx, rz) = (self.radius_x, self.radius_z);
+        Ray {
+            origin: Tuple::point(ray.origin.x() / rx, ray.origin.y(), ray.origin.z() / rz),
+            direction: Tuple::vector(
+                ray.direction.x() / rx,
+                ray.direction.y(),
+                ray.direction.z() / rz,
+            ),
+            ..*ray
+        }
     }
     fn intersect_caps<'a>(&'a self, ray: &Ray, object: &'a Object, xs: &mut Vec<Intersection<'a>>) {
         if !self.closed || equals(ray.direction.y(), 0.0) {
             return;
         }
         let t = (self.minimum - ray.origin.y()) / ray.direction.y();
-        if check_cap(ray, t) {
+        if ray.in_range(t) && check_cap(ray, t) {
             xs.push(Intersection::new(t, object));
         }
         let t = (self.maximum - ray.origin.y()) / ray.direction.y();
-        if check_cap(ray, t) {
+        if ray.in_range(t) && check_cap(ray, t) {
             xs.push(Intersection::new(t, object));
         }
     }
@@ -81,11 +106,11 @@ impl Cylinder {
         let t1 = (-b + disc.sqrt()) / (2.0 * a);
         let (t0, t1) = (t0.min(t1), t0.max(t1));
         let y0 = ray.origin.y() + t0 * ray.direction.y();
-        if self.minimum < y0 && y0 < self.maximum {
+        if ray.in_range(t0) && self.minimum < y0 && y0 < self.maximum {
             xs.push(Intersection::new(t0, object));
         }
         let y1 = ray.origin.y() + t1 * ray.direction.y();
-        if self.minimum < y1 && y1 < self.maximum {
+        if ray.in_range(t1) && self.minimum < y1 && y1 < self.maximum {
             xs.push(Intersection::new(t1, object));
         }
     }
@@ -106,7 +131,7 @@ fn check_cap(ray: &Ray, t: Float) -> bool {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::floats::Float;
+    use crate::floats::{Float, SQRT_2};
 
     #[test]
     fn a_ray_misses_a_cylinder() {
@@ -273,4 +298,81 @@ mod tests {
             assert_eq!(n, normals[i]);
         }
     }
+
+    #[test]
+    fn intersecting_a_cylinder_with_a_radius() {
+        let mut cyl = Object::new_cylinder();
+        cyl.as_mut_cylinder().resize(2.0, 2.0);
+        let r = Ray::new(Tuple::point(0.0, 0.0, -5.0), Tuple::vector(0.0, 0.0, 1.0));
+        let mut xs = Vec::new();
+        cyl.as_cylinder().local_intersect(&r, &cyl, &mut xs);
+        assert_eq!(xs.iter().map(|x| x.t).collect::<Vec<_>>(), vec![3.0, 7.0]);
+        let r = Ray::new(Tuple::point(1.5, 0.0, -5.0), Tuple::vector(0.0, 0.0, 1.0));
+        let mut xs = Vec::new();
+        cyl.as_cylinder().local_intersect(&r, &cyl, &mut xs);
+        assert_eq!(xs.len(), 2);
+    }
+
+    #[test]
+    fn intersecting_an_elliptical_cylinder() {
+        let mut cyl = Object::new_cylinder();
+        cyl.as_mut_cylinder().resize(3.0, 0.5);
+        let along_z = Ray::new(Tuple::point(0.0, 0.0, -5.0), Tuple::vector(0.0, 0.0, 1.0));
+        let along_x = Ray::new(Tuple::point(-5.0, 0.0, 0.0), Tuple::vector(1.0, 0.0, 0.0));
+        let mut xs = Vec::new();
+        cyl.as_cylinder().local_intersect(&along_z, &cyl, &mut xs);
+        assert_eq!(xs.iter().map(|x| x.t).collect::<Vec<_>>(), vec![4.5, 5.5]);
+        let mut xs = Vec::new();
+        cyl.as_cylinder().local_intersect(&along_x, &cyl, &mut xs);
+        assert!(equals(xs[0].t, 2.0));
+        assert!(equals(xs[1].t, 8.0));
+    }
+
+    #[test]
+    fn an_elliptical_cylinder_keeps_the_ray_range() {
+        let mut cyl = Object::new_cylinder();
+        cyl.as_mut_cylinder().resize(3.0, 0.5);
+        let r = Ray::new(Tuple::point(0.0, 0.0, -5.0), Tuple::vector(0.0, 0.0, 1.0))
+            .with_range(5.0, 10.0);
+        let mut xs = Vec::new();
+        cyl.as_cylinder().local_intersect(&r, &cyl, &mut xs);
+        assert_eq!(xs.iter().map(|x| x.t).collect::<Vec<_>>(), vec![5.5]);
+    }
+
+    #[test]
+    fn the_caps_of_a_cylinder_with_a_radius() {
+        let mut cyl = Object::new_cylinder();
+        cyl.as_mut_cylinder().resize(2.0, 2.0);
+        cyl.as_mut_cylinder().truncate(0.0, 1.0, true);
+        let r = Ray::new(Tuple::point(1.5, 5.0, 0.0), Tuple::vector(0.0, -1.0, 0.0));
+        let mut xs = Vec::new();
+        cyl.as_cylinder().local_intersect(&r, &cyl, &mut xs);
+        assert_eq!(xs.iter().map(|x| x.t).collect::<Vec<_>>(), vec![5.0, 4.0]);
+        assert_eq!(
+            cyl.as_cylinder()
+                .local_normal_at(Tuple::point(1.5, 1.0, 0.0)),
+            Tuple::vector(0.0, 1.0, 0.0)
+        );
+    }
+
+    #[test]
+    fn the_normal_on_an_elliptical_cylinder() {
+        let mut cyl = Cylinder::new();
+        cyl.resize(2.0, 1.0);
+        let n = cyl.local_normal_at(Tuple::point(2.0, 0.0, 0.0));
+        assert_eq!(n.normalize(), Tuple::vector(1.0, 0.0, 0.0));
+        let n = cyl.local_normal_at(Tuple::point(SQRT_2, 0.0, SQRT_2 / 2.0));
+        assert_eq!(n.normalize(), Tuple::vector(1.0, 0.0, 2.0).normalize());
+    }
+
+    #[test]
+    fn the_bounds_of_a_cylinder_with_radii() {
+        let mut cyl = Cylinder::new();
+        cyl.resize(2.0, 0.5);
+        cyl.truncate(-1.0, 3.0, false);
+        let mut bounds = Bounds::default();
+        cyl.prepare_bounds(&mut bounds);
+        assert_eq!(bounds.min, Tuple::point(-2.0, -1.0, -0.5));
+        assert_eq!(bounds.max, Tuple::point(2.0, 3.0, 0.5));
+    }
 }
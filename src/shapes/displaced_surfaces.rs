@@ -0,0 +1,264 @@
+use crate::bounds::Bounds;
+use crate::floats::{Float, EPSILON};
+use crate::intersections::Intersection;
+use crate::objects::Object;
+use crate::patterns::Pattern;
+use crate::rays::Ray;
+use crate::tuples::Tuple;
+use std::sync::Arc;
+
+const MARCH_STEPS: usize = 256;
+const REFINE_STEPS: usize = 24;
+const GRADIENT_STEP: Float = 0.001;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DisplacementBase {
+    Plane,
+    Sphere,
+}
+
+// A plane or a unit sphere pushed along its normal by `scale` times the
+// brightness of the height pattern, found by marching through the shell
+// that contains every height.
+#[derive(Debug, Clone)]
+pub struct DisplacedSurface {
+    pub base: DisplacementBase,
+    pub height: Arc<Pattern>,
+    pub scale: Float,
+}
+
+impl PartialEq for DisplacedSurface {
+    fn eq(&self, other: &DisplacedSurface) -> bool {
+        self.base == other.base
+            && Arc::ptr_eq(&self.height, &other.height)
+            && self.scale == other.scale
+    }
+}
+
+impl DisplacedSurface {
+    pub fn new(base: DisplacementBase, height: Pattern, scale: Float) -> DisplacedSurface {
+        DisplacedSurface {
+            base,
+            height: Arc::new(height),
+            scale,
+        }
+    }
+
+    pub fn prepare_bounds(&mut self, bounds: &mut Bounds) {
+        let (low, high) = self.shell();
+        match self.base {
+            DisplacementBase::Plane => {
+                bounds.min = Tuple::point(-Float::INFINITY, low - EPSILON, -Float::INFINITY);
+                bounds.max = Tuple::point(Float::INFINITY, high + EPSILON, Float::INFINITY);
+            }
+            DisplacementBase::Sphere => {
+                bounds.min = Tuple::point(-high, -high, -high);
+                bounds.max = Tuple::point(high, high, high);
+            }
+        }
+    }
+
+    pub fn local_intersect<'a>(
+        &'a self,
+        ray: &Ray,
+        object: &'a Object,
+        xs: &mut Vec<Intersection<'a>>,
+    ) {
+        let Some((t0, t1)) = self.shell_range(ray) else {
+            return;
+        };
+        let (t0, t1) = (t0.max(ray.t_min), t1.min(ray.t_max));
+        if t0 >= t1 || !(t1 - t0).is_finite() {
+            return;
+        }
+        let dt = (t1 - t0) / MARCH_STEPS as Float;
+        let mut previous = (t0, self.field(ray.position(t0)));
+        for i in 1..=MARCH_STEPS {
+            let t = t0 + dt * i as Float;
+            let current = (t, self.field(ray.position(t)));
+            if (previous.1 < 0.0) != (current.1 < 0.0) {
+                xs.push(Intersection::new(
+                    self.refine(ray, previous, current),
+                    object,
+                ));
+            }
+            previous = current;
+        }
+    }
+
+    pub fn local_normal_at(&self, local_point: Tuple) -> Tuple {
+        let derivative = |axis: Tuple| {
+            let step = axis * GRADIENT_STEP;
+            self.field(local_point + step) - self.field(local_point - step)
+        };
+        Tuple::vector(
+            derivative(Tuple::vector(1.0, 0.0, 0.0)),
+            derivative(Tuple::vector(0.0, 1.0, 0.0)),
+            derivative(Tuple::vector(0.0, 0.0, 1.0)),
+        )
+    }
+
+    pub fn height_at(&self, point: Tuple) -> Float {
+        let color = self.height.color_at_pattern(point);
+        (color.red() + color.green() + color.blue()) / 3.0
+    }
+
+    // Lowest and highest displacement, outward from the base surface.
+    fn shell(&self) -> (Float, Float) {
+        let offset = match self.base {
+            DisplacementBase::Plane => 0.0,
+            DisplacementBase::Sphere => 1.0,
+        };
+        (offset + self.scale.min(0.0), offset + self.scale.max(0.0))
+    }
+
+    // Negative below the displaced surface, positive above it.
+    fn field(&self, point: Tuple) -> Float {
+        match self.base {
+            DisplacementBase::Plane => {
+                point.y() - self.scale * self.height_at(Tuple::point(point.x(), 0.0, point.z()))
+            }
+            DisplacementBase::Sphere => {
+                let origin = Tuple::point(0.0, 0.0, 0.0);
+                let radius = (point - origin).magnitude();
+                if radius < EPSILON {
+                    return -1.0;
+                }
+                let on_sphere = origin + (point - origin) * (1.0 / radius);
+                radius - (1.0 + self.scale * self.height_at(on_sphere))
+            }
+        }
+    }
+
+    fn shell_range(&self, ray: &Ray) -> Option<(Float, Float)> {
+        let (low, high) = self.shell();
+        match self.base {
+            DisplacementBase::Plane => {
+                if ray.direction.y().abs() < EPSILON {
+                    return None;
+                }
+                let ta = (low - EPSILON - ray.origin.y()) / ray.direction.y();
+                let tb = (high + EPSILON - ray.origin.y()) / ray.direction.y();
+                Some((ta.min(tb), ta.max(tb)))
+            }
+            DisplacementBase::Sphere => {
+                let to_ray = ray.origin - Tuple::point(0.0, 0.0, 0.0);
+                let radius = high + EPSILON;
+                let a = ray.direction.dot(ray.direction);
+                let b = 2.0 * ray.direction.dot(to_ray);
+                let c = to_ray.dot(to_ray) - radius * radius;
+                let discriminant = b * b - 4.0 * a * c;
+                if discriminant < 0.0 {
+                    return None;
+                }
+                let root = discriminant.sqrt();
+                Some(((-b - root) / (2.0 * a), (-b + root) / (2.0 * a)))
+            }
+        }
+    }
+
+    fn refine(&self, ray: &Ray, mut a: (Float, Float), mut b: (Float, Float)) -> Float {
+        for _ in 0..REFINE_STEPS {
+            let t = (a.0 + b.0) / 2.0;
+            let middle = (t, self.field(ray.position(t)));
+            if (middle.1 < 0.0) == (a.1 < 0.0) {
+                a = middle;
+            } else {
+                b = middle;
+            }
+        }
+        (a.0 + b.0) / 2.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::colors::{BLACK, WHITE};
+    use crate::floats::equals;
+    use crate::transformations::scaling;
+
+    fn ts(object: &Object, origin: Tuple, direction: Tuple) -> Vec<Float> {
+        let mut xs = vec![];
+        object.intersect(&Ray::new(origin, direction), &mut xs);
+        let mut ts: Vec<Float> = xs.iter().map(|x| x.t).collect();
+        ts.sort_by(Float::total_cmp);
+        ts
+    }
+
+    #[test]
+    fn a_flat_height_pattern_keeps_the_base_surface() {
+        let flat = Pattern::new_solid(BLACK);
+        let plane = Object::new_displaced_surface(DisplacementBase::Plane, flat.clone(), 1.0);
+        let down = Tuple::vector(0.0, -1.0, 0.0);
+        let xs = ts(&plane, Tuple::point(0.0, 5.0, 0.0), down);
+        assert_eq!(xs.len(), 1);
+        assert!(equals(xs[0], 5.0));
+        let sphere = Object::new_displaced_surface(DisplacementBase::Sphere, flat, 1.0);
+        let xs = ts(
+            &sphere,
+            Tuple::point(0.0, 0.0, -5.0),
+            Tuple::vector(0.0, 0.0, 1.0),
+        );
+        assert_eq!(xs.len(), 2);
+        assert!(equals(xs[0], 4.0));
+        assert!(equals(xs[1], 6.0));
+    }
+
+    #[test]
+    fn a_ray_meets_the_displaced_height() {
+        let stripes = Pattern::new_stripe(WHITE, BLACK);
+        let plane = Object::new_displaced_surface(DisplacementBase::Plane, stripes, 0.5);
+        let down = Tuple::vector(0.0, -1.0, 0.0);
+        let high = ts(&plane, Tuple::point(0.5, 5.0, 0.0), down);
+        let low = ts(&plane, Tuple::point(1.5, 5.0, 0.0), down);
+        assert!(equals(high[0], 4.5));
+        assert!(equals(low[0], 5.0));
+        let across = ts(
+            &plane,
+            Tuple::point(3.5, 1.0, 0.0),
+            Tuple::vector(-1.0, -0.5, 0.0),
+        );
+        assert!(equals(across[0], 1.0));
+        // A grazing ray hits the side of a raised stripe.
+        let side = ts(
+            &plane,
+            Tuple::point(3.5, 0.25, 0.0),
+            Tuple::vector(-1.0, -0.01, 0.0),
+        );
+        let first = side.into_iter().find(|t| *t > 0.0).unwrap();
+        assert!(equals(first, 0.5));
+    }
+
+    #[test]
+    fn the_normal_follows_the_height_slope() {
+        let ramp = Pattern::new_gradient(BLACK, WHITE).with_transform(scaling(4.0, 1.0, 1.0));
+        let plane = Object::new_displaced_surface(DisplacementBase::Plane, ramp, 1.0);
+        let n = plane
+            .as_displaced_surface()
+            .local_normal_at(Tuple::point(2.0, 0.5, 0.0))
+            .normalize();
+        assert!(equals(n.x(), -0.24254));
+        assert!(equals(n.y(), 0.97014));
+        let bumps = Pattern::new_solid(WHITE);
+        let sphere = Object::new_displaced_surface(DisplacementBase::Sphere, bumps, 0.5);
+        let n = sphere
+            .as_displaced_surface()
+            .local_normal_at(Tuple::point(0.0, 1.5, 0.0))
+            .normalize();
+        assert_eq!(n, Tuple::vector(0.0, 1.0, 0.0));
+    }
+
+    #[test]
+    fn the_bounds_contain_the_whole_displacement() {
+        let mut sphere =
+            Object::new_displaced_surface(DisplacementBase::Sphere, Pattern::new_solid(WHITE), 0.5);
+        sphere.prepare();
+        assert_eq!(sphere.bounds.max, Tuple::point(1.5, 1.5, 1.5));
+        let mut plane =
+            Object::new_displaced_surface(DisplacementBase::Plane, Pattern::new_solid(WHITE), -2.0);
+        plane.prepare();
+        assert!(plane.bounds.min.y() < -2.0);
+        assert!(plane.bounds.max.y() > 0.0);
+    }
+}
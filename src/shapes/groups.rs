@@ -1,14 +1,17 @@
-use crate::bounds::Bounds;
+use crate::bounds::{self, Bounds};
 use crate::floats::Float;
 use crate::intersections::Intersection;
+use crate::materials::Material;
 use crate::matrices::Matrix;
-use crate::objects::Object;
+use crate::objects::{update_shared, Object};
 use crate::rays::Ray;
 use crate::tuples::Tuple;
+use std::collections::HashMap;
+use std::sync::Arc;
 
 #[derive(Debug, Clone, PartialEq)]
 pub struct Group {
-    pub children: Vec<Object>,
+    pub children: Vec<Arc<Object>>,
 }
 
 impl Group {
@@ -17,15 +20,27 @@ impl Group {
     }
 
     pub fn add_child(&mut self, object: Object) {
+        self.children.push(Arc::new(object));
+    }
+
+    pub fn add_shared_child(&mut self, object: Arc<Object>) {
         self.children.push(object);
     }
 
+    pub fn set_material_recursive(&mut self, material: &Material) {
+        for c in &mut self.children {
+            Arc::make_mut(c).set_material_recursive(material);
+        }
+    }
+
     pub fn prepare_bounds(&mut self, bounds: &mut Bounds) {
-        bounds.min = Tuple::point(Float::INFINITY, Float::INFINITY, Float::INFINITY);
-        bounds.max = Tuple::point(-Float::INFINITY, -Float::INFINITY, -Float::INFINITY);
+        *bounds = Bounds::empty();
         for c in &mut self.children {
-            c.prepare_bounds();
-            let transformed_bounds = c.bounds.transform(&c.transform);
+            // Shared children are prepared once, before they are shared.
+            if let Some(c) = Arc::get_mut(c) {
+                c.prepare_bounds();
+            }
+            let transformed_bounds = c.parent_bounds();
             // println!("bounds: {:#?}\ntransformed_bounds: {:#?}\n", c.bounds, transformed_bounds);
             bounds.merge(&transformed_bounds);
         }
@@ -34,12 +49,65 @@ impl Group {
 
     pub fn prepare_transform(&mut self, world_to_object: &Matrix<4>, object_to_world: &Matrix<4>) {
         for c in &mut self.children {
-            c.world_to_object = c.transform_inverse * *world_to_object;
-            c.object_to_world = *object_to_world * c.transform_inverse.transpose();
-            c.prepare_transform();
+            let world_to_object = c.transform_inverse * *world_to_object;
+            let changes =
+                |c: &Object| c.world_to_object != world_to_object || c.transforms_changed();
+            update_shared(c, changes, |c| {
+                c.world_to_object = world_to_object;
+                c.object_to_world = *object_to_world * c.transform_inverse.transpose();
+                c.prepare_transform();
+            });
         }
     }
 
+    pub fn divide(&mut self, threshold: usize) {
+        if self.children.len() > threshold {
+            let bounds: Vec<Bounds> = self
+                .children
+                .iter_mut()
+                .map(|c| {
+                    let c = Arc::make_mut(c);
+                    c.prepare_bounds();
+                    c.parent_bounds()
+                })
+                .collect();
+            if let Some((left, right)) = bounds::sah_partition(&bounds) {
+                let children = std::mem::take(&mut self.children);
+                for indices in [left, right] {
+                    if indices.len() == 1 {
+                        self.children.push(children[indices[0]].clone());
+                        continue;
+                    }
+                    let mut subgroup = Object::new_group();
+                    for i in indices {
+                        subgroup
+                            .as_mut_group()
+                            .add_shared_child(children[i].clone());
+                    }
+                    self.add_child(subgroup);
+                }
+            }
+        }
+        for c in &mut self.children {
+            Arc::make_mut(c).divide(threshold);
+        }
+    }
+
+    // Flips the triangles wound against their neighbours, then turns closed
+    // meshes outward. Returns the number of flipped triangles.
+    pub fn recompute_winding(&mut self) -> usize {
+        let mut faces = vec![];
+        for c in &self.children {
+            c.collect_faces(&mut faces);
+        }
+        let flips = consistent_flips(&faces);
+        let mut remaining = flips.iter().copied();
+        for c in &mut self.children {
+            Arc::make_mut(c).flip_faces(&mut remaining);
+        }
+        flips.iter().filter(|&&flip| flip).count()
+    }
+
     pub fn includes(&self, object: &Object) -> bool {
         self.children.iter().any(|c| c.includes(object))
     }
@@ -64,6 +132,84 @@ impl Group {
     }
 }
 
+type VertexKey = [u32; 3];
+
+fn vertex_key(p: Tuple) -> VertexKey {
+    [p.x(), p.y(), p.z()].map(Float::to_bits)
+}
+
+// Each edge is stored once, as its sorted vertex keys, with the faces that
+// use it and whether they traverse it in the sorted direction.
+fn face_edges(face: &[Tuple; 3]) -> [((VertexKey, VertexKey), bool); 3] {
+    [0, 1, 2].map(|i| {
+        let (a, b) = (vertex_key(face[i]), vertex_key(face[(i + 1) % 3]));
+        if a < b {
+            ((a, b), true)
+        } else {
+            ((b, a), false)
+        }
+    })
+}
+
+fn consistent_flips(faces: &[[Tuple; 3]]) -> Vec<bool> {
+    let mut edges: HashMap<(VertexKey, VertexKey), Vec<(usize, bool)>> = HashMap::new();
+    for (i, face) in faces.iter().enumerate() {
+        for (edge, forward) in face_edges(face) {
+            edges.entry(edge).or_default().push((i, forward));
+        }
+    }
+    let mut flips = vec![false; faces.len()];
+    let mut visited = vec![false; faces.len()];
+    for seed in 0..faces.len() {
+        if visited[seed] {
+            continue;
+        }
+        visited[seed] = true;
+        let mut component = vec![seed];
+        let mut stack = vec![seed];
+        while let Some(i) = stack.pop() {
+            for (edge, forward) in face_edges(&faces[i]) {
+                let shared = &edges[&edge];
+                if shared.len() != 2 {
+                    continue;
+                }
+                for &(n, n_forward) in shared {
+                    if visited[n] {
+                        continue;
+                    }
+                    // Consistent neighbours traverse their shared edge in opposite directions.
+                    flips[n] = flips[i] ^ (n_forward == forward);
+                    visited[n] = true;
+                    component.push(n);
+                    stack.push(n);
+                }
+            }
+        }
+        let volume: Float = component
+            .iter()
+            .map(|&i| signed_volume(&faces[i], flips[i]))
+            .sum();
+        if volume < 0.0 {
+            for &i in &component {
+                flips[i] = !flips[i];
+            }
+        }
+    }
+    flips
+}
+
+// Positive when the triangle normal points away from the origin side, so
+// that a closed mesh with outward normals has a positive total.
+fn signed_volume([p1, p2, p3]: &[Tuple; 3], flipped: bool) -> Float {
+    let origin = Tuple::point(0.0, 0.0, 0.0);
+    let volume = (*p1 - origin).dot((*p3 - *p1).cross(*p2 - *p1)) / 6.0;
+    if flipped {
+        -volume
+    } else {
+        volume
+    }
+}
+
 impl Default for Group {
     fn default() -> Group {
         Group::new()
@@ -73,9 +219,26 @@ impl Default for Group {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::transformations::{scaling, translation};
+    use crate::floats::PI;
+    use crate::transformations::{rotation_x, rotation_y, scaling, translation};
     use crate::tuples::Tuple;
 
+    #[test]
+    fn setting_the_material_of_a_group_recursively() {
+        let glass = Material::glass();
+        let mut inner = Object::new_group();
+        inner.as_mut_group().add_child(Object::new_cube());
+        let mut g = Object::new_group();
+        g.as_mut_group().add_child(Object::new_sphere());
+        g.as_mut_group().add_child(inner);
+        g.as_mut_group().set_material_recursive(&glass);
+        let children = &g.as_group().children;
+        assert_eq!(children[0].material, glass);
+        assert_eq!(children[1].material, glass);
+        assert_eq!(children[1].as_group().children[0].material, glass);
+        assert_eq!(g.material, Material::default());
+    }
+
     #[test]
     fn intersecting_a_ray_with_an_empty_group() {
         let r = Ray::new(Tuple::point(0.0, 0.0, 0.0), Tuple::vector(0.0, 0.0, 1.0));
@@ -115,4 +278,160 @@ mod tests {
         g.intersect(&r, &mut xs);
         assert_eq!(xs.len(), 2);
     }
+
+    #[test]
+    fn preparing_a_group_does_not_copy_unshared_children() {
+        let mut g = Object::new_group();
+        g.as_mut_group().add_child(Object::new_sphere());
+        let before = Arc::as_ptr(&g.as_group().children[0]);
+        g.prepare();
+        assert_eq!(Arc::as_ptr(&g.as_group().children[0]), before);
+    }
+
+    #[test]
+    fn preparing_a_group_leaves_shared_children_untouched() {
+        let shared = Arc::new(Object::new_sphere());
+        let mut g = Object::new_group().with_transform(translation(1.0, 0.0, 0.0));
+        g.as_mut_group().add_shared_child(Arc::clone(&shared));
+        g.prepare();
+        assert_eq!(shared.world_to_object, Matrix::identity());
+        assert_eq!(
+            g.as_group().children[0].world_to_object,
+            translation(-1.0, 0.0, 0.0)
+        );
+    }
+
+    #[test]
+    fn dividing_a_group_partitions_its_children() {
+        let mut g = Object::new_group();
+        for x in [-10.0, -7.0, 7.0, 10.0] {
+            g.as_mut_group()
+                .add_child(Object::new_sphere().with_transform(translation(x, 0.0, 0.0)));
+        }
+        g.divide(1);
+        let children = &g.as_group().children;
+        assert_eq!(children.len(), 2);
+        for subgroup in children {
+            assert_eq!(subgroup.as_group().children.len(), 2);
+        }
+        g.prepare();
+        let r = Ray::new(Tuple::point(7.0, 0.0, -5.0), Tuple::vector(0.0, 0.0, 1.0));
+        let mut xs = Vec::new();
+        g.intersect(&r, &mut xs);
+        assert_eq!(xs.iter().map(|x| x.t).collect::<Vec<_>>(), vec![4.0, 6.0]);
+    }
+
+    #[test]
+    fn dividing_a_small_group_does_nothing() {
+        let mut g = Object::new_group();
+        g.as_mut_group().add_child(Object::new_sphere());
+        g.as_mut_group()
+            .add_child(Object::new_sphere().with_transform(translation(5.0, 0.0, 0.0)));
+        let before = g.clone();
+        g.divide(4);
+        assert_eq!(g, before);
+    }
+
+    #[test]
+    fn bounding_spheres_keep_nested_rotations_tight() {
+        let nested = |spheres: bool| {
+            let bound = |o: Object| if spheres { o.with_bounding_sphere() } else { o };
+            let mut inner = bound(Object::new_group())
+                .with_transform(rotation_x(PI / 3.0) * rotation_y(PI / 4.0));
+            inner
+                .as_mut_group()
+                .add_child(bound(Object::new_sphere()).with_transform(rotation_x(PI / 4.0)));
+            let mut outer = Object::new_group();
+            outer.as_mut_group().add_child(inner);
+            outer.prepare();
+            outer.bounds
+        };
+        let loose = nested(false);
+        let tight = nested(true);
+        assert!(loose.max.x() > 1.5);
+        assert_eq!(tight.min, Tuple::point(-1.0, -1.0, -1.0));
+        assert_eq!(tight.max, Tuple::point(1.0, 1.0, 1.0));
+    }
+
+    // A tetrahedron with every face normal pointing out, except for the
+    // faces listed in `reversed`.
+    fn tetrahedron(reversed: &[usize]) -> Object {
+        let o = Tuple::point(0.0, 0.0, 0.0);
+        let a = Tuple::point(1.0, 0.0, 0.0);
+        let b = Tuple::point(0.0, 1.0, 0.0);
+        let c = Tuple::point(0.0, 0.0, 1.0);
+        let mut g = Object::new_group();
+        for (i, [p1, p2, p3]) in [[o, a, b], [o, c, a], [o, b, c], [a, c, b]]
+            .into_iter()
+            .enumerate()
+        {
+            let t = if reversed.contains(&i) {
+                Object::new_triangle(p1, p3, p2)
+            } else {
+                Object::new_triangle(p1, p2, p3)
+            };
+            g.as_mut_group().add_child(t);
+        }
+        g
+    }
+
+    fn points_outward(g: &Object) -> bool {
+        let center = Tuple::point(0.25, 0.25, 0.25);
+        g.as_group().children.iter().all(|c| {
+            let t = c.as_triangle();
+            t.normal.dot(t.p1 - center) > 0.0
+        })
+    }
+
+    #[test]
+    fn a_consistently_wound_mesh_is_left_alone() {
+        let mut g = tetrahedron(&[]);
+        assert!(points_outward(&g));
+        assert_eq!(g.as_mut_group().recompute_winding(), 0);
+        assert!(points_outward(&g));
+    }
+
+    #[test]
+    fn recomputing_the_winding_of_inconsistent_triangles() {
+        let mut g = tetrahedron(&[1, 3]);
+        assert!(!points_outward(&g));
+        assert_eq!(g.as_mut_group().recompute_winding(), 2);
+        assert!(points_outward(&g));
+    }
+
+    #[test]
+    fn recomputing_the_winding_turns_closed_meshes_outward() {
+        let mut g = tetrahedron(&[0, 1, 2, 3]);
+        assert_eq!(g.as_mut_group().recompute_winding(), 4);
+        assert!(points_outward(&g));
+    }
+
+    #[test]
+    fn recomputing_the_winding_across_subgroups() {
+        let mut g = tetrahedron(&[2]);
+        g.divide(1);
+        assert_eq!(g.as_mut_group().recompute_winding(), 1);
+        let mut faces = vec![];
+        g.collect_faces(&mut faces);
+        assert_eq!(faces.len(), 4);
+        let center = Tuple::point(0.25, 0.25, 0.25);
+        assert!(faces
+            .iter()
+            .all(|[p1, p2, p3]| (*p3 - *p1).cross(*p2 - *p1).dot(*p1 - center) > 0.0));
+    }
+
+    #[test]
+    fn preparing_a_group_only_copies_the_shared_children_that_change() {
+        let sphere = Object::new_sphere().into_shared();
+        let mut g = Object::new_group();
+        g.as_mut_group().add_shared_child(Arc::clone(&sphere));
+        g.prepare();
+        assert!(Arc::ptr_eq(&g.as_group().children[0], &sphere));
+        let mut g = g.with_transform(translation(1.0, 0.0, 0.0));
+        g.prepare();
+        let child = &g.as_group().children[0];
+        assert!(!Arc::ptr_eq(child, &sphere));
+        assert_eq!(child.world_to_object, translation(-1.0, 0.0, 0.0));
+        assert_eq!(sphere.world_to_object, Matrix::identity());
+    }
 }
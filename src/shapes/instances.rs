@@ -0,0 +1,144 @@
+use crate::bounds::Bounds;
+use crate::intersections::Intersection;
+use crate::objects::Object;
+use crate::rays::Ray;
+use crate::tuples::Tuple;
+use std::sync::Arc;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Instance {
+    pub object: Arc<Object>,
+}
+
+impl Instance {
+    pub fn new(object: Arc<Object>) -> Instance {
+        Instance { object }
+    }
+
+    pub fn prepare_bounds(&mut self, bounds: &mut Bounds) {
+        // Shared geometry is prepared once, before it is shared, and never copied here.
+        if let Some(object) = Arc::get_mut(&mut self.object) {
+            object.prepare();
+        }
+        *bounds = self.object.parent_bounds();
+    }
+
+    pub fn includes(&self, object: &Object) -> bool {
+        self.object.includes(object)
+    }
+
+    pub fn local_intersect<'b>(
+        &'b self,
+        ray: &Ray,
+        object: &'b Object,
+        xs: &mut Vec<Intersection<'b>>,
+    ) {
+        if !object.bounds.intersect(ray) {
+            return;
+        }
+        let start = xs.len();
+        self.object.intersect(ray, xs);
+        for x in &mut xs[start..] {
+            if x.instance.is_none() {
+                x.instance = Some(object);
+            }
+        }
+    }
+
+    pub fn local_normal_at(&self, _local_point: Tuple) -> Tuple {
+        panic!("We should never call local_normal_at on an instance");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::colors::Color;
+    use crate::floats::SQRT_2;
+    use crate::matrices::Matrix;
+    use crate::transformations::{scaling, translation};
+
+    fn shared_sphere() -> Arc<Object> {
+        Object::new_sphere()
+            .with_transform(scaling(2.0, 2.0, 2.0))
+            .into_shared()
+    }
+
+    #[test]
+    fn instances_share_the_referenced_geometry() {
+        let sphere = shared_sphere();
+        let a = Object::new_instance(Arc::clone(&sphere), translation(5.0, 0.0, 0.0));
+        let b = Object::new_instance(Arc::clone(&sphere), translation(-5.0, 0.0, 0.0));
+        assert!(Arc::ptr_eq(
+            &a.as_instance().object,
+            &b.as_instance().object
+        ));
+        assert_eq!(Arc::strong_count(&sphere), 3);
+    }
+
+    #[test]
+    fn the_bounds_of_an_instance_are_the_transformed_geometry_bounds() {
+        let mut i = Object::new_instance(shared_sphere(), translation(5.0, 0.0, 0.0));
+        i.prepare();
+        assert_eq!(i.bounds.min, Tuple::point(-2.0, -2.0, -2.0));
+        assert_eq!(i.bounds.max, Tuple::point(2.0, 2.0, 2.0));
+        assert_eq!(i.parent_bounds().min, Tuple::point(3.0, -2.0, -2.0));
+        assert_eq!(i.parent_bounds().max, Tuple::point(7.0, 2.0, 2.0));
+    }
+
+    #[test]
+    fn intersecting_an_instance_records_the_instance() {
+        let mut i = Object::new_instance(shared_sphere(), translation(5.0, 0.0, 0.0));
+        i.prepare();
+        let r = Ray::new(Tuple::point(5.0, 0.0, -5.0), Tuple::vector(0.0, 0.0, 1.0));
+        let mut xs = Vec::new();
+        i.intersect(&r, &mut xs);
+        assert_eq!(xs.iter().map(|x| x.t).collect::<Vec<_>>(), vec![3.0, 7.0]);
+        for x in &xs {
+            assert!(std::ptr::eq(x.object, &*i.as_instance().object));
+            assert!(std::ptr::eq(x.shading_object(), &i));
+        }
+    }
+
+    #[test]
+    fn a_ray_missing_an_instance_misses_the_geometry() {
+        let mut i = Object::new_instance(shared_sphere(), translation(5.0, 0.0, 0.0));
+        i.prepare();
+        let r = Ray::new(Tuple::point(0.0, 0.0, -5.0), Tuple::vector(0.0, 0.0, 1.0));
+        let mut xs = Vec::new();
+        i.intersect(&r, &mut xs);
+        assert!(xs.is_empty());
+    }
+
+    #[test]
+    fn the_normal_on_an_instance_uses_the_instance_transform() {
+        let mut i = Object::new_instance(shared_sphere(), translation(5.0, 0.0, 0.0));
+        i.prepare();
+        let r = Ray::new(
+            Tuple::point(5.0, 5.0, -5.0),
+            Tuple::vector(0.0, -1.0, 1.0).normalize(),
+        );
+        let mut xs = Vec::new();
+        i.intersect(&r, &mut xs);
+        let comps = xs[0].prepare_computations(&r, &xs);
+        assert_eq!(
+            comps.normalv,
+            Tuple::vector(0.0, 1.0 / SQRT_2, -1.0 / SQRT_2)
+        );
+    }
+
+    #[test]
+    fn instances_can_override_the_material_of_the_geometry() {
+        let mut sphere = Object::new_sphere();
+        sphere.material.color = Color::new(1.0, 0.0, 0.0);
+        let sphere = sphere.into_shared();
+        let plain = Object::new_instance(Arc::clone(&sphere), Matrix::identity());
+        assert_eq!(plain.material.color, Color::new(1.0, 0.0, 0.0));
+        let mut blue = Object::new_instance(sphere, Matrix::identity());
+        blue.material.color = Color::new(0.0, 0.0, 1.0);
+        assert_eq!(
+            blue.as_instance().object.material.color,
+            Color::new(1.0, 0.0, 0.0)
+        );
+    }
+}
@@ -0,0 +1,140 @@
+use crate::bounds::Bounds;
+use crate::floats::Float;
+use crate::intersections::Intersection;
+use crate::materials::Material;
+use crate::matrices::Matrix;
+use crate::objects::{update_shared, Object};
+use crate::rays::Ray;
+use crate::tuples::Tuple;
+use std::sync::Arc;
+
+// Levels of detail of the same object, each one used from its distance on,
+// measured from the ray origin to the center of the bounds in object space.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Lod {
+    pub levels: Vec<(Float, Arc<Object>)>,
+}
+
+impl Lod {
+    pub fn new(object: Object) -> Lod {
+        Lod {
+            levels: vec![(0.0, Arc::new(object))],
+        }
+    }
+
+    pub fn add_level(&mut self, distance: Float, object: Object) {
+        let index = self.levels.partition_point(|(d, _)| *d <= distance);
+        self.levels.insert(index, (distance, Arc::new(object)));
+    }
+
+    pub fn set_material_recursive(&mut self, material: &Material) {
+        for (_, c) in &mut self.levels {
+            Arc::make_mut(c).set_material_recursive(material);
+        }
+    }
+
+    pub fn prepare_bounds(&mut self, bounds: &mut Bounds) {
+        *bounds = Bounds::empty();
+        for (_, c) in &mut self.levels {
+            // Shared levels are prepared once, before they are shared.
+            if let Some(c) = Arc::get_mut(c) {
+                c.prepare_bounds();
+            }
+            bounds.merge(&c.parent_bounds());
+        }
+    }
+
+    pub fn prepare_transform(&mut self, world_to_object: &Matrix<4>, object_to_world: &Matrix<4>) {
+        for (_, c) in &mut self.levels {
+            let world_to_object = c.transform_inverse * *world_to_object;
+            let changes =
+                |c: &Object| c.world_to_object != world_to_object || c.transforms_changed();
+            update_shared(c, changes, |c| {
+                c.world_to_object = world_to_object;
+                c.object_to_world = *object_to_world * c.transform_inverse.transpose();
+                c.prepare_transform();
+            });
+        }
+    }
+
+    pub fn includes(&self, object: &Object) -> bool {
+        self.levels.iter().any(|(_, c)| c.includes(object))
+    }
+
+    pub fn level_at(&self, distance: Float) -> &Object {
+        let index = self.levels.partition_point(|(d, _)| *d <= distance);
+        &self.levels[index.saturating_sub(1)].1
+    }
+
+    pub fn local_intersect<'b>(
+        &'b self,
+        ray: &Ray,
+        object: &'b Object,
+        xs: &mut Vec<Intersection<'b>>,
+    ) {
+        if !object.bounds.intersect(ray) {
+            return;
+        }
+        let distance = (object.bounds.centroid() - ray.origin).magnitude();
+        self.level_at(distance).intersect(ray, xs);
+    }
+
+    pub fn local_normal_at(&self, _local_point: Tuple) -> Tuple {
+        panic!("We should never call local_normal_at on a lod");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::transformations::{scaling, translation};
+
+    fn sphere_levels() -> Object {
+        let mut lod = Object::new_lod(Object::new_sphere());
+        lod.as_mut_lod().add_level(
+            20.0,
+            Object::new_cube().with_transform(scaling(0.5, 0.5, 0.5)),
+        );
+        lod.as_mut_lod().add_level(10.0, Object::new_cube());
+        lod
+    }
+
+    #[test]
+    fn levels_are_sorted_by_distance() {
+        let lod = sphere_levels();
+        let distances: Vec<Float> = lod.as_lod().levels.iter().map(|(d, _)| *d).collect();
+        assert_eq!(distances, vec![0.0, 10.0, 20.0]);
+    }
+
+    #[test]
+    fn the_level_depends_on_the_distance() {
+        let lod = sphere_levels();
+        let levels = &lod.as_lod().levels;
+        assert!(std::ptr::eq(lod.as_lod().level_at(5.0), &*levels[0].1));
+        assert!(std::ptr::eq(lod.as_lod().level_at(10.0), &*levels[1].1));
+        assert!(std::ptr::eq(lod.as_lod().level_at(100.0), &*levels[2].1));
+    }
+
+    #[test]
+    fn the_bounds_of_a_lod_contain_every_level() {
+        let mut lod = sphere_levels();
+        lod.prepare();
+        assert_eq!(lod.bounds.min, Tuple::point(-1.0, -1.0, -1.0));
+        assert_eq!(lod.bounds.max, Tuple::point(1.0, 1.0, 1.0));
+    }
+
+    #[test]
+    fn far_rays_intersect_the_coarse_levels() {
+        let mut lod = sphere_levels().with_transform(translation(0.0, 0.0, 5.0));
+        lod.prepare();
+        let direction = Tuple::vector(0.0, 0.0, 1.0);
+        let ts = |z: Float| {
+            let mut xs = vec![];
+            lod.intersect(&Ray::new(Tuple::point(0.0, 0.0, z), direction), &mut xs);
+            xs.iter().map(|x| x.t).collect::<Vec<_>>()
+        };
+        assert_eq!(ts(0.0), vec![4.0, 6.0]);
+        assert_eq!(ts(-10.0), vec![14.0, 16.0]);
+        assert_eq!(ts(-20.0), vec![24.5, 25.5]);
+    }
+}
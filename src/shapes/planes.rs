@@ -29,7 +29,9 @@ impl Plane {
             return;
         }
         let t = -ray.origin.y() / ray.direction.y();
-        xs.push(Intersection::new(t, object));
+        if ray.in_range(t) {
+            xs.push(Intersection::new(t, object));
+        }
     }
 
     pub fn local_normal_at(&self, _point: Tuple) -> Tuple {
@@ -0,0 +1,252 @@
+use crate::bounds::Bounds;
+use crate::floats::{Float, EPSILON, PI};
+use crate::intersections::Intersection;
+use crate::objects::Object;
+use crate::rays::Ray;
+use crate::tuples::Tuple;
+
+// A polygon of the xz plane, convex or not, extruded from y = 0 to `height`
+// and closed by two caps.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Prism {
+    pub polygon: Vec<(Float, Float)>,
+    pub height: Float,
+    // 1 when the polygon turns counterclockwise from x to z, -1 otherwise.
+    orientation: Float,
+}
+
+impl Prism {
+    pub fn new(polygon: Vec<(Float, Float)>, height: Float) -> Prism {
+        assert!(polygon.len() >= 3, "A prism needs at least 3 sides");
+        let area: Float = edges(&polygon)
+            .map(|((ax, az), (bx, bz))| ax * bz - bx * az)
+            .sum();
+        Prism {
+            polygon,
+            height,
+            orientation: if area < 0.0 { -1.0 } else { 1.0 },
+        }
+    }
+
+    pub fn new_regular(sides: usize, height: Float) -> Prism {
+        let polygon = (0..sides)
+            .map(|i| {
+                let angle = 2.0 * PI * i as Float / sides as Float;
+                (angle.sin(), angle.cos())
+            })
+            .collect();
+        Prism::new(polygon, height)
+    }
+
+    pub fn prepare_bounds(&mut self, bounds: &mut Bounds) {
+        let (mut min_x, mut min_z) = (Float::INFINITY, Float::INFINITY);
+        let (mut max_x, mut max_z) = (Float::NEG_INFINITY, Float::NEG_INFINITY);
+        for &(x, z) in &self.polygon {
+            (min_x, max_x) = (min_x.min(x), max_x.max(x));
+            (min_z, max_z) = (min_z.min(z), max_z.max(z));
+        }
+        bounds.min = Tuple::point(min_x, self.height.min(0.0), min_z);
+        bounds.max = Tuple::point(max_x, self.height.max(0.0), max_z);
+    }
+
+    pub fn local_intersect<'a>(
+        &'a self,
+        ray: &Ray,
+        object: &'a Object,
+        xs: &mut Vec<Intersection<'a>>,
+    ) {
+        let (ox, oz, dx, dz) = (
+            ray.origin.x(),
+            ray.origin.z(),
+            ray.direction.x(),
+            ray.direction.z(),
+        );
+        for ((ax, az), (bx, bz)) in edges(&self.polygon) {
+            let (ex, ez) = (bx - ax, bz - az);
+            let denominator = dx * ez - dz * ex;
+            if denominator.abs() < EPSILON {
+                continue;
+            }
+            let (px, pz) = (ax - ox, az - oz);
+            let t = (px * ez - pz * ex) / denominator;
+            let s = (px * dz - pz * dx) / denominator;
+            let y = ray.origin.y() + t * ray.direction.y();
+            if ray.in_range(t) && (0.0..1.0).contains(&s) && self.within_height(y) {
+                xs.push(Intersection::new(t, object));
+            }
+        }
+        if ray.direction.y().abs() < EPSILON {
+            return;
+        }
+        for cap in [0.0, self.height] {
+            let t = (cap - ray.origin.y()) / ray.direction.y();
+            if ray.in_range(t) && self.contains(ox + t * dx, oz + t * dz) {
+                xs.push(Intersection::new(t, object));
+            }
+        }
+    }
+
+    pub fn local_normal_at(&self, local_point: Tuple) -> Tuple {
+        let (low, high) = (self.height.min(0.0), self.height.max(0.0));
+        if (local_point.y() - high).abs() < EPSILON {
+            return Tuple::vector(0.0, 1.0, 0.0);
+        }
+        if (local_point.y() - low).abs() < EPSILON {
+            return Tuple::vector(0.0, -1.0, 0.0);
+        }
+        let (x, z) = (local_point.x(), local_point.z());
+        edges(&self.polygon)
+            .min_by(|a, b| distance_to_edge(x, z, *a).total_cmp(&distance_to_edge(x, z, *b)))
+            .map_or(Tuple::vector(0.0, 1.0, 0.0), |((ax, az), (bx, bz))| {
+                Tuple::vector(bz - az, 0.0, ax - bx) * self.orientation
+            })
+    }
+
+    fn within_height(&self, y: Float) -> bool {
+        y >= self.height.min(0.0) && y <= self.height.max(0.0)
+    }
+
+    // Even-odd rule, so that concave polygons work as well.
+    fn contains(&self, x: Float, z: Float) -> bool {
+        edges(&self.polygon)
+            .filter(|&((ax, az), (bx, bz))| {
+                (az > z) != (bz > z) && x < ax + (z - az) * (bx - ax) / (bz - az)
+            })
+            .count()
+            % 2
+            == 1
+    }
+}
+
+fn edges(
+    polygon: &[(Float, Float)],
+) -> impl Iterator<Item = ((Float, Float), (Float, Float))> + '_ {
+    polygon
+        .iter()
+        .zip(polygon.iter().cycle().skip(1))
+        .map(|(a, b)| (*a, *b))
+}
+
+fn distance_to_edge(
+    x: Float,
+    z: Float,
+    ((ax, az), (bx, bz)): ((Float, Float), (Float, Float)),
+) -> Float {
+    let (ex, ez) = (bx - ax, bz - az);
+    let length2 = ex * ex + ez * ez;
+    let s = if length2 < EPSILON {
+        0.0
+    } else {
+        (((x - ax) * ex + (z - az) * ez) / length2).clamp(0.0, 1.0)
+    };
+    ((x - ax - s * ex).powi(2) + (z - az - s * ez).powi(2)).sqrt()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::floats::equals;
+
+    fn ts(object: &Object, origin: Tuple, direction: Tuple) -> Vec<Float> {
+        let mut xs = vec![];
+        object.intersect(&Ray::new(origin, direction), &mut xs);
+        let mut ts: Vec<Float> = xs.iter().map(|x| x.t).collect();
+        ts.sort_by(Float::total_cmp);
+        ts
+    }
+
+    #[test]
+    fn a_regular_prism_has_its_vertices_on_the_unit_circle() {
+        let prism = Object::new_prism(6, 2.0);
+        let prism = prism.as_prism();
+        assert_eq!(prism.polygon.len(), 6);
+        for (x, z) in &prism.polygon {
+            assert!(equals(x.hypot(*z), 1.0));
+        }
+        assert_eq!(prism.height, 2.0);
+    }
+
+    #[test]
+    fn a_ray_crosses_the_walls_and_caps_of_a_prism() {
+        let square = Object::new_extrusion(
+            vec![(-1.0, -1.0), (1.0, -1.0), (1.0, 1.0), (-1.0, 1.0)],
+            2.0,
+        );
+        let walls = ts(
+            &square,
+            Tuple::point(-5.0, 1.0, 0.5),
+            Tuple::vector(1.0, 0.0, 0.0),
+        );
+        assert_eq!(walls, vec![4.0, 6.0]);
+        let caps = ts(
+            &square,
+            Tuple::point(0.5, 5.0, 0.0),
+            Tuple::vector(0.0, -1.0, 0.0),
+        );
+        assert_eq!(caps, vec![3.0, 5.0]);
+        let above = ts(
+            &square,
+            Tuple::point(-5.0, 3.0, 0.0),
+            Tuple::vector(1.0, 0.0, 0.0),
+        );
+        assert!(above.is_empty());
+        let slanted = ts(
+            &square,
+            Tuple::point(-2.0, 2.5, 0.0),
+            Tuple::vector(1.0, -1.0, 0.0),
+        );
+        assert_eq!(slanted, vec![1.0, 2.5]);
+    }
+
+    #[test]
+    fn a_concave_prism_has_a_notch() {
+        let notched = Object::new_extrusion(
+            vec![
+                (0.0, 0.0),
+                (3.0, 0.0),
+                (3.0, 3.0),
+                (2.0, 3.0),
+                (2.0, 1.0),
+                (1.0, 1.0),
+                (1.0, 3.0),
+                (0.0, 3.0),
+            ],
+            1.0,
+        );
+        let across = ts(
+            &notched,
+            Tuple::point(-1.0, 0.5, 2.0),
+            Tuple::vector(1.0, 0.0, 0.0),
+        );
+        assert_eq!(across, vec![1.0, 2.0, 3.0, 4.0]);
+        let into_the_notch = ts(
+            &notched,
+            Tuple::point(1.5, 5.0, 2.0),
+            Tuple::vector(0.0, -1.0, 0.0),
+        );
+        assert!(into_the_notch.is_empty());
+    }
+
+    #[test]
+    fn the_normals_of_a_prism_point_outward() {
+        for polygon in [
+            vec![(-1.0, -1.0), (1.0, -1.0), (1.0, 1.0), (-1.0, 1.0)],
+            vec![(-1.0, 1.0), (1.0, 1.0), (1.0, -1.0), (-1.0, -1.0)],
+        ] {
+            let prism = Prism::new(polygon, 2.0);
+            let normal = |x, y, z| prism.local_normal_at(Tuple::point(x, y, z)).normalize();
+            assert_eq!(normal(1.0, 1.0, 0.2), Tuple::vector(1.0, 0.0, 0.0));
+            assert_eq!(normal(-0.3, 1.0, -1.0), Tuple::vector(0.0, 0.0, -1.0));
+            assert_eq!(normal(0.0, 2.0, 0.0), Tuple::vector(0.0, 1.0, 0.0));
+            assert_eq!(normal(0.0, 0.0, 0.5), Tuple::vector(0.0, -1.0, 0.0));
+        }
+    }
+
+    #[test]
+    fn the_bounds_of_a_prism() {
+        let mut prism = Object::new_prism(4, 3.0);
+        prism.prepare();
+        assert_eq!(prism.bounds.min, Tuple::point(-1.0, 0.0, -1.0));
+        assert_eq!(prism.bounds.max, Tuple::point(1.0, 3.0, 1.0));
+    }
+}
@@ -0,0 +1,92 @@
+use crate::bounds::Bounds;
+use crate::floats::{equals, Float, EPSILON};
+use crate::intersections::Intersection;
+use crate::objects::Object;
+use crate::rays::Ray;
+use crate::tuples::Tuple;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Quad {
+    pub width: Float,
+    pub height: Float,
+}
+
+impl Quad {
+    pub fn new(width: Float, height: Float) -> Quad {
+        Quad { width, height }
+    }
+
+    pub fn prepare_bounds(&mut self, bounds: &mut Bounds) {
+        bounds.min = Tuple::point(-self.width / 2.0, -EPSILON, -self.height / 2.0);
+        bounds.max = Tuple::point(self.width / 2.0, EPSILON, self.height / 2.0);
+    }
+
+    pub fn local_intersect<'a>(
+        &'a self,
+        ray: &Ray,
+        object: &'a Object,
+        xs: &mut Vec<Intersection<'a>>,
+    ) {
+        if equals(ray.direction.y(), 0.0) {
+            return;
+        }
+        let t = -ray.origin.y() / ray.direction.y();
+        if !ray.in_range(t) {
+            return;
+        }
+        let point = ray.position(t);
+        let u = point.x() / self.width + 0.5;
+        let v = point.z() / self.height + 0.5;
+        if (0.0..=1.0).contains(&u) && (0.0..=1.0).contains(&v) {
+            xs.push(Intersection::new_with_uv(t, object, u, v));
+        }
+    }
+
+    pub fn local_normal_at(&self, _point: Tuple) -> Tuple {
+        Tuple::vector(0.0, 1.0, 0.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn the_bounds_of_a_quad() {
+        let mut q = Object::new_quad(4.0, 2.0);
+        q.prepare();
+        assert_eq!(q.bounds.min, Tuple::point(-2.0, -EPSILON, -1.0));
+        assert_eq!(q.bounds.max, Tuple::point(2.0, EPSILON, 1.0));
+    }
+
+    #[test]
+    fn a_ray_hits_a_quad_inside_its_edges() {
+        let q = Object::new_quad(4.0, 2.0);
+        let r = Ray::new(Tuple::point(1.0, 1.0, -0.5), Tuple::vector(0.0, -1.0, 0.0));
+        let mut xs = Vec::new();
+        q.as_quad().local_intersect(&r, &q, &mut xs);
+        assert_eq!(xs.len(), 1);
+        assert_eq!(xs[0].t, 1.0);
+        assert_eq!((xs[0].u, xs[0].v), (0.75, 0.25));
+    }
+
+    #[test]
+    fn a_ray_misses_a_quad_outside_its_edges() {
+        let q = Object::new_quad(4.0, 2.0);
+        let mut xs = Vec::new();
+        for (x, z) in [(2.5, 0.0), (-2.5, 0.0), (0.0, 1.5), (0.0, -1.5)] {
+            let r = Ray::new(Tuple::point(x, 1.0, z), Tuple::vector(0.0, -1.0, 0.0));
+            q.as_quad().local_intersect(&r, &q, &mut xs);
+        }
+        assert!(xs.is_empty());
+    }
+
+    #[test]
+    fn a_ray_parallel_to_a_quad_misses() {
+        let q = Object::new_quad(4.0, 2.0);
+        let r = Ray::new(Tuple::point(0.0, 0.0, -5.0), Tuple::vector(0.0, 0.0, 1.0));
+        let mut xs = Vec::new();
+        q.as_quad().local_intersect(&r, &q, &mut xs);
+        assert!(xs.is_empty());
+    }
+}
@@ -0,0 +1,178 @@
+use crate::floats::{Float, EPSILON};
+use crate::intersections::Intersection;
+use crate::objects::Object;
+use crate::rays::Ray;
+use crate::shapes::cubes::check_axis;
+use crate::tuples::Tuple;
+
+const MAX_STEPS: usize = 256;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct RoundedCube {
+    pub radius: Float,
+}
+
+impl RoundedCube {
+    pub fn new(radius: Float) -> RoundedCube {
+        RoundedCube {
+            radius: radius.clamp(0.0, 1.0),
+        }
+    }
+
+    pub fn local_intersect<'a>(
+        &'a self,
+        ray: &Ray,
+        object: &'a Object,
+        xs: &mut Vec<Intersection<'a>>,
+    ) {
+        let (xtmin, xtmax) = check_axis(ray.origin.x(), ray.direction.x());
+        let (ytmin, ytmax) = check_axis(ray.origin.y(), ray.direction.y());
+        let (ztmin, ztmax) = check_axis(ray.origin.z(), ray.direction.z());
+        let tmin = xtmin.max(ytmin).max(ztmin);
+        let tmax = xtmax.min(ytmax).min(ztmax);
+        if tmin > tmax {
+            return;
+        }
+        let Some(t0) = self.march(ray.origin, ray.direction, tmin, tmax) else {
+            return;
+        };
+        let t1 = self
+            .march(ray.origin, -ray.direction, -tmax, -t0)
+            .map_or(t0, |t| -t);
+        for t in [t0, t1] {
+            if ray.in_range(t) {
+                xs.push(Intersection::new(t, object));
+            }
+        }
+    }
+
+    pub fn local_normal_at(&self, local_point: Tuple) -> Tuple {
+        let inner = 1.0 - self.radius;
+        let core = Tuple::point(
+            local_point.x().clamp(-inner, inner),
+            local_point.y().clamp(-inner, inner),
+            local_point.z().clamp(-inner, inner),
+        );
+        local_point - core
+    }
+
+    fn distance(&self, point: Tuple) -> Float {
+        let inner = 1.0 - self.radius;
+        let (qx, qy, qz) = (
+            point.x().abs() - inner,
+            point.y().abs() - inner,
+            point.z().abs() - inner,
+        );
+        let outside = Tuple::vector(qx.max(0.0), qy.max(0.0), qz.max(0.0)).magnitude();
+        let inside = qx.max(qy).max(qz).min(0.0);
+        outside + inside - self.radius
+    }
+
+    fn march(&self, origin: Tuple, direction: Tuple, tmin: Float, tmax: Float) -> Option<Float> {
+        let speed = direction.magnitude();
+        let mut t = tmin;
+        for _ in 0..MAX_STEPS {
+            let d = self.distance(origin + direction * t);
+            if d < EPSILON / 10.0 {
+                return Some(t);
+            }
+            t += d / speed;
+            if t > tmax + EPSILON {
+                return None;
+            }
+        }
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::floats::{equals, SQRT_2, SQRT_3};
+
+    #[test]
+    fn a_ray_intersects_the_flat_faces_of_a_rounded_cube() {
+        let c = Object::new_rounded_cube(0.25);
+        let examples = [
+            (
+                Tuple::point(5.0, 0.5, 0.0),
+                Tuple::vector(-1.0, 0.0, 0.0),
+                4.0,
+                6.0,
+            ),
+            (
+                Tuple::point(0.0, -5.0, 0.0),
+                Tuple::vector(0.0, 1.0, 0.0),
+                4.0,
+                6.0,
+            ),
+            (
+                Tuple::point(0.0, 0.5, 0.0),
+                Tuple::vector(0.0, 0.0, 1.0),
+                -1.0,
+                1.0,
+            ),
+        ];
+        for (origin, direction, t1, t2) in examples {
+            let r = Ray::new(origin, direction);
+            let mut xs = Vec::new();
+            c.as_rounded_cube().local_intersect(&r, &c, &mut xs);
+            assert_eq!(xs.len(), 2);
+            assert!(equals(xs[0].t, t1));
+            assert!(equals(xs[1].t, t2));
+        }
+    }
+
+    #[test]
+    fn a_ray_intersects_the_rounded_corner_of_a_cube() {
+        let c = Object::new_rounded_cube(0.5);
+        let direction = Tuple::vector(-1.0, -1.0, -1.0).normalize();
+        let r = Ray::new(Tuple::point(5.0, 5.0, 5.0), direction);
+        let mut xs = Vec::new();
+        c.as_rounded_cube().local_intersect(&r, &c, &mut xs);
+        assert_eq!(xs.len(), 2);
+        let corner = 5.0 * SQRT_3 - (0.5 * SQRT_3 + 0.5);
+        assert!((xs[0].t - corner).abs() < 0.001);
+        assert!((xs[1].t - (10.0 * SQRT_3 - corner)).abs() < 0.001);
+    }
+
+    #[test]
+    fn a_ray_misses_the_rounded_edge_of_a_cube() {
+        let c = Object::new_rounded_cube(0.5);
+        let r = Ray::new(Tuple::point(0.95, 0.95, -5.0), Tuple::vector(0.0, 0.0, 1.0));
+        let mut xs = Vec::new();
+        c.as_rounded_cube().local_intersect(&r, &c, &mut xs);
+        assert_eq!(xs.len(), 0);
+        let r = Ray::new(Tuple::point(-2.0, 0.0, 0.0), Tuple::vector(0.0, 0.0, 1.0));
+        let mut xs = Vec::new();
+        c.as_rounded_cube().local_intersect(&r, &c, &mut xs);
+        assert_eq!(xs.len(), 0);
+    }
+
+    #[test]
+    fn a_rounded_cube_with_no_radius_is_a_cube() {
+        let c = Object::new_rounded_cube(0.0);
+        let r = Ray::new(Tuple::point(0.5, 0.5, -5.0), Tuple::vector(0.0, 0.0, 1.0));
+        let mut xs = Vec::new();
+        c.as_rounded_cube().local_intersect(&r, &c, &mut xs);
+        assert_eq!(xs.len(), 2);
+        assert!(equals(xs[0].t, 4.0));
+        assert!(equals(xs[1].t, 6.0));
+    }
+
+    #[test]
+    fn the_normal_on_a_rounded_cube() {
+        let c = RoundedCube::new(0.5);
+        let examples = [
+            (Tuple::point(1.0, 0.25, -0.25), Tuple::vector(1.0, 0.0, 0.0)),
+            (Tuple::point(0.25, -1.0, 0.0), Tuple::vector(0.0, -1.0, 0.0)),
+            (
+                Tuple::point(0.5 + SQRT_2 / 4.0, 0.5 + SQRT_2 / 4.0, 0.0),
+                Tuple::vector(SQRT_2 / 2.0, SQRT_2 / 2.0, 0.0),
+            ),
+        ];
+        for (point, normal) in examples {
+            assert_eq!(c.local_normal_at(point).normalize(), normal);
+        }
+    }
+}
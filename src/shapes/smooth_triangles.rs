@@ -1,8 +1,8 @@
 use crate::bounds::Bounds;
-use crate::floats::EPSILON;
 use crate::intersections::Intersection;
 use crate::objects::Object;
 use crate::rays::Ray;
+use crate::shapes::triangles::{flipped_attributes, watertight_intersect};
 use crate::tuples::Tuple;
 
 #[derive(Debug, Clone, PartialEq)]
@@ -10,40 +10,36 @@ pub struct SmoothTriangle {
     pub p1: Tuple,
     pub p2: Tuple,
     pub p3: Tuple,
-    e1: Tuple,
-    e2: Tuple,
     pub n1: Tuple,
     pub n2: Tuple,
     pub n3: Tuple,
+    pub attributes: Vec<[Tuple; 3]>,
 }
 
 impl SmoothTriangle {
     pub fn new(p1: Tuple, p2: Tuple, p3: Tuple, n1: Tuple, n2: Tuple, n3: Tuple) -> SmoothTriangle {
-        let e1 = p2 - p1;
-        let e2 = p3 - p1;
         SmoothTriangle {
             p1,
             p2,
             p3,
-            e1,
-            e2,
             n1,
             n2,
             n3,
+            attributes: vec![],
         }
     }
 
+    pub fn flip(&mut self) {
+        let attributes = flipped_attributes(&self.attributes);
+        *self = SmoothTriangle {
+            attributes,
+            ..SmoothTriangle::new(self.p1, self.p3, self.p2, self.n1, self.n3, self.n2)
+        };
+    }
+
     pub fn prepare_bounds(&mut self, bounds: &mut Bounds) {
-        bounds.min = Tuple::point(
-            self.p1.x().min(self.p2.x().min(self.p3.x())),
-            self.p1.y().min(self.p2.y().min(self.p3.y())),
-            self.p1.z().min(self.p2.z().min(self.p3.z())),
-        );
-        bounds.max = Tuple::point(
-            self.p1.x().max(self.p2.x().max(self.p3.x())),
-            self.p1.y().max(self.p2.y().max(self.p3.y())),
-            self.p1.z().max(self.p2.z().max(self.p3.z())),
-        );
+        bounds.min = self.p1.min(self.p2).min(self.p3);
+        bounds.max = self.p1.max(self.p2).max(self.p3);
     }
 
     pub fn local_intersect<'a>(
@@ -52,24 +48,11 @@ impl SmoothTriangle {
         object: &'a Object,
         xs: &mut Vec<Intersection<'a>>,
     ) {
-        let dir_cross_e2 = ray.direction.cross(self.e2);
-        let det = self.e1.dot(dir_cross_e2);
-        if det.abs() < EPSILON {
-            return;
+        if let Some((t, u, v)) = watertight_intersect(ray, self.p1, self.p2, self.p3)
+            .filter(|&(t, _, _)| ray.in_range(t))
+        {
+            xs.push(Intersection::new_with_uv(t, object, u, v));
         }
-        let f = 1.0 / det;
-        let p1_to_origin = ray.origin - self.p1;
-        let u = f * p1_to_origin.dot(dir_cross_e2);
-        if !(0.0..=1.0).contains(&u) {
-            return;
-        }
-        let origin_cross_e1 = p1_to_origin.cross(self.e1);
-        let v = f * ray.direction.dot(origin_cross_e1);
-        if v < 0.0 || u + v > 1.0 {
-            return;
-        }
-        let t = f * self.e2.dot(origin_cross_e1);
-        xs.push(Intersection::new_with_uv(t, object, u, v));
     }
 
     pub fn local_normal_at(&self, _point: Tuple, hit: &Intersection) -> Tuple {
@@ -134,4 +117,42 @@ mod tests {
         let comps = i.prepare_computations(&r, &xs);
         assert_eq!(comps.normalv, Tuple::vector(-0.5547, 0.83205, 0.0));
     }
+
+    #[test]
+    fn preparing_the_interpolated_vertex_attributes() {
+        let tri = background()
+            .with_vertex_attribute([
+                Tuple::point(0.5, 1.0, 0.0),
+                Tuple::point(0.0, 0.0, 0.0),
+                Tuple::point(1.0, 0.0, 0.0),
+            ])
+            .with_vertex_attribute([
+                Tuple::vector(1.0, 0.0, 0.0),
+                Tuple::vector(0.0, 1.0, 0.0),
+                Tuple::vector(0.0, 0.0, 1.0),
+            ]);
+        let r = Ray::new(Tuple::point(-0.2, 0.3, -2.0), Tuple::vector(0.0, 0.0, 1.0));
+        let mut xs = Vec::new();
+        tri.intersect(&r, &mut xs);
+        let comps = xs[0].prepare_computations(&r, &xs);
+        assert_eq!(
+            comps.attributes,
+            vec![Tuple::point(0.4, 0.3, 0.0), Tuple::vector(0.3, 0.45, 0.25)]
+        );
+    }
+
+    #[test]
+    fn flipping_a_smooth_triangle_keeps_its_vertex_attributes() {
+        let tri = background().with_vertex_attribute([
+            Tuple::vector(1.0, 0.0, 0.0),
+            Tuple::vector(0.0, 1.0, 0.0),
+            Tuple::vector(0.0, 0.0, 1.0),
+        ]);
+        let mut flipped = tri.as_smooth_triangle().clone();
+        flipped.flip();
+        assert_eq!(flipped.p2, tri.as_smooth_triangle().p3);
+        assert_eq!(flipped.n2, tri.as_smooth_triangle().n3);
+        assert_eq!(flipped.attributes[0][1], Tuple::vector(0.0, 0.0, 1.0));
+        assert_eq!(flipped.attributes[0][2], Tuple::vector(0.0, 1.0, 0.0));
+    }
 }
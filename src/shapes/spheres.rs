@@ -27,8 +27,11 @@ impl Sphere {
         }
         let t1 = (-b - discriminant.sqrt()) / (2.0 * a);
         let t2 = (-b + discriminant.sqrt()) / (2.0 * a);
-        xs.push(Intersection::new(t1, object));
-        xs.push(Intersection::new(t2, object));
+        for t in [t1, t2] {
+            if ray.in_range(t) {
+                xs.push(Intersection::new(t, object));
+            }
+        }
     }
 
     pub fn local_normal_at(&self, local_point: Tuple) -> Tuple {
@@ -1,5 +1,5 @@
 use crate::bounds::Bounds;
-use crate::floats::EPSILON;
+use crate::floats::Float;
 use crate::intersections::Intersection;
 use crate::objects::Object;
 use crate::rays::Ray;
@@ -13,6 +13,7 @@ pub struct Triangle {
     pub e1: Tuple,
     pub e2: Tuple,
     pub normal: Tuple,
+    pub attributes: Vec<[Tuple; 3]>,
 }
 
 impl Triangle {
@@ -27,20 +28,21 @@ impl Triangle {
             e1,
             e2,
             normal,
+            attributes: vec![],
         }
     }
 
+    pub fn flip(&mut self) {
+        let attributes = flipped_attributes(&self.attributes);
+        *self = Triangle {
+            attributes,
+            ..Triangle::new(self.p1, self.p3, self.p2)
+        };
+    }
+
     pub fn prepare_bounds(&self, bounds: &mut Bounds) {
-        bounds.min = Tuple::point(
-            self.p1.x().min(self.p2.x().min(self.p3.x())),
-            self.p1.y().min(self.p2.y().min(self.p3.y())),
-            self.p1.z().min(self.p2.z().min(self.p3.z())),
-        );
-        bounds.max = Tuple::point(
-            self.p1.x().max(self.p2.x().max(self.p3.x())),
-            self.p1.y().max(self.p2.y().max(self.p3.y())),
-            self.p1.z().max(self.p2.z().max(self.p3.z())),
-        );
+        bounds.min = self.p1.min(self.p2).min(self.p3);
+        bounds.max = self.p1.max(self.p2).max(self.p3);
     }
 
     pub fn local_intersect<'a>(
@@ -49,24 +51,11 @@ impl Triangle {
         object: &'a Object,
         xs: &mut Vec<Intersection<'a>>,
     ) {
-        let dir_cross_e2 = ray.direction.cross(self.e2);
-        let det = self.e1.dot(dir_cross_e2);
-        if det.abs() < EPSILON {
-            return;
-        }
-        let f = 1.0 / det;
-        let p1_to_origin = ray.origin - self.p1;
-        let u = f * p1_to_origin.dot(dir_cross_e2);
-        if !(0.0..=1.0).contains(&u) {
-            return;
+        if let Some((t, u, v)) = watertight_intersect(ray, self.p1, self.p2, self.p3)
+            .filter(|&(t, _, _)| ray.in_range(t))
+        {
+            xs.push(Intersection::new_with_uv(t, object, u, v));
         }
-        let origin_cross_e1 = p1_to_origin.cross(self.e1);
-        let v = f * ray.direction.dot(origin_cross_e1);
-        if v < 0.0 || u + v > 1.0 {
-            return;
-        }
-        let t = f * self.e2.dot(origin_cross_e1);
-        xs.push(Intersection::new(t, object));
     }
 
     pub fn local_normal_at(&self, _local_point: Tuple) -> Tuple {
@@ -74,6 +63,67 @@ impl Triangle {
     }
 }
 
+// Per vertex attributes (texture coordinates, colors, tangents...) blended
+// with the barycentric weights of p2 and p3 stored in the intersection.
+pub fn interpolate_attributes(attributes: &[[Tuple; 3]], u: Float, v: Float) -> Vec<Tuple> {
+    attributes
+        .iter()
+        .map(|[a1, a2, a3]| *a2 * u + *a3 * v + *a1 * (1.0 - u - v))
+        .collect()
+}
+
+pub fn flipped_attributes(attributes: &[[Tuple; 3]]) -> Vec<[Tuple; 3]> {
+    attributes
+        .iter()
+        .map(|[a1, a2, a3]| [*a1, *a3, *a2])
+        .collect()
+}
+
+// Woop, Benthin & Wald, "Watertight Ray/Triangle Intersection": the triangle is
+// sheared into the ray's space so that edges shared by two triangles are
+// evaluated identically on both sides, leaving no gaps. Returns t and the
+// barycentric weights of p2 and p3.
+pub fn watertight_intersect(
+    ray: &Ray,
+    p1: Tuple,
+    p2: Tuple,
+    p3: Tuple,
+) -> Option<(Float, Float, Float)> {
+    let axes = |t: Tuple| [t.x(), t.y(), t.z()];
+    let d = axes(ray.direction);
+    let kz = (0..3)
+        .max_by(|&a, &b| d[a].abs().total_cmp(&d[b].abs()))
+        .unwrap();
+    let (mut kx, mut ky) = ((kz + 1) % 3, (kz + 2) % 3);
+    if d[kz] < 0.0 {
+        (kx, ky) = (ky, kx);
+    }
+    let (sx, sy, sz) = (d[kx] / d[kz], d[ky] / d[kz], 1.0 / d[kz]);
+    let [a, b, c] = [p1, p2, p3].map(|p| axes(p - ray.origin));
+    let shear = |p: [Float; 3]| (p[kx] - sx * p[kz], p[ky] - sy * p[kz]);
+    let ((ax, ay), (bx, by), (cx, cy)) = (shear(a), shear(b), shear(c));
+    let mut u = cx * by - cy * bx;
+    let mut v = ax * cy - ay * cx;
+    let mut w = bx * ay - by * ax;
+    if u == 0.0 || v == 0.0 || w == 0.0 {
+        let edge = |px: Float, py: Float, qx: Float, qy: Float| {
+            (px as f64 * qy as f64 - py as f64 * qx as f64) as Float
+        };
+        u = edge(cx, cy, bx, by);
+        v = edge(ax, ay, cx, cy);
+        w = edge(bx, by, ax, ay);
+    }
+    if (u < 0.0 || v < 0.0 || w < 0.0) && (u > 0.0 || v > 0.0 || w > 0.0) {
+        return None;
+    }
+    let det = u + v + w;
+    if det == 0.0 {
+        return None;
+    }
+    let t = (u * a[kz] + v * b[kz] + w * c[kz]) * sz;
+    Some((t / det, v / det, w / det))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -170,4 +220,62 @@ mod tests {
         t.as_triangle().local_intersect(&r, &t, &mut xs);
         assert_eq!(xs.iter().map(|x| x.t).collect::<Vec<_>>(), vec![2.0]);
     }
+
+    #[test]
+    fn a_triangle_interpolates_its_vertex_attributes() {
+        let t = Object::new_triangle(
+            Tuple::point(0.0, 1.0, 0.0),
+            Tuple::point(-1.0, 0.0, 0.0),
+            Tuple::point(1.0, 0.0, 0.0),
+        )
+        .with_vertex_attribute([
+            Tuple::point(0.5, 1.0, 0.0),
+            Tuple::point(0.0, 0.0, 0.0),
+            Tuple::point(1.0, 0.0, 0.0),
+        ]);
+        let r = Ray::new(Tuple::point(0.0, 0.5, -2.0), Tuple::vector(0.0, 0.0, 1.0));
+        let mut xs = Vec::new();
+        t.as_triangle().local_intersect(&r, &t, &mut xs);
+        assert_eq!(
+            t.vertex_attributes(&xs[0]),
+            vec![Tuple::point(0.5, 0.5, 0.0)]
+        );
+        let mut flipped = t.as_triangle().clone();
+        flipped.flip();
+        assert_eq!(flipped.normal, -t.as_triangle().normal);
+        assert_eq!(flipped.attributes[0][1], Tuple::point(1.0, 0.0, 0.0));
+    }
+
+    #[test]
+    fn rays_through_a_shared_edge_do_not_leak() {
+        let a = Tuple::point(-0.3, -0.7, 0.1);
+        let b = Tuple::point(0.7, 0.3, -0.2);
+        let t1 = Object::new_triangle(a, b, Tuple::point(-1.1, 0.9, 0.05));
+        let t2 = Object::new_triangle(b, a, Tuple::point(1.3, -0.9, -0.1));
+        for i in 1..100 {
+            let s = i as Float / 100.0;
+            let target = a + (b - a) * s;
+            let origin = Tuple::point(0.13, 0.07, -3.0);
+            let r = Ray::new(origin, (target - origin).normalize());
+            let mut xs = Vec::new();
+            t1.as_triangle().local_intersect(&r, &t1, &mut xs);
+            t2.as_triangle().local_intersect(&r, &t2, &mut xs);
+            assert!(!xs.is_empty(), "ray {} leaked through the shared edge", i);
+        }
+    }
+
+    #[test]
+    fn watertight_intersections_report_barycentric_coordinates() {
+        let r = Ray::new(Tuple::point(-0.2, 0.3, -2.0), Tuple::vector(0.0, 0.0, 1.0));
+        let (t, u, v) = watertight_intersect(
+            &r,
+            Tuple::point(0.0, 1.0, 0.0),
+            Tuple::point(-1.0, 0.0, 0.0),
+            Tuple::point(1.0, 0.0, 0.0),
+        )
+        .unwrap();
+        assert!(crate::floats::equals(t, 2.0));
+        assert!(crate::floats::equals(u, 0.45));
+        assert!(crate::floats::equals(v, 0.25));
+    }
 }
@@ -0,0 +1,181 @@
+use crate::colors::{Color, BLACK, WHITE};
+use crate::floats::{Float, PI};
+use crate::lights::Light;
+use crate::tuples::Tuple;
+
+const SUN_DISTANCE: Float = 1.0e5;
+// Red, green and blue wavelengths in micrometers, for the sun transmittance.
+const WAVELENGTHS: [Float; 3] = [0.68, 0.55, 0.44];
+
+// Perez distribution coefficients (A to E) as linear functions of turbidity,
+// for the luminance Y and the x and y chromaticities (Preetham et al. 1999).
+const PEREZ_Y: [[Float; 2]; 5] = [
+    [0.1787, -1.4630],
+    [-0.3554, 0.4275],
+    [-0.0227, 5.3251],
+    [0.1206, -2.5771],
+    [-0.0670, 0.3703],
+];
+const PEREZ_X: [[Float; 2]; 5] = [
+    [-0.0193, -0.2592],
+    [-0.0665, 0.0008],
+    [-0.0004, 0.2125],
+    [-0.0641, -0.8989],
+    [-0.0033, 0.0452],
+];
+const PEREZ_Y_CHROMA: [[Float; 2]; 5] = [
+    [-0.0167, -0.2608],
+    [-0.0950, 0.0092],
+    [-0.0079, 0.2102],
+    [-0.0441, -1.6537],
+    [-0.0109, 0.0529],
+];
+// Zenith chromaticities as T^2, T and 1 weighted cubics of the sun zenith angle.
+const ZENITH_X: [[Float; 4]; 3] = [
+    [0.00166, -0.00375, 0.00209, 0.0],
+    [-0.02903, 0.06377, -0.03202, 0.00394],
+    [0.11693, -0.21196, 0.06052, 0.25886],
+];
+const ZENITH_Y: [[Float; 4]; 3] = [
+    [0.00275, -0.00610, 0.00317, 0.0],
+    [-0.04214, 0.08970, -0.04153, 0.00516],
+    [0.15346, -0.26756, 0.06670, 0.26688],
+];
+
+// Preetham daylight sky: `intensity` is the luminance at the zenith, and the
+// sun is a distant point light of `sun_intensity` dimmed through the air.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Sky {
+    pub sun_direction: Tuple,
+    pub turbidity: Float,
+    pub intensity: Float,
+    pub sun_intensity: Float,
+}
+
+impl Sky {
+    pub fn new(sun_direction: Tuple, turbidity: Float) -> Sky {
+        Sky {
+            sun_direction: sun_direction.normalize(),
+            turbidity: turbidity.max(1.0),
+            intensity: 1.0,
+            sun_intensity: 1.0,
+        }
+    }
+
+    pub fn with_intensity(self, intensity: Float, sun_intensity: Float) -> Sky {
+        Sky {
+            intensity,
+            sun_intensity,
+            ..self
+        }
+    }
+
+    pub fn color_at(&self, direction: Tuple) -> Color {
+        let direction = direction.normalize();
+        // Below the horizon, the sky is seen as it is at the horizon.
+        let theta = direction.y().clamp(0.001, 1.0).acos();
+        let gamma = direction.dot(self.sun_direction).clamp(-1.0, 1.0).acos();
+        let theta_s = self.sun_zenith_angle();
+        let t = self.turbidity;
+        let relative = |coefficients: &[[Float; 2]; 5]| {
+            let c = coefficients.map(|[a, b]| a * t + b);
+            perez(c, theta, gamma) / perez(c, 0.0, theta_s)
+        };
+        let luminance = self.intensity * relative(&PEREZ_Y);
+        let x = zenith_chromaticity(&ZENITH_X, t, theta_s) * relative(&PEREZ_X);
+        let y = zenith_chromaticity(&ZENITH_Y, t, theta_s) * relative(&PEREZ_Y_CHROMA);
+        let color = Color::from_xyz([x / y * luminance, luminance, (1.0 - x - y) / y * luminance]);
+        Color::new(
+            color.red().max(0.0),
+            color.green().max(0.0),
+            color.blue().max(0.0),
+        )
+    }
+
+    pub fn sun_color(&self) -> Color {
+        let theta_s = self.sun_zenith_angle();
+        if theta_s >= PI / 2.0 {
+            return BLACK;
+        }
+        // Kasten and Young relative air mass, then Rayleigh and aerosol extinction.
+        let air_mass =
+            1.0 / (theta_s.cos() + 0.50572 * (96.07995 - theta_s.to_degrees()).powf(-1.6364));
+        let beta = 0.04608 * self.turbidity - 0.04586;
+        let [r, g, b] = WAVELENGTHS.map(|l| {
+            let depth = 0.008735 * l.powf(-4.08) + beta * l.powf(-1.3);
+            (-air_mass * depth).exp()
+        });
+        WHITE * self.sun_intensity * Color::new(r, g, b)
+    }
+
+    pub fn sun_light(&self) -> Light {
+        Light::new_point(
+            Tuple::point(0.0, 0.0, 0.0) + self.sun_direction * SUN_DISTANCE,
+            self.sun_color(),
+        )
+    }
+
+    fn sun_zenith_angle(&self) -> Float {
+        self.sun_direction.y().clamp(-1.0, 1.0).acos()
+    }
+}
+
+fn perez([a, b, c, d, e]: [Float; 5], theta: Float, gamma: Float) -> Float {
+    (1.0 + a * (b / theta.cos().max(0.01)).exp())
+        * (1.0 + c * (d * gamma).exp() + e * gamma.cos().powi(2))
+}
+
+fn zenith_chromaticity(coefficients: &[[Float; 4]; 3], t: Float, theta_s: Float) -> Float {
+    let cubic = |[a, b, c, d]: [Float; 4]| ((a * theta_s + b) * theta_s + c) * theta_s + d;
+    t * t * cubic(coefficients[0]) + t * cubic(coefficients[1]) + cubic(coefficients[2])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::floats::equals;
+
+    fn noon() -> Sky {
+        Sky::new(Tuple::vector(0.0, 1.0, 1.0), 3.0)
+    }
+
+    #[test]
+    fn the_zenith_has_the_sky_intensity() {
+        let sky = noon().with_intensity(2.0, 1.0);
+        let zenith = sky.color_at(Tuple::vector(0.0, 1.0, 0.0));
+        assert!(equals(zenith.to_xyz()[1], 2.0));
+    }
+
+    #[test]
+    fn a_clear_sky_is_blue_away_from_the_sun() {
+        let color = noon().color_at(Tuple::vector(0.0, 1.0, -1.0));
+        assert!(color.blue() > color.red());
+    }
+
+    #[test]
+    fn the_sky_is_brighter_around_the_sun() {
+        let sky = noon();
+        let near = sky.color_at(Tuple::vector(0.0, 1.0, 1.2)).to_xyz()[1];
+        let far = sky.color_at(Tuple::vector(0.0, 1.0, -1.2)).to_xyz()[1];
+        assert!(near > far);
+    }
+
+    #[test]
+    fn the_sun_reddens_toward_the_horizon() {
+        let high = noon().sun_color();
+        let low = Sky::new(Tuple::vector(0.0, 0.05, 1.0), 3.0).sun_color();
+        assert!(low.blue() / low.red() < high.blue() / high.red());
+        assert_eq!(
+            Sky::new(Tuple::vector(0.0, -1.0, 1.0), 3.0).sun_color(),
+            BLACK
+        );
+    }
+
+    #[test]
+    fn the_sun_light_is_far_along_the_sun_direction() {
+        let light = noon().sun_light();
+        assert!(light.position.y() > 1000.0);
+        assert!(equals(light.position.y(), light.position.z()));
+        assert_eq!(light.intensity, noon().sun_color());
+    }
+}
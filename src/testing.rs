@@ -0,0 +1,171 @@
+use crate::canvas::Canvas;
+use crate::colors::{Color, ColorSpace, WHITE};
+use crate::floats::{Float, PI};
+use crate::lights::Light;
+use crate::materials::Material;
+use crate::objects::Object;
+use crate::scene_files::SceneFile;
+use crate::transformations::{scaling, translation, view_transform};
+use crate::tuples::Tuple;
+use crate::worlds::World;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+
+const SMALL_RADIUS: Float = 0.2;
+const BIG_SPHERES: [Float; 3] = [-4.0, 0.0, 4.0];
+
+// The field of random spheres from the cover of "Ray Tracing in One
+// Weekend": three big spheres, of diffuse, glass and metal, among `n` small
+// ones laid out on a jittered grid. The same seed gives the same scene, so
+// that it can be used to compare the speed of renders.
+pub fn random_spheres_scene(n: usize, seed: u64) -> SceneFile {
+    let mut rng = StdRng::seed_from_u64(seed);
+    let mut world = World::new();
+    world.lights = vec![Light::new_point(Tuple::point(10.0, 20.0, 10.0), WHITE)];
+    let mut ground = Object::new_plane();
+    ground.material.color = Color::new(0.5, 0.5, 0.5);
+    ground.material.specular = 0.0;
+    world.add_object(ground);
+    let big_materials = [
+        Material {
+            color: Color::new(0.4, 0.2, 0.1),
+            specular: 0.0,
+            ..Material::default()
+        },
+        Material::glass(),
+        Material::metal(Color::new(0.7, 0.6, 0.5), 0.0),
+    ];
+    for (x, material) in BIG_SPHERES.into_iter().zip(big_materials) {
+        let mut sphere = Object::new_sphere().with_transform(translation(x, 1.0, 0.0));
+        sphere.material = material;
+        world.add_object(sphere);
+    }
+    // Rows are added past the square grid if the big spheres took too many
+    // of its cells.
+    let side = (n as Float).sqrt().ceil() as usize + 4;
+    let mut cell = 0;
+    while world.objects.len() < n + 4 {
+        let x = (cell % side) as Float - side as Float / 2.0 + 0.9 * rng.random::<Float>();
+        let z = (cell / side) as Float - side as Float / 2.0 + 0.9 * rng.random::<Float>();
+        cell += 1;
+        let center = Tuple::point(x, SMALL_RADIUS, z);
+        if BIG_SPHERES
+            .iter()
+            .any(|&bx| (center - Tuple::point(bx, 1.0, 0.0)).magnitude() < 1.0 + SMALL_RADIUS)
+        {
+            continue;
+        }
+        let mut sphere = Object::new_sphere().with_transform(
+            translation(x, SMALL_RADIUS, z) * scaling(SMALL_RADIUS, SMALL_RADIUS, SMALL_RADIUS),
+        );
+        sphere.material = random_material(&mut rng);
+        world.add_object(sphere);
+    }
+    SceneFile {
+        world,
+        width: 400,
+        height: 225,
+        // The 20 degrees of the book are vertical, these are horizontal.
+        field_of_view: 35.0 * PI / 180.0,
+        view: view_transform(
+            Tuple::point(13.0, 2.0, 3.0),
+            Tuple::point(0.0, 0.0, 0.0),
+            Tuple::vector(0.0, 1.0, 0.0),
+        ),
+        exposure: 0.0,
+        white_balance: None,
+        physical_exposure: None,
+        shift: (0.0, 0.0),
+        roll: 0.0,
+        distortion: (0.0, 0.0),
+        input_color_space: ColorSpace::Linear,
+        output_color_space: ColorSpace::Linear,
+    }
+}
+
+// Mostly diffuse, some metal and a few glass spheres.
+fn random_material(rng: &mut StdRng) -> Material {
+    let color = |rng: &mut StdRng| Color::new(rng.random(), rng.random(), rng.random());
+    let choice: Float = rng.random();
+    if choice < 0.8 {
+        Material {
+            color: color(rng) * color(rng),
+            specular: 0.0,
+            ..Material::default()
+        }
+    } else if choice < 0.95 {
+        let color = color(rng) * 0.5 + Color::new(0.5, 0.5, 0.5);
+        Material::metal(color, rng.random_range(0.0..0.5))
+    } else {
+        Material::glass()
+    }
+}
+
+// Panics unless the images have the same size and every channel of their
+// pixels is within `tolerance`, reporting the first pixel that differs.
+pub fn assert_images_close(a: &Canvas, b: &Canvas, tolerance: Float) {
+    assert_eq!(
+        (a.width(), a.height()),
+        (b.width(), b.height()),
+        "image sizes differ"
+    );
+    let mut mismatches = 0;
+    let mut first = None;
+    for y in 0..a.height() {
+        for x in 0..a.width() {
+            let (pa, pb) = (a.pixel_at(x, y), b.pixel_at(x, y));
+            let d = pa - pb;
+            if d.red().abs() > tolerance
+                || d.green().abs() > tolerance
+                || d.blue().abs() > tolerance
+            {
+                mismatches += 1;
+                first.get_or_insert((x, y, pa, pb));
+            }
+        }
+    }
+    if let Some((x, y, pa, pb)) = first {
+        panic!(
+            "{} pixels differ by more than {}, first at ({}, {}): {:?} != {:?}",
+            mismatches, tolerance, x, y, pa, pb
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn the_random_spheres_scene_depends_on_its_seed_only() {
+        let scene = random_spheres_scene(50, 7);
+        assert_eq!(scene.world.objects.len(), 54);
+        let same = random_spheres_scene(50, 7);
+        let other = random_spheres_scene(50, 8);
+        let transforms =
+            |s: &SceneFile| -> Vec<_> { s.world.objects.iter().map(|o| o.transform).collect() };
+        assert_eq!(transforms(&scene), transforms(&same));
+        assert_ne!(transforms(&scene), transforms(&other));
+    }
+
+    #[test]
+    fn small_spheres_stay_clear_of_the_big_ones() {
+        let scene = random_spheres_scene(200, 1);
+        assert_eq!(scene.world.objects.len(), 204);
+        for small in &scene.world.objects[4..] {
+            let center = small.transform * Tuple::point(0.0, 0.0, 0.0);
+            assert_eq!(center.y(), SMALL_RADIUS);
+            for x in BIG_SPHERES {
+                assert!((center - Tuple::point(x, 1.0, 0.0)).magnitude() >= 1.0 + SMALL_RADIUS);
+            }
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "1 pixels differ by more than 0.01, first at (1, 0)")]
+    fn comparing_different_images() {
+        let mut c = Canvas::new(3, 2);
+        c.write_pixel(1, 0, WHITE);
+        assert_images_close(&Canvas::new(3, 2), &c, 0.01);
+    }
+}
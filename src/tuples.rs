@@ -1,5 +1,5 @@
-use crate::floats::{equals, rand, Float};
-use std::{cmp, ops};
+use crate::floats::{equals, rand, Float, EPSILON};
+use std::{cmp, fmt, ops};
 
 #[derive(Debug, Copy, Clone)]
 pub struct Tuple(Float, Float, Float, Float);
@@ -64,9 +64,87 @@ impl Tuple {
         )
     }
 
+    pub fn min(self, other: Tuple) -> Tuple {
+        Tuple(
+            self.0.min(other.0),
+            self.1.min(other.1),
+            self.2.min(other.2),
+            self.3.min(other.3),
+        )
+    }
+
+    pub fn max(self, other: Tuple) -> Tuple {
+        Tuple(
+            self.0.max(other.0),
+            self.1.max(other.1),
+            self.2.max(other.2),
+            self.3.max(other.3),
+        )
+    }
+
+    pub fn abs(self) -> Tuple {
+        Tuple(self.0.abs(), self.1.abs(), self.2.abs(), self.3.abs())
+    }
+
+    pub fn clamp(self, min: Tuple, max: Tuple) -> Tuple {
+        self.max(min).min(max)
+    }
+
+    // The largest of x, y and z.
+    pub fn max_component(self) -> Float {
+        self.0.max(self.1).max(self.2)
+    }
+
     pub fn reflect(self, normal: Tuple) -> Tuple {
         self - normal * 2.0 * self.dot(normal)
     }
+
+    // Tangent, bitangent and normal, with tangent x bitangent = normal, that
+    // stay continuous around the poles (Duff et al. 2017). A null vector gets
+    // the world axes.
+    pub fn orthonormal_basis(self) -> (Tuple, Tuple, Tuple) {
+        if self.magnitude() < EPSILON {
+            return (
+                Tuple::vector(1.0, 0.0, 0.0),
+                Tuple::vector(0.0, 1.0, 0.0),
+                Tuple::vector(0.0, 0.0, 1.0),
+            );
+        }
+        let n = Tuple::vector(self.x(), self.y(), self.z()).normalize();
+        let sign = (1.0 as Float).copysign(n.z());
+        let a = -1.0 / (sign + n.z());
+        let b = n.x() * n.y() * a;
+        let tangent = Tuple::vector(1.0 + sign * n.x() * n.x() * a, sign * b, -sign * n.x());
+        let bitangent = Tuple::vector(b, sign + n.y() * n.y() * a, -n.y());
+        (tangent, bitangent, n)
+    }
+}
+
+// x, y, z and w by index, e.g. to loop over the axes.
+impl ops::Index<usize> for Tuple {
+    type Output = Float;
+
+    fn index(&self, index: usize) -> &Float {
+        match index {
+            0 => &self.0,
+            1 => &self.1,
+            2 => &self.2,
+            3 => &self.3,
+            _ => panic!("Tuple index {} is out of bounds", index),
+        }
+    }
+}
+
+impl ops::IndexMut<usize> for Tuple {
+    fn index_mut(&mut self, index: usize) -> &mut Float {
+        match index {
+            0 => &mut self.0,
+            1 => &mut self.1,
+            2 => &mut self.2,
+            3 => &mut self.3,
+            _ => panic!("Tuple index {} is out of bounds", index),
+        }
+    }
 }
 
 impl cmp::PartialEq for Tuple {
@@ -138,6 +216,27 @@ impl ops::Div<Float> for Tuple {
     }
 }
 
+// `point(x, y, z)`, `vector(x, y, z)` or `tuple(x, y, z, w)`, with the
+// precision of the format applied to each coordinate.
+impl fmt::Display for Tuple {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let coordinate = |v: Float| match f.precision() {
+            Some(precision) => format!("{:.*}", precision, v),
+            None => v.to_string(),
+        };
+        let (x, y, z) = (
+            coordinate(self.x()),
+            coordinate(self.y()),
+            coordinate(self.z()),
+        );
+        match self.w() {
+            1.0 => write!(f, "point({}, {}, {})", x, y, z),
+            0.0 => write!(f, "vector({}, {}, {})", x, y, z),
+            w => write!(f, "tuple({}, {}, {}, {})", x, y, z, coordinate(w)),
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -294,6 +393,35 @@ mod tests {
         assert_eq!(b.cross(a), Tuple::vector(1.0, -2.0, 1.0));
     }
 
+    #[test]
+    fn building_an_orthonormal_basis_from_a_normal() {
+        for n in [
+            Tuple::vector(1.0, 2.0, 3.0),
+            Tuple::vector(0.0, 0.0, 1.0),
+            Tuple::vector(0.0, 0.0, -1.0),
+            Tuple::vector(0.0, 0.0, -0.0),
+            Tuple::vector(1e-7, 0.0, -1.0),
+            Tuple::vector(0.0, -1.0, 0.0),
+        ] {
+            let (t, b, normal) = n.orthonormal_basis();
+            for v in [t, b, normal] {
+                assert!(equals(v.magnitude(), 1.0), "{:?}", n);
+                assert_eq!(v.w(), 0.0);
+            }
+            assert!(equals(t.dot(b), 0.0) && equals(t.dot(normal), 0.0));
+            assert!(equals(b.dot(normal), 0.0));
+            assert_eq!(t.cross(b), normal);
+        }
+        assert_eq!(
+            Tuple::vector(0.0, 0.0, 2.0).orthonormal_basis(),
+            (
+                Tuple::vector(1.0, 0.0, 0.0),
+                Tuple::vector(0.0, 1.0, 0.0),
+                Tuple::vector(0.0, 0.0, 1.0)
+            )
+        );
+    }
+
     #[test]
     fn reflecting_a_vector_approaching_at_45() {
         let v = Tuple::vector(1.0, -1.0, 0.0);
@@ -309,4 +437,49 @@ mod tests {
         let r = v.reflect(n);
         assert_eq!(r, Tuple::vector(1.0, 0.0, 0.0));
     }
+
+    #[test]
+    fn displaying_tuples() {
+        assert_eq!(
+            format!("{}", Tuple::point(1.0, -2.5, 0.0)),
+            "point(1, -2.5, 0)"
+        );
+        assert_eq!(
+            format!("{:.2}", Tuple::vector(1.0, 0.5, 1.0 / 3.0)),
+            "vector(1.00, 0.50, 0.33)"
+        );
+        assert_eq!(
+            format!("{}", Tuple::new(1.0, 2.0, 3.0, 0.5)),
+            "tuple(1, 2, 3, 0.5)"
+        );
+    }
+
+    #[test]
+    fn component_wise_helpers() {
+        let a = Tuple::point(1.0, -2.0, 3.0);
+        let b = Tuple::point(-1.0, 0.0, 5.0);
+        assert_eq!(a.min(b), Tuple::point(-1.0, -2.0, 3.0));
+        assert_eq!(a.max(b), Tuple::point(1.0, 0.0, 5.0));
+        assert_eq!(
+            Tuple::vector(-1.0, 2.0, -3.0).abs(),
+            Tuple::vector(1.0, 2.0, 3.0)
+        );
+        let (low, high) = (Tuple::point(0.0, 0.0, 0.0), Tuple::point(2.0, 2.0, 2.0));
+        assert_eq!(a.clamp(low, high), Tuple::point(1.0, 0.0, 2.0));
+        assert_eq!(a.max_component(), 3.0);
+    }
+
+    #[test]
+    fn accessing_components_by_index() {
+        let mut v = Tuple::vector(1.0, 2.0, 3.0);
+        assert_eq!((v[0], v[1], v[2], v[3]), (1.0, 2.0, 3.0, 0.0));
+        v[1] = 5.0;
+        assert_eq!(v, Tuple::vector(1.0, 5.0, 3.0));
+    }
+
+    #[test]
+    #[should_panic(expected = "Tuple index 4 is out of bounds")]
+    fn indexing_past_w_panics() {
+        let _ = Tuple::vector(1.0, 2.0, 3.0)[4];
+    }
 }
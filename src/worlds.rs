@@ -1,120 +1,945 @@
+use crate::accelerators::{self, Accelerator, AcceleratorKind};
+use crate::bounds::Bounds;
 use crate::colors::{Color, BLACK, WHITE};
-use crate::intersections::{self, schlick, Intersection, IntersectionComputations};
+use crate::floats::{rand, Float, EPSILON, PI};
+#[cfg(not(feature = "polarization"))]
+use crate::intersections::schlick;
+use crate::intersections::{self, Intersection, IntersectionComputations};
 use crate::lights::Light;
-use crate::objects::Object;
+use crate::materials::{Material, MaterialLibrary};
+use crate::matrices::Matrix;
+use crate::media::Fog;
+use crate::objects::{update_shared, Object};
+#[cfg(feature = "polarization")]
+use crate::polarization::Polarization;
 use crate::rays::Ray;
+use crate::skies::Sky;
+use crate::tuples::Tuple;
+use std::fmt::{self, Write};
+use std::path::Path;
+use std::ptr;
+use std::sync::Arc;
+
+#[derive(Clone, Copy)]
+struct QueuedRay {
+    ray: Ray,
+    min_t: Float,
+    weight: Color,
+    depth: Depth,
+    sample: usize,
+    #[cfg(feature = "polarization")]
+    polarization: Polarization,
+}
+
+impl QueuedRay {
+    fn primary(ray: Ray, min_t: Float, depth: Depth, sample: usize) -> QueuedRay {
+        QueuedRay {
+            ray,
+            min_t,
+            weight: WHITE,
+            depth,
+            sample,
+            #[cfg(feature = "polarization")]
+            polarization: Polarization::unpolarized(),
+        }
+    }
+
+    fn next(
+        &self,
+        comps: &IntersectionComputations,
+        ray: Ray,
+        weight: Color,
+        depth: Depth,
+    ) -> QueuedRay {
+        QueuedRay {
+            min_t: World::min_hit_t(comps, &ray),
+            ray,
+            weight,
+            depth,
+            ..*self
+        }
+    }
+
+    #[cfg(not(feature = "polarization"))]
+    fn reflectance(&self, comps: &IntersectionComputations) -> Float {
+        schlick(comps)
+    }
+
+    // The exact Fresnel reflectance for the polarization of the ray.
+    #[cfg(feature = "polarization")]
+    fn reflectance(&self, comps: &IntersectionComputations) -> Float {
+        self.polarization.reflectance(comps)
+    }
+
+    #[cfg(not(feature = "polarization"))]
+    fn reflected(self, _comps: &IntersectionComputations) -> QueuedRay {
+        self
+    }
+
+    #[cfg(feature = "polarization")]
+    fn reflected(self, comps: &IntersectionComputations) -> QueuedRay {
+        QueuedRay {
+            polarization: self.polarization.reflected(comps),
+            ..self
+        }
+    }
+
+    #[cfg(not(feature = "polarization"))]
+    fn refracted(self, _comps: &IntersectionComputations) -> QueuedRay {
+        self
+    }
+
+    #[cfg(feature = "polarization")]
+    fn refracted(self, comps: &IntersectionComputations) -> QueuedRay {
+        QueuedRay {
+            polarization: self.polarization.refracted(comps),
+            ..self
+        }
+    }
+}
+
+// Bounces left for a ray: in total, and for each kind of secondary ray.
+#[derive(Debug, Clone, Copy)]
+struct Depth {
+    total: usize,
+    reflections: usize,
+    refractions: usize,
+}
+
+impl Depth {
+    fn reflected(self) -> Option<Depth> {
+        (self.total > 0 && self.reflections > 0).then(|| Depth {
+            total: self.total - 1,
+            reflections: self.reflections - 1,
+            ..self
+        })
+    }
+
+    // Crossing a portal is not a reflection, but still uses one bounce so
+    // that facing portals end.
+    fn teleported(self) -> Option<Depth> {
+        (self.total > 0).then(|| Depth {
+            total: self.total - 1,
+            ..self
+        })
+    }
+
+    fn refracted(self) -> Option<Depth> {
+        (self.total > 0 && self.refractions > 0).then(|| Depth {
+            total: self.total - 1,
+            refractions: self.refractions - 1,
+            ..self
+        })
+    }
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Bounce {
+    Reflected,
+    Refracted,
+    Coated,
+    Teleported,
+}
+
+struct Shading {
+    ambient: Color,
+    diffuse: Color,
+    specular: Color,
+    // The sum of the terms, clamped to the maximum bounce value.
+    local: Color,
+    reflectance: Option<Float>,
+    secondary: Vec<(Bounce, QueuedRay)>,
+}
+
+#[derive(Debug, Clone)]
+pub struct RayTrace {
+    pub ray: Ray,
+    // Relative to the hit that sent the ray, white for camera rays.
+    pub weight: Color,
+    pub intersections: Vec<Float>,
+    pub hit: Option<HitTrace>,
+    pub color: Color,
+}
+
+#[derive(Debug, Clone)]
+pub struct HitTrace {
+    pub t: Float,
+    pub object: String,
+    pub point: Tuple,
+    pub normalv: Tuple,
+    pub inside: bool,
+    pub ambient: Color,
+    pub diffuse: Color,
+    pub specular: Color,
+    pub reflectance: Option<Float>,
+    pub reflected: Vec<RayTrace>,
+    pub refracted: Vec<RayTrace>,
+    pub coated: Vec<RayTrace>,
+    pub teleported: Vec<RayTrace>,
+}
+
+// The first surface hit by a ray, as returned by `World::trace`.
+#[derive(Debug, Clone)]
+pub struct HitInfo<'a> {
+    pub t: Float,
+    pub point: Tuple,
+    // Unit surface normal, flipped to face the ray origin.
+    pub normal: Tuple,
+    pub inside: bool,
+    pub u: Float,
+    pub v: Float,
+    pub object: &'a Object,
+    pub material: &'a Material,
+    // Shaded color seen along the ray, including reflections and refractions.
+    pub color: Color,
+}
+
+// The primitive and isoline cell seen along a ray, see `World::wire_cell`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct WireCell {
+    object: usize,
+    instance: usize,
+    cell: [i64; 2],
+}
 
 #[derive(Debug, Clone)]
 pub struct World {
+    pub accelerator: AcceleratorKind,
+    acceleration: Option<Arc<dyn Accelerator>>,
     pub ambient_light: Color,
+    // Hits closer than this to the camera are ignored, so that a camera
+    // placed inside an object sees out of it.
+    pub camera_t_min: Float,
+    pub fog: Option<Fog>,
     pub lights: Vec<Light>,
-    pub objects: Vec<Object>,
+    pub material_override: Option<Material>,
+    pub materials: MaterialLibrary,
+    pub max_bounce_value: Float,
+    pub max_reflection_depth: usize,
+    pub max_refraction_depth: usize,
+    pub objects: Vec<Arc<Object>>,
+    // The top level planes, recorded by `prepare`.
+    planes: Vec<usize>,
+    pub shadow_bias: Float,
+    // Shadow rays ignore occluders closer than this to the shaded point.
+    pub shadow_t_min: Float,
+    pub sky: Option<Sky>,
+    pub white_balance: Option<Float>,
 }
 
 impl World {
     pub fn new() -> World {
         World {
+            accelerator: AcceleratorKind::Auto,
+            acceleration: None,
             ambient_light: WHITE,
+            camera_t_min: 0.0,
+            fog: None,
             lights: vec![],
+            material_override: None,
+            materials: MaterialLibrary::new(),
+            max_bounce_value: Float::INFINITY,
+            max_reflection_depth: usize::MAX,
+            max_refraction_depth: usize::MAX,
             objects: vec![],
+            planes: vec![],
+            shadow_bias: EPSILON,
+            shadow_t_min: 0.0,
+            sky: None,
+            white_balance: None,
+        }
+    }
+
+    pub fn add_object(&mut self, object: Object) {
+        self.objects.push(Arc::new(object));
+    }
+
+    // Adds the objects and lights of `other`, e.g. a prefab read from a scene
+    // file, moved by `transform`. Its library materials are added too, unless
+    // this world defines one of their names differently: the merged objects
+    // would take the wrong material, so nothing is merged.
+    pub fn merge(&mut self, other: World, transform: Matrix<4>) -> Result<(), String> {
+        for (name, material) in other.materials.iter() {
+            if self.materials.get(name).is_some_and(|m| !m.identical(material)) {
+                return Err(format!("material '{}' is already defined differently", name));
+            }
+        }
+        for o in other.objects {
+            let o = Arc::unwrap_or_clone(o);
+            let moved = transform * o.transform;
+            self.add_object(o.with_transform(moved));
+        }
+        self.lights
+            .extend(other.lights.into_iter().map(|l| l.transformed(transform)));
+        for (name, material) in other.materials.iter() {
+            if !self.materials.contains(name) {
+                self.materials.insert(name, material.clone());
+            }
+        }
+        Ok(())
+    }
+
+    // Infinite objects, like planes, are left out so that the result can be
+    // used to frame the scene. The world must have been prepared first.
+    pub fn bounds(&self) -> Bounds {
+        let mut bounds = Bounds::empty();
+        for o in &self.objects {
+            let b = o.parent_bounds();
+            if b.is_finite() {
+                bounds.merge(&b);
+            }
         }
+        bounds
+    }
+
+    pub fn describe(&self) -> String {
+        let mut out = String::from("world\n");
+        for l in &self.lights {
+            out.push_str(&format!("  {}\n", l.describe()));
+        }
+        for o in &self.objects {
+            for line in o.tree_string().lines() {
+                out.push_str(&format!("  {}\n", line));
+            }
+        }
+        out
     }
 
     pub fn prepare(&mut self) {
+        let library = &self.materials;
         for o in &mut self.objects {
-            o.prepare();
+            let changes = |o: &Object| {
+                o.resolves_new_material(library)
+                    || o.children_inherit_new_material()
+                    || o.transforms_changed()
+            };
+            update_shared(o, changes, |o| {
+                o.resolve_materials(library);
+                o.prepare();
+            });
         }
+        self.acceleration = accelerators::build(self.accelerator, &self.objects);
+        self.planes = (0..self.objects.len())
+            .filter(|&i| self.objects[i].is_plane())
+            .collect();
     }
 
-    fn intersect<'a>(&'a self, ray: &Ray) -> Vec<Intersection<'a>> {
+    fn intersect_objects<'a>(&'a self, ray: &Ray, xs: &mut Vec<Intersection<'a>>) {
+        match &self.acceleration {
+            Some(a) if a.object_count() == self.objects.len() => {
+                a.intersect(&self.objects, ray, xs)
+            }
+            _ => {
+                for o in &self.objects {
+                    o.intersect(ray, xs);
+                }
+            }
+        }
+    }
+
+    // The intersections up to the first one past `t_min`, and maybe more,
+    // sorted. Enough to find the hit and the refractive indices around it.
+    fn intersect_until_hit<'a>(&'a self, ray: &Ray, t_min: Float) -> Vec<Intersection<'a>> {
         let mut intersections = Vec::with_capacity(self.objects.len() * 2);
-        for o in &self.objects {
-            o.intersect(ray, &mut intersections);
+        match &self.acceleration {
+            Some(a) if a.object_count() == self.objects.len() => {
+                a.intersect_until_hit(&self.objects, ray, t_min, &mut intersections)
+            }
+            _ => self.intersect_objects(ray, &mut intersections),
         }
         intersections.sort_by(|i1, i2| i1.t.partial_cmp(&i2.t).unwrap());
         intersections
     }
 
-    fn reflected_color(
-        &self,
-        hit: &Intersection,
-        comps: &IntersectionComputations,
-        depth: usize,
-    ) -> Color {
-        if depth == 0 || hit.object.material.reflective == 0.0 {
-            return BLACK;
+    fn intersect<'a>(&'a self, ray: &Ray) -> Vec<Intersection<'a>> {
+        let mut intersections = Vec::with_capacity(self.objects.len() * 2);
+        self.intersect_objects(ray, &mut intersections);
+        intersections.sort_by(|i1, i2| i1.t.partial_cmp(&i2.t).unwrap());
+        intersections
+    }
+
+    pub fn intersect_any(&self, ray: &Ray, max_t: Float) -> bool {
+        self.occluded_except(ray, max_t, None)
+    }
+
+    pub fn occluded(&self, p0: Tuple, p1: Tuple) -> bool {
+        let v = p1 - p0;
+        let distance = v.magnitude();
+        if distance < EPSILON {
+            return false;
         }
-        let reflect_ray = Ray::new(comps.over_point, comps.reflectv);
-        let color = self.color_at(&reflect_ray, depth - 1);
-        color * hit.object.material.reflective
+        self.intersect_any(&Ray::new(p0, v / distance), distance)
     }
 
-    fn refracted_color(
-        &self,
-        hit: &Intersection,
+    pub fn distance_to_nearest(&self, ray: &Ray) -> Option<Float> {
+        let xs = self.intersect(ray);
+        intersections::hit(&xs).map(|hit| hit.t * ray.direction.magnitude())
+    }
+
+    fn occluded_except(&self, ray: &Ray, max_t: Float, except: Option<&Object>) -> bool {
+        let ray = &ray.with_range(self.shadow_t_min, max_t);
+        let blocks = |x: &Intersection| {
+            x.t < max_t && except.is_none_or(|e| !ptr::eq(x.shading_object(), e))
+        };
+        let mut xs = Vec::new();
+        let mut blocked_by = |i: usize| {
+            xs.clear();
+            self.objects[i].intersect(ray, &mut xs);
+            xs.iter().any(blocks)
+        };
+        // Planes have infinite bounds, so culling never skips them, but they
+        // are cheap to test exactly: a floor shadowing the light saves the
+        // traversal of everything else.
+        match &self.acceleration {
+            Some(a) if a.object_count() == self.objects.len() => {
+                self.planes.iter().any(|&i| blocked_by(i))
+                    || a.intersect_any(&self.objects, ray, &self.planes, &blocks)
+            }
+            _ => {
+                let is_plane = |&i: &usize| self.objects[i].is_plane();
+                let planes = (0..self.objects.len()).filter(is_plane);
+                let others = (0..self.objects.len()).filter(|i| !is_plane(i));
+                planes.chain(others).any(blocked_by)
+            }
+        }
+    }
+
+    fn reflected_ray(comps: &IntersectionComputations, roughness: Float) -> Ray {
+        let mut direction = comps.reflectv;
+        if roughness > 0.0 {
+            let jittered = cone_sample(comps.reflectv, roughness.min(1.0) * PI / 2.0);
+            if jittered.dot(comps.normalv) > 0.0 {
+                direction = jittered;
+            }
+        }
+        let ray = Ray::new(comps.over_point, direction);
+        match comps.differentials {
+            Some(d) => ray.with_differentials(d.reflect(comps.normalv)),
+            None => ray,
+        }
+    }
+
+    // Secondary rays skip hits closer than the surface offset they start from,
+    // so that glancing rays do not find the surface they leave again.
+    fn min_hit_t(comps: &IntersectionComputations, ray: &Ray) -> Float {
+        (comps.over_point - comps.point).magnitude() / ray.direction.magnitude()
+    }
+
+    fn portal_ray(comps: &IntersectionComputations, portal: Matrix<4>) -> Ray {
+        Ray::new(portal * comps.point, portal * -comps.eyev)
+    }
+
+    fn reflected_rays(
         comps: &IntersectionComputations,
-        depth: usize,
-    ) -> Color {
-        if depth == 0 || hit.object.material.transparency == 0.0 {
-            return BLACK;
+        roughness: Float,
+        glossy_samples: usize,
+    ) -> Vec<Ray> {
+        let samples = if roughness > 0.0 {
+            glossy_samples.max(1)
+        } else {
+            1
+        };
+        (0..samples)
+            .map(|_| World::reflected_ray(comps, roughness))
+            .collect()
+    }
+
+    fn refracted_ray(comps: &IntersectionComputations) -> Option<Ray> {
+        World::refracted_ray_between(comps, comps.n1, comps.n2)
+    }
+
+    // Dispersive materials split the refracted ray into one ray per color channel.
+    fn refracted_rays(comps: &IntersectionComputations, material: &Material) -> Vec<(Ray, Color)> {
+        if material.dispersion == 0.0 {
+            return World::refracted_ray(comps)
+                .map(|r| (r, WHITE))
+                .into_iter()
+                .collect();
         }
-        let n_ratio = comps.n1 / comps.n2;
+        material
+            .dispersed_indices()
+            .iter()
+            .filter_map(|&(channel, n)| {
+                let (n1, n2) = if comps.inside {
+                    (n, comps.n2)
+                } else {
+                    (comps.n1, n)
+                };
+                World::refracted_ray_between(comps, n1, n2).map(|r| (r, channel))
+            })
+            .collect()
+    }
+
+    fn refracted_ray_between(
+        comps: &IntersectionComputations,
+        n1: Float,
+        n2: Float,
+    ) -> Option<Ray> {
+        let n_ratio = n1 / n2;
         let cos_i = comps.eyev.dot(comps.normalv);
         let sin2_t = n_ratio.powi(2) * (1.0 - cos_i.powi(2));
         if sin2_t > 1.0 {
-            return BLACK;
+            return None;
         }
         let cos_t = (1.0 - sin2_t).sqrt();
         let direction = comps.normalv * (n_ratio * cos_i - cos_t) - comps.eyev * n_ratio;
-        let refract_ray = Ray::new(comps.under_point, direction);
-        let color = self.color_at(&refract_ray, depth - 1);
-        color * hit.object.material.transparency
+        let ray = Ray::new(comps.under_point, direction);
+        Some(match comps.differentials {
+            Some(d) => {
+                ray.with_differentials(d.refract(-comps.eyev, direction, comps.normalv, n_ratio))
+            }
+            None => ray,
+        })
     }
 
-    fn shade_hit(
-        &self,
-        hit: &Intersection,
-        comps: &IntersectionComputations,
-        depth: usize,
-    ) -> Color {
-        let shadowed_lights = self
-            .lights
-            .iter()
+    fn material_of<'a>(&'a self, object: &'a Object) -> &'a Material {
+        match &self.material_override {
+            Some(material) => material,
+            None => &object.material,
+        }
+    }
+
+    fn shadowed_lights(&self, comps: &IntersectionComputations) -> Vec<Light> {
+        if self.material_of(comps.object).translucency == 0.0 {
+            return self.lights_at(comps.over_point);
+        }
+        self.all_lights()
             .map(|l| {
-                l.shadowed(comps.over_point, |r| {
-                    let xs = self.intersect(r);
-                    intersections::hit(&xs).map(|h| h.t)
+                let light = l.white_balanced(self.white_balance);
+                if (light.position - comps.point).dot(comps.normalv) >= 0.0 {
+                    return light
+                        .shadowed(comps.over_point, |r, max_t| self.intersect_any(r, max_t));
+                }
+                light.shadowed(comps.under_point, |r, max_t| {
+                    self.occluded_except(r, max_t, Some(comps.object))
                 })
             })
-            .collect();
-        let surface = hit.object.material.lighting(
-            hit.object,
+            .collect()
+    }
+
+    // The scene lights, and the sun when there is a sky.
+    fn all_lights(&self) -> impl Iterator<Item = Light> + '_ {
+        let sun = self.sky.as_ref().map(Sky::sun_light);
+        self.lights.iter().cloned().chain(sun)
+    }
+
+    fn background(&self, ray: &Ray) -> Color {
+        self.sky
+            .as_ref()
+            .map_or(BLACK, |sky| sky.color_at(ray.direction))
+    }
+
+    fn lights_at(&self, point: Tuple) -> Vec<Light> {
+        self.all_lights()
+            .map(|l| {
+                l.white_balanced(self.white_balance)
+                    .shadowed(point, |r, max_t| self.intersect_any(r, max_t))
+            })
+            .collect()
+    }
+
+    fn fog_scatter(&self, ray: &Ray, hit: Option<&Intersection>) -> (Float, Color) {
+        let Some(fog) = &self.fog else {
+            return (1.0, BLACK);
+        };
+        let speed = ray.direction.magnitude();
+        let (distance, transmittance) = match hit {
+            Some(hit) => (hit.t * speed, fog.transmittance(hit.t * speed)),
+            None => (Float::INFINITY, fog.transmittance(Float::INFINITY)),
+        };
+        let mut scattered = fog.color * self.ambient_light * (1.0 - transmittance);
+        for (s, weight) in fog.sample_distances(distance) {
+            for light in self.lights_at(ray.position(s / speed)) {
+                scattered = scattered + fog.color * light.intensity * weight;
+            }
+        }
+        (transmittance, scattered)
+    }
+
+    fn surface_terms(&self, comps: &IntersectionComputations) -> (Color, Color, Color) {
+        let material = self.material_of(comps.object);
+        material.lighting_terms_with_color(
+            material.color_at(
+                comps.object,
+                comps.over_point,
+                comps.normalv,
+                comps.eyev,
+                comps.footprint,
+            ),
             self.ambient_light,
-            &shadowed_lights,
+            &self.shadowed_lights(comps),
             comps.over_point,
             comps.eyev,
-            comps.normalv,
-        );
-        let reflected = self.reflected_color(hit, comps, depth);
-        let refracted = self.refracted_color(hit, comps, depth);
-        let material = &hit.object.material;
-        if material.reflective > 0.0 && material.transparency > 0.0 {
-            let reflectance = schlick(comps);
-            surface + reflected * reflectance + refracted * (1.0 - reflectance)
-        } else {
-            surface + reflected + refracted
+            (
+                comps.normalv,
+                material.tangent_at(
+                    comps.object,
+                    comps.over_point,
+                    comps.normalv,
+                    &comps.attributes,
+                ),
+            ),
+        )
+    }
+
+    fn absorbed(&self, ray: &Ray, comps: &IntersectionComputations) -> Color {
+        if !comps.inside {
+            return WHITE;
         }
+        self.material_of(comps.object)
+            .transmittance(comps.t * ray.direction.magnitude())
+    }
+
+    // One shading step: what the hit contributes by itself, and the
+    // secondary rays that gather the rest, weighted relative to the hit.
+    // Rendering and ray debugging both trace from here.
+    fn shade(&self, comps: &IntersectionComputations, q: &QueuedRay) -> Shading {
+        let depth = q.depth;
+        let material = self.material_of(comps.object);
+        if let Some(portal) = material.portal
+            && let Some(depth) = depth.teleported()
+        {
+            let ray = World::portal_ray(comps, portal);
+            return Shading {
+                ambient: BLACK,
+                diffuse: BLACK,
+                specular: BLACK,
+                local: BLACK,
+                reflectance: None,
+                secondary: vec![(Bounce::Teleported, q.next(comps, ray, WHITE, depth))],
+            };
+        }
+        let (ambient, diffuse, specular) = self.surface_terms(comps);
+        let mut shading = Shading {
+            ambient,
+            diffuse,
+            specular,
+            local: (ambient + diffuse + specular).clamped(self.max_bounce_value),
+            reflectance: None,
+            secondary: vec![],
+        };
+        if depth.total == 0 {
+            return shading;
+        }
+        let reflectance = material.fresnel_weighted().then(|| q.reflectance(comps));
+        shading.reflectance = reflectance;
+        let coat = material.coat_reflectance(comps.eyev, comps.normalv);
+        if let Some(clear_coat) = &material.clear_coat
+            && let Some(depth) = depth.reflected()
+        {
+            let rays = World::reflected_rays(comps, clear_coat.roughness, material.glossy_samples);
+            let weight = WHITE * (coat / rays.len() as Float);
+            for ray in rays {
+                let next = q.next(comps, ray, weight, depth);
+                shading.secondary.push((Bounce::Coated, next));
+            }
+        }
+        let base = WHITE * (1.0 - coat);
+        if material.reflective > 0.0
+            && let Some(depth) = depth.reflected()
+        {
+            let rays = World::reflected_rays(comps, material.roughness, material.glossy_samples);
+            let weight = base
+                * material.reflection_tint()
+                * reflectance.unwrap_or(1.0)
+                * (1.0 / rays.len() as Float);
+            for ray in rays {
+                let next = q.next(comps, ray, weight, depth);
+                let next = match reflectance {
+                    Some(_) => next.reflected(comps),
+                    None => next,
+                };
+                shading.secondary.push((Bounce::Reflected, next));
+            }
+        }
+        if material.transparency > 0.0
+            && let Some(depth) = depth.refracted()
+        {
+            let weight = base * (material.transparency * (1.0 - reflectance.unwrap_or(0.0)));
+            for (ray, channel) in World::refracted_rays(comps, material) {
+                if weight * channel == BLACK {
+                    continue;
+                }
+                let next = q.next(comps, ray, weight * channel, depth);
+                let next = match reflectance {
+                    Some(_) => next.refracted(comps),
+                    None => next,
+                };
+                shading.secondary.push((Bounce::Refracted, next));
+            }
+        }
+        shading
+    }
+
+    fn trace_queue(&self, mut queue: Vec<QueuedRay>, colors: &mut [Color]) {
+        while !queue.is_empty() {
+            let hits: Vec<Vec<Intersection>> = queue
+                .iter()
+                .map(|q| self.intersect_until_hit(&q.ray, q.min_t))
+                .collect();
+            let mut next = Vec::with_capacity(queue.len());
+            for (q, xs) in queue.iter().zip(&hits) {
+                let hit = intersections::hit_after(xs, q.min_t).map(|hit| {
+                    let comps = hit.prepare_computations_with_bias(&q.ray, xs, self.shadow_bias);
+                    (hit, comps)
+                });
+                let hit = hit.as_ref().map(|(hit, comps)| (*hit, comps));
+                self.gather(q, hit, colors, &mut next);
+            }
+            queue = next;
+        }
+    }
+
+    // Adds to `colors` what the queued ray sees up to its hit, if any, and
+    // queues the secondary rays of the hit.
+    fn gather(
+        &self,
+        q: &QueuedRay,
+        hit: Option<(&Intersection, &IntersectionComputations)>,
+        colors: &mut [Color],
+        next: &mut Vec<QueuedRay>,
+    ) {
+        let (transmittance, scattered) = self.fog_scatter(&q.ray, hit.map(|(hit, _)| hit));
+        colors[q.sample] = colors[q.sample] + q.weight * scattered;
+        let Some((_, comps)) = hit else {
+            colors[q.sample] =
+                colors[q.sample] + q.weight * self.background(&q.ray) * transmittance;
+            return;
+        };
+        let weight = q.weight * transmittance * self.absorbed(&q.ray, comps);
+        let shading = self.shade(comps, q);
+        colors[q.sample] = colors[q.sample] + weight * shading.local;
+        next.extend(shading.secondary.into_iter().map(|(_, s)| QueuedRay {
+            weight: weight * s.weight,
+            ..s
+        }));
+    }
+
+    // The color of one shading step, with its secondary rays traced to the
+    // end. Only the secondary rays of `bounce` are kept when it is given,
+    // without the surface itself.
+    #[cfg(test)]
+    fn shade_bounces(
+        &self,
+        comps: &IntersectionComputations,
+        depth: usize,
+        bounce: Option<Bounce>,
+    ) -> Color {
+        let ray = Ray::new(comps.point, -comps.eyev);
+        let q = QueuedRay::primary(ray, 0.0, self.depth(depth), 0);
+        let shading = self.shade(comps, &q);
+        let mut colors = [match bounce {
+            Some(_) => BLACK,
+            None => shading.local,
+        }];
+        let queue = shading
+            .secondary
+            .into_iter()
+            .filter(|&(b, _)| bounce.is_none_or(|bounce| b == bounce))
+            .map(|(_, q)| q)
+            .collect();
+        self.trace_queue(queue, &mut colors);
+        colors[0]
+    }
+
+    #[cfg(test)]
+    fn shade_hit(&self, comps: &IntersectionComputations, depth: usize) -> Color {
+        self.shade_bounces(comps, depth, None)
+    }
+
+    #[cfg(test)]
+    fn reflected_color(&self, comps: &IntersectionComputations, depth: usize) -> Color {
+        self.shade_bounces(comps, depth, Some(Bounce::Reflected))
+    }
+
+    #[cfg(test)]
+    fn refracted_color(&self, comps: &IntersectionComputations, depth: usize) -> Color {
+        self.shade_bounces(comps, depth, Some(Bounce::Refracted))
+    }
+
+    pub fn color_at_batch(&self, rays: &[Ray], depth: usize) -> Vec<Color> {
+        let mut colors = vec![BLACK; rays.len()];
+        let queue = rays
+            .iter()
+            .enumerate()
+            .map(|(sample, ray)| {
+                QueuedRay::primary(*ray, self.camera_t_min, self.depth(depth), sample)
+            })
+            .collect();
+        self.trace_queue(queue, &mut colors);
+        colors
     }
 
     pub fn color_at(&self, ray: &Ray, depth: usize) -> Color {
+        self.color_at_batch(std::slice::from_ref(ray), depth)[0]
+    }
+
+    // Writes the world bounds of every object, down to the group children,
+    // as box meshes named after their depth in the tree. Infinite bounds are
+    // left out. The world must have been prepared first.
+    pub fn export_bounds_obj(&self, path: &Path) -> Result<(), String> {
+        std::fs::write(path, self.bounds_obj()).map_err(|e| format!("{}: {}", path.display(), e))
+    }
+
+    pub fn bounds_obj(&self) -> String {
+        let mut boxes = vec![];
+        for o in &self.objects {
+            o.bounds_tree(&Matrix::identity(), 0, &mut boxes);
+        }
+        let mut out = String::new();
+        let mut vertices = 0;
+        for (i, (depth, b)) in boxes.iter().filter(|(_, b)| b.is_finite()).enumerate() {
+            writeln!(out, "o box{}_depth{}", i, depth).unwrap();
+            for corner in 0..8 {
+                let pick =
+                    |bit: usize, min: Float, max: Float| if corner & bit == 0 { min } else { max };
+                writeln!(
+                    out,
+                    "v {} {} {}",
+                    pick(1, b.min.x(), b.max.x()),
+                    pick(2, b.min.y(), b.max.y()),
+                    pick(4, b.min.z(), b.max.z())
+                )
+                .unwrap();
+            }
+            // Corners are numbered by their x, y and z bits.
+            for face in [
+                [0, 2, 3, 1],
+                [4, 5, 7, 6],
+                [0, 1, 5, 4],
+                [2, 6, 7, 3],
+                [0, 4, 6, 2],
+                [1, 3, 7, 5],
+            ] {
+                let [a, b, c, d] = face.map(|v| vertices + v + 1);
+                writeln!(out, "f {} {} {} {}", a, b, c, d).unwrap();
+            }
+            vertices += 8;
+        }
+        out
+    }
+
+    // Finds the closest hit past `camera_t_min` and shades it with up to
+    // `depth` bounces. The world must have been prepared first.
+    pub fn trace(&self, ray: &Ray, depth: usize) -> Option<HitInfo<'_>> {
         let xs = self.intersect(ray);
-        if let Some(hit) = intersections::hit(&xs) {
-            let comps = hit.prepare_computations(ray, &xs);
-            self.shade_hit(hit, &comps, depth)
-        } else {
-            crate::colors::BLACK
+        let hit = intersections::hit_after(&xs, self.camera_t_min)?;
+        let object = hit.shading_object();
+        let comps = hit.prepare_computations_with_bias(ray, &xs, self.shadow_bias);
+        let q = QueuedRay::primary(*ray, self.camera_t_min, self.depth(depth), 0);
+        let mut color = [BLACK];
+        let mut queue = vec![];
+        self.gather(&q, Some((hit, &comps)), &mut color, &mut queue);
+        self.trace_queue(queue, &mut color);
+        Some(HitInfo {
+            t: hit.t,
+            point: comps.point,
+            normal: comps.normalv,
+            inside: comps.inside,
+            u: hit.u,
+            v: hit.v,
+            object,
+            material: self.material_of(object),
+            color: color[0],
+        })
+    }
+
+    // Identifies the surface hit by a ray, down to the isoline cell when
+    // `isolines` is not zero. Wireframes are drawn where neighbouring cells differ.
+    pub fn wire_cell(&self, ray: &Ray, isolines: Float) -> Option<WireCell> {
+        let xs = self.intersect(ray);
+        let hit = intersections::hit_after(&xs, self.camera_t_min)?;
+        let (s, t) = hit.surface_coordinates(ray.position(hit.t));
+        Some(WireCell {
+            object: ptr::from_ref(hit.object) as usize,
+            instance: hit.instance.map_or(0, |i| ptr::from_ref(i) as usize),
+            cell: [(s * isolines).floor() as i64, (t * isolines).floor() as i64],
+        })
+    }
+
+    fn depth(&self, total: usize) -> Depth {
+        Depth {
+            total,
+            reflections: self.max_reflection_depth,
+            refractions: self.max_refraction_depth,
         }
     }
+
+    pub fn debug_ray(&self, ray: &Ray, depth: usize) -> RayTrace {
+        self.debug_queued(&QueuedRay::primary(
+            *ray,
+            self.camera_t_min,
+            self.depth(depth),
+            0,
+        ))
+    }
+
+    fn debug_queued(&self, q: &QueuedRay) -> RayTrace {
+        let ray = &q.ray;
+        let xs = self.intersect(ray);
+        let intersections = xs.iter().map(|x| x.t).collect();
+        let hit = intersections::hit_after(&xs, q.min_t);
+        let (transmittance, scattered) = self.fog_scatter(ray, hit);
+        let Some(hit) = hit else {
+            return RayTrace {
+                ray: *ray,
+                weight: q.weight,
+                intersections,
+                hit: None,
+                color: scattered + self.background(ray) * transmittance,
+            };
+        };
+        let comps = hit.prepare_computations_with_bias(ray, &xs, self.shadow_bias);
+        let shading = self.shade(&comps, q);
+        let mut color = shading.local;
+        let mut trace = HitTrace {
+            t: hit.t,
+            object: hit.object.tree_string().trim_end().to_string(),
+            point: comps.point,
+            normalv: comps.normalv,
+            inside: comps.inside,
+            ambient: shading.ambient,
+            diffuse: shading.diffuse,
+            specular: shading.specular,
+            reflectance: shading.reflectance,
+            reflected: vec![],
+            refracted: vec![],
+            coated: vec![],
+            teleported: vec![],
+        };
+        for (bounce, next) in &shading.secondary {
+            let secondary = self.debug_queued(next);
+            color = color + secondary.color * secondary.weight;
+            match bounce {
+                Bounce::Reflected => trace.reflected.push(secondary),
+                Bounce::Refracted => trace.refracted.push(secondary),
+                Bounce::Coated => trace.coated.push(secondary),
+                Bounce::Teleported => trace.teleported.push(secondary),
+            }
+        }
+        RayTrace {
+            ray: *ray,
+            weight: q.weight,
+            intersections,
+            hit: Some(trace),
+            color: color * self.absorbed(ray, &comps) * transmittance + scattered,
+        }
+    }
+}
+
+fn cone_sample(axis: Tuple, half_angle: Float) -> Tuple {
+    let (tangent, bitangent, axis) = axis.orthonormal_basis();
+    let cos_theta = 1.0 - (rand(0.5) + 0.5) * (1.0 - half_angle.cos());
+    let sin_theta = (1.0 - cos_theta.powi(2)).max(0.0).sqrt();
+    let phi = rand(PI);
+    (tangent * (sin_theta * phi.cos()) + bitangent * (sin_theta * phi.sin()) + axis * cos_theta)
+        .normalize()
+}
+
+impl fmt::Display for World {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.describe())
+    }
 }
 
 impl Default for World {
@@ -127,10 +952,10 @@ impl Default for World {
 pub mod tests {
     use super::*;
     use crate::colors::BLACK;
-    use crate::floats::SQRT_2;
+    use crate::floats::{equals, SQRT_2};
+    use crate::materials::{ClearCoat, Material};
     use crate::patterns::Pattern;
-    use crate::transformations::{scaling, translation};
-    use crate::tuples::Tuple;
+    use crate::transformations::{rotation_x, scaling, translation};
 
     pub fn default_world() -> World {
         let mut s1 = Object::new_sphere();
@@ -143,10 +968,30 @@ pub mod tests {
             Tuple::point(-10.0, 10.0, -10.0),
             Color::new(1.0, 1.0, 1.0),
         )];
-        w.objects = vec![s1, s2];
+        w.objects = [s1, s2].map(Arc::new).into();
         w
     }
 
+    #[test]
+    fn describing_the_default_world() {
+        let w = default_world();
+        assert_eq!(
+            w.describe(),
+            "world
+  point light at (-10, 10, -10) intensity (1, 1, 1)
+  sphere material color (0.8, 1, 0.6) ambient 0.1 diffuse 0.7 specular 0.2 shininess 200
+  sphere transform [0.5 0 0 0 | 0 0.5 0 0 | 0 0 0.5 0 | 0 0 0 1] material color (1, 1, 1) \
+ambient 0.1 diffuse 0.9 specular 0.9 shininess 200
+"
+        );
+    }
+
+    #[test]
+    fn displaying_a_world_describes_it() {
+        let w = default_world();
+        assert_eq!(w.to_string(), w.describe());
+    }
+
     #[test]
     fn intersect_a_world_with_a_ray() {
         let w = default_world();
@@ -159,6 +1004,199 @@ pub mod tests {
         assert_eq!(xs[3].t, 6.0);
     }
 
+    #[test]
+    fn intersect_any_finds_a_blocker_before_the_maximum_distance() {
+        let w = default_world();
+        let r = Ray::new(Tuple::point(0.0, 0.0, -5.0), Tuple::vector(0.0, 0.0, 1.0));
+        assert!(w.intersect_any(&r, 10.0));
+        assert!(!w.intersect_any(&r, 4.0));
+        let r = Ray::new(Tuple::point(0.0, 0.0, 5.0), Tuple::vector(0.0, 0.0, 1.0));
+        assert!(!w.intersect_any(&r, 10.0));
+        let r = Ray::new(Tuple::point(0.0, 0.0, 0.0), Tuple::vector(0.0, 0.0, 1.0));
+        assert!(w.intersect_any(&r, 0.6));
+        assert!(!w.occluded_except(&r, 0.6, Some(&w.objects[1])));
+    }
+
+    #[test]
+    fn objects_take_the_library_materials_they_refer_to_when_prepared() {
+        let mut w = World::new();
+        let steel = Material {
+            color: Color::new(0.5, 0.5, 0.6),
+            ..Material::default()
+        };
+        w.materials.insert("steel", steel.clone());
+        let mut group = Object::new_group();
+        group
+            .as_mut_group()
+            .add_child(Object::new_sphere().with_material_ref("steel"));
+        w.add_object(group);
+        w.add_object(Object::new_cube().with_material_ref("steel"));
+        w.add_object(Object::new_cube().with_material_ref("gold"));
+        w.prepare();
+        assert_eq!(w.objects[0].as_group().children[0].material, steel);
+        assert_eq!(w.objects[1].material, steel);
+        assert_eq!(w.objects[2].material, Material::default());
+        w.materials.get_mut("steel").unwrap().color = WHITE;
+        w.prepare();
+        assert_eq!(w.objects[0].as_group().children[0].material.color, WHITE);
+        assert_eq!(w.objects[1].material.color, WHITE);
+    }
+
+    #[test]
+    fn preparing_again_only_copies_the_shared_objects_that_change() {
+        let mut w = World::new();
+        w.materials.insert("steel", Material::default());
+        let mut group = Object::new_group();
+        group
+            .as_mut_group()
+            .add_child(Object::new_sphere().with_material_ref("steel"));
+        w.add_object(group);
+        w.prepare();
+        let previous = w.clone();
+        w.prepare();
+        assert!(Arc::ptr_eq(&w.objects[0], &previous.objects[0]));
+        w.materials.get_mut("steel").unwrap().color = BLACK;
+        w.prepare();
+        assert!(!Arc::ptr_eq(&w.objects[0], &previous.objects[0]));
+        assert_eq!(w.objects[0].as_group().children[0].material.color, BLACK);
+        assert_eq!(
+            previous.objects[0].as_group().children[0].material.color,
+            WHITE
+        );
+    }
+
+    #[test]
+    fn merging_a_world_moves_its_objects_and_lights() {
+        let mut lamp = World::new();
+        lamp.add_object(Object::new_sphere().with_transform(translation(0.0, 1.0, 0.0)));
+        lamp.lights
+            .push(Light::new_point(Tuple::point(0.0, 2.0, 0.0), WHITE));
+        lamp.materials.insert("brass", Material::default());
+        lamp.materials.insert("shade", Material::glass());
+        let mut w = default_world();
+        w.materials.insert("shade", Material::glass());
+        w.merge(lamp, translation(5.0, 0.0, 0.0)).unwrap();
+        assert_eq!(w.objects.len(), 3);
+        assert_eq!(w.objects[2].transform, translation(5.0, 1.0, 0.0));
+        assert_eq!(w.lights.len(), 2);
+        assert_eq!(w.lights[1].position, Tuple::point(5.0, 2.0, 0.0));
+        assert!(w.materials.contains("brass"));
+        assert_eq!(w.materials.get("shade"), Some(&Material::glass()));
+    }
+
+    #[test]
+    fn merging_a_world_that_defines_a_material_differently_fails() {
+        let mut lamp = World::new();
+        lamp.add_object(Object::new_sphere().with_material_ref("shade"));
+        lamp.materials.insert("shade", Material::glass());
+        let mut w = default_world();
+        w.materials.insert("shade", Material::clay());
+        let error = w.merge(lamp, Matrix::identity()).unwrap_err();
+        assert_eq!(error, "material 'shade' is already defined differently");
+        assert_eq!(w.objects.len(), 2);
+        assert_eq!(w.materials.get("shade"), Some(&Material::clay()));
+    }
+
+    #[test]
+    fn merging_a_world_that_defines_a_material_as_less_reflective_fails() {
+        let mut prefab = World::new();
+        prefab.materials.insert("mirror", Material::default());
+        let mut w = default_world();
+        w.materials.insert(
+            "mirror",
+            Material {
+                reflective: 1.0,
+                ..Material::default()
+            },
+        );
+        let error = w.merge(prefab, Matrix::identity()).unwrap_err();
+        assert_eq!(error, "material 'mirror' is already defined differently");
+    }
+
+    #[test]
+    fn floor_planes_are_tested_first_for_shadows() {
+        let mut w = World::new();
+        w.add_object(Object::new_sphere().with_transform(translation(5.0, 1.0, 0.0)));
+        w.add_object(Object::new_plane());
+        for i in 0..20 {
+            w.add_object(
+                Object::new_sphere().with_transform(
+                    translation(i as Float * 3.0, 5.0, 10.0) * scaling(0.5, 0.5, 0.5),
+                ),
+            );
+        }
+        let light = Tuple::point(0.0, 10.0, 0.0);
+        let below = Tuple::point(0.0, -1.0, 0.0);
+        let above = Tuple::point(0.0, 1.0, 0.0);
+        let behind_the_sphere = Tuple::point(10.0, -8.0, 0.0);
+        assert!(w.occluded(below, light));
+        assert!(!w.occluded(above, light));
+        w.accelerator = AcceleratorKind::Bvh;
+        w.prepare();
+        assert_eq!(w.planes, vec![1]);
+        assert!(w.occluded(below, light));
+        assert!(!w.occluded(above, light));
+        assert!(w.occluded(Tuple::point(5.0, 3.0, 0.0), Tuple::point(5.0, 0.5, 0.0)));
+        assert!(w.occluded(behind_the_sphere, light));
+    }
+
+    #[test]
+    fn accelerated_worlds_render_like_unaccelerated_ones() {
+        let mut w = default_world();
+        for i in 0..30 {
+            let x = (i % 6) as Float - 2.5;
+            let y = (i / 6) as Float - 2.0;
+            w.add_object(
+                Object::new_sphere()
+                    .with_transform(translation(x, y, 3.0) * scaling(0.3, 0.3, 0.3)),
+            );
+        }
+        w.add_object(Object::new_plane().with_transform(translation(0.0, -3.0, 0.0)));
+        let rays: Vec<Ray> = (0..50)
+            .map(|i| {
+                let x = (i % 10) as Float * 0.1 - 0.5;
+                let y = (i / 10) as Float * 0.1 - 0.25;
+                Ray::new(Tuple::point(0.0, 0.0, -5.0), Tuple::vector(x, y, 1.0))
+            })
+            .collect();
+        w.accelerator = AcceleratorKind::None;
+        w.prepare();
+        let expected: Vec<Color> = rays.iter().map(|r| w.color_at(r, 3)).collect();
+        for kind in [
+            AcceleratorKind::Auto,
+            AcceleratorKind::Bvh,
+            AcceleratorKind::Grid,
+        ] {
+            w.accelerator = kind;
+            w.prepare();
+            let colors: Vec<Color> = rays.iter().map(|r| w.color_at(r, 3)).collect();
+            assert_eq!(colors, expected, "{:?}", kind);
+        }
+    }
+
+    #[test]
+    fn checking_the_line_of_sight_between_two_points() {
+        let w = default_world();
+        let p0 = Tuple::point(0.0, 0.0, -5.0);
+        assert!(w.occluded(p0, Tuple::point(0.0, 0.0, 5.0)));
+        assert!(!w.occluded(p0, Tuple::point(0.0, 0.0, -2.0)));
+        assert!(!w.occluded(p0, Tuple::point(0.0, 5.0, -5.0)));
+        assert!(!w.occluded(p0, p0));
+        let inside = Tuple::point(0.0, 0.0, 0.0);
+        assert!(!w.occluded(inside, inside));
+    }
+
+    #[test]
+    fn measuring_the_distance_to_the_nearest_surface() {
+        let w = default_world();
+        let r = Ray::new(Tuple::point(0.0, 0.0, -5.0), Tuple::vector(0.0, 0.0, 2.0));
+        assert_eq!(w.distance_to_nearest(&r), Some(4.0));
+        let r = Ray::new(Tuple::point(0.0, 0.0, 0.0), Tuple::vector(0.0, 0.0, 1.0));
+        assert_eq!(w.distance_to_nearest(&r), Some(0.5));
+        let r = Ray::new(Tuple::point(0.0, 0.0, -5.0), Tuple::vector(0.0, 1.0, 0.0));
+        assert_eq!(w.distance_to_nearest(&r), None);
+    }
+
     #[test]
     fn shading_an_intersection() {
         let w = default_world();
@@ -166,10 +1204,22 @@ pub mod tests {
         let object = &w.objects[0];
         let i = Intersection::new(4.0, object);
         let comps = i.prepare_computations(&r, &vec![]);
-        let c = w.shade_hit(&i, &comps, 1);
+        let c = w.shade_hit(&comps, 1);
         assert_eq!(c, Color::new(0.38066, 0.47583, 0.2855));
     }
 
+    #[test]
+    fn shading_clamps_the_radiance_of_each_bounce() {
+        let mut w = default_world();
+        w.max_bounce_value = 0.2;
+        let r = Ray::new(Tuple::point(0.0, 0.0, -5.0), Tuple::vector(0.0, 0.0, 1.0));
+        let object = &w.objects[0];
+        let i = Intersection::new(4.0, object);
+        let comps = i.prepare_computations(&r, &vec![]);
+        let c = w.shade_hit(&comps, 1);
+        assert_eq!(c, Color::new(0.38066, 0.47583, 0.2855) * (0.2 / 0.47583));
+    }
+
     #[test]
     fn shading_an_intersection_from_the_inside() {
         let mut w = default_world();
@@ -181,7 +1231,7 @@ pub mod tests {
         let object = &w.objects[1];
         let i = Intersection::new(0.5, object);
         let comps = i.prepare_computations(&r, &vec![]);
-        let c = w.shade_hit(&i, &comps, 1);
+        let c = w.shade_hit(&comps, 1);
         assert_eq!(c, Color::new(0.90498, 0.90498, 0.90498));
     }
 
@@ -204,13 +1254,25 @@ pub mod tests {
     #[test]
     fn the_color_with_an_intersection_behind_the_ray() {
         let mut w = default_world();
-        w.objects[0].material.ambient = 1.0;
-        w.objects[1].material.ambient = 1.0;
+        Arc::make_mut(&mut w.objects[0]).material.ambient = 1.0;
+        Arc::make_mut(&mut w.objects[1]).material.ambient = 1.0;
         let r = Ray::new(Tuple::point(0.0, 0.0, 0.75), Tuple::vector(0.0, 0.0, -1.0));
         let c = w.color_at(&r, 1);
         assert_eq!(c, w.objects[1].material.color);
     }
 
+    #[test]
+    fn a_material_override_replaces_every_material() {
+        let mut w = default_world();
+        Arc::make_mut(&mut w.objects[0]).material = Material::glass();
+        w.material_override = Some(Material::clay());
+        let r = Ray::new(Tuple::point(0.0, 0.0, -5.0), Tuple::vector(0.0, 0.0, 1.0));
+        let hit = w.trace(&r, 5).unwrap();
+        assert_eq!(hit.material, &Material::clay());
+        assert_eq!(hit.color.red(), hit.color.green());
+        assert_eq!(hit.color.green(), hit.color.blue());
+    }
+
     #[test]
     fn shade_hit_is_given_an_intersection_in_shadow() {
         let s1 = Object::new_sphere();
@@ -220,22 +1282,47 @@ pub mod tests {
             Tuple::point(0.0, 0.0, -10.0),
             Color::new(1.0, 1.0, 1.0),
         )];
-        w.objects = vec![s1, s2];
+        w.objects = [s1, s2].map(Arc::new).into();
         let r = Ray::new(Tuple::point(0.0, 0.0, 5.0), Tuple::vector(0.0, 0.0, 1.0));
         let i = Intersection::new(4.0, &w.objects[1]);
         let comps = i.prepare_computations(&r, &vec![]);
-        let c = w.shade_hit(&i, &comps, 1);
+        let c = w.shade_hit(&comps, 1);
         assert_eq!(c, Color::new(0.1, 0.1, 0.1));
     }
 
+    #[test]
+    fn the_default_shadow_bias() {
+        let w = World::new();
+        assert_eq!(w.shadow_bias, EPSILON);
+    }
+
+    #[test]
+    fn a_large_shadow_bias_lifts_the_shadow_ray_above_the_blocker() {
+        let mut w = World::new();
+        w.lights = vec![Light::new_point(
+            Tuple::point(0.0, 10.0, 0.0),
+            Color::new(1.0, 1.0, 1.0),
+        )];
+        let floor = Object::new_plane();
+        let blocker = Object::new_plane().with_transform(translation(0.0, 0.5, 0.0));
+        w.objects = [floor, blocker].map(Arc::new).into();
+        let r = Ray::new(
+            Tuple::point(0.0, 0.25, -1.0),
+            Tuple::vector(0.0, -0.25, 1.0),
+        );
+        assert_eq!(w.color_at(&r, 1), Color::new(0.1, 0.1, 0.1));
+        w.shadow_bias = 1.0;
+        assert_eq!(w.color_at(&r, 1), Color::new(1.0, 1.0, 1.0));
+    }
+
     #[test]
     fn the_reflected_color_for_a_nonreflective_material() {
         let mut w = default_world();
-        w.objects[1].material.ambient = 1.0;
+        Arc::make_mut(&mut w.objects[1]).material.ambient = 1.0;
         let r = Ray::new(Tuple::point(0.0, 0.0, 0.0), Tuple::vector(0.0, 0.0, 1.0));
         let i = Intersection::new(1.0, &w.objects[1]);
         let comps = i.prepare_computations(&r, &vec![]);
-        let color = w.reflected_color(&i, &comps, 1);
+        let color = w.reflected_color(&comps, 1);
         assert_eq!(color, BLACK);
     }
 
@@ -244,30 +1331,141 @@ pub mod tests {
         let mut w = default_world();
         let mut shape = Object::new_plane().with_transform(translation(0.0, -1.0, 0.0));
         shape.material.reflective = 0.5;
-        w.objects.push(shape);
+        w.add_object(shape);
+        let r = Ray::new(
+            Tuple::point(0.0, 0.0, -3.0),
+            Tuple::vector(0.0, -SQRT_2 / 2.0, SQRT_2 / 2.0),
+        );
+        let i = Intersection::new(SQRT_2, &w.objects[2]);
+        let comps = i.prepare_computations(&r, &vec![]);
+        let color = w.reflected_color(&comps, 1);
+        assert_eq!(color, Color::new(0.19032222, 0.23791526, 0.14274));
+    }
+
+    #[test]
+    fn secondary_rays_skip_hits_within_the_surface_offset() {
+        let mut w = World::new();
+        w.shadow_bias = 0.1;
+        let mut floor = Object::new_plane();
+        floor.material.reflective = 1.0;
+        floor.material.ambient = 0.0;
+        floor.material.diffuse = 0.0;
+        floor.material.specular = 0.0;
+        w.add_object(floor);
+        let mut film = Object::new_plane().with_transform(translation(0.0, 0.15, 0.0));
+        film.material.color = Color::new(1.0, 0.0, 0.0);
+        film.material.ambient = 1.0;
+        w.add_object(film);
+        w.prepare();
+        let r = Ray::new(
+            Tuple::point(0.0, 1.0, -1.0),
+            Tuple::vector(0.0, -SQRT_2 / 2.0, SQRT_2 / 2.0),
+        );
+        let i = Intersection::new(SQRT_2, &w.objects[0]);
+        let comps = i.prepare_computations_with_bias(&r, &vec![], w.shadow_bias);
+        assert_eq!(w.shade_hit(&comps, 1), BLACK);
+        // A camera ray from the same point does see the film.
+        let reflected = Ray::new(comps.over_point, comps.reflectv);
+        assert_ne!(w.color_at(&reflected, 0), BLACK);
+    }
+
+    #[test]
+    fn the_reflected_color_for_a_metal_is_tinted() {
+        let mut w = default_world();
+        let mut shape = Object::new_plane().with_transform(translation(0.0, -1.0, 0.0));
+        shape.material = Material::metal(Color::new(1.0, 0.5, 0.0), 0.0);
+        w.add_object(shape);
+        let r = Ray::new(
+            Tuple::point(0.0, 0.0, -3.0),
+            Tuple::vector(0.0, -SQRT_2 / 2.0, SQRT_2 / 2.0),
+        );
+        let i = Intersection::new(SQRT_2, &w.objects[2]);
+        let comps = i.prepare_computations(&r, &vec![]);
+        let color = w.reflected_color(&comps, 1);
+        assert_eq!(color, Color::new(0.38064, 0.23792, 0.0));
+    }
+
+    #[test]
+    fn rough_reflections_stay_above_the_surface() {
+        let shape = Object::new_plane();
+        let r = Ray::new(
+            Tuple::point(0.0, 1.0, -1.0),
+            Tuple::vector(0.0, -SQRT_2 / 2.0, SQRT_2 / 2.0),
+        );
+        let i = Intersection::new(SQRT_2, &shape);
+        let comps = i.prepare_computations(&r, &vec![]);
+        assert_eq!(World::reflected_ray(&comps, 0.0).direction, comps.reflectv);
+        for _ in 0..100 {
+            let reflected = World::reflected_ray(&comps, 1.0);
+            assert!(reflected.direction.dot(comps.normalv) > 0.0);
+            assert!(equals(reflected.direction.magnitude(), 1.0));
+        }
+    }
+
+    #[test]
+    fn glossy_reflections_sample_within_a_cone() {
+        let axis = Tuple::vector(0.0, 1.0, 1.0).normalize();
+        for _ in 0..100 {
+            let v = cone_sample(axis, PI / 8.0);
+            assert!(equals(v.magnitude(), 1.0));
+            assert!(v.dot(axis) >= (PI / 8.0).cos() - EPSILON);
+        }
+    }
+
+    #[test]
+    fn glossy_reflections_average_several_rays() {
+        let mut w = default_world();
+        let mut shape = Object::new_plane().with_transform(translation(0.0, -1.0, 0.0));
+        shape.material.reflective = 0.5;
+        shape.material.roughness = 0.0001;
+        shape.material.glossy_samples = 8;
+        w.add_object(shape);
         let r = Ray::new(
             Tuple::point(0.0, 0.0, -3.0),
             Tuple::vector(0.0, -SQRT_2 / 2.0, SQRT_2 / 2.0),
         );
         let i = Intersection::new(SQRT_2, &w.objects[2]);
         let comps = i.prepare_computations(&r, &vec![]);
-        let color = w.reflected_color(&i, &comps, 1);
+        assert_eq!(World::reflected_rays(&comps, 0.5, 8).len(), 8);
+        let color = w.reflected_color(&comps, 1);
         assert_eq!(color, Color::new(0.19032222, 0.23791526, 0.14274));
     }
 
+    #[test]
+    fn shading_a_clear_coated_surface() {
+        let mut w = default_world();
+        w.lights = vec![];
+        let coat = ClearCoat::new(1.0, 1.5, 0.0);
+        let mut shape = Object::new_plane().with_transform(translation(0.0, -1.0, 0.0));
+        shape.material = shape.material.with_clear_coat(coat);
+        w.add_object(shape);
+        let r = Ray::new(
+            Tuple::point(0.0, 0.0, -3.0),
+            Tuple::vector(0.0, -SQRT_2 / 2.0, SQRT_2 / 2.0),
+        );
+        let i = Intersection::new(SQRT_2, &w.objects[2]);
+        let comps = i.prepare_computations(&r, &vec![]);
+        let f = coat.fresnel(SQRT_2 / 2.0);
+        let expected = WHITE * (0.1 * (1.0 - f)) + Color::new(0.08, 0.1, 0.06) * f;
+        assert_eq!(w.shade_hit(&comps, 1), expected);
+        let trace = w.debug_ray(&r, 1);
+        assert_eq!(trace.hit.as_ref().unwrap().coated.len(), 1);
+        assert_eq!(trace.color, expected);
+    }
+
     #[test]
     fn the_reflected_color_at_the_maximum_recursive_depth() {
         let mut w = default_world();
         let mut shape = Object::new_plane().with_transform(translation(0.0, -1.0, 0.0));
         shape.material.reflective = 0.5;
-        w.objects.push(shape);
+        w.add_object(shape);
         let r = Ray::new(
             Tuple::point(0.0, 0.0, -3.0),
             Tuple::vector(0.0, -SQRT_2 / 2.0, SQRT_2 / 2.0),
         );
         let i = Intersection::new(SQRT_2, &w.objects[2]);
         let comps = i.prepare_computations(&r, &vec![]);
-        let color = w.reflected_color(&i, &comps, 0);
+        let color = w.reflected_color(&comps, 0);
         assert_eq!(color, BLACK);
     }
 
@@ -276,17 +1474,39 @@ pub mod tests {
         let mut w = default_world();
         let mut shape = Object::new_plane().with_transform(translation(0.0, -1.0, 0.0));
         shape.material.reflective = 0.5;
-        w.objects.push(shape);
+        w.add_object(shape);
         let r = Ray::new(
             Tuple::point(0.0, 0.0, -3.0),
             Tuple::vector(0.0, -SQRT_2 / 2.0, SQRT_2 / 2.0),
         );
         let i = Intersection::new(SQRT_2, &w.objects[2]);
         let comps = i.prepare_computations(&r, &vec![]);
-        let color = w.shade_hit(&i, &comps, 1);
+        let color = w.shade_hit(&comps, 1);
         assert_eq!(color, Color::new(0.8767573, 0.924_340_37, 0.8291743));
     }
 
+    #[test]
+    fn limiting_the_reflection_depth_separately() {
+        let mut w = default_world();
+        let mut shape = Object::new_plane().with_transform(translation(0.0, -1.0, 0.0));
+        shape.material.reflective = 0.5;
+        w.add_object(shape);
+        w.max_reflection_depth = 0;
+        let r = Ray::new(
+            Tuple::point(0.0, 0.0, -3.0),
+            Tuple::vector(0.0, -SQRT_2 / 2.0, SQRT_2 / 2.0),
+        );
+        let i = Intersection::new(SQRT_2, &w.objects[2]);
+        let comps = i.prepare_computations(&r, &vec![]);
+        assert_eq!(w.shade_hit(&comps, 5), w.shade_hit(&comps, 0));
+        w.max_refraction_depth = 0;
+        w.max_reflection_depth = 1;
+        assert_eq!(
+            w.shade_hit(&comps, 5),
+            Color::new(0.8767573, 0.924_340_37, 0.8291743)
+        );
+    }
+
     #[test]
     fn color_at_with_mutually_reflective_surfaces() {
         let mut w = World::new();
@@ -296,10 +1516,10 @@ pub mod tests {
         )];
         let mut lower = Object::new_plane().with_transform(translation(0.0, -1.0, 0.0));
         lower.material.reflective = 1.0;
-        w.objects.push(lower);
+        w.add_object(lower);
         let mut upper = Object::new_plane().with_transform(translation(0.0, 1.0, 0.0));
         upper.material.reflective = 1.0;
-        w.objects.push(upper);
+        w.add_object(upper);
         let r = Ray::new(Tuple::point(0.0, 0.0, 0.0), Tuple::vector(0.0, 1.0, 0.0));
         assert_eq!(
             w.color_at(&r, 10),
@@ -317,15 +1537,15 @@ pub mod tests {
             Intersection::new(6.0, object),
         ];
         let comps = xs[0].prepare_computations(&r, &xs);
-        let c = w.refracted_color(&xs[0], &comps, 5);
+        let c = w.refracted_color(&comps, 5);
         assert_eq!(c, BLACK);
     }
 
     #[test]
     fn the_refracted_color_at_the_maximum_recursive_depth() {
         let mut w = default_world();
-        w.objects[0].material.transparency = 1.0;
-        w.objects[0].material.refractive_index = 1.5;
+        Arc::make_mut(&mut w.objects[0]).material.transparency = 1.0;
+        Arc::make_mut(&mut w.objects[0]).material.refractive_index = 1.5;
         let object = &w.objects[0];
         let r = Ray::new(Tuple::point(0.0, 0.0, -5.0), Tuple::vector(0.0, 0.0, 1.0));
         let xs = vec![
@@ -333,15 +1553,15 @@ pub mod tests {
             Intersection::new(6.0, object),
         ];
         let comps = xs[0].prepare_computations(&r, &xs);
-        let c = w.refracted_color(&xs[0], &comps, 0);
+        let c = w.refracted_color(&comps, 0);
         assert_eq!(c, BLACK);
     }
 
     #[test]
     fn the_refracted_color_under_total_internal_reflection() {
         let mut w = default_world();
-        w.objects[0].material.transparency = 1.0;
-        w.objects[0].material.refractive_index = 1.5;
+        Arc::make_mut(&mut w.objects[0]).material.transparency = 1.0;
+        Arc::make_mut(&mut w.objects[0]).material.refractive_index = 1.5;
         let object = &w.objects[0];
         let r = Ray::new(
             Tuple::point(0.0, 0.0, SQRT_2 / 2.0),
@@ -352,17 +1572,17 @@ pub mod tests {
             Intersection::new(SQRT_2 / 2.0, object),
         ];
         let comps = xs[1].prepare_computations(&r, &xs);
-        let c = w.refracted_color(&xs[1], &comps, 5);
+        let c = w.refracted_color(&comps, 5);
         assert_eq!(c, BLACK);
     }
 
     #[test]
     fn the_refracted_color_with_a_refracted_ray() {
         let mut w = default_world();
-        w.objects[0].material.ambient = 1.0;
-        w.objects[0].material.pattern = Some(Pattern::new_test());
-        w.objects[1].material.transparency = 1.0;
-        w.objects[1].material.refractive_index = 1.5;
+        Arc::make_mut(&mut w.objects[0]).material.ambient = 1.0;
+        Arc::make_mut(&mut w.objects[0]).material.pattern = Some(Pattern::new_test());
+        Arc::make_mut(&mut w.objects[1]).material.transparency = 1.0;
+        Arc::make_mut(&mut w.objects[1]).material.refractive_index = 1.5;
         let r = Ray::new(Tuple::point(0.0, 0.0, 0.1), Tuple::vector(0.0, 1.0, 0.0));
         let xs = vec![
             Intersection::new(-0.9899, &w.objects[0]),
@@ -371,10 +1591,41 @@ pub mod tests {
             Intersection::new(0.9899, &w.objects[0]),
         ];
         let comps = xs[2].prepare_computations(&r, &xs);
-        let c = w.refracted_color(&xs[2], &comps, 5);
+        let c = w.refracted_color(&comps, 5);
         assert_eq!(c, Color::new(0.0, 0.99887455, 0.047_218_92));
     }
 
+    #[test]
+    fn a_dispersive_material_refracts_each_channel_separately() {
+        let mut w = World::new();
+        w.lights = vec![Light::new_point(Tuple::point(-10.0, 10.0, -10.0), WHITE)];
+        let mut ball = Object::new_sphere();
+        ball.material = Material {
+            dispersion: 0.05,
+            ..Material::glass()
+        };
+        w.add_object(ball);
+        w.prepare();
+        let r = Ray::new(Tuple::point(0.0, 0.5, -5.0), Tuple::vector(0.0, 0.0, 1.0));
+        let xs = w.intersect(&r);
+        let hit = intersections::hit(&xs).unwrap();
+        let comps = hit.prepare_computations(&r, &xs);
+        let q = QueuedRay::primary(r, 0.0, w.depth(5), 0);
+        let shading = w.shade(&comps, &q);
+        let refracted: Vec<&QueuedRay> = shading
+            .secondary
+            .iter()
+            .filter(|&&(b, _)| b == Bounce::Refracted)
+            .map(|(_, q)| q)
+            .collect();
+        assert_eq!(refracted.len(), 3);
+        assert_eq!(refracted[0].weight.green(), 0.0);
+        assert_eq!(refracted[2].weight.red(), 0.0);
+        let bend = |q: &QueuedRay| q.ray.direction.normalize().y();
+        assert!(bend(refracted[2]) < bend(refracted[1]));
+        assert!(bend(refracted[1]) < bend(refracted[0]));
+    }
+
     #[test]
     fn shade_hit_with_a_transparent_material() {
         let mut floor = Object::new_plane().with_transform(translation(0.0, -1.0, 0.0));
@@ -384,8 +1635,8 @@ pub mod tests {
         ball.material.color = Color::new(1.0, 0.0, 0.0);
         ball.material.ambient = 0.5;
         let mut w = default_world();
-        w.objects.push(floor);
-        w.objects.push(ball);
+        w.add_object(floor);
+        w.add_object(ball);
         let r = Ray::new(
             Tuple::point(0.0, 0.0, -3.0),
             Tuple::vector(0.0, -SQRT_2 / 2.0, SQRT_2 / 2.0),
@@ -393,11 +1644,13 @@ pub mod tests {
         let i = Intersection::new(SQRT_2, &w.objects[2]);
         let xs = vec![i];
         let comps = xs[0].prepare_computations(&r, &xs);
-        let c = w.shade_hit(&xs[0], &comps, 5);
+        let c = w.shade_hit(&comps, 5);
         assert_eq!(c, Color::new(0.9364251, 0.6864251, 0.6864251));
     }
 
+    // The expected color comes from Schlick's approximation.
     #[test]
+    #[cfg(not(feature = "polarization"))]
     fn shade_hit_with_a_reflective_transparent_material() {
         let mut floor = Object::new_plane().with_transform(translation(0.0, -1.0, 0.0));
         floor.material.reflective = 0.5;
@@ -407,8 +1660,8 @@ pub mod tests {
         ball.material.color = Color::new(1.0, 0.0, 0.0);
         ball.material.ambient = 0.5;
         let mut w = default_world();
-        w.objects.push(floor);
-        w.objects.push(ball);
+        w.add_object(floor);
+        w.add_object(ball);
         let r = Ray::new(
             Tuple::point(0.0, 0.0, -3.0),
             Tuple::vector(0.0, -SQRT_2 / 2.0, SQRT_2 / 2.0),
@@ -416,7 +1669,384 @@ pub mod tests {
         let i = Intersection::new(SQRT_2, &w.objects[2]);
         let xs = vec![i];
         let comps = xs[0].prepare_computations(&r, &xs);
-        let c = w.shade_hit(&xs[0], &comps, 5);
+        let c = w.shade_hit(&comps, 5);
         assert_eq!(c, Color::new(0.93391, 0.696432, 0.6924281));
     }
+
+    #[test]
+    fn an_opaque_fresnel_material_reflects_more_at_grazing_angles() {
+        let mut floor = Object::new_plane();
+        floor.material = Material::default().with_fresnel(1.5);
+        floor.material.reflective = 1.0;
+        let mut w = default_world();
+        w.add_object(floor);
+        let reflectance = |direction: Tuple| {
+            let r = Ray::new(Tuple::point(0.0, 1.0, 0.0) - direction, direction);
+            let xs = vec![Intersection::new(1.0, &w.objects[2])];
+            let comps = xs[0].prepare_computations(&r, &xs);
+            let q = QueuedRay::primary(r, 0.0, w.depth(1), 0);
+            w.shade(&comps, &q).reflectance.unwrap()
+        };
+        assert!(equals(reflectance(Tuple::vector(0.0, -1.0, 0.0)), 0.04));
+        assert!(reflectance(Tuple::vector(0.0, -0.1, 1.0).normalize()) > 0.5);
+        let mut mirror = Object::new_plane();
+        mirror.material.reflective = 1.0;
+        let xs = vec![Intersection::new(1.0, &mirror)];
+        let r = Ray::new(Tuple::point(0.0, 1.0, 0.0), Tuple::vector(0.0, -1.0, 0.0));
+        let comps = xs[0].prepare_computations(&r, &xs);
+        let shading = w.shade(&comps, &QueuedRay::primary(r, 0.0, w.depth(1), 0));
+        assert_eq!(shading.reflectance, None);
+        assert_eq!(shading.secondary[0].1.weight, WHITE);
+    }
+
+    fn glowing(color: Color) -> Material {
+        Material {
+            color,
+            ambient: 1.0,
+            diffuse: 0.0,
+            specular: 0.0,
+            ..Material::default()
+        }
+    }
+
+    #[test]
+    fn a_ray_crossing_a_portal_leaves_from_its_partner() {
+        let entry = Object::new_quad(2.0, 2.0);
+        let exit = Object::new_quad(2.0, 2.0).with_transform(translation(10.0, 0.0, 0.0));
+        let portal = entry.portal_to(&exit);
+        assert_eq!(
+            portal * Tuple::point(0.5, 0.0, 0.0),
+            Tuple::point(10.5, 0.0, 0.0)
+        );
+        assert_eq!(
+            portal * Tuple::vector(0.0, -1.0, 0.0),
+            Tuple::vector(0.0, 1.0, 0.0)
+        );
+        let mut entry = entry;
+        entry.material = glowing(Color::new(0.0, 0.0, 1.0)).with_portal(portal);
+        let mut ball = Object::new_sphere().with_transform(translation(10.0, 3.0, 0.0));
+        ball.material = glowing(Color::new(1.0, 0.0, 0.0));
+        let mut w = World::new();
+        w.add_object(entry);
+        w.add_object(exit);
+        w.add_object(ball);
+        w.prepare();
+        let r = Ray::new(Tuple::point(0.0, 5.0, 0.0), Tuple::vector(0.0, -1.0, 0.0));
+        assert_eq!(w.color_at(&r, 5), Color::new(1.0, 0.0, 0.0));
+        // Without bounces left, the portal shows its own surface.
+        assert_eq!(w.color_at(&r, 0), Color::new(0.0, 0.0, 1.0));
+        let trace = w.debug_ray(&r, 5);
+        let teleported = trace.hit.unwrap().teleported.remove(0);
+        assert_eq!(teleported.ray.origin, Tuple::point(10.0, 0.0, 0.0));
+        assert!(equals(teleported.hit.unwrap().t, 2.0));
+        assert_eq!(trace.color, Color::new(1.0, 0.0, 0.0));
+    }
+
+    #[test]
+    fn facing_portals_stop_after_the_last_bounce() {
+        let floor = Object::new_quad(2.0, 2.0);
+        let ceiling =
+            Object::new_quad(2.0, 2.0).with_transform(translation(0.0, 2.0, 0.0) * rotation_x(PI));
+        let mut w = World::new();
+        let mut portal = floor.clone();
+        portal.material = glowing(Color::new(0.0, 1.0, 0.0)).with_portal(floor.portal_to(&ceiling));
+        w.add_object(portal);
+        w.add_object(ceiling);
+        w.prepare();
+        let r = Ray::new(Tuple::point(0.0, 1.0, 0.0), Tuple::vector(0.0, -1.0, 0.0));
+        assert_eq!(w.color_at(&r, 10), Color::new(0.0, 1.0, 0.0));
+    }
+
+    #[test]
+    fn a_camera_inside_an_object_can_ignore_the_closest_hits() {
+        let mut w = default_world();
+        w.prepare();
+        let r = Ray::new(Tuple::point(0.0, 0.0, 0.0), Tuple::vector(0.0, 0.0, 1.0));
+        assert!(equals(w.trace(&r, 5).unwrap().t, 0.5));
+        let inner = w.color_at(&r, 5);
+        w.camera_t_min = 0.75;
+        assert!(equals(w.trace(&r, 5).unwrap().t, 1.0));
+        assert!(equals(w.debug_ray(&r, 5).hit.unwrap().t, 1.0));
+        assert_ne!(w.color_at(&r, 5), inner);
+        w.camera_t_min = 2.0;
+        assert!(w.trace(&r, 5).is_none());
+    }
+
+    #[test]
+    fn shadow_rays_can_ignore_nearby_occluders() {
+        let mut w = default_world();
+        w.prepare();
+        let light = Tuple::point(-10.0, 10.0, -10.0);
+        let inside = Tuple::point(0.0, 0.0, -0.75);
+        assert!(w.occluded(inside, light));
+        w.shadow_t_min = 0.5;
+        assert!(!w.occluded(inside, light));
+        // Camera rays still see the surface the shadow rays skip.
+        let r = Ray::new(inside, (light - inside).normalize());
+        assert!(w.trace(&r, 5).unwrap().t < 0.5);
+    }
+
+    #[test]
+    fn exporting_the_bounds_as_boxes() {
+        let mut group = Object::new_group();
+        group
+            .as_mut_group()
+            .add_child(Object::new_sphere().with_transform(translation(2.0, 0.0, 0.0)));
+        group.as_mut_group().add_child(Object::new_cube());
+        let mut w = World::new();
+        w.add_object(group.with_transform(scaling(2.0, 2.0, 2.0)));
+        w.add_object(Object::new_plane());
+        w.prepare();
+        let obj = w.bounds_obj();
+        let lines = |prefix: &str| obj.lines().filter(|l| l.starts_with(prefix)).count();
+        assert_eq!((lines("o "), lines("v "), lines("f ")), (3, 24, 18));
+        assert!(obj.starts_with("o box0_depth0\nv -2 -2 -2\n"));
+        assert!(obj.contains("o box1_depth1\nv 2 -2 -2\n"));
+        assert!(obj.ends_with("f 18 20 24 22\n"));
+        let path = std::env::temp_dir().join("rt_rust_bounds.obj");
+        w.export_bounds_obj(&path).unwrap();
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), obj);
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn tracing_a_ray_that_misses() {
+        let w = default_world();
+        let r = Ray::new(Tuple::point(0.0, 0.0, -5.0), Tuple::vector(0.0, 1.0, 0.0));
+        assert!(w.trace(&r, 5).is_none());
+    }
+
+    #[test]
+    fn tracing_a_ray_that_hits() {
+        let w = default_world();
+        let r = Ray::new(Tuple::point(0.0, 0.0, -5.0), Tuple::vector(0.0, 0.0, 1.0));
+        let hit = w.trace(&r, 5).unwrap();
+        assert_eq!(hit.t, 4.0);
+        assert_eq!(hit.point, Tuple::point(0.0, 0.0, -1.0));
+        assert_eq!(hit.normal, Tuple::vector(0.0, 0.0, -1.0));
+        assert!(!hit.inside);
+        assert!(ptr::eq(hit.object, &*w.objects[0]));
+        assert_eq!(hit.material, &w.objects[0].material);
+        assert_eq!(hit.color, Color::new(0.38066, 0.47583, 0.2855));
+    }
+
+    #[test]
+    fn debugging_a_ray_that_misses() {
+        let w = default_world();
+        let r = Ray::new(Tuple::point(0.0, 0.0, -5.0), Tuple::vector(0.0, 1.0, 0.0));
+        let trace = w.debug_ray(&r, 1);
+        assert!(trace.intersections.is_empty());
+        assert!(trace.hit.is_none());
+        assert_eq!(trace.color, BLACK);
+    }
+
+    #[test]
+    fn debugging_a_ray_that_hits() {
+        let w = default_world();
+        let r = Ray::new(Tuple::point(0.0, 0.0, -5.0), Tuple::vector(0.0, 0.0, 1.0));
+        let trace = w.debug_ray(&r, 1);
+        assert_eq!(trace.intersections, vec![4.0, 4.5, 5.5, 6.0]);
+        assert_eq!(trace.color, w.color_at(&r, 1));
+        let hit = trace.hit.unwrap();
+        assert_eq!(hit.t, 4.0);
+        assert_eq!(hit.point, Tuple::point(0.0, 0.0, -1.0));
+        assert_eq!(hit.ambient, Color::new(0.08, 0.1, 0.06));
+        assert_eq!(hit.ambient + hit.diffuse + hit.specular, trace.color);
+        assert!(hit.reflected.is_empty());
+        assert!(hit.refracted.is_empty());
+    }
+
+    #[test]
+    fn debugging_a_ray_follows_reflections() {
+        let mut w = default_world();
+        let mut shape = Object::new_plane().with_transform(translation(0.0, -1.0, 0.0));
+        shape.material.reflective = 0.5;
+        w.add_object(shape);
+        let r = Ray::new(
+            Tuple::point(0.0, 0.0, -3.0),
+            Tuple::vector(0.0, -SQRT_2 / 2.0, SQRT_2 / 2.0),
+        );
+        let trace = w.debug_ray(&r, 1);
+        assert_eq!(trace.color, w.color_at(&r, 1));
+        let reflected = trace.hit.unwrap().reflected.remove(0);
+        assert!(reflected.hit.is_some());
+        assert_eq!(
+            reflected.color * 0.5,
+            Color::new(0.19032222, 0.23791526, 0.14274)
+        );
+    }
+
+    #[test]
+    fn debugging_a_ray_shades_like_rendering() {
+        let mut w = World::new();
+        w.lights = vec![Light::new_point(Tuple::point(-10.0, 10.0, -10.0), WHITE)];
+        w.max_bounce_value = 0.5;
+        // Glossy rays only see the flat walls, whatever their directions.
+        let mut walls = Object::new_sphere().with_transform(scaling(50.0, 50.0, 50.0));
+        walls.material = glowing(Color::new(0.4, 0.6, 0.8));
+        w.add_object(walls);
+        let mut prism = Object::new_sphere().with_transform(translation(-2.0, 0.0, 0.0));
+        prism.material = Material {
+            dispersion: 0.05,
+            ..Material::glass()
+        };
+        w.add_object(prism);
+        let mut ball = Object::new_sphere().with_transform(translation(2.0, 0.0, 0.0));
+        ball.material = Material::metal(Color::new(0.9, 0.8, 0.7), 0.1);
+        ball.material.glossy_samples = 8;
+        w.add_object(ball);
+        w.prepare();
+        let from = Tuple::point(0.0, 0.0, -5.0);
+        let r = Ray::new(from, (Tuple::point(-2.0, 0.5, 0.0) - from).normalize());
+        let trace = w.debug_ray(&r, 5);
+        assert_eq!(trace.hit.as_ref().unwrap().refracted.len(), 3);
+        assert_eq!(trace.color, w.color_at(&r, 5));
+        let r = Ray::new(from, (Tuple::point(2.0, 0.0, 0.0) - from).normalize());
+        let trace = w.debug_ray(&r, 5);
+        assert_eq!(trace.hit.as_ref().unwrap().reflected.len(), 8);
+        assert_eq!(trace.color, w.color_at(&r, 5));
+    }
+
+    #[test]
+    fn coloring_a_batch_of_rays() {
+        let w = default_world();
+        let rays = [
+            Ray::new(Tuple::point(0.0, 0.0, -5.0), Tuple::vector(0.0, 1.0, 0.0)),
+            Ray::new(Tuple::point(0.0, 0.0, -5.0), Tuple::vector(0.0, 0.0, 1.0)),
+        ];
+        let colors = w.color_at_batch(&rays, 1);
+        assert_eq!(colors, vec![BLACK, Color::new(0.38066, 0.47583, 0.2855)]);
+    }
+
+    #[test]
+    fn light_is_absorbed_travelling_through_a_dense_material() {
+        let mut shape = Object::new_sphere().with_transform(scaling(2.0, 2.0, 2.0));
+        shape.material.ambient = 1.0;
+        shape.material.diffuse = 0.0;
+        shape.material.specular = 0.0;
+        shape.material.transparency = 1.0;
+        shape.material.attenuation = Color::new(0.5, 1.0, 1.0);
+        shape.material.density = 0.5;
+        let mut w = World::new();
+        w.add_object(shape);
+        w.prepare();
+        let r = Ray::new(Tuple::point(0.0, 0.0, 0.0), Tuple::vector(0.0, 0.0, 1.0));
+        assert_eq!(w.color_at(&r, 5), Color::new(0.60653, 1.0, 1.0));
+        let r = Ray::new(Tuple::point(0.0, 0.0, -5.0), Tuple::vector(0.0, 0.0, 1.0));
+        assert_eq!(w.color_at(&r, 5), Color::new(1.0 + 0.36788, 2.0, 2.0));
+        assert_eq!(
+            w.debug_ray(&r, 5).color,
+            Color::new(1.0 + 0.36788, 2.0, 2.0)
+        );
+    }
+
+    #[test]
+    fn fog_fades_surfaces_toward_the_fog_color() {
+        let mut shape = Object::new_sphere();
+        shape.material.color = BLACK;
+        let mut w = World::new();
+        w.add_object(shape);
+        w.fog = Some(Fog::new(Color::new(1.0, 0.5, 0.0), 0.5));
+        let r = Ray::new(Tuple::point(0.0, 0.0, -5.0), Tuple::vector(0.0, 0.0, 1.0));
+        assert_eq!(w.color_at(&r, 5), Color::new(0.86466, 0.43233, 0.0));
+        assert_eq!(w.debug_ray(&r, 5).color, Color::new(0.86466, 0.43233, 0.0));
+        let r = Ray::new(Tuple::point(0.0, 0.0, -5.0), Tuple::vector(0.0, 1.0, 0.0));
+        assert_eq!(w.color_at(&r, 5), Color::new(1.0, 0.5, 0.0));
+    }
+
+    #[test]
+    fn the_bounds_of_a_world_leave_out_infinite_objects() {
+        let mut w = default_world();
+        w.add_object(Object::new_plane());
+        w.add_object(Object::new_sphere().with_transform(translation(0.0, 3.0, 0.0)));
+        w.prepare();
+        let bounds = w.bounds();
+        assert_eq!(bounds.min, Tuple::point(-1.0, -1.0, -1.0));
+        assert_eq!(bounds.max, Tuple::point(1.0, 4.0, 1.0));
+        assert!(!World::new().bounds().is_finite());
+    }
+
+    #[test]
+    fn a_sky_is_seen_behind_the_objects_and_lights_them() {
+        let mut w = World::new();
+        w.ambient_light = BLACK;
+        w.add_object(Object::new_sphere());
+        w.prepare();
+        let miss = Ray::new(Tuple::point(0.0, 0.0, -5.0), Tuple::vector(0.0, 1.0, 0.0));
+        let hit = Ray::new(Tuple::point(0.0, 0.0, -5.0), Tuple::vector(0.0, 0.0, 1.0));
+        assert_eq!(w.color_at(&miss, 5), BLACK);
+        assert_eq!(w.color_at(&hit, 5), BLACK);
+        let sky = Sky::new(Tuple::vector(0.0, 1.0, -1.0), 3.0);
+        w.sky = Some(sky);
+        assert_eq!(w.color_at(&miss, 5), sky.color_at(miss.direction));
+        assert_eq!(w.debug_ray(&miss, 5).color, sky.color_at(miss.direction));
+        assert_ne!(w.color_at(&hit, 5), BLACK);
+    }
+
+    #[test]
+    fn the_sky_shows_through_clear_fog() {
+        let mut w = World::new();
+        let sky = Sky::new(Tuple::vector(0.0, 1.0, -1.0), 3.0);
+        w.sky = Some(sky);
+        w.fog = Some(Fog::new(Color::new(1.0, 0.5, 0.0), 0.0));
+        let miss = Ray::new(Tuple::point(0.0, 0.0, -5.0), Tuple::vector(0.0, 1.0, 0.0));
+        assert_eq!(w.color_at(&miss, 5), sky.color_at(miss.direction));
+        assert_eq!(w.debug_ray(&miss, 5).color, sky.color_at(miss.direction));
+    }
+
+    #[test]
+    fn fog_scatters_light_toward_the_eye() {
+        let mut w = World::new();
+        w.ambient_light = BLACK;
+        w.fog = Some(Fog::new(WHITE, 0.5).with_samples(64));
+        let r = Ray::new(Tuple::point(0.0, 0.0, 0.0), Tuple::vector(0.0, 0.0, 1.0));
+        assert_eq!(w.color_at(&r, 5), BLACK);
+        w.lights = vec![Light::new_point(Tuple::point(0.0, 5.0, 0.0), WHITE)];
+        let lit = w.color_at(&r, 5);
+        assert!((lit.red() - 0.999).abs() < 0.01);
+        let mut blocker = Object::new_plane().with_transform(translation(0.0, 1.0, 0.0));
+        blocker.material.color = BLACK;
+        w.add_object(blocker);
+        w.prepare();
+        assert_eq!(w.color_at(&r, 5), BLACK);
+    }
+
+    #[test]
+    fn a_translucent_object_is_lit_through_from_behind() {
+        let mut w = World::new();
+        w.add_object(Object::new_sphere());
+        w.lights = vec![Light::new_point(Tuple::point(0.0, 0.0, 10.0), WHITE)];
+        let r = Ray::new(Tuple::point(0.0, 0.0, -5.0), Tuple::vector(0.0, 0.0, 1.0));
+        assert_eq!(w.color_at(&r, 5), Color::new(0.1, 0.1, 0.1));
+        Arc::make_mut(&mut w.objects[0]).material.translucency = 0.5;
+        assert_eq!(w.color_at(&r, 5), Color::new(0.55, 0.55, 0.55));
+        let mut wall =
+            Object::new_plane().with_transform(translation(0.0, 0.0, 5.0) * rotation_x(PI / 2.0));
+        wall.material.color = BLACK;
+        w.add_object(wall);
+        w.prepare();
+        assert_eq!(w.color_at(&r, 5), Color::new(0.1, 0.1, 0.1));
+    }
+
+    #[test]
+    fn instances_of_shared_geometry_are_shaded_with_their_own_material() {
+        let sphere = Object::new_sphere().into_shared();
+        let mut w = World::new();
+        for (x, color) in [
+            (-2.0, Color::new(1.0, 0.0, 0.0)),
+            (2.0, Color::new(0.0, 0.0, 1.0)),
+        ] {
+            let mut instance = Object::new_instance(Arc::clone(&sphere), translation(x, 0.0, 0.0));
+            instance.material.color = color;
+            instance.material.ambient = 1.0;
+            instance.material.diffuse = 0.0;
+            instance.material.specular = 0.0;
+            w.add_object(instance);
+        }
+        w.prepare();
+        let r = Ray::new(Tuple::point(-2.0, 0.0, -5.0), Tuple::vector(0.0, 0.0, 1.0));
+        assert_eq!(w.color_at(&r, 1), Color::new(1.0, 0.0, 0.0));
+        let r = Ray::new(Tuple::point(2.0, 0.0, -5.0), Tuple::vector(0.0, 0.0, 1.0));
+        assert_eq!(w.color_at(&r, 1), Color::new(0.0, 0.0, 1.0));
+    }
 }